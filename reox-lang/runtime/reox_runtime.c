@@ -277,6 +277,89 @@ rx_float str_to_float(const rx_str* s) {
     return atof(s->data);
 }
 
+static rx_string copy_cstr(const char* s) {
+    size_t len = strlen(s);
+    char* copy = (char*)rx_alloc(len + 1);
+    memcpy(copy, s, len + 1);
+    return copy;
+}
+
+rx_string reox_concat(rx_string a, rx_string b) {
+    size_t a_len = a ? strlen(a) : 0;
+    size_t b_len = b ? strlen(b) : 0;
+    char* result = (char*)rx_alloc(a_len + b_len + 1);
+    if (a_len) memcpy(result, a, a_len);
+    if (b_len) memcpy(result + a_len, b, b_len);
+    result[a_len + b_len] = '\0';
+    return result;
+}
+
+rx_string reox_int_to_string(rx_int n) {
+    char buffer[32];
+    snprintf(buffer, sizeof(buffer), "%lld", (long long)n);
+    return copy_cstr(buffer);
+}
+
+rx_string reox_uint_to_string(uint64_t n) {
+    char buffer[32];
+    snprintf(buffer, sizeof(buffer), "%llu", (unsigned long long)n);
+    return copy_cstr(buffer);
+}
+
+/* Whole floats keep one decimal ("3.0"); others print up to 12 places with
+ * trailing zeros trimmed, as the interpreter does */
+rx_string reox_float_to_string(rx_float n) {
+    char buffer[64];
+    double a = fabs(n);
+    if (isnan(n)) return "NaN";
+    if (isinf(n)) return n > 0 ? "inf" : "-inf";
+    if (a != 0.0 && (a < 1e-6 || a >= 1e16)) {
+        snprintf(buffer, sizeof(buffer), "%g", n);
+        return copy_cstr(buffer);
+    }
+    if (n == trunc(n)) {
+        snprintf(buffer, sizeof(buffer), "%.1f", n);
+        return copy_cstr(buffer);
+    }
+    snprintf(buffer, sizeof(buffer), "%.12f", n);
+    size_t len = strlen(buffer);
+    while (len > 0 && buffer[len - 1] == '0') buffer[--len] = '\0';
+    if (len > 0 && buffer[len - 1] == '.') buffer[len++] = '0';
+    buffer[len] = '\0';
+    return copy_cstr(buffer);
+}
+
+rx_string reox_bool_to_string(rx_bool b) {
+    return b ? "true" : "false";
+}
+
+/* Encode a code point as UTF-8 */
+rx_string reox_char_to_string(uint32_t c) {
+    char* out = (char*)rx_alloc(5);
+    size_t len;
+    if (c < 0x80) {
+        out[0] = (char)c;
+        len = 1;
+    } else if (c < 0x800) {
+        out[0] = (char)(0xC0 | (c >> 6));
+        out[1] = (char)(0x80 | (c & 0x3F));
+        len = 2;
+    } else if (c < 0x10000) {
+        out[0] = (char)(0xE0 | (c >> 12));
+        out[1] = (char)(0x80 | ((c >> 6) & 0x3F));
+        out[2] = (char)(0x80 | (c & 0x3F));
+        len = 3;
+    } else {
+        out[0] = (char)(0xF0 | (c >> 18));
+        out[1] = (char)(0x80 | ((c >> 12) & 0x3F));
+        out[2] = (char)(0x80 | ((c >> 6) & 0x3F));
+        out[3] = (char)(0x80 | (c & 0x3F));
+        len = 4;
+    }
+    out[len] = '\0';
+    return out;
+}
+
 /* ============================================================================
  * Math Functions (libm wrappers)
  * Note: abs_int, min/max/clamp for int are inline in header
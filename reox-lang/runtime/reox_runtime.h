@@ -177,6 +177,34 @@ extern rx_str float_to_str(rx_float n);
 extern RX_PURE rx_int str_to_int(const rx_str* s);
 extern RX_PURE rx_float str_to_float(const rx_str* s);
 
+/* String `+` in compiled REOX: the operands are formatted like `to_string`
+ * and joined into a new heap string */
+extern rx_string reox_concat(rx_string a, rx_string b);
+extern rx_string reox_int_to_string(rx_int n);
+extern rx_string reox_uint_to_string(uint64_t n);
+extern rx_string reox_float_to_string(rx_float n);
+extern rx_string reox_bool_to_string(rx_bool b);
+extern rx_string reox_char_to_string(uint32_t c);
+
+RX_INLINE rx_string reox_string_to_string(rx_string s) {
+    return s;
+}
+
+/* Pick the formatter from the C type; bools and chars must be passed to
+ * their own formatter, since C sees them as plain ints */
+#define reox_to_string(x) _Generic((x), \
+    char*: reox_string_to_string, \
+    const char*: reox_string_to_string, \
+    bool: reox_bool_to_string, \
+    float: reox_float_to_string, \
+    double: reox_float_to_string, \
+    unsigned char: reox_uint_to_string, \
+    unsigned short: reox_uint_to_string, \
+    unsigned int: reox_uint_to_string, \
+    unsigned long: reox_uint_to_string, \
+    unsigned long long: reox_uint_to_string, \
+    default: reox_int_to_string)(x)
+
 /* ============================================================================
  * Memory Functions (inline wrappers for performance)
  * ============================================================================ */
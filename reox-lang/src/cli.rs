@@ -8,12 +8,26 @@ use std::env;
 use std::process::Command;
 use std::path::Path;
 
+pub use crate::codegen::{Runtime, Target};
+pub use crate::profiler::OutputFormat;
+pub use crate::formatter::{BraceStyle, IndentStyle};
+
 /// CLI Command
 #[derive(Debug, Clone)]
 pub enum CliCommand {
     Compile(Args),
     Init { template: String, name: Option<String> },
     New { name: String, template: String },
+    Repl,
+    Explain(String),
+    Doc { input: String, output: Option<String> },
+    /// Check a file without generating code; `syntax_only` skips the type checker
+    /// and `warnings_as_errors` fails the check on any warning
+    Check { input: String, syntax_only: bool, warnings_as_errors: bool },
+    /// Run every `@test` function in a file with the interpreter
+    Test { input: String },
+    /// Format in place; flags override the `[fmt]` table of `reox.toml`
+    Fmt { input: String, indent: Option<IndentStyle>, brace_style: Option<BraceStyle> },
     Help,
     Version,
 }
@@ -30,6 +44,20 @@ pub struct Args {
     pub verbose: bool,
     pub runtime_path: Option<String>,
     pub run: bool,
+    pub warnings_as_errors: bool,
+    pub target: Target,
+    /// Explicit runtime; `None` lets codegen pick from the program's UI use
+    pub runtime: Option<Runtime>,
+    /// Profile an interpreted run (`--run`) and print a report afterwards
+    pub profile: bool,
+    /// Include estimated allocations in the profile
+    pub trace_memory: bool,
+    /// Hide functions with less total time than this many milliseconds
+    pub profile_min_time: f64,
+    /// Hide functions called fewer times than this
+    pub profile_min_calls: u64,
+    /// How the profile report is written
+    pub profile_format: OutputFormat,
 }
 
 /// Output type
@@ -38,6 +66,7 @@ pub enum EmitType {
     C,      // Generate C code only
     Obj,    // Compile to object file
     Exe,    // Compile to executable (default)
+    Llvm,   // Generate textual LLVM IR (integer subset)
 }
 
 impl Default for EmitType {
@@ -86,6 +115,16 @@ pub fn parse_cli() -> Result<CliCommand, String> {
     match args[1].as_str() {
         "init" => return parse_init(&args[2..]),
         "new" => return parse_new(&args[2..]),
+        "repl" => return Ok(CliCommand::Repl),
+        "explain" => {
+            return args.get(2)
+                .map(|code| CliCommand::Explain(code.clone()))
+                .ok_or_else(|| "error code required. Usage: reoxc explain <CODE>".to_string());
+        }
+        "doc" => return parse_doc(&args[2..]),
+        "fmt" => return parse_fmt(&args[2..]),
+        "check" => return parse_check(&args[2..]),
+        "test" => return parse_test(&args[2..]),
         "help" | "--help" | "-h" => return Ok(CliCommand::Help),
         "version" | "--version" | "-V" => return Ok(CliCommand::Version),
         _ => {}
@@ -160,6 +199,96 @@ fn parse_new(args: &[String]) -> Result<CliCommand, String> {
     Ok(CliCommand::New { name, template })
 }
 
+fn parse_doc(args: &[String]) -> Result<CliCommand, String> {
+    let mut input: Option<String> = None;
+    let mut output: Option<String> = None;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "-o" | "--output" => {
+                i += 1;
+                output = Some(args.get(i).ok_or("expected file after --output")?.clone());
+            }
+            arg if !arg.starts_with('-') && input.is_none() => input = Some(arg.to_string()),
+            arg => return Err(format!("unknown doc option: {}", arg)),
+        }
+        i += 1;
+    }
+
+    let input = input.ok_or("input file required. Usage: reoxc doc <INPUT> [-o <FILE>]")?;
+    Ok(CliCommand::Doc { input, output })
+}
+
+fn parse_check(args: &[String]) -> Result<CliCommand, String> {
+    let mut input: Option<String> = None;
+    let mut syntax_only = false;
+    let mut warnings_as_errors = false;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--syntax-only" | "--check-syntax-only" => syntax_only = true,
+            "--warnings-as-errors" | "-Werror" | "--strict" => warnings_as_errors = true,
+            "-W" => {
+                i += 1;
+                match args.get(i).map(String::as_str) {
+                    Some("error") => warnings_as_errors = true,
+                    Some(other) => return Err(format!("unknown warning setting: -W {} (expected: error)", other)),
+                    None => return Err("expected 'error' after -W".to_string()),
+                }
+            }
+            arg if !arg.starts_with('-') && input.is_none() => input = Some(arg.to_string()),
+            arg => return Err(format!("unknown check option: {}", arg)),
+        }
+        i += 1;
+    }
+    let input = input.ok_or("input file required. Usage: reoxc check <INPUT> [--syntax-only] [--strict]")?;
+    Ok(CliCommand::Check { input, syntax_only, warnings_as_errors })
+}
+
+fn parse_test(args: &[String]) -> Result<CliCommand, String> {
+    let mut input: Option<String> = None;
+    for arg in args {
+        match arg.as_str() {
+            arg if !arg.starts_with('-') && input.is_none() => input = Some(arg.to_string()),
+            arg => return Err(format!("unknown test option: {}", arg)),
+        }
+    }
+    let input = input.ok_or("input file required. Usage: reoxc test <INPUT>")?;
+    Ok(CliCommand::Test { input })
+}
+
+fn parse_fmt(args: &[String]) -> Result<CliCommand, String> {
+    let mut input: Option<String> = None;
+    let mut indent: Option<IndentStyle> = None;
+    let mut brace_style: Option<BraceStyle> = None;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--indent" => {
+                i += 1;
+                let value = args.get(i).ok_or("expected width or 'tab' after --indent")?;
+                indent = Some(crate::formatter::FormatOptions::parse_indent(value)
+                    .ok_or_else(|| format!("invalid indent: {} (expected 1-16 or 'tab')", value))?);
+            }
+            "--tabs" => indent = Some(IndentStyle::Tabs),
+            "--brace-style" => {
+                i += 1;
+                let value = args.get(i).ok_or("expected style after --brace-style")?;
+                brace_style = Some(BraceStyle::from_name(value)
+                    .ok_or_else(|| format!("unknown brace style: {} (expected same-line or next-line)", value))?);
+            }
+            arg if !arg.starts_with('-') && input.is_none() => input = Some(arg.to_string()),
+            arg => return Err(format!("unknown fmt option: {}", arg)),
+        }
+        i += 1;
+    }
+
+    let input = input.ok_or("input file required. Usage: reoxc fmt <INPUT> [--indent <N|tab>] [--brace-style <STYLE>]")?;
+    Ok(CliCommand::Fmt { input, indent, brace_style })
+}
+
 fn parse_compile_args(args: &[String]) -> Result<Args, String> {
     let mut input: Option<String> = None;
     let mut output: Option<String> = None;
@@ -170,6 +299,14 @@ fn parse_compile_args(args: &[String]) -> Result<Args, String> {
     let mut verbose = false;
     let mut runtime_path: Option<String> = None;
     let mut run = false;
+    let mut profile = false;
+    let mut trace_memory = false;
+    let mut profile_min_time = 0.0;
+    let mut profile_min_calls = 0;
+    let mut profile_format = OutputFormat::Text;
+    let mut warnings_as_errors = false;
+    let mut target = Target::Hosted;
+    let mut runtime: Option<Runtime> = None;
 
     let mut i = 0;
     while i < args.len() {
@@ -192,9 +329,21 @@ fn parse_compile_args(args: &[String]) -> Result<Args, String> {
                     "c" => EmitType::C,
                     "obj" => EmitType::Obj,
                     "exe" => EmitType::Exe,
+                    "llvm" | "llvm-ir" => EmitType::Llvm,
                     other => return Err(format!("unknown emit type: {}", other)),
                 };
             }
+            "--target" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("expected target after --target".to_string());
+                }
+                target = match args[i].as_str() {
+                    "hosted" => Target::Hosted,
+                    "freestanding" => Target::Freestanding,
+                    other => return Err(format!("unknown target: {}", other)),
+                };
+            }
             "-O0" => opt_level = OptLevel::O0,
             "-O1" => opt_level = OptLevel::O1,
             "-O2" => opt_level = OptLevel::O2,
@@ -209,8 +358,60 @@ fn parse_compile_args(args: &[String]) -> Result<Args, String> {
                 }
                 runtime_path = Some(args[i].clone());
             }
+            "--runtime-kind" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("expected runtime after --runtime-kind".to_string());
+                }
+                runtime = Some(match args[i].as_str() {
+                    "native" => Runtime::Native,
+                    "neolyx" => Runtime::Neolyx,
+                    other => return Err(format!("unknown runtime: {} (use native or neolyx)", other)),
+                });
+            }
             "--run" | "-r" => run = true,
+            "--profile" => profile = true,
+            "--trace-memory" => {
+                profile = true;
+                trace_memory = true;
+            }
+            "--profile-min-time" => {
+                i += 1;
+                profile_min_time = args.get(i)
+                    .and_then(|v| v.parse::<f64>().ok())
+                    .filter(|ms| *ms >= 0.0)
+                    .ok_or("expected milliseconds after --profile-min-time")?;
+                profile = true;
+            }
+            "--profile-min-calls" => {
+                i += 1;
+                profile_min_calls = args.get(i)
+                    .and_then(|v| v.parse().ok())
+                    .ok_or("expected a call count after --profile-min-calls")?;
+                profile = true;
+            }
+            "--profile-format" => {
+                i += 1;
+                profile_format = match args.get(i).map(String::as_str) {
+                    Some("text") => OutputFormat::Text,
+                    Some("json") => OutputFormat::Json,
+                    Some("flamegraph") => OutputFormat::Flamegraph,
+                    Some("speedscope") => OutputFormat::Speedscope,
+                    Some(other) => return Err(format!("unknown profile format: {}", other)),
+                    None => return Err("expected a format after --profile-format".to_string()),
+                };
+                profile = true;
+            }
             "-v" | "--verbose" => verbose = true,
+            "--warnings-as-errors" | "-Werror" | "--strict" => warnings_as_errors = true,
+            "-W" => {
+                i += 1;
+                match args.get(i).map(String::as_str) {
+                    Some("error") => warnings_as_errors = true,
+                    Some(other) => return Err(format!("unknown warning setting: -W {} (expected: error)", other)),
+                    None => return Err("expected 'error' after -W".to_string()),
+                }
+            }
             "-h" | "--help" => {
                 print_usage();
                 std::process::exit(0);
@@ -257,6 +458,14 @@ fn parse_compile_args(args: &[String]) -> Result<Args, String> {
         verbose,
         runtime_path,
         run,
+        warnings_as_errors,
+        target,
+        runtime,
+        profile,
+        trace_memory,
+        profile_min_time,
+        profile_min_calls,
+        profile_format,
     })
 }
 
@@ -333,12 +542,21 @@ pub fn print_usage() {
     println!("COMMANDS:");
     println!("    init          Initialize a new project in current directory");
     println!("    new <name>    Create a new named project");
+    println!("    repl          Start an interactive interpreter session");
+    println!("    explain <code> Describe an error code (e.g. E0002)");
+    println!("    doc <input>   Write a Markdown reference from /// comments (-o <FILE>)");
+    println!("    check <input> Report errors without generating code (--syntax-only: lex and parse only,");
+    println!("                  --strict: fail on warnings too)");
+    println!("    test <input>  Run the @test functions in a file and report failures");
+    println!("    fmt <input>   Format a file in place (--indent <N|tab>, --brace-style same-line|next-line)");
     println!("    help          Show this help message");
     println!("    version       Show version information");
     println!();
     println!("COMPILE OPTIONS:");
     println!("    -o, --output <FILE>    Output file path");
-    println!("    --emit <TYPE>          Output type: c, obj, exe (default: c)");
+    println!("    --emit <TYPE>          Output type: c, obj, exe, llvm (default: c)");
+    println!("    --target <ENV>         C environment: hosted, freestanding (default: hosted)");
+    println!("    --runtime-kind <KIND>  Runtime: native, neolyx (default: neolyx if UI is used)");
     println!();
     println!("  Optimization:");
     println!("    -O0                    No optimization");
@@ -352,7 +570,14 @@ pub fn print_usage() {
     println!("  Other:");
     println!("    --runtime <PATH>       Path to runtime library");
     println!("    --run, -r              Run immediately (interpreter mode)");
+    println!("    --profile              With --run, print a function timing report");
+    println!("    --trace-memory         Also report estimated allocations (implies --profile)");
+    println!("    --profile-min-time MS  Omit functions with less total time (implies --profile)");
+    println!("    --profile-min-calls N  Omit functions called fewer times (implies --profile)");
+    println!("    --profile-format FMT   Report as text, json, flamegraph or speedscope (implies --profile)");
     println!("    -v, --verbose          Verbose output");
+    println!("    --strict, -W error     Fail compilation on type checker warnings");
+    println!("                           (alias: --warnings-as-errors)");
     println!("    -h, --help             Print help information");
     println!("    -V, --version          Print version information");
     println!();
@@ -373,6 +598,7 @@ pub fn compile_c_to_exe(
     c_file: &str,
     output: &str,
     args: &Args,
+    runtime: Runtime,
 ) -> Result<(), String> {
     let mut cmd = Command::new("gcc");
     
@@ -397,6 +623,9 @@ pub fn compile_c_to_exe(
     if let Some(ref runtime) = args.runtime_path {
         cmd.arg(format!("{}/libreox_runtime.a", runtime));
     }
+    if runtime == Runtime::Neolyx {
+        cmd.arg("-lnxrender");
+    }
     cmd.arg("-lm");
     
     // Section garbage collection
@@ -442,4 +671,87 @@ mod tests {
         assert_eq!(OptLevel::O3.to_flag(), "-O3");
         assert_eq!(OptLevel::Os.to_flag(), "-Os");
     }
+
+    #[test]
+    fn test_warnings_as_errors_flag() {
+        let args: Vec<String> = ["main.rx", "--warnings-as-errors"].iter().map(|s| s.to_string()).collect();
+        assert!(parse_compile_args(&args).unwrap().warnings_as_errors);
+        assert!(!parse_compile_args(&args[..1]).unwrap().warnings_as_errors);
+        for flags in [&["--strict"][..], &["-W", "error"]] {
+            let args: Vec<String> = std::iter::once("main.rx").chain(flags.iter().copied()).map(String::from).collect();
+            assert!(parse_compile_args(&args).unwrap().warnings_as_errors);
+        }
+        assert!(parse_compile_args(&["main.rx".to_string(), "-W".to_string(), "all".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_target_flag() {
+        let args: Vec<String> = ["main.rx", "--target", "freestanding"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(parse_compile_args(&args).unwrap().target, Target::Freestanding);
+        assert_eq!(parse_compile_args(&args[..1]).unwrap().target, Target::Hosted);
+        assert!(parse_compile_args(&["main.rx".to_string(), "--target".to_string(), "bare".to_string()]).is_err());
+
+        assert!(parse_compile_args(&["main.rx".to_string(), "--target".to_string(), "native".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_runtime_flag() {
+        let args: Vec<String> = ["main.rx", "--runtime-kind", "native", "--target", "freestanding", "--runtime", "rt"]
+            .iter().map(|s| s.to_string()).collect();
+        let parsed = parse_compile_args(&args).unwrap();
+        assert_eq!((parsed.target, parsed.runtime), (Target::Freestanding, Some(Runtime::Native)));
+        assert_eq!(parsed.runtime_path.as_deref(), Some("rt"));
+        assert_eq!(parse_compile_args(&args[..1]).unwrap().runtime, None);
+        assert!(parse_compile_args(&["main.rx".to_string(), "--runtime-kind".to_string(), "rt".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_profile_format_flag() {
+        let args: Vec<String> = ["main.rx", "--run", "--profile-format", "speedscope"].iter().map(|s| s.to_string()).collect();
+        let parsed = parse_compile_args(&args).unwrap();
+        assert!(parsed.profile);
+        assert_eq!(parsed.profile_format, OutputFormat::Speedscope);
+        assert_eq!(parse_compile_args(&args[..2]).unwrap().profile_format, OutputFormat::Text);
+        assert!(parse_compile_args(&["main.rx".to_string(), "--profile-format".to_string(), "xml".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_doc_command() {
+        let args: Vec<String> = ["lib.rx", "-o", "api.md"].iter().map(|s| s.to_string()).collect();
+        match parse_doc(&args).unwrap() {
+            CliCommand::Doc { input, output } => {
+                assert_eq!(input, "lib.rx");
+                assert_eq!(output.as_deref(), Some("api.md"));
+            }
+            other => panic!("expected doc command, got {:?}", other),
+        }
+        assert!(parse_doc(&[]).is_err());
+    }
+
+    #[test]
+    fn test_fmt_command() {
+        let args: Vec<String> = ["main.rx", "--indent", "2", "--brace-style", "next-line"].iter().map(|s| s.to_string()).collect();
+        match parse_fmt(&args).unwrap() {
+            CliCommand::Fmt { input, indent, brace_style } => {
+                assert_eq!(input, "main.rx");
+                assert_eq!(indent, Some(IndentStyle::Spaces(2)));
+                assert_eq!(brace_style, Some(BraceStyle::NextLine));
+            }
+            other => panic!("expected fmt command, got {:?}", other),
+        }
+        assert!(parse_fmt(&["main.rx".to_string(), "--brace-style".to_string(), "k&r".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_check_command() {
+        let args: Vec<String> = ["big.rx", "--check-syntax-only"].iter().map(|s| s.to_string()).collect();
+        assert!(matches!(parse_check(&args), Ok(CliCommand::Check { syntax_only: true, .. })));
+        assert!(matches!(parse_check(&args[..1]), Ok(CliCommand::Check { syntax_only: false, warnings_as_errors: false, .. })));
+        assert!(parse_check(&[]).is_err());
+        for flags in [&["--strict"][..], &["-W", "error"]] {
+            let args: Vec<String> = ["big.rx"].iter().chain(flags).map(|s| s.to_string()).collect();
+            assert!(matches!(parse_check(&args), Ok(CliCommand::Check { warnings_as_errors: true, .. })));
+        }
+        assert!(parse_check(&["big.rx".to_string(), "-W".to_string()]).is_err());
+    }
 }
@@ -0,0 +1,607 @@
+// REOX Compiler - LLVM IR Backend
+// Emits textual LLVM IR (opaque pointers, LLVM 15+) for the integer subset of REOX
+// Zero external dependencies
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+use crate::parser::{
+    Ast, BinOp, Block, CompoundOp, Decl, Expr, FnDecl, Literal, Stmt, Type, UnaryOp,
+};
+
+/// An SSA operand together with its LLVM type
+#[derive(Clone)]
+enum Val {
+    I64(String),
+    I1(String),
+}
+
+/// Signature of a function visible to calls
+#[derive(Clone, Copy)]
+struct Sig {
+    arity: usize,
+    returns_int: bool,
+}
+
+/// LLVM IR generator state
+pub struct LlvmGen {
+    output: String,
+    allocas: String,  // Entry-block allocas of the current function
+    body: String,     // Instructions of the current function
+    temp_count: usize,
+    label_count: usize,
+    terminated: bool, // Current basic block already ended with br/ret
+    returns_int: bool,
+    scopes: Vec<HashMap<String, String>>, // REOX name -> alloca slot
+    loops: Vec<(String, String)>,         // (continue label, break label)
+    functions: HashMap<String, Sig>,
+    panics: Vec<&'static str>,            // Messages passed to rx_panic, one global each
+}
+
+type GenResult<T> = Result<T, String>;
+
+fn unsupported(what: &str) -> String {
+    format!("llvm backend: {} is not supported (only int functions are)", what)
+}
+
+impl LlvmGen {
+    pub fn new() -> Self {
+        Self {
+            output: String::new(),
+            allocas: String::new(),
+            body: String::new(),
+            temp_count: 0,
+            label_count: 0,
+            terminated: false,
+            returns_int: false,
+            scopes: Vec::new(),
+            loops: Vec::new(),
+            functions: HashMap::new(),
+            panics: Vec::new(),
+        }
+    }
+
+    pub fn generate(&mut self, ast: &Ast) -> GenResult<String> {
+        self.output.push_str("; Generated by REOX Compiler\n");
+        self.output.push_str("; ModuleID = 'reox'\n\n");
+
+        for decl in &ast.declarations {
+            match decl {
+                Decl::Function(f) => {
+                    let sig = Self::signature(f)?;
+                    self.functions.insert(f.name.clone(), sig);
+                }
+                Decl::Import(_) => {}
+                _ => return Err(unsupported("a non-function declaration")),
+            }
+        }
+
+        for decl in &ast.declarations {
+            if let Decl::Function(f) = decl {
+                self.gen_function(f)?;
+            }
+        }
+
+        // Runtime checks report through the REOX runtime, as compiled C does
+        for (i, msg) in self.panics.iter().enumerate() {
+            let _ = writeln!(self.output, "@.panic{} = private unnamed_addr constant [{} x i8] c\"{}\\00\"", i, msg.len() + 1, msg);
+        }
+        if !self.panics.is_empty() {
+            self.output.push_str("declare void @rx_panic(ptr) noreturn\n");
+        }
+
+        Ok(std::mem::take(&mut self.output))
+    }
+
+    fn signature(f: &FnDecl) -> GenResult<Sig> {
+        if f.is_async {
+            return Err(unsupported("an async function"));
+        }
+        if let Some(p) = f.params.iter().find(|p| p.ty != Type::Int) {
+            return Err(unsupported(&format!("parameter '{}' of '{}' with a non-int type", p.name, f.name)));
+        }
+        let returns_int = match &f.return_type {
+            None | Some(Type::Void) => false,
+            Some(Type::Int) => true,
+            Some(_) => return Err(unsupported(&format!("the return type of '{}'", f.name))),
+        };
+        Ok(Sig { arity: f.params.len(), returns_int })
+    }
+
+    fn gen_function(&mut self, f: &FnDecl) -> GenResult<()> {
+        let sig = self.functions[&f.name];
+        self.allocas.clear();
+        self.body.clear();
+        self.temp_count = 0;
+        self.label_count = 0;
+        self.terminated = false;
+        self.returns_int = sig.returns_int;
+        self.scopes = vec![HashMap::new()];
+
+        // Parameters get their own prefix so names like `t0` cannot clash with temporaries
+        let params: Vec<String> = f.params.iter().map(|p| format!("i64 %arg.{}", p.name)).collect();
+        for p in &f.params {
+            let slot = self.declare(&p.name);
+            let _ = writeln!(self.body, "  store i64 %arg.{}, ptr {}", p.name, slot);
+        }
+
+        self.gen_block(&f.body)?;
+        if !self.terminated {
+            self.inst(if sig.returns_int { "ret i64 0" } else { "ret void" });
+        }
+
+        let ret = if sig.returns_int { "i64" } else { "void" };
+        let _ = writeln!(self.output, "define {} @{}({}) {{", ret, f.name, params.join(", "));
+        self.output.push_str("entry:\n");
+        self.output.push_str(&self.allocas);
+        self.output.push_str(&self.body);
+        self.output.push_str("}\n\n");
+        Ok(())
+    }
+
+    // ---- Emission helpers ----
+
+    fn temp(&mut self) -> String {
+        let t = format!("%t{}", self.temp_count);
+        self.temp_count += 1;
+        t
+    }
+
+    fn label(&mut self, hint: &str) -> String {
+        let l = format!("{}{}", hint, self.label_count);
+        self.label_count += 1;
+        l
+    }
+
+    /// Emit an instruction, opening an unreachable block if the current one is closed
+    fn inst(&mut self, text: &str) {
+        if self.terminated {
+            let dead = self.label("dead");
+            self.start_block(&dead);
+        }
+        let _ = writeln!(self.body, "  {}", text);
+        if text.starts_with("br ") || text.starts_with("ret ") || text == "unreachable" {
+            self.terminated = true;
+        }
+    }
+
+    fn start_block(&mut self, label: &str) {
+        if !self.terminated {
+            let _ = writeln!(self.body, "  br label %{}", label);
+        }
+        let _ = writeln!(self.body, "{}:", label);
+        self.terminated = false;
+    }
+
+    /// Allocate a stack slot for a new variable in the innermost scope
+    fn declare(&mut self, name: &str) -> String {
+        let slot = format!("%{}.addr{}", name, self.temp_count);
+        self.temp_count += 1;
+        let _ = writeln!(self.allocas, "  {} = alloca i64", slot);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), slot.clone());
+        }
+        slot
+    }
+
+    fn lookup(&self, name: &str) -> GenResult<String> {
+        self.scopes.iter().rev()
+            .find_map(|s| s.get(name).cloned())
+            .ok_or_else(|| format!("llvm backend: undefined variable '{}'", name))
+    }
+
+    fn as_i64(&mut self, v: Val) -> String {
+        match v {
+            Val::I64(s) => s,
+            Val::I1(s) => {
+                let t = self.temp();
+                self.inst(&format!("{} = zext i1 {} to i64", t, s));
+                t
+            }
+        }
+    }
+
+    fn as_i1(&mut self, v: Val) -> String {
+        match v {
+            Val::I1(s) => s,
+            Val::I64(s) => {
+                let t = self.temp();
+                self.inst(&format!("{} = icmp ne i64 {}, 0", t, s));
+                t
+            }
+        }
+    }
+
+    // ---- Statements ----
+
+    fn gen_block(&mut self, b: &Block) -> GenResult<()> {
+        self.scopes.push(HashMap::new());
+        let result = b.statements.iter().try_for_each(|s| self.gen_stmt(s));
+        self.scopes.pop();
+        result
+    }
+
+    fn gen_stmt(&mut self, s: &Stmt) -> GenResult<()> {
+        match s {
+            Stmt::Let(l) => {
+                if matches!(&l.ty, Some(t) if *t != Type::Int) {
+                    return Err(unsupported(&format!("variable '{}' with a non-int type", l.name)));
+                }
+                let init = match &l.init {
+                    Some(e) => {
+                        let v = self.gen_expr(e)?;
+                        self.as_i64(v)
+                    }
+                    None => "0".to_string(),
+                };
+                let slot = self.declare(&l.name);
+                self.inst(&format!("store i64 {}, ptr {}", init, slot));
+            }
+            Stmt::Expr(e) => {
+                self.gen_expr(e)?;
+            }
+            Stmt::Return(r) => match (&r.value, self.returns_int) {
+                (Some(e), true) => {
+                    let v = self.gen_expr(e)?;
+                    let v = self.as_i64(v);
+                    self.inst(&format!("ret i64 {}", v));
+                }
+                (None, false) => self.inst("ret void"),
+                (None, true) => self.inst("ret i64 0"),
+                (Some(_), false) => return Err("llvm backend: returning a value from a void function".to_string()),
+            },
+            Stmt::If(i) => {
+                let cond = self.gen_expr(&i.condition)?;
+                let cond = self.as_i1(cond);
+                let then_l = self.label("then");
+                let else_l = self.label("else");
+                let end_l = self.label("endif");
+                let false_target = if i.else_block.is_some() { &else_l } else { &end_l };
+                self.inst(&format!("br i1 {}, label %{}, label %{}", cond, then_l, false_target));
+                self.start_block(&then_l);
+                self.gen_block(&i.then_block)?;
+                if let Some(else_block) = &i.else_block {
+                    self.start_branch_to(&end_l);
+                    self.start_block(&else_l);
+                    self.gen_block(else_block)?;
+                }
+                self.start_block(&end_l);
+            }
+            Stmt::While(w) => {
+                let cond_l = self.label("while.cond");
+                let body_l = self.label("while.body");
+                let end_l = self.label("while.end");
+                self.start_block(&cond_l);
+                let cond = self.gen_expr(&w.condition)?;
+                let cond = self.as_i1(cond);
+                self.inst(&format!("br i1 {}, label %{}, label %{}", cond, body_l, end_l));
+                self.start_block(&body_l);
+                self.loops.push((cond_l.clone(), end_l.clone()));
+                let result = self.gen_block(&w.body);
+                self.loops.pop();
+                result?;
+                self.start_branch_to(&cond_l);
+                self.start_block(&end_l);
+            }
+            Stmt::Loop(l) => {
+                let body_l = self.label("loop.body");
+                let end_l = self.label("loop.end");
+                self.start_block(&body_l);
+                self.loops.push((body_l.clone(), end_l.clone()));
+                let result = self.gen_block(&l.body);
+                self.loops.pop();
+                result?;
+                self.start_branch_to(&body_l);
+                self.start_block(&end_l);
+            }
+            Stmt::Block(b) => self.gen_block(b)?,
+            Stmt::Break(_) | Stmt::Continue(_) => {
+                let (cont, brk) = self.loops.last().cloned()
+                    .ok_or_else(|| "llvm backend: break/continue outside of a loop".to_string())?;
+                let target = if matches!(s, Stmt::Break(_)) { brk } else { cont };
+                self.inst(&format!("br label %{}", target));
+            }
+            Stmt::For(_) => return Err(unsupported("a for loop")),
+            Stmt::Guard(_) => return Err(unsupported("guard")),
+            Stmt::Defer(_) => return Err(unsupported("defer")),
+            Stmt::LetTuple(_) => return Err(unsupported("tuples")),
+            Stmt::TryCatch(_) | Stmt::Throw(_) => return Err(unsupported("exceptions")),
+            Stmt::FnDecl(f) => return Err(unsupported(&format!("local function '{}'", f.name))),
+        }
+        Ok(())
+    }
+
+    /// Close the current block with a jump unless it is already terminated
+    fn start_branch_to(&mut self, label: &str) {
+        if !self.terminated {
+            self.inst(&format!("br label %{}", label));
+        }
+    }
+
+    // ---- Expressions ----
+
+    fn gen_expr(&mut self, e: &Expr) -> GenResult<Val> {
+        match e {
+            Expr::Literal(Literal::Int(n, _)) => Ok(Val::I64(n.to_string())),
+            Expr::Literal(Literal::Bool(b, _)) => Ok(Val::I1(b.to_string())),
+            Expr::Literal(_) => Err(unsupported("a non-integer literal")),
+            Expr::Identifier(name, _) => {
+                let slot = self.lookup(name)?;
+                let t = self.temp();
+                self.inst(&format!("{} = load i64, ptr {}", t, slot));
+                Ok(Val::I64(t))
+            }
+            Expr::Binary(l, BinOp::And, r, _) => self.gen_logical(l, r, true),
+            Expr::Binary(l, BinOp::Or, r, _) => self.gen_logical(l, r, false),
+            Expr::Binary(l, op, r, _) => {
+                let lv = self.gen_expr(l)?;
+                let lv = self.as_i64(lv);
+                let rv = self.gen_expr(r)?;
+                let rv = self.as_i64(rv);
+                self.gen_binop(op, &lv, &rv)
+            }
+            Expr::Unary(op, x, _) => {
+                let v = self.gen_expr(x)?;
+                let t = self.temp();
+                match op {
+                    UnaryOp::Neg => {
+                        let v = self.as_i64(v);
+                        self.inst(&format!("{} = sub i64 0, {}", t, v));
+                        Ok(Val::I64(t))
+                    }
+                    UnaryOp::BitwiseNot => {
+                        let v = self.as_i64(v);
+                        self.inst(&format!("{} = xor i64 {}, -1", t, v));
+                        Ok(Val::I64(t))
+                    }
+                    UnaryOp::Not => {
+                        let v = self.as_i1(v);
+                        self.inst(&format!("{} = xor i1 {}, true", t, v));
+                        Ok(Val::I1(t))
+                    }
+                }
+            }
+            Expr::Call(callee, args, _) => {
+                let Expr::Identifier(name, _) = callee.as_ref() else {
+                    return Err(unsupported("an indirect call"));
+                };
+                let sig = *self.functions.get(name)
+                    .ok_or_else(|| format!("llvm backend: unknown function '{}'", name))?;
+                if sig.arity != args.len() {
+                    return Err(format!("llvm backend: '{}' expects {} arguments, found {}", name, sig.arity, args.len()));
+                }
+                let mut vals = Vec::with_capacity(args.len());
+                for a in args {
+                    let v = self.gen_expr(a)?;
+                    vals.push(format!("i64 {}", self.as_i64(v)));
+                }
+                if sig.returns_int {
+                    let t = self.temp();
+                    self.inst(&format!("{} = call i64 @{}({})", t, name, vals.join(", ")));
+                    Ok(Val::I64(t))
+                } else {
+                    self.inst(&format!("call void @{}({})", name, vals.join(", ")));
+                    Ok(Val::I64("0".to_string()))
+                }
+            }
+            Expr::Assign(target, value, _) => {
+                let Expr::Identifier(name, _) = target.as_ref() else {
+                    return Err(unsupported("assignment to a non-variable"));
+                };
+                let slot = self.lookup(name)?;
+                let v = self.gen_expr(value)?;
+                let v = self.as_i64(v);
+                self.inst(&format!("store i64 {}, ptr {}", v, slot));
+                Ok(Val::I64(v))
+            }
+            Expr::CompoundAssign(target, op, value, _) => {
+                let Expr::Identifier(name, _) = target.as_ref() else {
+                    return Err(unsupported("assignment to a non-variable"));
+                };
+                let slot = self.lookup(name)?;
+                let cur = self.temp();
+                self.inst(&format!("{} = load i64, ptr {}", cur, slot));
+                let rhs = self.gen_expr(value)?;
+                let rhs = self.as_i64(rhs);
+                let op = match op {
+                    CompoundOp::AddEq => BinOp::Add,
+                    CompoundOp::SubEq => BinOp::Sub,
+                    CompoundOp::MulEq => BinOp::Mul,
+                    CompoundOp::DivEq => BinOp::Div,
+                    CompoundOp::ModEq => BinOp::Mod,
+                };
+                let v = self.gen_binop(&op, &cur, &rhs)?;
+                let v = self.as_i64(v);
+                self.inst(&format!("store i64 {}, ptr {}", v, slot));
+                Ok(Val::I64(v))
+            }
+            _ => Err(unsupported("this expression")),
+        }
+    }
+
+    fn gen_binop(&mut self, op: &BinOp, l: &str, r: &str) -> GenResult<Val> {
+        let (instr, is_cmp) = match op {
+            BinOp::Add => ("add", false),
+            BinOp::Sub => ("sub", false),
+            BinOp::Mul => ("mul", false),
+            BinOp::Div | BinOp::Mod => return Ok(self.gen_division(matches!(op, BinOp::Div), l, r)),
+            BinOp::BitwiseAnd => ("and", false),
+            BinOp::BitwiseOr => ("or", false),
+            BinOp::BitwiseXor => ("xor", false),
+            BinOp::ShiftLeft | BinOp::ShiftRight => {
+                // Amounts outside 0..64 give poison in LLVM; the interpreter rejects them
+                if !r.parse::<i64>().is_ok_and(|n| (0..64).contains(&n)) {
+                    let bad = self.temp();
+                    self.inst(&format!("{} = icmp uge i64 {}, 64", bad, r));
+                    self.guard(&bad, "shift amount out of range 0..64");
+                }
+                (if matches!(op, BinOp::ShiftLeft) { "shl" } else { "ashr" }, false)
+            }
+            BinOp::Eq => ("icmp eq", true),
+            BinOp::Ne => ("icmp ne", true),
+            BinOp::Lt => ("icmp slt", true),
+            BinOp::Gt => ("icmp sgt", true),
+            BinOp::Le => ("icmp sle", true),
+            BinOp::Ge => ("icmp sge", true),
+            BinOp::And | BinOp::Or => unreachable!("logical operators short-circuit"),
+            BinOp::In => return Err(unsupported("'in'")),
+        };
+        let t = self.temp();
+        self.inst(&format!("{} = {} i64 {}, {}", t, instr, l, r));
+        Ok(if is_cmp { Val::I1(t) } else { Val::I64(t) })
+    }
+
+    /// `sdiv`/`srem` with the interpreter's semantics: dividing by zero panics and
+    /// `i64::MIN / -1` wraps instead of being undefined
+    fn gen_division(&mut self, is_div: bool, l: &str, r: &str) -> Val {
+        let instr = if is_div { "sdiv" } else { "srem" };
+        let t = self.temp();
+        if r.parse::<i64>().is_ok_and(|n| n != 0 && n != -1) {
+            self.inst(&format!("{} = {} i64 {}, {}", t, instr, l, r));
+            return Val::I64(t);
+        }
+        let zero = self.temp();
+        self.inst(&format!("{} = icmp eq i64 {}, 0", zero, r));
+        self.guard(&zero, "division by zero");
+        let minus_one = self.temp();
+        self.inst(&format!("{} = icmp eq i64 {}, -1", minus_one, r));
+        let divisor = self.temp();
+        self.inst(&format!("{} = select i1 {}, i64 1, i64 {}", divisor, minus_one, r));
+        let q = self.temp();
+        self.inst(&format!("{} = {} i64 {}, {}", q, instr, l, divisor));
+        let wrapped = if is_div {
+            let n = self.temp();
+            self.inst(&format!("{} = sub i64 0, {}", n, l));
+            n
+        } else {
+            "0".to_string()
+        };
+        self.inst(&format!("{} = select i1 {}, i64 {}, i64 {}", t, minus_one, wrapped, q));
+        Val::I64(t)
+    }
+
+    /// Panic with `msg` when `failed` is true, then continue in a fresh block
+    fn guard(&mut self, failed: &str, msg: &'static str) {
+        let fail_l = self.label("panic");
+        let ok_l = self.label("ok");
+        self.inst(&format!("br i1 {}, label %{}, label %{}", failed, fail_l, ok_l));
+        self.start_block(&fail_l);
+        let id = match self.panics.iter().position(|m| *m == msg) {
+            Some(id) => id,
+            None => {
+                self.panics.push(msg);
+                self.panics.len() - 1
+            }
+        };
+        self.inst(&format!("call void @rx_panic(ptr @.panic{})", id));
+        self.inst("unreachable");
+        self.start_block(&ok_l);
+    }
+
+    /// Short-circuit `&&` / `||` via branches and a phi
+    fn gen_logical(&mut self, l: &Expr, r: &Expr, is_and: bool) -> GenResult<Val> {
+        let lv = self.gen_expr(l)?;
+        let lv = self.as_i1(lv);
+        let lhs_block = self.current_block();
+        let rhs_l = self.label("logic.rhs");
+        let end_l = self.label("logic.end");
+        let (on_true, on_false) = if is_and { (&rhs_l, &end_l) } else { (&end_l, &rhs_l) };
+        self.inst(&format!("br i1 {}, label %{}, label %{}", lv, on_true, on_false));
+        self.start_block(&rhs_l);
+        let rv = self.gen_expr(r)?;
+        let rv = self.as_i1(rv);
+        let rhs_block = self.current_block();
+        self.start_block(&end_l);
+        let t = self.temp();
+        let short = if is_and { "false" } else { "true" };
+        self.inst(&format!("{} = phi i1 [ {}, %{} ], [ {}, %{} ]", t, short, lhs_block, rv, rhs_block));
+        Ok(Val::I1(t))
+    }
+
+    /// Label of the block currently being filled
+    fn current_block(&self) -> String {
+        self.body.lines().rev()
+            .find_map(|line| line.strip_suffix(':').filter(|l| !l.starts_with(' ')))
+            .unwrap_or("entry")
+            .to_string()
+    }
+}
+
+impl Default for LlvmGen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Generate LLVM IR for the program and write it to `output_path`
+pub fn generate(ast: &Ast, output_path: &str) -> Result<(), String> {
+    let ir = LlvmGen::new().generate(ast)?;
+    std::fs::write(output_path, ir).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+
+    fn ir(source: &str) -> GenResult<String> {
+        let tokens = tokenize(source).unwrap();
+        LlvmGen::new().generate(&parse(&tokens))
+    }
+
+    #[test]
+    fn test_add_function() {
+        let out = ir("fn add(a: int, b: int) -> int { return a + b; }").unwrap();
+        assert!(out.contains("define i64 @add(i64 %arg.a, i64 %arg.b)"));
+        assert!(out.contains(" = add i64 "));
+        assert!(out.contains("ret i64 %t"));
+    }
+
+    #[test]
+    fn test_branches_and_loops() {
+        let out = ir(r#"
+            fn count(n: int) -> int {
+                let mut i = 0;
+                while i < n && i != 100 {
+                    if i == 5 { break; }
+                    i += 1;
+                }
+                return i;
+            }
+        "#).unwrap();
+        assert!(out.contains("icmp slt i64"));
+        assert!(out.contains("phi i1 [ false, %while.cond"));
+        assert!(out.contains("br label %while.end"));
+        // Every alloca lives in the entry block
+        let entry = out.split("entry:\n").nth(1).unwrap();
+        assert!(entry.starts_with("  %n.addr"));
+    }
+
+    #[test]
+    fn test_params_do_not_clash_with_temporaries() {
+        let out = ir("fn f(a: int, t2: int) -> int { return a + t2; }").unwrap();
+        assert!(out.contains("define i64 @f(i64 %arg.a, i64 %arg.t2)"));
+        assert!(out.contains("store i64 %arg.t2, ptr %t2.addr"));
+    }
+
+    #[test]
+    fn test_division_and_shifts_are_guarded() {
+        let out = ir("fn f(a: int, b: int) -> int { return (a / b) % b + (a << b) + a / 2 + (a >> 3); }").unwrap();
+        assert_eq!(out.matches("icmp eq i64 %t").count(), 4); // zero and -1 checks per division
+        assert_eq!(out.matches("icmp uge i64 ").count(), 1);
+        assert_eq!(out.matches("call void @rx_panic(ptr @.panic0)").count(), 2);
+        assert!(out.contains("call void @rx_panic(ptr @.panic1)"));
+        assert!(out.contains("@.panic0 = private unnamed_addr constant [17 x i8] c\"division by zero\\00\""));
+        assert!(out.contains("declare void @rx_panic(ptr) noreturn"));
+        // A literal divisor or shift amount needs no check
+        assert!(out.contains(", 2\n"));
+        assert!(!ir("fn f(a: int) -> int { return a / 2 + (a << 3); }").unwrap().contains("rx_panic"));
+    }
+
+    #[test]
+    fn test_rejects_non_int_code() {
+        assert!(ir("fn greet(name: string) { }").is_err());
+        assert!(ir(r#"fn main() { let s = "hi"; }"#).is_err());
+    }
+}
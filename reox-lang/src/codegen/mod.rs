@@ -4,17 +4,81 @@
 
 use crate::parser::{
     Ast, Decl, Stmt, Expr, Literal, BinOp, UnaryOp, CompoundOp,
-    FnDecl, StructDecl, ExternDecl, Block, Type, LetStmt,
+    FnDecl, StructDecl, ExternDecl, ConstDecl, Block, Param, Type, LetStmt, LetTupleStmt,
     ReturnStmt, IfStmt, WhileStmt, ForStmt, GuardStmt, DeferStmt,
-    TryCatchStmt, ThrowStmt,
+    TryCatchStmt, ThrowStmt, IntWidth,
 };
+use std::collections::{HashMap, HashSet};
+
+pub mod llvm_ir;
+pub mod widget_chain;
 use std::io::{self, Write};
 
+/// C environment the generated code runs in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Target {
+    /// Standard libc is available (default)
+    #[default]
+    Hosted,
+    /// No libc: only freestanding headers, I/O goes through `reox_*` runtime hooks
+    Freestanding,
+}
+
+/// Runtime library the generated program links against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Runtime {
+    /// Portable `reox_runtime` only (default for programs without UI)
+    #[default]
+    Native,
+    /// NeolyxOS runtime with the NXRender UI bridge
+    Neolyx,
+}
+
+/// Widget constructors that need the NeolyxOS UI runtime
+pub const UI_WIDGETS: &[&str] = &[
+    "button", "text", "label", "input", "textfield", "slider", "checkbox", "vstack", "hstack", "window",
+];
+
+/// Runtime hooks used for I/O and aborts on freestanding targets
+const FREESTANDING_HOOKS: &[&str] = &[
+    "extern void reox_print(const char* s);",
+    "extern void reox_println(const char* s);",
+    "extern void reox_print_int(int64_t n);",
+    "extern void reox_print_float(double n);",
+    "extern void reox_print_bool(bool b);",
+    "extern void reox_abort(void);",
+];
+
 /// Code generator state
 pub struct CodeGen {
     output: String,
     indent: usize,
-    defer_stack: Vec<Block>,  // Track deferred blocks for cleanup
+    defer_stack: Vec<Deferred>,
+    defer_count: usize,
+    lifted: Vec<String>,      // Closures lifted to static functions
+    closure_count: usize,
+    newtypes: HashSet<String>, // Distinct aliases; `Name(x)` becomes a cast
+    target: Target,
+    widget_count: usize,       // Temporaries for folded widget builder chains
+    runtime: Option<Runtime>,  // `None` picks Neolyx only if UI widgets are used
+    uses_ui: bool,
+    locals: HashMap<String, Type>,                // Declared types of the current function's variables
+    structs: HashMap<String, Vec<(String, Type)>>, // Field types by struct name
+    fn_returns: HashMap<String, Type>,            // Return types of top-level functions
+    fn_params: HashMap<String, Vec<Type>>,        // Parameter types of top-level functions
+    return_type: Option<Type>,                    // Return type of the function being generated
+    renames: HashMap<String, String>,             // Locals read through a defer's snapshots
+    renamed: HashSet<String>,                     // Locals `renames` was consulted for
+}
+
+/// A registered `defer` block
+#[derive(Clone)]
+struct Deferred {
+    flag: String,
+    body: Block,
+    /// Locals the block reads, with the function-scope temporaries their
+    /// values are copied into when the `defer` is reached
+    snapshots: Vec<(String, String, Type)>,
 }
 
 impl CodeGen {
@@ -23,9 +87,39 @@ impl CodeGen {
             output: String::new(),
             indent: 0,
             defer_stack: Vec::new(),
+            defer_count: 0,
+            lifted: Vec::new(),
+            closure_count: 0,
+            newtypes: HashSet::new(),
+            target: Target::Hosted,
+            widget_count: 0,
+            runtime: None,
+            uses_ui: false,
+            locals: HashMap::new(),
+            structs: HashMap::new(),
+            fn_returns: HashMap::new(),
+            fn_params: HashMap::new(),
+            return_type: None,
+            renames: HashMap::new(),
+            renamed: HashSet::new(),
         }
     }
 
+    pub fn with_target(target: Target) -> Self {
+        Self { target, ..Self::new() }
+    }
+
+    /// Force a runtime instead of choosing one from the program's use of UI widgets
+    pub fn with_runtime(mut self, runtime: Runtime) -> Self {
+        self.runtime = Some(runtime);
+        self
+    }
+
+    /// The runtime the generated code was written for
+    pub fn runtime(&self) -> Runtime {
+        self.runtime.unwrap_or(if self.uses_ui { Runtime::Neolyx } else { Runtime::Native })
+    }
+
     fn emit(&mut self, s: &str) {
         self.output.push_str(s);
     }
@@ -58,19 +152,49 @@ impl CodeGen {
         self.emit_line("// Generated by REOX Compiler");
         self.emit_line("// Do not edit manually");
         self.emit_line("");
-        self.emit_line("#include <stdio.h>");
-        self.emit_line("#include <stdlib.h>");
+        if self.target == Target::Hosted {
+            self.emit_line("#include <stdio.h>");
+            self.emit_line("#include <stdlib.h>");
+        }
         self.emit_line("#include <stdint.h>");
         self.emit_line("#include <stdbool.h>");
-        self.emit_line("#include <string.h>");
+        if self.target == Target::Hosted {
+            self.emit_line("#include <string.h>");
+        } else {
+            self.emit_line("#include <stddef.h>");
+        }
         self.emit_line("#include \"reox_runtime.h\"");
-        self.emit_line("#include \"reox_nxrender_bridge.h\"");  // NXRender integration
+        // The UI bridge include is added once the body shows which runtime is needed
+        let runtime_includes_at = self.output.len();
         self.emit_line("");
 
+        if self.target == Target::Freestanding {
+            for hook in FREESTANDING_HOOKS {
+                self.emit_line(hook);
+            }
+            self.emit_line("");
+        }
+
+        // Type aliases (distinct or not) are plain typedefs in C
+        for decl in &ast.declarations {
+            if let Decl::TypeAlias(a) = decl {
+                let decl = self.c_decl(&a.ty, &a.name);
+                self.emit_line(&format!("typedef {};", decl));
+                if a.distinct {
+                    self.newtypes.insert(a.name.clone());
+                }
+            }
+        }
+        if ast.declarations.iter().any(|d| matches!(d, Decl::TypeAlias(_))) {
+            self.emit_line("");
+        }
+
         // Forward declarations for structs
         for decl in &ast.declarations {
             if let Decl::Struct(s) = decl {
                 self.emit_line(&format!("typedef struct {} {};", s.name, s.name));
+                let fields = s.fields.iter().map(|f| (f.name.clone(), f.ty.clone())).collect();
+                self.structs.insert(s.name.clone(), fields);
             }
         }
         if ast.declarations.iter().any(|d| matches!(d, Decl::Struct(_))) {
@@ -99,13 +223,29 @@ impl CodeGen {
         for decl in &ast.declarations {
             if let Decl::Function(f) = decl {
                 self.gen_fn_prototype(f);
+                self.fn_params.insert(f.name.clone(), f.params.iter().map(|p| p.ty.clone()).collect());
+                if let Some(ret) = &f.return_type {
+                    self.fn_returns.insert(f.name.clone(), ret.clone());
+                }
             }
         }
         if ast.declarations.iter().any(|d| matches!(d, Decl::Function(_))) {
             self.emit_line("");
         }
 
+        // Generate constants (after prototypes so they may call const fns)
+        for decl in &ast.declarations {
+            if let Decl::Const(c) = decl {
+                self.gen_const(c);
+                self.locals.insert(c.name.clone(), c.ty.clone());
+            }
+        }
+        if ast.declarations.iter().any(|d| matches!(d, Decl::Const(_))) {
+            self.emit_line("");
+        }
+
         // Generate function implementations
+        let impl_start = self.output.len();
         for decl in &ast.declarations {
             if let Decl::Function(f) = decl {
                 self.gen_function(f);
@@ -113,6 +253,16 @@ impl CodeGen {
             }
         }
 
+        // Closures are lifted ahead of the functions that reference them
+        if !self.lifted.is_empty() {
+            let lifted = self.lifted.concat();
+            self.output.insert_str(impl_start, &lifted);
+        }
+
+        if self.runtime() == Runtime::Neolyx {
+            self.output.insert_str(runtime_includes_at, "#include \"reox_nxrender_bridge.h\"  // NXRender integration\n");
+        }
+
         self.output.clone()
     }
 
@@ -120,20 +270,27 @@ impl CodeGen {
         self.emit_line(&format!("struct {} {{", s.name));
         self.indent();
         for field in &s.fields {
-            let c_type = self.type_to_c(&field.ty);
-            self.emit_line(&format!("{} {};", c_type, field.name));
+            let decl = self.c_decl(&field.ty, &field.name);
+            self.emit_line(&format!("{};", decl));
         }
         self.dedent();
         self.emit_line("};");
     }
 
+    fn gen_const(&mut self, c: &ConstDecl) {
+        let c_type = self.type_to_c(&c.ty);
+        self.emit(&format!("#define {} (({})(", c.name, c_type));
+        self.gen_expr(&c.value);
+        self.emit("))\n");
+    }
+
     fn gen_extern(&mut self, e: &ExternDecl) {
         let ret_type = e.return_type.as_ref()
             .map(|t| self.type_to_c(t))
             .unwrap_or_else(|| "void".to_string());
 
         let params: Vec<String> = e.params.iter()
-            .map(|p| format!("{} {}", self.type_to_c(&p.ty), p.name))
+            .map(|p| self.c_decl(&p.ty, &p.name))
             .collect();
 
         let params_str = if params.is_empty() {
@@ -151,7 +308,7 @@ impl CodeGen {
             .unwrap_or_else(|| "void".to_string());
 
         let params: Vec<String> = f.params.iter()
-            .map(|p| format!("{} {}", self.type_to_c(&p.ty), p.name))
+            .map(|p| self.c_decl(&p.ty, &p.name))
             .collect();
 
         let params_str = if params.is_empty() {
@@ -172,7 +329,7 @@ impl CodeGen {
             .unwrap_or_else(|| "void".to_string());
 
         let params: Vec<String> = f.params.iter()
-            .map(|p| format!("{} {}", self.type_to_c(&p.ty), p.name))
+            .map(|p| self.c_decl(&p.ty, &p.name))
             .collect();
 
         let params_str = if params.is_empty() {
@@ -181,17 +338,44 @@ impl CodeGen {
             params.join(", ")
         };
 
+        let outer_locals = self.locals.clone();
+        for p in &f.params {
+            self.locals.insert(p.name.clone(), p.ty.clone());
+        }
+        let outer_return = std::mem::replace(&mut self.return_type, f.return_type.clone());
+
         self.emit_line(&format!("{} {}({}) {{", ret_type, f.name, params_str));
         self.indent();
-        self.gen_block(&f.body);
-        
+        self.gen_body(&f.body);
+        self.dedent();
+        self.emit_line("}");
+        self.locals = outer_locals;
+        self.return_type = outer_return;
+    }
+
+    /// Emit a function body. Every `defer` gets a flag, declared at the top of
+    /// the function and set where the `defer` is reached, so cleanup only runs
+    /// for defers that were actually registered and runs once even when
+    /// registered inside a loop. Locals the block reads are snapshotted next
+    /// to the flag, since they may be out of scope by the time it runs.
+    fn gen_body(&mut self, body: &Block) {
+        let flags_at = self.output.len();
+        self.gen_block(body);
+
         // Emit any remaining deferred cleanup at function end (for void functions)
         if !self.defer_stack.is_empty() {
             self.emit_deferred_cleanup();
         }
-        
-        self.dedent();
-        self.emit_line("}");
+
+        let indent = "    ".repeat(self.indent);
+        let mut flags = String::new();
+        for d in &self.defer_stack {
+            flags += &format!("{}int {} = 0;\n", indent, d.flag);
+            for (_, tmp, ty) in &d.snapshots {
+                flags += &format!("{}{};\n", indent, self.c_decl(ty, tmp));
+            }
+        }
+        self.output.insert_str(flags_at, &flags);
     }
 
     fn gen_block(&mut self, block: &Block) {
@@ -203,9 +387,17 @@ impl CodeGen {
     fn gen_statement(&mut self, stmt: &Stmt) {
         match stmt {
             Stmt::Let(l) => self.gen_let(l),
+            Stmt::LetTuple(l) => self.gen_let_tuple(l),
             Stmt::Return(r) => self.gen_return(r),
             Stmt::If(i) => self.gen_if(i),
             Stmt::While(w) => self.gen_while(w),
+            Stmt::Loop(l) => {
+                self.emit_line("for (;;) {");
+                self.indent();
+                self.gen_block(&l.body);
+                self.dedent();
+                self.emit_line("}");
+            }
             Stmt::For(f) => self.gen_for(f),
             Stmt::Expr(e) => {
                 self.emit_indent();
@@ -226,6 +418,12 @@ impl CodeGen {
             Stmt::Defer(d) => self.gen_defer(d),
             Stmt::TryCatch(t) => self.gen_try_catch(t),
             Stmt::Throw(t) => self.gen_throw(t),
+            // GNU C nested function; the enclosing function's defers are kept aside
+            Stmt::FnDecl(f) => {
+                let outer_defers = std::mem::take(&mut self.defer_stack);
+                self.gen_function(f);
+                self.defer_stack = outer_defers;
+            }
         }
     }
     
@@ -243,18 +441,66 @@ impl CodeGen {
     }
     
     fn gen_defer(&mut self, d: &DeferStmt) {
-        // Push deferred block onto stack - will be emitted before returns
-        self.defer_stack.push(d.body.clone());
+        // Mark the defer as registered; the block is emitted before returns.
+        // `break`/`continue` leave the function running, so they emit nothing.
+        // The locals it reads keep their values from the first time it is reached.
+        let flag = format!("_rx_defer_{}", self.defer_count);
+        self.defer_count += 1;
+        let snapshots = self.defer_reads(&flag, &d.body);
+        if !snapshots.is_empty() {
+            self.emit_line(&format!("if (!{}) {{", flag));
+            self.indent();
+            for (name, tmp, _) in &snapshots {
+                self.emit_line(&format!("{} = {};", tmp, name));
+            }
+            self.dedent();
+            self.emit_line("}");
+        }
+        self.emit_line(&format!("{} = 1;", flag));
+        self.defer_stack.push(Deferred { flag, body: d.body.clone(), snapshots });
     }
-    
+
+    /// The known locals `body` reads, found by generating it once with every
+    /// local renamed and keeping the renames that were used
+    fn defer_reads(&mut self, flag: &str, body: &Block) -> Vec<(String, String, Type)> {
+        let saved_output = std::mem::take(&mut self.output);
+        let saved_lifted = self.lifted.len();
+        let saved_counts = (self.closure_count, self.widget_count, self.defer_count);
+        let saved_defers = std::mem::take(&mut self.defer_stack);
+        let saved_locals = self.locals.clone();
+        self.renames = self.locals.keys()
+            .map(|name| (name.clone(), format!("{}_{}", flag, name)))
+            .collect();
+
+        self.gen_block(body);
+
+        let renames = std::mem::take(&mut self.renames);
+        let mut reads: Vec<_> = std::mem::take(&mut self.renamed).into_iter()
+            .filter_map(|name| {
+                let ty = saved_locals.get(&name)?.clone();
+                Some((name.clone(), renames[&name].clone(), ty))
+            })
+            .collect();
+        reads.sort_by(|a, b| a.0.cmp(&b.0));
+        self.output = saved_output;
+        self.lifted.truncate(saved_lifted);
+        (self.closure_count, self.widget_count, self.defer_count) = saved_counts;
+        self.defer_stack = saved_defers;
+        self.locals = saved_locals;
+        reads
+    }
+
     fn emit_deferred_cleanup(&mut self) {
         // Emit all deferred blocks in reverse order (LIFO)
-        let blocks: Vec<Block> = self.defer_stack.iter().rev().cloned().collect();
-        for block in blocks {
+        let blocks: Vec<Deferred> = self.defer_stack.iter().rev().cloned().collect();
+        for d in blocks {
             self.emit_line("/* defer cleanup */");
-            self.emit_line("{");
+            self.emit_line(&format!("if ({}) {{", d.flag));
             self.indent();
-            self.gen_block(&block);
+            self.renames = d.snapshots.into_iter().map(|(name, tmp, _)| (name, tmp)).collect();
+            self.gen_block(&d.body);
+            self.renames.clear();
+            self.renamed.clear();
             self.dedent();
             self.emit_line("}");
         }
@@ -268,41 +514,75 @@ impl CodeGen {
         self.gen_block(&t.try_block);
         self.dedent();
         self.emit_line("}");
-        self.emit_line("/* catch block */");
-        self.emit_line("{");
-        self.indent();
-        if let Some(var) = &t.catch_var {
-            self.emit_line(&format!("/* error in {} */", var));
+        for clause in &t.catches {
+            self.emit_line("/* catch block */");
+            self.emit_line("{");
+            self.indent();
+            match (&clause.var, &clause.ty) {
+                (Some(var), Some(ty)) => self.emit_line(&format!("/* error in {}: {} */", var, ty)),
+                (Some(var), None) => self.emit_line(&format!("/* error in {} */", var)),
+                _ => {}
+            }
+            self.gen_block(&clause.block);
+            self.dedent();
+            self.emit_line("}");
+        }
+        if let Some(finally) = &t.finally_block {
+            self.emit_line("/* finally block */");
+            self.emit_line("{");
+            self.indent();
+            self.gen_block(finally);
+            self.dedent();
+            self.emit_line("}");
         }
-        self.gen_block(&t.catch_block);
-        self.dedent();
-        self.emit_line("}");
     }
     
     fn gen_throw(&mut self, t: &ThrowStmt) {
         // C doesn't have throw - emit as comment + abort for now
         self.emit_indent();
-        self.emit("/* throw */ abort(); /* ");
+        let abort = if self.target == Target::Freestanding { "reox_abort" } else { "abort" };
+        self.emit(&format!("/* throw */ {}(); /* ", abort));
         self.gen_expr(&t.value);
         self.emit(" */\n");
     }
 
     fn gen_let(&mut self, l: &LetStmt) {
-        let c_type = l.ty.as_ref()
-            .map(|t| self.type_to_c(t))
-            .unwrap_or_else(|| "auto".to_string()); // C23 auto or infer from init
+        self.renames.remove(&l.name); // Shadows a snapshotted local
+        let ty = l.ty.clone().or_else(|| l.init.as_ref().and_then(|e| self.static_type(e)));
+        if let Some(ty) = &ty {
+            self.locals.insert(l.name.clone(), ty.clone());
+        }
+        let decl = l.ty.as_ref()
+            .map(|t| self.c_decl(t, &l.name))
+            .unwrap_or_else(|| format!("auto {}", l.name)); // C23 auto or infer from init
 
         self.emit_indent();
-        self.emit(&format!("{} {}", c_type, l.name));
+        self.emit(&decl);
 
         if let Some(init) = &l.init {
             self.emit(" = ");
-            self.gen_expr(init);
+            self.gen_expr_as(ty.as_ref(), init);
         }
 
         self.emit(";\n");
     }
 
+    /// Tuples are anonymous structs with fields `_0`, `_1`, ...
+    fn gen_let_tuple(&mut self, l: &LetTupleStmt) {
+        let tmp = format!("_tuple_{}", l.names[0]);
+        let decl = l.ty.as_ref()
+            .map(|t| self.c_decl(t, &tmp))
+            .unwrap_or_else(|| format!("__auto_type {}", tmp));
+        self.emit_indent();
+        self.emit(&decl);
+        self.emit(" = ");
+        self.gen_expr(&l.init);
+        self.emit(";\n");
+        for (i, name) in l.names.iter().enumerate() {
+            self.emit_line(&format!("__auto_type {} = {}._{};", name, tmp, i));
+        }
+    }
+
     fn gen_return(&mut self, r: &ReturnStmt) {
         // Emit deferred cleanup before return (in reverse order)
         if !self.defer_stack.is_empty() {
@@ -312,7 +592,8 @@ impl CodeGen {
         self.emit_indent();
         if let Some(value) = &r.value {
             self.emit("return ");
-            self.gen_expr(value);
+            let ty = self.return_type.clone();
+            self.gen_expr_as(ty.as_ref(), value);
             self.emit(";\n");
         } else {
             self.emit("return;\n");
@@ -350,6 +631,8 @@ impl CodeGen {
     }
 
     fn gen_for(&mut self, f: &ForStmt) {
+        self.renames.remove(&f.var);
+        self.locals.insert(f.var.clone(), Type::Int);
         match &f.iterable {
             Expr::Range(start, end, _) => {
                 // Optimized C loop: for (int64_t i = start; i <= end; ++i)
@@ -364,6 +647,17 @@ impl CodeGen {
                 self.dedent();
                 self.emit_line("}");
             }
+            // A bare int `n` counts 0..n-1, so the bound is exclusive
+            iterable if matches!(self.static_type(iterable), Some(Type::Int | Type::FixedInt(_))) => {
+                self.emit_indent();
+                self.emit(&format!("for (int64_t {} = 0; {} < ", f.var, f.var));
+                self.gen_expr(iterable);
+                self.emit(&format!("; ++{}) {{\n", f.var));
+                self.indent();
+                self.gen_block(&f.body);
+                self.dedent();
+                self.emit_line("}");
+            }
             _ => {
                 // Default array iteration (simplified for C)
                 // Assuming iterable is an array pointer or similar convention
@@ -391,22 +685,121 @@ impl CodeGen {
     fn gen_expr(&mut self, expr: &Expr) {
         match expr {
             Expr::Literal(lit) => self.gen_literal(lit),
-            Expr::Identifier(name, _) => self.emit(name),
+            // An optional read as a value has been narrowed to non-nil
+            Expr::Identifier(name, _) if self.locals.get(name).is_some_and(is_heap_optional) => {
+                self.emit("(*");
+                self.gen_name(name);
+                self.emit(")");
+            }
+            Expr::Identifier(name, _) => self.gen_name(name),
+            Expr::Binary(left, BinOp::In, right, _) => {
+                // Membership has no C operator; defer to the runtime
+                self.emit("reox_contains(");
+                self.gen_expr(right);
+                self.emit(", ");
+                self.gen_expr(left);
+                self.emit(")");
+            }
+            Expr::Binary(left, BinOp::Add, right, _) if self.is_string(left) || self.is_string(right) => {
+                // `+` with a string side concatenates, formatting the other side
+                self.emit("reox_concat(");
+                self.gen_to_string(left);
+                self.emit(", ");
+                self.gen_to_string(right);
+                self.emit(")");
+            }
             Expr::Binary(left, op, right, _) => {
+                // C promotes narrow ints to int; cast back so the result wraps to the width
+                let width = self.fixed_width(expr);
+                if let Some(w) = width {
+                    self.emit(&format!("(({})", w.c_type()));
+                }
                 self.emit("(");
-                self.gen_expr(left);
-                self.emit(&format!(" {} ", self.binop_to_c(op)));
-                self.gen_expr(right);
+                if matches!(op, BinOp::Eq | BinOp::Ne) && [left, right].iter().any(|e| matches!(***e, Expr::Nil(_))) {
+                    // Nil checks compare the optional's pointer
+                    self.gen_pointer(left);
+                    self.emit(&format!(" {} ", self.binop_to_c(op)));
+                    self.gen_pointer(right);
+                } else {
+                    self.gen_expr(left);
+                    self.emit(&format!(" {} ", self.binop_to_c(op)));
+                    self.gen_expr(right);
+                }
                 self.emit(")");
+                if width.is_some() {
+                    self.emit(")");
+                }
             }
             Expr::Unary(op, operand, _) => {
+                let width = self.fixed_width(expr);
+                if let Some(w) = width {
+                    self.emit(&format!("(({})", w.c_type()));
+                }
                 self.emit(self.unaryop_to_c(op));
                 self.gen_expr(operand);
+                if width.is_some() {
+                    self.emit(")");
+                }
             }
             Expr::Call(callee, args, _) => {
+                // Fluent widget chains build the view in a GNU statement expression
+                if let Some(chain) = widget_chain::fold(expr) {
+                    self.uses_ui = true;
+                    let var = format!("_widget{}", self.widget_count);
+                    self.widget_count += 1;
+                    self.emit("({ ");
+                    for line in chain.to_c_code(&var).lines() {
+                        self.emit(line);
+                        self.emit(" ");
+                    }
+                    self.emit(&format!("{}; }})", var));
+                    return;
+                }
                 // Check for UI widget function calls
                 if let Expr::Identifier(name, _) = callee.as_ref() {
+                    if let (Some(width), [arg]) = (IntWidth::from_name(name), args.as_slice()) {
+                        self.emit(&format!("(({})(", width.c_type()));
+                        self.gen_expr(arg);
+                        self.emit("))");
+                        return;
+                    }
+                    if self.newtypes.contains(name) && args.len() == 1 {
+                        self.emit(&format!("(({})(", name));
+                        self.gen_expr(&args[0]);
+                        self.emit("))");
+                        return;
+                    }
+                    if UI_WIDGETS.contains(&name.as_str()) {
+                        self.uses_ui = true;
+                    }
                     match name.as_str() {
+                        // Freestanding builds have no libc; print through the runtime hooks
+                        "print" | "println" | "print_int" | "print_float" | "print_bool"
+                            if self.target == Target::Freestanding =>
+                        {
+                            self.emit(&format!("reox_{}(", name));
+                            for (i, arg) in args.iter().enumerate() {
+                                if i > 0 { self.emit(", "); }
+                                self.gen_expr(arg);
+                            }
+                            self.emit(")");
+                            return;
+                        }
+                        // The builtin formats through the runtime, as string `+` does
+                        "to_string" if args.len() == 1 && !self.fn_params.contains_key(name) => {
+                            self.gen_to_string(&args[0]);
+                            return;
+                        }
+                        // len() of a literal is known at compile time
+                        "len" if matches!(args.as_slice(), [Expr::ArrayLit(..)] | [Expr::Literal(Literal::String(..))]) => {
+                            let n = match &args[0] {
+                                Expr::ArrayLit(elems, _) => elems.len(),
+                                Expr::Literal(Literal::String(s, _)) => s.len(),
+                                _ => unreachable!(),
+                            };
+                            self.emit(&n.to_string());
+                            return;
+                        }
                         // UI Widget constructors -> emit reox FFI calls
                         "button" => {
                             self.emit("reox_button_create(");
@@ -496,19 +889,24 @@ impl CodeGen {
                     }
                 }
                 // Default: emit as regular function call
+                let params = match callee.as_ref() {
+                    Expr::Identifier(name, _) => self.fn_params.get(name).cloned(),
+                    _ => None,
+                };
                 self.gen_expr(callee);
                 self.emit("(");
                 for (i, arg) in args.iter().enumerate() {
                     if i > 0 {
                         self.emit(", ");
                     }
-                    self.gen_expr(arg);
+                    let ty = params.as_ref().and_then(|p| p.get(i));
+                    self.gen_expr_as(ty, arg);
                 }
                 self.emit(")");
             }
             Expr::Member(obj, field, _) => {
-                self.gen_expr(obj);
-                self.emit(&format!(".{}", field));
+                self.gen_pointer(obj);
+                self.emit(&format!("{}{}", self.member_access(obj), field));
             }
             Expr::Index(arr, idx, _) => {
                 self.gen_expr(arr);
@@ -517,22 +915,36 @@ impl CodeGen {
                 self.emit("]");
             }
             Expr::Assign(target, value, _) => {
-                self.gen_expr(target);
+                self.gen_pointer(target);
                 self.emit(" = ");
-                self.gen_expr(value);
+                let ty = self.static_type(target);
+                self.gen_expr_as(ty.as_ref(), value);
+            }
+            Expr::StructLit(name, fields, Some(base), _) => {
+                // GNU statement expression: copy the base, then overwrite the listed fields
+                self.emit(&format!("({{ {} _rx_update = ", name));
+                self.gen_expr(base);
+                self.emit("; ");
+                for (field_name, value) in fields {
+                    self.emit(&format!("_rx_update.{} = ", field_name));
+                    self.gen_expr(value);
+                    self.emit("; ");
+                }
+                self.emit("_rx_update; })");
             }
-            Expr::StructLit(name, fields, _) => {
+            Expr::StructLit(name, fields, None, _) => {
                 self.emit(&format!("({}){{", name));
                 for (i, (field_name, value)) in fields.iter().enumerate() {
                     if i > 0 {
                         self.emit(", ");
                     }
                     self.emit(&format!(".{} = ", field_name));
-                    self.gen_expr(value);
+                    let ty = self.field_type(name, field_name);
+                    self.gen_expr_as(ty.as_ref(), value);
                 }
                 self.emit("}");
             }
-            Expr::ArrayLit(elements, _) => {
+            Expr::Tuple(elements, _) | Expr::ArrayLit(elements, _) => {
                 self.emit("{");
                 for (i, elem) in elements.iter().enumerate() {
                     if i > 0 {
@@ -566,28 +978,35 @@ impl CodeGen {
                     }
                     
                     // Generate pattern condition
-                    match &arm.pattern {
-                        crate::parser::Pattern::Literal(lit) => {
-                            self.emit("_match_val == ");
-                            self.gen_literal(lit);
-                        }
-                        crate::parser::Pattern::Identifier(name) => {
-                            // Binding pattern - always matches
-                            self.emit("1 /* bind ");
-                            self.emit(name);
-                            self.emit(" */");
-                        }
-                        crate::parser::Pattern::Wildcard => {
-                            self.emit("1 /* wildcard */");
-                        }
-                    }
+                    self.gen_pattern_cond(&arm.pattern, "_match_val");
                     
                     self.emit(") {\n");
                     self.indent();
-                    self.emit_indent();
-                    self.emit("_match_result = ");
-                    self.gen_expr(&arm.body);
-                    self.emit(";\n");
+                    for (name, path) in arm.pattern.binding_paths() {
+                        let place: String = path.iter().map(|field| format!(".{}", field)).collect();
+                        self.emit_line(&format!("__auto_type {} = _match_val{};", name, place));
+                    }
+                    if let Expr::Block(block, _) = &arm.body {
+                        // Emit block arms inline so return/break reach the enclosing code
+                        let (last, init) = match block.statements.split_last() {
+                            Some((Stmt::Expr(e), init)) => (Some(e), init),
+                            _ => (None, &block.statements[..]),
+                        };
+                        for stmt in init {
+                            self.gen_statement(stmt);
+                        }
+                        if let Some(e) = last {
+                            self.emit_indent();
+                            self.emit("_match_result = ");
+                            self.gen_expr(e);
+                            self.emit(";\n");
+                        }
+                    } else {
+                        self.emit_indent();
+                        self.emit("_match_result = ");
+                        self.gen_expr(&arm.body);
+                        self.emit(";\n");
+                    }
                     self.dedent();
                 }
                 
@@ -601,6 +1020,14 @@ impl CodeGen {
                 self.emit("})");
             }
             // Swift/C++ style expressions
+            Expr::CompoundAssign(target, CompoundOp::AddEq, value, _) if self.is_string(target) => {
+                self.gen_expr(target);
+                self.emit(" = reox_concat(");
+                self.gen_expr(target);
+                self.emit(", ");
+                self.gen_to_string(value);
+                self.emit(")");
+            }
             Expr::CompoundAssign(target, op, value, _) => {
                 self.gen_expr(target);
                 self.emit(&format!(" {} ", self.compound_op_to_c(op)));
@@ -625,17 +1052,20 @@ impl CodeGen {
             Expr::NullCoalesce(left, right, _) => {
                 // C doesn't have ?? - use ternary conditional
                 self.emit("((");
-                self.gen_expr(left);
+                self.gen_pointer(left);
                 self.emit(") ? (");
-                self.gen_expr(left);
+                if self.static_type(left).is_some_and(|t| is_heap_optional(&t)) {
+                    self.emit("*");
+                }
+                self.gen_pointer(left);
                 self.emit(") : (");
                 self.gen_expr(right);
                 self.emit("))");
             }
             Expr::OptionalChain(obj, field, _) => {
                 // C doesn't have ?. - emit regular member access
-                self.gen_expr(obj);
-                self.emit(&format!(".{}", field));
+                self.gen_pointer(obj);
+                self.emit(&format!("{}{}", self.member_access(obj), field));
             }
             Expr::TrailingClosure(callee, body, _) => {
                 // Emit callee call, then the closure body as a block
@@ -650,6 +1080,19 @@ impl CodeGen {
             Expr::Nil(_) => {
                 self.emit("NULL");
             }
+            Expr::Block(block, _) => {
+                // GCC statement expression: value is the trailing expression
+                self.emit("({\n");
+                self.indent();
+                self.gen_block(block);
+                self.dedent();
+                self.emit_indent();
+                self.emit("})");
+            }
+            Expr::Closure(params, return_type, body, _) => {
+                let name = self.gen_closure(params, return_type, body);
+                self.emit(&name);
+            }
             Expr::Await(operand, _) => {
                 // Emit await as rx_await() runtime call
                 self.emit("rx_await(");
@@ -683,6 +1126,12 @@ impl CodeGen {
             Literal::Float(n, _) => self.emit(&format!("{:.15}", n)),
             Literal::String(s, _) => self.emit(&format!("\"{}\"", self.escape_string(s))),
             Literal::Bool(b, _) => self.emit(if *b { "true" } else { "false" }),
+            // Chars are code points (`uint32_t`), so anything but printable ASCII is numeric
+            Literal::Char(c, _) => match c {
+                '\'' | '\\' => self.emit(&format!("'\\{}'", c)),
+                ' '..='~' => self.emit(&format!("'{}'", c)),
+                _ => self.emit(&format!("0x{:X}", *c as u32)),
+            },
         }
     }
 
@@ -702,6 +1151,191 @@ impl CodeGen {
         result
     }
 
+    /// Lift a closure into a static C function and return its name.
+    /// Captured variables are not supported by the C backend.
+    fn gen_closure(&mut self, params: &[Param], return_type: &Option<Type>, body: &Block) -> String {
+        let name = format!("__rx_action_{}", self.closure_count);
+        self.closure_count += 1;
+
+        let saved_output = std::mem::take(&mut self.output);
+        let saved_indent = std::mem::replace(&mut self.indent, 0);
+        let saved_defers = std::mem::take(&mut self.defer_stack);
+        let outer_locals = self.locals.clone();
+        for p in params {
+            self.locals.insert(p.name.clone(), p.ty.clone());
+        }
+        let outer_return = std::mem::replace(&mut self.return_type, return_type.clone());
+
+        let ret_type = return_type.as_ref()
+            .map(|t| self.type_to_c(t))
+            .unwrap_or_else(|| "void".to_string());
+        let params: Vec<String> = params.iter()
+            .map(|p| self.c_decl(&p.ty, &p.name))
+            .collect();
+        let params_str = if params.is_empty() {
+            "void".to_string()
+        } else {
+            params.join(", ")
+        };
+
+        self.emit_line(&format!("static {} {}({}) {{", ret_type, name, params_str));
+        self.indent();
+        self.gen_body(body);
+        self.dedent();
+        self.emit_line("}");
+        self.emit_line("");
+
+        let lifted = std::mem::replace(&mut self.output, saved_output);
+        self.locals = outer_locals;
+        self.return_type = outer_return;
+        self.indent = saved_indent;
+        self.defer_stack = saved_defers;
+        self.lifted.push(lifted);
+        name
+    }
+
+    /// Emit `value` where a `ty` is expected. A struct value stored into a
+    /// `Box<T>` is copied to the heap, since boxes are pointers in C.
+    fn gen_expr_as(&mut self, ty: Option<&Type>, value: &Expr) {
+        let Some(inner) = ty.and_then(boxed_type) else {
+            return self.gen_expr(value);
+        };
+        let already_boxed = matches!(value, Expr::Nil(_))
+            || self.static_type(value).is_some_and(|t| boxed_type(&t).is_some());
+        if already_boxed {
+            return self.gen_pointer(value);
+        }
+        let c_type = self.type_to_c(inner);
+        self.emit(&format!("({{ {0}* _rx_box = rx_alloc(sizeof({0})); *_rx_box = ", c_type));
+        self.gen_expr(value);
+        self.emit("; _rx_box; })");
+    }
+
+    /// Emit a variable, or the snapshot a defer cleanup reads it through
+    fn gen_name(&mut self, name: &str) {
+        match self.renames.get(name).cloned() {
+            Some(tmp) => {
+                self.renamed.insert(name.to_string());
+                self.emit(&tmp);
+            }
+            None => self.emit(name),
+        }
+    }
+
+    /// Emit `expr` without dereferencing an optional variable, for nil
+    /// checks, member access and destinations that hold the pointer itself
+    fn gen_pointer(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Identifier(name, _) => self.gen_name(name),
+            _ => self.gen_expr(expr),
+        }
+    }
+
+    /// `->` for members of boxed values, `.` otherwise
+    fn member_access(&self, obj: &Expr) -> &'static str {
+        match self.static_type(obj) {
+            Some(ty) if boxed_type(&ty).is_some() => "->",
+            _ => ".",
+        }
+    }
+
+    /// Width of an arithmetic or bitwise expression on fixed-width ints
+    fn fixed_width(&self, expr: &Expr) -> Option<IntWidth> {
+        let operands = match expr {
+            Expr::Binary(l, BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod | BinOp::BitwiseAnd
+                | BinOp::BitwiseOr | BinOp::BitwiseXor | BinOp::ShiftLeft | BinOp::ShiftRight, r, _) => [Some(l), Some(r)],
+            Expr::Unary(UnaryOp::Neg | UnaryOp::BitwiseNot, x, _) => [Some(x), None],
+            _ => return None,
+        };
+        operands.into_iter().flatten().find_map(|e| match self.static_type(e) {
+            Some(Type::FixedInt(w)) => Some(w),
+            _ => None,
+        })
+    }
+
+    /// Declared type of `field` in struct `name`
+    fn field_type(&self, name: &str, field: &str) -> Option<Type> {
+        self.structs.get(name)?.iter().find(|(f, _)| f == field).map(|(_, ty)| ty.clone())
+    }
+
+    /// Emit `expr` as a C string, formatted as `to_string` would
+    fn gen_to_string(&mut self, expr: &Expr) {
+        let formatter = match self.static_type(expr) {
+            Some(Type::String) => return self.gen_expr(expr),
+            // C sees bools and chars as ints, so the generic formatter cannot tell them apart
+            Some(Type::Bool) => "reox_bool_to_string",
+            Some(Type::Named(name)) if name == "char" => "reox_char_to_string",
+            _ => "reox_to_string",
+        };
+        self.emit(&format!("{}(", formatter));
+        self.gen_expr(expr);
+        self.emit(")");
+    }
+
+    /// Whether `expr` is statically a string, so `+` on it concatenates
+    fn is_string(&self, expr: &Expr) -> bool {
+        match self.static_type(expr) {
+            Some(Type::String) => true,
+            Some(Type::Optional(inner)) => *inner == Type::String,
+            _ => false,
+        }
+    }
+
+    /// The REOX type of `expr` where it follows from declarations alone
+    fn static_type(&self, expr: &Expr) -> Option<Type> {
+        match expr {
+            Expr::Literal(Literal::Int(..)) => Some(Type::Int),
+            Expr::Literal(Literal::Float(..)) => Some(Type::Float),
+            Expr::Literal(Literal::String(..)) => Some(Type::String),
+            Expr::Literal(Literal::Bool(..)) => Some(Type::Bool),
+            Expr::Literal(Literal::Char(..)) => Some(Type::Named("char".to_string())),
+            Expr::Identifier(name, _) => self.locals.get(name).cloned(),
+            Expr::Binary(
+                _,
+                BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge | BinOp::And | BinOp::Or | BinOp::In,
+                _,
+                _,
+            ) => Some(Type::Bool),
+            Expr::Binary(left, BinOp::Add, right, _) if self.is_string(left) || self.is_string(right) => Some(Type::String),
+            Expr::Binary(..) | Expr::Unary(..) if self.fixed_width(expr).is_some() => self.fixed_width(expr).map(Type::FixedInt),
+            // A narrowed optional operand contributes its value's type
+            Expr::Binary(left, _, _, _) | Expr::Unary(_, left, _) => self.static_type(left).map(|t| match t {
+                Type::Optional(inner) if boxed_type(&inner).is_none() => *inner,
+                t => t,
+            }),
+            Expr::Call(callee, _, _) => match callee.as_ref() {
+                Expr::Identifier(name, _) if name == "to_string" && !self.fn_params.contains_key(name) => Some(Type::String),
+                Expr::Identifier(name, _) => self.fn_returns.get(name).cloned()
+                    .or_else(|| IntWidth::from_name(name).map(Type::FixedInt)),
+                _ => None,
+            },
+            Expr::Member(obj, field, _) | Expr::OptionalChain(obj, field, _) => {
+                let ty = self.static_type(obj)?;
+                match boxed_type(&ty).unwrap_or(&ty) {
+                    Type::Named(name) => self.field_type(name, field),
+                    _ => None,
+                }
+            }
+            Expr::Index(arr, _, _) => match self.static_type(arr)? {
+                Type::Array(inner) => Some(*inner),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Declare `name` with type `ty` (function types need C's pointer declarator syntax)
+    fn c_decl(&self, ty: &Type, name: &str) -> String {
+        match ty {
+            Type::Function(params, ret) => {
+                let params: Vec<String> = params.iter().map(|p| self.type_to_c(p)).collect();
+                let params_str = if params.is_empty() { "void".to_string() } else { params.join(", ") };
+                format!("{} (*{})({})", self.type_to_c(ret), name, params_str)
+            }
+            _ => format!("{} {}", self.type_to_c(ty), name),
+        }
+    }
+
     fn type_to_c(&self, ty: &Type) -> String {
         match ty {
             Type::Int => "int64_t".to_string(),
@@ -709,8 +1343,61 @@ impl CodeGen {
             Type::String => "const char*".to_string(),
             Type::Bool => "bool".to_string(),
             Type::Void => "void".to_string(),
+            Type::Named(name) if name == "char" => "uint32_t".to_string(),
             Type::Named(name) => name.clone(),
             Type::Array(inner) => format!("{}*", self.type_to_c(inner)),
+            Type::Function(..) => "void*".to_string(),
+            Type::Box(inner) => format!("{}*", self.type_to_c(inner)),
+            // A box is already a pointer, so an optional box is a nullable one
+            Type::Optional(inner) if matches!(**inner, Type::Box(_)) => self.type_to_c(inner),
+            // nil is NULL, so optionals are nullable pointers
+            Type::Optional(inner) => format!("{}*", self.type_to_c(inner)),
+            Type::FixedInt(w) => w.c_type().to_string(),
+            Type::Tuple(elems) => {
+                let fields: Vec<String> = elems.iter().enumerate()
+                    .map(|(i, e)| format!("{} _{};", self.type_to_c(e), i))
+                    .collect();
+                format!("struct {{ {} }}", fields.join(" "))
+            }
+        }
+    }
+
+    /// Emit the C condition for `pattern` matching the value at `subject`
+    fn gen_pattern_cond(&mut self, pattern: &crate::parser::Pattern, subject: &str) {
+        match pattern {
+            crate::parser::Pattern::Literal(lit) => {
+                self.emit(subject);
+                self.emit(" == ");
+                self.gen_literal(lit);
+            }
+            crate::parser::Pattern::Identifier(name) => {
+                // Binding pattern - always matches
+                self.emit("1 /* bind ");
+                self.emit(name);
+                self.emit(" */");
+            }
+            crate::parser::Pattern::Wildcard => {
+                self.emit("1 /* wildcard */");
+            }
+            crate::parser::Pattern::Range(lo, hi) => {
+                self.emit(&format!("({} >= {} && {} <= {})", subject, lo, subject, hi));
+            }
+            crate::parser::Pattern::Binding { inner, .. } => self.gen_pattern_cond(inner, subject),
+            // The struct type is checked statically, so only the fields are tested
+            crate::parser::Pattern::Struct { fields, .. } => {
+                if fields.is_empty() {
+                    self.emit("1");
+                    return;
+                }
+                self.emit("(");
+                for (i, (field, inner)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        self.emit(" && ");
+                    }
+                    self.gen_pattern_cond(inner, &format!("{}.{}", subject, field));
+                }
+                self.emit(")");
+            }
         }
     }
 
@@ -734,6 +1421,7 @@ impl CodeGen {
             BinOp::BitwiseXor => "^",
             BinOp::ShiftLeft => "<<",
             BinOp::ShiftRight => ">>",
+            BinOp::In => unreachable!("'in' is lowered to reox_contains"),
         }
     }
 
@@ -746,21 +1434,39 @@ impl CodeGen {
     }
 }
 
+/// The pointee of a `Box<T>`, `Box<T>?` or `T?`, each of which C holds as a `T*`
+fn boxed_type(ty: &Type) -> Option<&Type> {
+    match ty {
+        Type::Box(inner) => Some(inner),
+        Type::Optional(inner) => boxed_type(inner).or(Some(inner)),
+        _ => None,
+    }
+}
+
+/// An optional whose value lives behind its own heap pointer (`T?` for a non-box `T`)
+fn is_heap_optional(ty: &Type) -> bool {
+    matches!(ty, Type::Optional(inner) if boxed_type(inner).is_none())
+}
+
 impl Default for CodeGen {
     fn default() -> Self {
         Self::new()
     }
 }
 
-/// Generate C code from AST and write to file
-pub fn generate(ast: &Ast, output_path: &str) -> io::Result<()> {
-    let mut codegen = CodeGen::new();
+/// Generate C code from AST and write to file, returning the runtime it needs.
+/// With no `runtime` given, programs using UI widgets get the NeolyxOS runtime.
+pub fn generate(ast: &Ast, output_path: &str, target: Target, runtime: Option<Runtime>) -> io::Result<Runtime> {
+    let mut codegen = CodeGen::with_target(target);
+    if let Some(runtime) = runtime {
+        codegen = codegen.with_runtime(runtime);
+    }
     let c_code = codegen.generate(ast);
 
     let mut file = std::fs::File::create(output_path)?;
     file.write_all(c_code.as_bytes())?;
 
-    Ok(())
+    Ok(codegen.runtime())
 }
 
 #[cfg(test)]
@@ -769,6 +1475,24 @@ mod tests {
     use crate::lexer::tokenize;
     use crate::parser::parse;
 
+    /// Check generated C with gcc against the bundled runtime headers.
+    /// Returns without checking when gcc is not installed.
+    fn assert_gcc_accepts(c: &str, name: &str) {
+        if std::process::Command::new("gcc").arg("--version").output().is_err() {
+            return;
+        }
+        let path = std::env::temp_dir().join(format!("reox_{}_{}.c", name, std::process::id()));
+        std::fs::write(&path, c).unwrap();
+        let out = std::process::Command::new("gcc")
+            .args(["-fsyntax-only", "-Wno-main", "-I"])
+            .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/runtime"))
+            .arg(&path)
+            .output()
+            .unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(out.status.success(), "gcc rejected:\n{}\n{}", c, String::from_utf8_lossy(&out.stderr));
+    }
+
     #[test]
     fn test_simple_function() {
         let source = r#"
@@ -858,4 +1582,302 @@ mod tests {
 
         assert!(output.contains("while ((i < 10))"));
     }
+
+    #[test]
+    fn test_const_len_folded() {
+        let tokens = tokenize(r#"const N: int = len([1, 2, 3]);"#).unwrap();
+        let ast = parse(&tokens);
+        let code = CodeGen::new().generate(&ast);
+        assert!(code.contains("#define N ((int64_t)(3))"));
+    }
+
+    #[test]
+    fn test_const_fn_result_is_emitted_as_value() {
+        let source = r#"
+            const fn square(n: int) -> int {
+                return n * n;
+            }
+            const SIZE: int = square(4);
+        "#;
+        let ast = crate::typechecker::check_with(&parse(&tokenize(source).unwrap()), false).unwrap();
+        let code = CodeGen::new().generate(&ast);
+        assert!(code.contains("#define SIZE ((int64_t)(16))"), "{}", code);
+        // A constant expression, so C can size an array with it
+        assert_gcc_accepts(&format!("{}\nstatic int64_t table[SIZE];\n", code), "const_fn_size");
+    }
+
+    #[test]
+    fn test_closure_lifted_to_static_function() {
+        let source = r#"
+            fn apply(f: fn(int) -> int, x: int) -> int {
+                return f(x);
+            }
+            fn main() {
+                let y: int = apply(action(n: int) -> int { return n * 2; }, 21);
+            }
+        "#;
+        let tokens = tokenize(source).unwrap();
+        let ast = parse(&tokens);
+        let mut codegen = CodeGen::new();
+        let output = codegen.generate(&ast);
+
+        assert!(output.contains("int64_t apply(int64_t (*f)(int64_t), int64_t x)"));
+        assert!(output.contains("static int64_t __rx_action_0(int64_t n)"));
+        assert!(output.contains("apply(__rx_action_0, 21)"));
+        let lifted_at = output.find("static int64_t __rx_action_0").unwrap();
+        let main_at = output.find("void main(void) {").unwrap();
+        assert!(lifted_at < main_at);
+    }
+
+    #[test]
+    fn test_freestanding_target_uses_runtime_hooks() {
+        let source = r#"
+            fn main() {
+                println("hello");
+                print_int(42);
+            }
+        "#;
+        let tokens = tokenize(source).unwrap();
+        let ast = parse(&tokens);
+
+        let hosted = CodeGen::new().generate(&ast);
+        assert!(hosted.contains("#include <stdio.h>"));
+        assert!(hosted.contains("println(\"hello\")"));
+
+        let freestanding = CodeGen::with_target(Target::Freestanding).generate(&ast);
+        assert!(!freestanding.contains("#include <stdio.h>"));
+        assert!(!freestanding.contains("#include <stdlib.h>"));
+        assert!(freestanding.contains("extern void reox_println(const char* s);"));
+        assert!(freestanding.contains("reox_println(\"hello\")"));
+        assert!(freestanding.contains("reox_print_int(42)"));
+    }
+
+    #[test]
+    fn test_boxed_field_is_pointer() {
+        let tokens = tokenize("struct Node { value: int, next: Box<Node> }").unwrap();
+        let ast = parse(&tokens);
+        let output = CodeGen::new().generate(&ast);
+        assert!(output.contains("typedef struct Node Node;"));
+        assert!(output.contains("Node* next;"));
+    }
+
+    #[test]
+    fn test_linked_list_boxes_and_derefs_nodes() {
+        let source = r#"
+            struct Node { value: int, next: Box<Node>? }
+            fn push_front(value: int, list: Node) -> Node {
+                return Node { value: value, next: list };
+            }
+            fn second(list: Node) -> int {
+                let next: Box<Node>? = list.next;
+                if next != nil {
+                    return next.value;
+                }
+                return 0;
+            }
+            fn main() -> int {
+                let tail: Node = Node { value: 3, next: nil };
+                return second(push_front(1, push_front(2, tail)));
+            }
+        "#;
+        let ast = parse(&tokenize(source).unwrap());
+        let output = CodeGen::new().generate(&ast);
+        assert!(output.contains("Node* next;"));
+        assert!(output.contains(".next = ({ Node* _rx_box = rx_alloc(sizeof(Node)); *_rx_box = list; _rx_box; })"));
+        assert!(output.contains("(Node){.value = 3, .next = NULL}"));
+        assert!(output.contains("return next->value;"));
+        assert_gcc_accepts(&output, "linked_list");
+    }
+
+    #[test]
+    fn test_optional_is_a_nullable_pointer() {
+        let source = r#"
+            fn area(w: int?) -> int {
+                guard w != nil else { return 0; }
+                return w * w;
+            }
+            fn half(n: int) -> int? {
+                if n % 2 == 1 { return nil; }
+                return n / 2;
+            }
+            fn main() -> int {
+                let h: int? = half(8);
+                return area(3) + area(nil) + area(h) + (half(3) ?? 1);
+            }
+        "#;
+        let ast = parse(&tokenize(source).unwrap());
+        let output = CodeGen::new().generate(&ast);
+        assert!(output.contains("int64_t area(int64_t* w)"));
+        assert!(output.contains("if (!((w != NULL))) {"));
+        assert!(output.contains("return ((*w) * (*w));"));
+        assert!(output.contains("return ({ int64_t* _rx_box = rx_alloc(sizeof(int64_t)); *_rx_box = (n / 2); _rx_box; });"));
+        assert!(output.contains("area(({ int64_t* _rx_box = rx_alloc(sizeof(int64_t)); *_rx_box = 3; _rx_box; }))"));
+        assert!(output.contains("area(NULL)"));
+        assert!(output.contains("area(h)"));
+        assert_gcc_accepts(&output, "optional");
+    }
+
+    #[test]
+    fn test_button_method_chain_uses_builder() {
+        let source = r#"
+            fn main() {
+                let ok = button("OK").corner_radius(4.0).on_click(handle_ok);
+            }
+        "#;
+        let tokens = tokenize(source).unwrap();
+        let ast = parse(&tokens);
+        let output = CodeGen::new().generate(&ast);
+
+        assert!(output.contains("rx_button_view* _widget0 = button_view_new(\"OK\");"));
+        assert!(output.contains("_widget0->corner_radius = 4;"));
+        assert!(output.contains("_widget0->on_click = handle_ok;"));
+    }
+
+    #[test]
+    fn test_fixed_width_ints_map_to_stdint() {
+        let source = r#"
+            struct Pixel { r: u8, g: u8, b: u8, a: u8 }
+            fn shrink(x: int) -> i16 {
+                return i16(x);
+            }
+            fn brighten(p: Pixel) -> int {
+                return p.r + 1;
+            }
+        "#;
+        let tokens = tokenize(source).unwrap();
+        let ast = parse(&tokens);
+        let output = CodeGen::new().generate(&ast);
+        assert!(output.contains("uint8_t r;"));
+        assert!(output.contains("int16_t shrink(int64_t x)"));
+        assert!(output.contains("((int16_t)(x))"));
+        // Promoted to int by C, then wrapped back to 8 bits like the interpreter does
+        assert!(output.contains("return ((uint8_t)(p.r + 1));"));
+    }
+
+    #[test]
+    fn test_defer_in_loop_is_flagged() {
+        let source = r#"
+            fn main() {
+                while true {
+                    defer { cleanup(); }
+                    break;
+                }
+            }
+        "#;
+        let tokens = tokenize(source).unwrap();
+        let ast = parse(&tokens);
+        let output = CodeGen::new().generate(&ast);
+
+        // Declared at function scope, set when reached, checked once at exit
+        assert!(output.contains("void main(void) {\n    int _rx_defer_0 = 0;\n"));
+        assert!(output.contains("        _rx_defer_0 = 1;\n        break;"));
+        assert!(output.contains("    if (_rx_defer_0) {\n        cleanup();"));
+    }
+
+    #[test]
+    fn test_defer_snapshots_loop_locals() {
+        let source = r#"
+            extern fn close(fd: int);
+            fn main() {
+                for i in 3 {
+                    let f: int = i * 10;
+                    defer { close(f); }
+                    let f2: int = f;
+                }
+            }
+        "#;
+        let tokens = tokenize(source).unwrap();
+        let ast = parse(&tokens);
+        let output = CodeGen::new().generate(&ast);
+
+        // `f` is gone by the time the function exits; its value at registration is kept
+        assert!(output.contains("    int _rx_defer_0 = 0;\n    int64_t _rx_defer_0_f;\n"));
+        assert!(output.contains("if (!_rx_defer_0) {\n            _rx_defer_0_f = f;\n        }\n        _rx_defer_0 = 1;\n"));
+        assert!(output.contains("    if (_rx_defer_0) {\n        close(_rx_defer_0_f);"));
+        assert!(output.contains("int64_t f2 = f;"));
+        assert_gcc_accepts(&output, "defer_snapshot");
+    }
+
+    #[test]
+    fn test_runtime_selects_ui_includes() {
+        let ast = parse(&tokenize("fn main() { let n = 1; }").unwrap());
+        let native = CodeGen::new().with_runtime(Runtime::Native).generate(&ast);
+        assert!(native.contains("#include \"reox_runtime.h\""));
+        assert!(!native.contains("reox_nxrender_bridge.h"));
+
+        let neolyx = CodeGen::new().with_runtime(Runtime::Neolyx).generate(&ast);
+        assert!(neolyx.contains("#include \"reox_nxrender_bridge.h\""));
+
+        // Without an explicit runtime, only UI programs get the NeolyxOS bridge
+        assert!(!CodeGen::new().generate(&ast).contains("reox_nxrender_bridge.h"));
+        let ui = parse(&tokenize("fn main() { let b = button(\"OK\"); }").unwrap());
+        let mut codegen = CodeGen::new();
+        assert!(codegen.generate(&ui).contains("reox_nxrender_bridge.h"));
+        assert_eq!(codegen.runtime(), Runtime::Neolyx);
+    }
+
+    #[test]
+    fn test_struct_update_copies_base() {
+        let source = r#"
+            struct Point { x: int, y: int }
+            fn moved(p: Point) -> Point {
+                return Point { ..p, x: 9 };
+            }
+        "#;
+        let ast = parse(&tokenize(source).unwrap());
+        let output = CodeGen::new().generate(&ast);
+        assert!(output.contains("({ Point _rx_update = p; _rx_update.x = 9; _rx_update; })"));
+    }
+
+    #[test]
+    fn test_for_over_int_counts_up_to_bound() {
+        let source = r#"
+            fn total(n: int) -> int {
+                let mut sum: int = 0;
+                for i in n {
+                    sum += i;
+                }
+                return sum;
+            }
+        "#;
+        let ast = parse(&tokenize(source).unwrap());
+        let output = CodeGen::new().generate(&ast);
+        assert!(output.contains("for (int64_t i = 0; i < n; ++i) {"));
+        assert_gcc_accepts(&output, "for_int");
+    }
+
+    #[test]
+    fn test_string_plus_formats_the_other_side() {
+        let source = r#"
+            fn describe(n: int, ok: bool) -> string {
+                let s: string = "x=" + n;
+                let mut t: string = s + ", ok=" + ok;
+                t += 2.5;
+                return t;
+            }
+        "#;
+        let ast = parse(&tokenize(source).unwrap());
+        let output = CodeGen::new().generate(&ast);
+        assert!(output.contains("const char* s = reox_concat(\"x=\", reox_to_string(n));"));
+        assert!(output.contains("reox_concat(reox_concat(s, \", ok=\"), reox_bool_to_string(ok))"));
+        assert!(output.contains("t = reox_concat(t, reox_to_string(2.500000000000000))"));
+        assert!(!output.contains("\"x=\" + n"));
+        assert_gcc_accepts(&output, "string_plus");
+    }
+
+    #[test]
+    fn test_interpolation_formats_each_expression() {
+        let source = r#"
+            struct Point { x: int, y: float }
+            fn show(p: Point, label: string) -> string {
+                return "${label}: v=${p.x} at ${p.y}!";
+            }
+        "#;
+        let ast = parse(&tokenize(source).unwrap());
+        let output = CodeGen::new().generate(&ast);
+        assert!(output.contains(
+            "return reox_concat(reox_concat(reox_concat(reox_concat(reox_concat(label, \": v=\"), reox_to_string(p.x)), \" at \"), reox_to_string(p.y)), \"!\");"
+        ), "{}", output);
+        assert_gcc_accepts(&output, "interpolation");
+    }
 }
@@ -0,0 +1,92 @@
+// REOX Compiler - Widget Builder Chains
+// Folds fluent widget calls like `button("OK").corner_radius(8.0)` into the
+// stdlib UI builders so codegen can emit their C setup code
+// Zero external dependencies
+
+use crate::parser::{Expr, Literal};
+use crate::stdlib::ui::{ButtonBuilder, TextBuilder};
+
+/// A widget constructor followed by builder method calls
+#[derive(Debug, Clone)]
+pub enum WidgetChain {
+    Button(ButtonBuilder),
+    Text(TextBuilder),
+}
+
+impl WidgetChain {
+    /// C statements declaring `var_name` and applying every chained setting
+    pub fn to_c_code(&self, var_name: &str) -> String {
+        match self {
+            WidgetChain::Button(b) => b.to_c_code(var_name),
+            WidgetChain::Text(t) => t.to_c_code(var_name),
+        }
+    }
+}
+
+/// Fold `expr` into a builder if it is a widget constructor with at least one
+/// chained method call and every argument is known at compile time
+pub fn fold(expr: &Expr) -> Option<WidgetChain> {
+    match expr {
+        Expr::Call(callee, _, _) if matches!(callee.as_ref(), Expr::Member(..)) => fold_call(expr),
+        _ => None,
+    }
+}
+
+fn fold_call(expr: &Expr) -> Option<WidgetChain> {
+    let Expr::Call(callee, args, _) = expr else { return None };
+    match callee.as_ref() {
+        Expr::Identifier(name, _) => {
+            let [Expr::Literal(Literal::String(label, _))] = args.as_slice() else { return None };
+            match name.as_str() {
+                "button" => Some(WidgetChain::Button(ButtonBuilder::new(label))),
+                "text" | "label" => Some(WidgetChain::Text(TextBuilder::new(label))),
+                _ => None,
+            }
+        }
+        Expr::Member(obj, method, _) => {
+            let chain = fold_call(obj)?;
+            apply(chain, method, args)
+        }
+        _ => None,
+    }
+}
+
+fn apply(chain: WidgetChain, method: &str, args: &[Expr]) -> Option<WidgetChain> {
+    match (chain, method, args) {
+        (WidgetChain::Button(b), "corner_radius", [r]) => Some(WidgetChain::Button(b.corner_radius(number(r)?))),
+        (WidgetChain::Button(b), "font_size", [s]) => Some(WidgetChain::Button(b.font_size(number(s)?))),
+        (WidgetChain::Button(b), "on_click", [h]) => Some(WidgetChain::Button(b.on_click(&handler(h)?))),
+        (WidgetChain::Button(b), "disabled", []) => Some(WidgetChain::Button(b.disabled())),
+        (WidgetChain::Text(t), "font_size", [s]) => Some(WidgetChain::Text(t.font_size(number(s)?))),
+        (WidgetChain::Text(t), "bold", []) => Some(WidgetChain::Text(t.bold())),
+        (WidgetChain::Text(t), "center", []) => Some(WidgetChain::Text(t.center())),
+        (WidgetChain::Text(t), "color", [r, g, b, a]) => {
+            Some(WidgetChain::Text(t.color(channel(r)?, channel(g)?, channel(b)?, channel(a)?)))
+        }
+        _ => None,
+    }
+}
+
+fn number(expr: &Expr) -> Option<f64> {
+    match expr {
+        Expr::Literal(Literal::Int(n, _)) => Some(*n as f64),
+        Expr::Literal(Literal::Float(f, _)) => Some(*f),
+        _ => None,
+    }
+}
+
+fn channel(expr: &Expr) -> Option<u8> {
+    match expr {
+        Expr::Literal(Literal::Int(n, _)) => u8::try_from(*n).ok(),
+        _ => None,
+    }
+}
+
+/// Click handlers name a C function, either directly or as a string
+fn handler(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Identifier(name, _) => Some(name.clone()),
+        Expr::Literal(Literal::String(name, _)) => Some(name.clone()),
+        _ => None,
+    }
+}
@@ -0,0 +1,100 @@
+// REOX Compiler - Documentation Generator
+// Renders `///` doc comments as Markdown for `reoxc doc <input>`
+// Zero external dependencies
+
+use crate::lexer::{self, TokenKind};
+use crate::parser::{self, Decl, FnDecl, Parser, Program, StructDecl};
+
+/// Parse `source` and render its functions and structs as Markdown
+pub fn generate(source: &str, title: &str) -> Result<String, String> {
+    let trivia = lexer::tokenize_with_trivia(source).map_err(|e| e.display())?;
+    let tokens: Vec<_> = trivia
+        .iter()
+        .filter(|t| !matches!(t.kind, TokenKind::LineComment(_) | TokenKind::BlockComment(_)))
+        .cloned()
+        .collect();
+    let mut program = Parser::new(&tokens).parse_program().map_err(|e| e.display())?;
+    parser::attach_doc_comments(&mut program, &trivia);
+    Ok(render(&program, title))
+}
+
+/// One `##` section per top-level function and struct, in source order
+pub fn render(program: &Program, title: &str) -> String {
+    let mut out = format!("# {}\n", title);
+    for decl in &program.declarations {
+        let (kind, name, signature, doc) = match decl {
+            Decl::Function(f) => ("fn", &f.name, fn_signature(f), &f.doc),
+            Decl::Struct(s) => ("struct", &s.name, struct_signature(s), &s.doc),
+            _ => continue,
+        };
+        out.push_str(&format!("\n## {} `{}`\n\n```reox\n{}\n```\n", kind, name, signature));
+        if let Some(doc) = doc {
+            out.push_str(&format!("\n{}\n", doc));
+        }
+    }
+    out
+}
+
+fn fn_signature(f: &FnDecl) -> String {
+    let params: Vec<String> = f.params.iter().map(|p| format!("{}: {}", p.name, p.ty)).collect();
+    let mut sig = String::new();
+    if f.is_const {
+        sig.push_str("const ");
+    }
+    if f.is_async {
+        sig.push_str("async ");
+    }
+    sig.push_str(&format!("fn {}({})", f.name, params.join(", ")));
+    if let Some(ret) = &f.return_type {
+        sig.push_str(&format!(" -> {}", ret));
+    }
+    sig
+}
+
+fn struct_signature(s: &StructDecl) -> String {
+    let mut sig = format!("struct {} {{\n", s.name);
+    for field in &s.fields {
+        sig.push_str(&format!("    {}: {},\n", field.name, field.ty));
+    }
+    sig.push('}');
+    sig
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_documented_function_section() {
+        let src = "\
+/// Adds two numbers.
+/// Overflow wraps.
+fn add(a: int, b: int) -> int { return a + b; }
+
+// not a doc comment
+fn helper(xs: [string]) { }
+";
+        let md = generate(src, "math").unwrap();
+        assert!(md.starts_with("# math\n"));
+        assert!(md.contains(
+            "## fn `add`\n\n```reox\nfn add(a: int, b: int) -> int\n```\n\nAdds two numbers.\nOverflow wraps.\n"
+        ));
+        assert!(md.contains("## fn `helper`\n\n```reox\nfn helper(xs: [string])\n```\n"));
+        assert!(!md.contains("not a doc comment"));
+    }
+
+    #[test]
+    fn test_struct_and_attributed_function() {
+        let src = "\
+/// A point in 2D space
+struct Point { x: float, y: float? }
+
+/// Old entry point
+@deprecated(\"use run\")
+fn start() { }
+";
+        let md = generate(src, "geo").unwrap();
+        assert!(md.contains("struct Point {\n    x: float,\n    y: float?,\n}\n```\n\nA point in 2D space\n"));
+        assert!(md.contains("fn start()\n```\n\nOld entry point\n"));
+    }
+}
@@ -0,0 +1,162 @@
+// REOX Compiler - Error Code Explanations
+// Long-form help for `reoxc explain <code>`
+// Zero external dependencies
+
+/// Explanation text for every known error code, in code order
+pub static EXPLANATIONS: &[(&str, &str)] = &[
+    ("E0001", "\
+A literal or character could not be tokenized.
+
+The lexer rejects characters that do not start any token and numeric
+literals whose digits do not fit their base.
+
+Erroneous code example:
+
+    let mask = 0xGG;
+
+Hexadecimal literals only accept the digits 0-9 and a-f. Fix the literal:
+
+    let mask = 0xFF;
+"),
+    ("E0002", "\
+A name was used that is not defined in any enclosing scope.
+
+Erroneous code example:
+
+    fn main() {
+        let total: int = count + 1;
+    }
+
+Declare the variable (or parameter) before using it:
+
+    fn main() {
+        let count: int = 0;
+        let total: int = count + 1;
+    }
+"),
+    ("E0003", "\
+A value's type does not match the type that was expected.
+
+Erroneous code example:
+
+    let name: string = 42;
+
+Either change the annotation or convert the value:
+
+    let name: string = to_string(42);
+"),
+    ("E0004", "\
+A variable was read before it was definitely assigned.
+
+A `let` without an initializer must be assigned on every path before use.
+
+Erroneous code example:
+
+    let x: int;
+    if ready {
+        x = 1;
+    }
+    print_int(x);
+
+Assign the variable on every branch, or give it an initializer:
+
+    let x: int = 0;
+"),
+    ("E0005", "\
+A function was called with the wrong number of arguments.
+
+Erroneous code example:
+
+    fn add(a: int, b: int) -> int {
+        return a + b;
+    }
+
+    let n: int = add(1);
+
+Pass one argument per parameter:
+
+    let n: int = add(1, 2);
+"),
+    ("E0006", "\
+A field was accessed that the struct does not declare.
+
+Erroneous code example:
+
+    struct Point { x: int, y: int }
+
+    fn main() {
+        let p = Point { x: 1, y: 2 };
+        let z: int = p.z;
+    }
+
+Only `x` and `y` exist on `Point`; use one of the declared fields.
+"),
+    ("E0007", "\
+A condition did not have type `bool`.
+
+`if`, `while` and `guard` conditions are not converted implicitly.
+
+Erroneous code example:
+
+    let n: int = 3;
+    if n {
+        print(\"nonzero\");
+    }
+
+Compare explicitly:
+
+    if n != 0 {
+        print(\"nonzero\");
+    }
+"),
+    ("E0008", "\
+A `const` initializer could not be evaluated at compile time.
+
+Constants may only use literals, other constants, operators, `len` and
+calls to `const fn` functions.
+
+Erroneous code example:
+
+    fn load() -> int { return 4; }
+    const SIZE: int = load();
+
+Mark the function `const fn` (its body must itself be constant):
+
+    const fn load() -> int { return 4; }
+    const SIZE: int = load();
+"),
+];
+
+/// Look up the explanation for an error code such as `E0002`
+pub fn explain(code: &str) -> Result<&'static str, String> {
+    let code = code.trim().to_ascii_uppercase();
+    EXPLANATIONS
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map(|(_, text)| *text)
+        .ok_or_else(|| format!("no such error code: {}", code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain_known_code() {
+        let text = explain("E0002").unwrap();
+        assert!(text.contains("not defined"));
+        assert!(!explain("e0003").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_explain_unknown_code() {
+        assert_eq!(explain("E9999").unwrap_err(), "no such error code: E9999");
+    }
+
+    #[test]
+    fn test_codes_are_sorted_and_unique() {
+        for pair in EXPLANATIONS.windows(2) {
+            assert!(pair[0].0 < pair[1].0);
+        }
+    }
+}
@@ -0,0 +1,329 @@
+// REOX Compiler - Source Formatter
+// Re-indents REOX source for `reoxc fmt`, preserving comments
+// Zero external dependencies
+
+use crate::lexer::{self, LexError, Token, TokenKind};
+
+/// How one indentation level is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    Spaces(usize),
+    Tabs,
+}
+
+/// Where the `{` opening a block goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BraceStyle {
+    /// `fn main() {`
+    #[default]
+    SameLine,
+    /// `fn main()` with `{` on its own line
+    NextLine,
+}
+
+impl BraceStyle {
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s {
+            "same-line" => Some(BraceStyle::SameLine),
+            "next-line" => Some(BraceStyle::NextLine),
+            _ => None,
+        }
+    }
+}
+
+/// Formatter settings; the default is 4-space indents with same-line braces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatOptions {
+    pub indent: IndentStyle,
+    pub brace_style: BraceStyle,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self { indent: IndentStyle::Spaces(4), brace_style: BraceStyle::SameLine }
+    }
+}
+
+impl FormatOptions {
+    /// Parse an `indent` setting: a space count or `tab`
+    pub fn parse_indent(s: &str) -> Option<IndentStyle> {
+        match s {
+            "tab" | "tabs" => Some(IndentStyle::Tabs),
+            n => n.parse().ok().filter(|&n| n > 0 && n <= 16).map(IndentStyle::Spaces),
+        }
+    }
+
+    /// Apply the `indent` and `brace_style` keys of the `[fmt]` table in `reox.toml`
+    pub fn apply_config(&mut self, toml: &str) -> Result<(), String> {
+        let mut in_fmt = false;
+        for (i, line) in toml.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.starts_with('[') {
+                in_fmt = line == "[fmt]";
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else { continue };
+            if !in_fmt {
+                continue;
+            }
+            let value = value.trim().trim_matches('"');
+            let invalid = || format!("reox.toml:{}: invalid value '{}' for fmt.{}", i + 1, value, key.trim());
+            match key.trim() {
+                "indent" => self.indent = Self::parse_indent(value).ok_or_else(invalid)?,
+                "brace_style" => self.brace_style = BraceStyle::from_name(value).ok_or_else(invalid)?,
+                other => return Err(format!("reox.toml:{}: unknown fmt option '{}'", i + 1, other)),
+            }
+        }
+        Ok(())
+    }
+
+    fn indent_unit(&self) -> String {
+        match self.indent {
+            IndentStyle::Spaces(n) => " ".repeat(n),
+            IndentStyle::Tabs => "\t".to_string(),
+        }
+    }
+}
+
+/// A source line with the tokens that start on it
+struct Line<'a> {
+    /// Trimmed text, or the raw text of a verbatim line
+    text: String,
+    tokens: Vec<&'a Token>,
+    /// Inside a multi-line block comment; emitted untouched
+    verbatim: bool,
+}
+
+impl Line<'_> {
+    fn first(&self) -> Option<&TokenKind> {
+        self.tokens.first().map(|t| &t.kind)
+    }
+
+    fn is_lone_brace(&self) -> bool {
+        self.tokens.len() == 1 && self.tokens[0].kind == TokenKind::LBrace
+    }
+}
+
+/// Whether a `{` at the end of `line` opens a block rather than a literal
+fn opens_block(line: &Line) -> bool {
+    let starts_block = matches!(
+        line.first(),
+        Some(
+            TokenKind::Fn | TokenKind::Async | TokenKind::Const | TokenKind::If | TokenKind::Else
+                | TokenKind::While | TokenKind::Loop | TokenKind::For | TokenKind::Match
+                | TokenKind::Struct | TokenKind::Kind | TokenKind::Guard | TokenKind::Defer
+                | TokenKind::Extern | TokenKind::Try | TokenKind::Catch | TokenKind::RBrace
+        )
+    );
+    let arm_block = line.tokens.len() >= 2 && line.tokens[line.tokens.len() - 2].kind == TokenKind::FatArrow;
+    starts_block || arm_block
+}
+
+/// Format `source` according to `options`
+pub fn format_source(source: &str, options: &FormatOptions) -> Result<String, LexError> {
+    let tokens = lexer::tokenize_with_trivia(source)?;
+    let mut lines: Vec<Line> = source
+        .lines()
+        .map(|text| Line { text: text.trim().to_string(), tokens: Vec::new(), verbatim: false })
+        .collect();
+    for token in tokens.iter().filter(|t| t.kind != TokenKind::Eof) {
+        let first = token.span.line as usize - 1;
+        let extra = source[token.span.start..token.span.end].matches('\n').count();
+        lines[first].tokens.push(token);
+        for (line, raw) in lines[first + 1..=first + extra].iter_mut().zip(source.lines().skip(first + 1)) {
+            line.text = raw.to_string();
+            line.verbatim = true;
+        }
+    }
+
+    let lines = match options.brace_style {
+        BraceStyle::SameLine => join_braces(lines),
+        BraceStyle::NextLine => split_braces(lines, source),
+    };
+
+    let unit = options.indent_unit();
+    let mut out = String::new();
+    let mut depth: usize = 0;
+    for line in &lines {
+        if line.verbatim {
+            out.push_str(&line.text);
+            out.push('\n');
+        } else if line.text.is_empty() {
+            out.push('\n');
+        } else {
+            let leading_closers = line.tokens.iter().take_while(|t| is_closer(&t.kind)).count();
+            out.push_str(&unit.repeat(depth.saturating_sub(leading_closers)));
+            out.push_str(&line.text);
+            out.push('\n');
+        }
+        for token in &line.tokens {
+            if is_opener(&token.kind) {
+                depth += 1;
+            } else if is_closer(&token.kind) {
+                depth = depth.saturating_sub(1);
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn is_opener(kind: &TokenKind) -> bool {
+    matches!(kind, TokenKind::LBrace | TokenKind::LParen | TokenKind::LBracket)
+}
+
+fn is_closer(kind: &TokenKind) -> bool {
+    matches!(kind, TokenKind::RBrace | TokenKind::RParen | TokenKind::RBracket)
+}
+
+/// Pull lone `{` lines up onto the block head, and `else`/`catch` up onto its `}`
+fn join_braces(lines: Vec<Line>) -> Vec<Line> {
+    let mut out: Vec<Line> = Vec::new();
+    for line in lines {
+        let prev = out.iter_mut().rev().find(|l| !l.text.is_empty());
+        let joins = match &prev {
+            Some(prev) if !prev.verbatim && !line.verbatim => {
+                (line.is_lone_brace() && opens_block_head(prev))
+                    || (matches!(line.first(), Some(TokenKind::Else | TokenKind::Catch))
+                        && prev.tokens.len() == 1
+                        && prev.tokens[0].kind == TokenKind::RBrace)
+            }
+            _ => false,
+        };
+        match prev {
+            Some(prev) if joins => {
+                prev.text = format!("{} {}", prev.text, line.text);
+                prev.tokens.extend(line.tokens);
+                while out.last().is_some_and(|l| l.text.is_empty()) {
+                    out.pop();
+                }
+            }
+            _ => out.push(line),
+        }
+    }
+    out
+}
+
+/// Whether `line` is a block head still waiting for its `{`
+fn opens_block_head(line: &Line) -> bool {
+    !line.tokens.iter().any(|t| t.kind == TokenKind::LBrace)
+        && !matches!(line.tokens.last().map(|t| &t.kind), Some(TokenKind::Semicolon | TokenKind::LineComment(_)))
+        && (opens_block(line) || line.tokens.last().map(|t| &t.kind) == Some(&TokenKind::FatArrow))
+}
+
+/// Move a block-opening `{` onto its own line, and `else`/`catch` off its `}`
+fn split_braces<'a>(lines: Vec<Line<'a>>, source: &str) -> Vec<Line<'a>> {
+    let mut out = Vec::new();
+    for mut line in lines {
+        if line.verbatim {
+            out.push(line);
+            continue;
+        }
+        if line.tokens.len() >= 2
+            && line.tokens[0].kind == TokenKind::RBrace
+            && matches!(line.tokens[1].kind, TokenKind::Else | TokenKind::Catch)
+        {
+            let rest = line.tokens.split_off(1);
+            let text = source[rest[0].span.start..].lines().next().unwrap_or("").trim().to_string();
+            out.push(Line { text: "}".to_string(), tokens: line.tokens, verbatim: false });
+            line = Line { text, tokens: rest, verbatim: false };
+        }
+        let ends_in_brace = line.tokens.len() >= 2 && line.tokens.last().map(|t| &t.kind) == Some(&TokenKind::LBrace);
+        if ends_in_brace && opens_block(&line) {
+            let brace = line.tokens.pop().expect("line has a brace");
+            let head_end = line.tokens.last().map_or(brace.span.start, |t| t.span.end);
+            let head_start = line.tokens[0].span.start;
+            out.push(Line {
+                text: source[head_start..head_end].trim().to_string(),
+                tokens: line.tokens,
+                verbatim: false,
+            });
+            out.push(Line { text: "{".to_string(), tokens: vec![brace], verbatim: false });
+        } else {
+            out.push(line);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+fn main() {
+let p = Point { x: 1, y: 2 };
+if p.x > 0 {
+print(p.x);
+} else {
+match p.y {
+2 => {
+print(\"two\");
+}
+_ => print(\"other\"),
+}
+}
+}
+";
+
+    fn fmt(src: &str, options: FormatOptions) -> String {
+        format_source(src, &options).unwrap()
+    }
+
+    #[test]
+    fn test_default_style() {
+        let out = fmt(SAMPLE, FormatOptions::default());
+        assert!(out.contains("\n    let p = Point { x: 1, y: 2 };\n"));
+        assert!(out.contains("\n    } else {\n        match p.y {\n            2 => {\n                print(\"two\");\n"));
+        assert_eq!(fmt(&out, FormatOptions::default()), out);
+    }
+
+    #[test]
+    fn test_tab_and_two_space_indent() {
+        let tabs = fmt(SAMPLE, FormatOptions { indent: IndentStyle::Tabs, ..Default::default() });
+        assert!(tabs.contains("\n\tif p.x > 0 {\n\t\tprint(p.x);\n"));
+        assert!(!tabs.contains("  "));
+
+        let two = fmt(SAMPLE, FormatOptions { indent: IndentStyle::Spaces(2), ..Default::default() });
+        assert!(two.contains("\n  if p.x > 0 {\n    print(p.x);\n"));
+        assert!(two.contains("\n      2 => {\n        print(\"two\");\n"));
+    }
+
+    #[test]
+    fn test_next_line_braces_round_trip() {
+        let next = FormatOptions { brace_style: BraceStyle::NextLine, ..Default::default() };
+        let out = fmt(SAMPLE, next);
+        assert!(out.starts_with("fn main()\n{\n    let p = Point { x: 1, y: 2 };\n    if p.x > 0\n    {\n"));
+        assert!(out.contains("\n    }\n    else\n    {\n"));
+        assert!(out.contains("\n            2 =>\n            {\n"));
+
+        let back = fmt(&out, FormatOptions::default());
+        assert_eq!(back, fmt(SAMPLE, FormatOptions::default()));
+    }
+
+    #[test]
+    fn test_try_catch_blocks() {
+        let src = "fn f() {\ntry {\nrisky();\n} catch e {\nprint(e);\n}\n}\n";
+        let out = fmt(src, FormatOptions::default());
+        assert_eq!(out, "fn f() {\n    try {\n        risky();\n    } catch e {\n        print(e);\n    }\n}\n");
+
+        let next = fmt(src, FormatOptions { brace_style: BraceStyle::NextLine, ..Default::default() });
+        assert!(next.contains("\n    try\n    {\n        risky();\n    }\n    catch e\n    {\n        print(e);\n"));
+        assert_eq!(fmt(&next, FormatOptions::default()), out);
+    }
+
+    #[test]
+    fn test_comments_kept() {
+        let src = "fn f() {\n// note\nlet s = \"{ (\";\n/* x\n   y */\n}\n";
+        let out = fmt(src, FormatOptions::default());
+        assert_eq!(out, "fn f() {\n    // note\n    let s = \"{ (\";\n    /* x\n   y */\n}\n");
+    }
+
+    #[test]
+    fn test_config() {
+        let mut options = FormatOptions::default();
+        options.apply_config("[package]\nname = \"app\"\n\n[fmt]\nindent = \"tab\"\nbrace_style = \"next-line\"\n").unwrap();
+        assert_eq!(options, FormatOptions { indent: IndentStyle::Tabs, brace_style: BraceStyle::NextLine });
+        assert!(options.apply_config("[fmt]\nindent = 0\n").is_err());
+        assert!(options.apply_config("[fmt]\nwidth = 80\n").is_err());
+    }
+}
@@ -2,17 +2,31 @@
 
 #![allow(dead_code)]
 
+use crate::lexer::{Span, TokenKind};
 use crate::parser::*;
-use std::collections::HashMap;
+use crate::profiler::{Profiler, ProfilerConfig, ProfilingSummary};
+use crate::stdlib::io::Stream;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 
 #[derive(Debug, Clone)]
 pub enum Value {
-    Nil, Bool(bool), Int(i64), Float(f64), String(String),
+    Nil, Bool(bool), Int(i64), Float(f64), String(String), Char(char),
+    Bytes(Vec<u8>),
     Array(Vec<Value>),
-    Map(HashMap<String, Value>),
+    Tuple(Vec<Value>),
+    Map(HashMap<MapKey, Value>),
     Color { r: u8, g: u8, b: u8, a: u8 },
     Struct { name: String, fields: HashMap<String, Value> },
     NativeAction(fn(Vec<Value>) -> Value),
+    /// Native that can fail; its error propagates like a `throw` and can be caught
+    FallibleAction(fn(Vec<Value>) -> Result<Value, RuntimeError>),
+    /// Native that needs the interpreter, e.g. to call an action it was given
+    InterpreterAction(fn(&mut Interpreter, Vec<Value>) -> Result<Value, RuntimeError>),
+    Stream(Stream),
+    /// `env` is the scope captured at creation; `None` runs in the caller's scope like a named function.
+    /// `name` is the `let` the action was bound to, visible in its body so it can call itself.
+    Closure { params: Vec<String>, body: Rc<Block>, env: Option<ScopeSnapshot>, name: Option<String> },
 }
 
 impl Value {
@@ -21,9 +35,23 @@ impl Value {
     }
     pub fn type_name(&self) -> &'static str {
         match self { Value::Nil => "nil", Value::Bool(_) => "bool", Value::Int(_) => "int",
-                     Value::Float(_) => "float", Value::String(_) => "string", Value::Array(_) => "array",
-                     Value::Map(_) => "map", Value::Color {..} => "color",
-                     Value::Struct {..} => "struct", Value::NativeAction(_) => "native" }
+                     Value::Float(_) => "float", Value::String(_) => "string", Value::Char(_) => "char", Value::Bytes(_) => "bytes", Value::Array(_) => "array",
+                     Value::Tuple(_) => "tuple", Value::Map(_) => "map", Value::Color {..} => "color",
+                     Value::Struct {..} => "struct", Value::NativeAction(_) | Value::FallibleAction(_) | Value::InterpreterAction(_) => "native", Value::Stream(_) => "stream",
+                     Value::Closure {..} => "action" }
+    }
+    /// Estimated bytes this value's own heap storage takes: string bytes, or
+    /// one slot per element. Nested collections are counted when they are built.
+    pub fn heap_size(&self) -> u64 {
+        let slot = std::mem::size_of::<Value>();
+        (match self {
+            Value::String(s) => s.len(),
+            Value::Bytes(b) => b.len(),
+            Value::Array(a) | Value::Tuple(a) => a.len() * slot,
+            Value::Map(m) => m.len() * (slot + std::mem::size_of::<MapKey>()),
+            Value::Struct { fields, .. } => fields.keys().map(|k| k.len() + slot).sum(),
+            _ => 0,
+        }) as u64
     }
 }
 
@@ -31,42 +59,556 @@ impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Value::Nil => write!(f, "nil"), Value::Bool(b) => write!(f, "{}", b),
-            Value::Int(i) => write!(f, "{}", i), Value::Float(n) => write!(f, "{}", n),
+            Value::Int(i) => write!(f, "{}", i), Value::Float(n) => write!(f, "{}", format_float(*n)),
             Value::String(s) => write!(f, "{}", s),
+            Value::Char(c) => write!(f, "{}", c),
+            Value::Bytes(b) => write!(f, "<bytes len={}>", b.len()),
             Value::Array(a) => { write!(f, "[")?; for (i,v) in a.iter().enumerate() { if i>0 {write!(f,",")?;} write!(f,"{}",v)?; } write!(f, "]") },
+            Value::Tuple(t) => { write!(f, "(")?; for (i,v) in t.iter().enumerate() { if i>0 {write!(f,",")?;} write!(f,"{}",v)?; } write!(f, ")") },
             Value::Map(m) => { write!(f, "{{")?; for (i,(k,v)) in m.iter().enumerate() { if i>0 {write!(f,",")?;} write!(f,"{}:{}",k,v)?; } write!(f, "}}") },
             Value::Color{r,g,b,a} => write!(f, "rgba({},{},{},{})", r, g, b, a),
             Value::Struct{name,..} => write!(f, "<{}>", name),
-            Value::NativeAction(_) => write!(f, "<native>"),
+            Value::NativeAction(_) | Value::FallibleAction(_) | Value::InterpreterAction(_) => write!(f, "<native>"),
+            Value::Stream(s) => write!(f, "<{}>", s.name()),
+            Value::Closure{..} => write!(f, "<action>"),
+        }
+    }
+}
+
+/// A hashable `Value` used as a map key: bools, ints, strings, chars, and
+/// arrays, tuples or structs built only from those
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Bool(bool),
+    Int(i64),
+    String(String),
+    Char(char),
+    Array(Vec<MapKey>),
+    /// Kept apart from `Array`, so `(1, 2)` and `[1, 2]` are different keys
+    Tuple(Vec<MapKey>),
+    /// Fields sorted by name so equal structs hash alike
+    Struct { name: String, fields: Vec<(String, MapKey)> },
+}
+
+impl MapKey {
+    pub fn to_value(&self) -> Value {
+        match self {
+            MapKey::Bool(b) => Value::Bool(*b),
+            MapKey::Int(i) => Value::Int(*i),
+            MapKey::String(s) => Value::String(s.clone()),
+            MapKey::Char(c) => Value::Char(*c),
+            MapKey::Array(items) => Value::Array(items.iter().map(MapKey::to_value).collect()),
+            MapKey::Tuple(items) => Value::Tuple(items.iter().map(MapKey::to_value).collect()),
+            MapKey::Struct { name, fields } => Value::Struct {
+                name: name.clone(),
+                fields: fields.iter().map(|(k, v)| (k.clone(), v.to_value())).collect(),
+            },
+        }
+    }
+}
+
+impl TryFrom<&Value> for MapKey {
+    type Error = String;
+    fn try_from(v: &Value) -> Result<Self, String> {
+        match v {
+            Value::Bool(b) => Ok(MapKey::Bool(*b)),
+            Value::Int(i) => Ok(MapKey::Int(*i)),
+            Value::String(s) => Ok(MapKey::String(s.clone())),
+            Value::Char(c) => Ok(MapKey::Char(*c)),
+            Value::Array(items) => items.iter().map(MapKey::try_from).collect::<Result<_, _>>().map(MapKey::Array),
+            Value::Tuple(items) => items.iter().map(MapKey::try_from).collect::<Result<_, _>>().map(MapKey::Tuple),
+            Value::Struct { name, fields } => {
+                let mut keyed = fields.iter()
+                    .map(|(k, v)| MapKey::try_from(v).map(|key| (k.clone(), key)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                keyed.sort_by(|a, b| a.0.cmp(&b.0));
+                Ok(MapKey::Struct { name: name.clone(), fields: keyed })
+            }
+            other => Err(format!("unhashable map key of type '{}'", other.type_name())),
+        }
+    }
+}
+
+impl From<&str> for MapKey { fn from(v: &str) -> Self { MapKey::String(v.to_string()) } }
+
+impl std::fmt::Display for MapKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { write!(f, "{}", self.to_value()) }
+}
+
+/// What a native accepts in one argument position
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ArgKind { Int, Number, String, Array, Color }
+
+impl ArgKind {
+    fn accepts(self, v: &Value) -> bool {
+        match self {
+            ArgKind::Int => matches!(v, Value::Int(_)),
+            ArgKind::Number => matches!(v, Value::Int(_) | Value::Float(_)),
+            ArgKind::String => matches!(v, Value::String(_)),
+            ArgKind::Array => matches!(v, Value::Array(_)),
+            ArgKind::Color => matches!(v, Value::Color { .. }),
+        }
+    }
+    fn name(self) -> &'static str {
+        match self {
+            ArgKind::Int => "int", ArgKind::Number => "number",
+            ArgKind::String => "string", ArgKind::Array => "array", ArgKind::Color => "color",
+        }
+    }
+}
+
+/// Arguments a builtin expects; the first `required` of `params` must be given
+struct NativeSig { name: &'static str, params: &'static [ArgKind], required: usize }
+
+const fn sig(name: &'static str, params: &'static [ArgKind]) -> NativeSig { NativeSig { name, params, required: params.len() } }
+
+/// Natives checked before they run, so bad calls fail instead of defaulting to 0 or ""
+const NATIVE_SIGS: &[NativeSig] = {
+    use ArgKind::*;
+    &[
+        sig("rgba", &[Int, Int, Int, Int]), sig("rgb", &[Int, Int, Int]), sig("hex", &[String]),
+        sig("hsl", &[Number, Number, Number]), sig("color_lerp", &[Color, Color, Number]),
+        sig("color_lighten", &[Color, Number]), sig("color_darken", &[Color, Number]),
+        sig("color_with_alpha", &[Color, Number]), sig("with_alpha", &[Color, Number]),
+        sig("lerp", &[Number, Number, Number]), sig("ease_linear", &[Number]), sig("ease_in", &[Number]),
+        sig("ease_out", &[Number]), sig("ease_in_out", &[Number]),
+        sig("abs", &[Number]), sig("sqrt", &[Number]), sig("pow", &[Number, Number]),
+        sig("min", &[Number, Number]), sig("max", &[Number, Number]),
+        sig("floor", &[Number]), sig("ceil", &[Number]), sig("round", &[Number]),
+        sig("str_split", &[String, String]), sig("str_join", &[Array, String]),
+        sig("str_trim", &[String]), sig("str_upper", &[String]), sig("str_lower", &[String]),
+        sig("to_lower", &[String]), sig("to_upper", &[String]), sig("trim", &[String]),
+        sig("trim_start", &[String]), sig("trim_end", &[String]), sig("repeat_string", &[String, Int]),
+        NativeSig { name: "pad_left", params: &[String, Int, String], required: 2 },
+        NativeSig { name: "pad_right", params: &[String, Int, String], required: 2 },
+        sig("eq_ignore_case", &[String, String]), sig("str_contains", &[String, String]),
+        sig("str_replace", &[String, String, String]),
+        sig("reverse", &[Array]), sig("flatten", &[Array]), sig("unique", &[Array]),
+        sig("slice", &[Array, Int, Int]), sig("array_slice", &[Array, Int, Int]),
+        sig("sum", &[Array]), sig("avg", &[Array]), sig("array_min", &[Array]), sig("array_max", &[Array]),
+        sig("file_write", &[String, String]),
+    ]
+};
+
+/// Check a call to builtin `name` against its signature, if it has one
+fn check_native_args(name: &str, args: &[Value]) -> Result<(), RuntimeError> {
+    let Some(sig) = NATIVE_SIGS.iter().find(|s| s.name == name) else { return Ok(()) };
+    if args.len() < sig.required || args.len() > sig.params.len() {
+        let count = if sig.required == sig.params.len() { sig.required.to_string() } else { format!("{} to {}", sig.required, sig.params.len()) };
+        let plural = if sig.params.len() == 1 { "" } else { "s" };
+        let kind = match sig.params.first() {
+            Some(first) if sig.params.iter().all(|k| k == first) => format!("{} ", first.name()),
+            _ => String::new(),
+        };
+        return Err(RuntimeError::new(format!("{} expects {} {}argument{}, got {}", name, count, kind, plural, args.len())));
+    }
+    match sig.params.iter().zip(args).position(|(kind, v)| !kind.accepts(v)) {
+        Some(i) => Err(RuntimeError::new(format!("{} argument {} must be {}, found {}", name, i + 1, sig.params[i].name(), args[i].type_name()))),
+        None => Ok(()),
+    }
+}
+
+// ============ Host Conversions ============
+
+impl From<i64> for Value { fn from(v: i64) -> Self { Value::Int(v) } }
+impl From<f64> for Value { fn from(v: f64) -> Self { Value::Float(v) } }
+impl From<bool> for Value { fn from(v: bool) -> Self { Value::Bool(v) } }
+impl From<String> for Value { fn from(v: String) -> Self { Value::String(v) } }
+impl From<&str> for Value { fn from(v: &str) -> Self { Value::String(v.to_string()) } }
+
+/// Convert any vector of convertible items into a `Value::Array`.
+///
+/// ```
+/// use reoxc::interpreter::Value;
+///
+/// let arr = Value::from(vec![1i64, 2, 3]);
+/// assert_eq!(arr.to_string(), "[1,2,3]");
+///
+/// let back: Vec<i64> = arr.try_into().unwrap();
+/// assert_eq!(back, vec![1, 2, 3]);
+/// ```
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(v: Vec<T>) -> Self { Value::Array(v.into_iter().map(Into::into).collect()) }
+}
+
+/// Whether `v` is a value of the source type `ty`, for typed `catch` clauses
+fn value_has_type(v: &Value, ty: &Type) -> bool {
+    match (ty, v) {
+        (Type::Named(n), Value::Struct { name, .. }) => n == name,
+        (Type::Int | Type::FixedInt(_), Value::Int(_))
+        | (Type::Float, Value::Float(_))
+        | (Type::String, Value::String(_))
+        | (Type::Bool, Value::Bool(_))
+        | (Type::Array(_), Value::Array(_))
+        | (Type::Optional(_), Value::Nil) => true,
+        (Type::Optional(inner), v) => value_has_type(v, inner),
+        (Type::Tuple(elems), Value::Tuple(items)) => elems.len() == items.len() && elems.iter().zip(items).all(|(ty, v)| value_has_type(v, ty)),
+        _ => false,
+    }
+}
+
+/// Widen ints to floats where the declared type `ty` expects a float and wrap
+/// them where it is a fixed-width int, element-wise through arrays, mirroring
+/// the typechecker's literal coercion
+fn widen_to(ty: &Type, v: Value) -> Value {
+    match (ty, v) {
+        (Type::Float, Value::Int(i)) => Value::Float(i as f64),
+        (Type::FixedInt(w), Value::Int(i)) => Value::Int(w.wrap(i)),
+        (Type::Array(elem), Value::Array(items)) => Value::Array(items.into_iter().map(|v| widen_to(elem, v)).collect()),
+        (Type::Optional(inner), v) => widen_to(inner, v),
+        (Type::Tuple(elems), Value::Tuple(items)) => Value::Tuple(items.into_iter().zip(elems).map(|(v, ty)| widen_to(ty, v)).collect()),
+        (_, v) => v,
+    }
+}
+
+/// Structural equality; floats compare within epsilon
+fn values_eq(a: &Value, b: &Value) -> bool {
+    match (a,b) { 
+        (Value::Nil,Value::Nil) => true, 
+        (Value::Bool(a),Value::Bool(b)) => a==b, 
+        (Value::Int(a),Value::Int(b)) => a==b, 
+        (Value::Float(a),Value::Float(b)) => (a - b).abs() < f64::EPSILON,
+        (Value::String(a),Value::String(b)) => a==b, 
+        (Value::Char(a),Value::Char(b)) => a==b,
+        (Value::Bytes(a),Value::Bytes(b)) => a==b,
+        (Value::Array(a),Value::Array(b)) | (Value::Tuple(a),Value::Tuple(b)) => a.len()==b.len() && a.iter().zip(b).all(|(x,y)| values_eq(x,y)),
+        (Value::Map(a),Value::Map(b)) => a.len()==b.len() && a.iter().all(|(k,v)| b.get(k).is_some_and(|w| values_eq(v,w))),
+        (Value::Color{r,g,b,a},Value::Color{r:r2,g:g2,b:b2,a:a2}) => (r,g,b,a)==(r2,g2,b2,a2),
+        (Value::Struct{name:n1,fields:f1},Value::Struct{name:n2,fields:f2}) => n1==n2 && f1.len()==f2.len() && f1.iter().all(|(k,v)| f2.get(k).is_some_and(|w| values_eq(v,w))),
+        _ => false 
+    } 
+}
+
+/// Ordering for the relational operators: numbers (mixed int/float) and
+/// strings (lexicographic by code point). `None` means NaN was involved,
+/// so every comparison is false; other pairs are an error
+fn compare_values(a: &Value, b: &Value, op: &str) -> Result<Option<std::cmp::Ordering>, RuntimeError> {
+    Ok(match (a,b) {
+        (Value::Int(a),Value::Int(b)) => Some(a.cmp(b)),
+        (Value::Float(a),Value::Float(b)) => a.partial_cmp(b),
+        (Value::Int(a),Value::Float(b)) => (*a as f64).partial_cmp(b),
+        (Value::Float(a),Value::Int(b)) => a.partial_cmp(&(*b as f64)),
+        (Value::String(a),Value::String(b)) => Some(a.cmp(b)),
+        (Value::Char(a),Value::Char(b)) => Some(a.cmp(b)),
+        _ => return Err(RuntimeError::new(format!("cannot compare {} and {} with '{}'", a.type_name(), b.type_name(), op)))
+    })
+}
+
+/// The value `++`/`--` store: ints step by one (wrapping), floats by 1.0
+fn step(v: Value, delta: i64, span: Span) -> Result<Value, RuntimeError> {
+    match v {
+        Value::Int(i) => Ok(Value::Int(i.wrapping_add(delta))),
+        Value::Float(f) => Ok(Value::Float(f + delta as f64)),
+        other => Err(RuntimeError::with_span(format!("cannot {} {}", if delta > 0 { "increment" } else { "decrement" }, other.type_name()), span)),
+    }
+}
+
+fn mismatch(expected: &str, found: &Value) -> RuntimeError {
+    RuntimeError::new(format!("expected {}, found {}", expected, found.type_name()))
+}
+
+impl TryFrom<Value> for i64 {
+    type Error = RuntimeError;
+    fn try_from(v: Value) -> Result<Self, Self::Error> { match v { Value::Int(i) => Ok(i), other => Err(mismatch("int", &other)) } }
+}
+impl TryFrom<Value> for f64 {
+    type Error = RuntimeError;
+    /// Ints widen to floats, matching the typechecker's assignability rules
+    fn try_from(v: Value) -> Result<Self, Self::Error> {
+        match v { Value::Float(f) => Ok(f), Value::Int(i) => Ok(i as f64), other => Err(mismatch("float", &other)) }
+    }
+}
+impl TryFrom<Value> for bool {
+    type Error = RuntimeError;
+    fn try_from(v: Value) -> Result<Self, Self::Error> { match v { Value::Bool(b) => Ok(b), other => Err(mismatch("bool", &other)) } }
+}
+impl TryFrom<Value> for String {
+    type Error = RuntimeError;
+    fn try_from(v: Value) -> Result<Self, Self::Error> { match v { Value::String(s) => Ok(s), other => Err(mismatch("string", &other)) } }
+}
+
+/// Convert a `Value::Array` back into a vector, failing on the first mismatched element.
+///
+/// ```
+/// use reoxc::interpreter::Value;
+///
+/// let mixed = Value::Array(vec![Value::Int(1), Value::String("two".into())]);
+/// let err = Vec::<i64>::try_from(mixed).unwrap_err();
+/// assert_eq!(err.message, "element 1: expected int, found string");
+/// ```
+impl<T: TryFrom<Value, Error = RuntimeError>> TryFrom<Value> for Vec<T> {
+    type Error = RuntimeError;
+    fn try_from(v: Value) -> Result<Self, Self::Error> {
+        match v {
+            Value::Array(items) => items.into_iter().enumerate()
+                .map(|(i, x)| T::try_from(x).map_err(|e| RuntimeError::new(format!("element {}: {}", i, e.message))))
+                .collect(),
+            other => Err(mismatch("array", &other)),
+        }
+    }
+}
+
+/// Largest string `string * int`, `repeat_string` or padding may build, in bytes
+pub const MAX_REPEAT_BYTES: usize = 16 * 1024 * 1024;
+
+/// Repeat `s` `n` times, rejecting negative counts and oversized results
+fn repeat_string(s: &str, n: i64) -> Result<String, RuntimeError> {
+    if n < 0 { return Err(RuntimeError::new(format!("cannot repeat a string {} times", n))); }
+    match s.len().checked_mul(n as usize) {
+        Some(total) if total <= MAX_REPEAT_BYTES => Ok(s.repeat(n as usize)),
+        _ => Err(RuntimeError::new(format!("repeated string would exceed {} bytes", MAX_REPEAT_BYTES))),
+    }
+}
+
+/// `csv_parse`/`tsv_parse`: text -> array of arrays of strings, nil on malformed input
+fn delimited_parse(a: &[Value], delim: char) -> Value {
+    match a.first() {
+        Some(Value::String(text)) => match crate::stdlib::csv::parse(text, delim) {
+            Ok(rows) => Value::Array(rows.into_iter().map(Value::from).collect()),
+            Err(_) => Value::Nil,
+        },
+        _ => Value::Nil,
+    }
+}
+
+/// `csv_write`/`tsv_write`: array of arrays -> text; non-string fields use their display form
+fn delimited_write(a: &[Value], delim: char) -> Value {
+    let Some(Value::Array(rows)) = a.first() else { return Value::Nil };
+    let rows: Vec<Vec<String>> = rows.iter().map(|r| match r {
+        Value::Array(fields) => fields.iter().map(|f| match f { Value::String(s) => s.clone(), other => other.to_string() }).collect(),
+        other => vec![other.to_string()],
+    }).collect();
+    Value::String(crate::stdlib::csv::write(&rows, delim))
+}
+
+/// The key argument of a map native; an unhashable key is an error, not a miss
+fn key_arg(a: &[Value]) -> Result<Option<MapKey>, RuntimeError> {
+    a.get(1).map(MapKey::try_from).transpose().map_err(RuntimeError::new)
+}
+
+/// `array_slice(arr, start, end)`: the elements in `start..end`, clamped to the array
+fn array_slice(a: Vec<Value>) -> Value {
+    if let (Some(Value::Array(arr)), Some(Value::Int(start)), Some(Value::Int(end))) = (a.first(), a.get(1), a.get(2)) {
+        let s = (*start).clamp(0, arr.len() as i64) as usize;
+        let e = (*end).clamp(s as i64, arr.len() as i64) as usize;
+        return Value::Array(arr[s..e].to_vec());
+    }
+    Value::Array(vec![])
+}
+
+/// Apply `f` to a string first argument; anything else gives an empty string
+fn map_string(a: &[Value], f: fn(&str) -> String) -> Value {
+    match a.first() {
+        Some(Value::String(s)) => Value::String(f(s)),
+        _ => Value::String(String::new()),
+    }
+}
+
+/// Pad a string to `width` characters with the first character of `fill`
+/// (a space by default); strings already that wide are returned unchanged
+fn pad_string(a: &[Value], left: bool) -> Result<Value, RuntimeError> {
+    let (Some(Value::String(s)), Some(Value::Int(width))) = (a.first(), a.get(1)) else {
+        return Ok(Value::String(String::new()));
+    };
+    let fill = match a.get(2) {
+        Some(Value::String(f)) => f.chars().next().unwrap_or(' '),
+        _ => ' ',
+    };
+    let gap = usize::try_from(*width).unwrap_or(0).saturating_sub(s.chars().count());
+    if gap.saturating_mul(fill.len_utf8()).saturating_add(s.len()) > MAX_REPEAT_BYTES {
+        return Err(RuntimeError::new(format!("padded string would exceed {} bytes", MAX_REPEAT_BYTES)));
+    }
+    let padding: String = std::iter::repeat_n(fill, gap).collect();
+    Ok(Value::String(if left { padding + s } else { s.clone() + &padding }))
+}
+
+fn int_convert(width: IntWidth, a: &[Value]) -> Value {
+    match a.first() {
+        Some(Value::Int(i)) => Value::Int(width.wrap(*i)),
+        Some(Value::Float(f)) => Value::Int(width.wrap(*f as i64)),
+        _ => Value::Nil,
+    }
+}
+
+/// Validate a shift amount for a 64-bit int
+fn shift_amount(b: i64) -> Result<u32, RuntimeError> {
+    if (0..64).contains(&b) { Ok(b as u32) } else { Err(RuntimeError::new(format!("shift amount {} out of range 0..64", b))) }
+}
+
+/// Unpack a color argument into its (r, g, b, a) components
+fn color_arg(v: Option<&Value>) -> Option<(u8, u8, u8, u8)> {
+    match v { Some(Value::Color { r, g, b, a }) => Some((*r, *g, *b, *a)), _ => None }
+}
+
+/// `color_with_alpha(c, a)`: int alpha is taken as 0-255, float alpha as 0.0-1.0
+fn color_with_alpha(a: Vec<Value>) -> Value {
+    let alpha = match a.get(1) {
+        Some(Value::Int(v)) => (*v).clamp(0, 255) as u8,
+        Some(Value::Float(v)) => (v.clamp(0.0, 1.0) * 255.0).round() as u8,
+        _ => 255,
+    };
+    match color_arg(a.first()) {
+        Some((r, g, b, _)) => Value::Color { r, g, b, a: alpha },
+        None => Value::Nil,
+    }
+}
+
+/// The `.r`, `.g`, `.b` or `.a` component slot of a color
+fn color_component<'c>(color: &'c mut [u8; 4], field: &str) -> Option<&'c mut u8> {
+    match field {
+        "r" => Some(&mut color[0]),
+        "g" => Some(&mut color[1]),
+        "b" => Some(&mut color[2]),
+        "a" => Some(&mut color[3]),
+        _ => None,
+    }
+}
+
+/// Read a numeric argument as f64, accepting both ints and floats
+fn num_arg(v: Option<&Value>) -> Option<f64> {
+    match v { Some(Value::Float(f)) => Some(*f), Some(Value::Int(i)) => Some(*i as f64), _ => None }
+}
+
+/// Decimal places kept when printing non-whole floats
+const FLOAT_PRECISION: usize = 12;
+
+/// Format a float deterministically: whole values keep one decimal (`1.0`),
+/// others are rounded to `FLOAT_PRECISION` places with trailing zeros trimmed
+/// (so `0.1 + 0.2` prints `0.3`), and very large or tiny magnitudes use exponent form.
+pub fn format_float(n: f64) -> String {
+    if n.is_nan() { return "NaN".into(); }
+    if n.is_infinite() { return if n > 0.0 { "inf".into() } else { "-inf".into() }; }
+    let abs = n.abs();
+    if abs != 0.0 && !(1e-6..1e16).contains(&abs) { return format!("{:e}", n); }
+    if n.fract() == 0.0 { return format!("{:.1}", n); }
+    let s = format!("{:.*}", FLOAT_PRECISION, n);
+    let s = s.trim_end_matches('0');
+    if s.ends_with('.') { format!("{}0", s) } else { s.to_string() }
+}
+
+/// Groups of natives a program may use beyond the core ones (printing,
+/// strings, math, collections, colors, time). The default suits untrusted
+/// code: no filesystem and no process access
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Prelude { pub fs: bool, pub process: bool, pub net: bool, pub ai: bool }
+
+impl Prelude {
+    /// Every group; what `reoxc --run` gives local programs
+    pub const ALL: Prelude = Prelude { fs: true, process: true, net: true, ai: true };
+
+    /// Whether `import module;` may be used: the modules that map to groups
+    /// need them allowed, and every other module is left to the compiler
+    fn permits(&self, module: &str) -> bool {
+        match module {
+            "system" => self.fs && self.process,
+            "io" | "fs" => self.fs,
+            "process" => self.process,
+            "net" => self.net,
+            "ai" => self.ai,
+            _ => true,
         }
     }
 }
 
+/// Names a program may use without declaring them: every native
+/// `Prelude::ALL` provides, plus the widget constructors the compiler lowers
+pub fn builtin_names() -> Vec<String> {
+    Environment::new(Prelude::ALL).global_names().cloned()
+        .chain(crate::codegen::UI_WIDGETS.iter().map(|w| w.to_string()))
+        .collect()
+}
+
+impl Default for Prelude {
+    fn default() -> Self { Prelude { fs: false, process: false, net: true, ai: true } }
+}
+
+/// One level of bindings; `widths` marks variables declared with a fixed-width int type
+#[derive(Debug, Clone, Default)]
+struct Scope { values: HashMap<String, Value>, widths: HashMap<String, IntWidth> }
+
+/// Scopes are reference-counted so snapshots share them until one side writes
+#[derive(Debug, Clone)]
+pub struct Environment { scopes: Vec<Rc<Scope>> }
+
+/// A cheap, immutable view of an `Environment` taken by `snapshot()`
 #[derive(Debug, Clone)]
-pub struct Environment { scopes: Vec<HashMap<String, Value>> }
+pub struct ScopeSnapshot { scopes: Vec<Rc<Scope>> }
 
 impl Environment {
-    pub fn new() -> Self {
-        let mut e = Self { scopes: vec![HashMap::new()] };
+    /// Global scope with the core natives plus the groups `prelude` allows
+    pub fn new(prelude: Prelude) -> Self {
+        let mut e = Self { scopes: vec![Rc::default()] };
         // I/O
-        e.define("print", Value::NativeAction(|a| { for x in &a { print!("{} ", x); } println!(); Value::Nil }));
+        e.define("print", Value::NativeAction(|a| {
+            let line: String = a.iter().map(|x| format!("{} ", x)).collect();
+            let _ = crate::stdlib::io::write_stream(Stream::Stdout, &(line + "\n"));
+            Value::Nil
+        }));
+        // Standard streams; `print` and `stream_write(stdout(), ..)` share the stdout sink
+        e.define("stdin", Value::NativeAction(|_| Value::Stream(Stream::Stdin)));
+        e.define("stdout", Value::NativeAction(|_| Value::Stream(Stream::Stdout)));
+        e.define("stderr", Value::NativeAction(|_| Value::Stream(Stream::Stderr)));
+        e.define("stream_write", Value::NativeAction(|a| match (a.first(), a.get(1)) {
+            (Some(Value::Stream(h)), Some(text)) => {
+                Value::Bool(crate::stdlib::io::write_stream(*h, &text.to_string()).is_ok())
+            }
+            _ => Value::Bool(false),
+        }));
+        // nil at end of input
+        e.define("stream_read_line", Value::NativeAction(|a| match a.first() {
+            Some(Value::Stream(h)) => match crate::stdlib::io::read_stream_line(*h) {
+                Ok(Some(line)) => Value::String(line),
+                _ => Value::Nil,
+            },
+            _ => Value::Nil,
+        }));
         // Collections
-        e.define("len", Value::NativeAction(|a| match a.first() { Some(Value::Array(v)) => Value::Int(v.len() as i64), Some(Value::String(s)) => Value::Int(s.len() as i64), Some(Value::Map(m)) => Value::Int(m.len() as i64), _ => Value::Int(0) }));
+        e.define("len", Value::NativeAction(|a| match a.first() { Some(Value::Array(v)) => Value::Int(v.len() as i64), Some(Value::String(s)) => Value::Int(s.len() as i64), Some(Value::Bytes(b)) => Value::Int(b.len() as i64), Some(Value::Map(m)) => Value::Int(m.len() as i64), _ => Value::Int(0) }));
         e.define("push", Value::NativeAction(|a| {
             if a.len() >= 2 { if let Value::Array(mut arr) = a[0].clone() { arr.push(a[1].clone()); return Value::Array(arr); } }
             Value::Nil
         }));
+        // Returns the last element and leaves the array as is (arrays are values);
+        // nil for an empty array, so check is_empty first when elements may be nil
         e.define("pop", Value::NativeAction(|a| {
             if let Some(Value::Array(mut arr)) = a.first().cloned() { arr.pop().unwrap_or(Value::Nil) } else { Value::Nil }
         }));
+        e.define("arr_first", Value::NativeAction(|a| match a.first() { Some(Value::Array(v)) => v.first().cloned().unwrap_or(Value::Nil), _ => Value::Nil }));
+        e.define("arr_last", Value::NativeAction(|a| match a.first() { Some(Value::Array(v)) => v.last().cloned().unwrap_or(Value::Nil), _ => Value::Nil }));
+        e.define("is_empty", Value::NativeAction(|a| match a.first() { Some(Value::Array(v)) => Value::Bool(v.is_empty()), Some(Value::String(s)) => Value::Bool(s.is_empty()), Some(Value::Bytes(b)) => Value::Bool(b.is_empty()), Some(Value::Map(m)) => Value::Bool(m.is_empty()), _ => Value::Bool(false) }));
+        // Optionals
+        e.define("is_nil", Value::NativeAction(|a| Value::Bool(matches!(a.first(), Some(Value::Nil) | None))));
+        e.define("is_some", Value::NativeAction(|a| Value::Bool(!matches!(a.first(), Some(Value::Nil) | None))));
+        e.define("unwrap", Value::FallibleAction(|a| match a.into_iter().next() {
+            Some(Value::Nil) | None => Err(RuntimeError::new("called 'unwrap' on nil")),
+            Some(v) => Ok(v),
+        }));
+        e.define("unwrap_or", Value::NativeAction(|a| {
+            let mut a = a.into_iter();
+            match (a.next(), a.next()) {
+                (Some(Value::Nil) | None, default) => default.unwrap_or(Value::Nil),
+                (Some(v), _) => v,
+            }
+        }));
+        // Explicit deep copy: independent of how plain assignment shares collections
+        e.define("clone", Value::NativeAction(|a| a.into_iter().next().unwrap_or(Value::Nil)));
         e.define("map_new", Value::NativeAction(|_| Value::Map(HashMap::new())));
-        e.define("map_set", Value::NativeAction(|a| {
-            if a.len() >= 3 { if let (Value::Map(mut m), Value::String(k)) = (a[0].clone(), a[1].clone()) { m.insert(k, a[2].clone()); return Value::Map(m); } }
-            Value::Nil
+        e.define("map_set", Value::FallibleAction(|a| {
+            if let (Some(Value::Map(m)), Some(k), Some(v)) = (a.first(), key_arg(&a)?, a.get(2)) {
+                let mut m = m.clone();
+                m.insert(k, v.clone());
+                return Ok(Value::Map(m));
+            }
+            Ok(Value::Nil)
         }));
-        e.define("map_get", Value::NativeAction(|a| {
-            if a.len() >= 2 { if let (Value::Map(m), Value::String(k)) = (&a[0], &a[1]) { return m.get(k).cloned().unwrap_or(Value::Nil); } }
-            Value::Nil
+        e.define("struct_to_map", Value::NativeAction(|a| match a.first() {
+            Some(Value::Struct { fields, .. }) => Value::Map(fields.iter().map(|(k, v)| (MapKey::from(k.as_str()), v.clone())).collect()),
+            _ => Value::Nil,
+        }));
+        e.define("map_to_struct", Value::InterpreterAction(|i, a| i.map_to_struct(a).map_err(RuntimeError::new)));
+        e.define("map_get", Value::FallibleAction(|a| match (a.first(), key_arg(&a)?) {
+            (Some(Value::Map(m)), Some(k)) => Ok(m.get(&k).cloned().unwrap_or(Value::Nil)),
+            _ => Ok(Value::Nil),
         }));
         // Color management
         e.define("rgba", Value::NativeAction(|a| {
@@ -133,38 +675,103 @@ impl Environment {
             }
             Value::Bool(false)
         }));
-        e.define("array_slice", Value::NativeAction(|a| {
-            if a.len() >= 3 {
-                if let (Value::Array(arr), Value::Int(start), Value::Int(end)) = (&a[0], &a[1], &a[2]) {
-                    let s = (*start as usize).min(arr.len());
-                    let e = (*end as usize).min(arr.len());
-                    return Value::Array(arr[s..e].to_vec());
+        e.define("array_slice", Value::NativeAction(array_slice));
+        e.define("slice", Value::NativeAction(array_slice));
+        e.define("reverse", Value::NativeAction(|a| match a.into_iter().next() {
+            Some(Value::Array(mut arr)) => { arr.reverse(); Value::Array(arr) },
+            _ => Value::Nil,
+        }));
+        // One level only; non-array elements are kept as they are
+        e.define("flatten", Value::NativeAction(|a| match a.into_iter().next() {
+            Some(Value::Array(arr)) => Value::Array(arr.into_iter().flat_map(|v| match v {
+                Value::Array(inner) => inner,
+                v => vec![v],
+            }).collect()),
+            _ => Value::Nil,
+        }));
+        // Keeps the first of each set of `==` elements, in their original order
+        e.define("unique", Value::NativeAction(|a| match a.into_iter().next() {
+            Some(Value::Array(arr)) => {
+                let mut out: Vec<Value> = Vec::with_capacity(arr.len());
+                for v in arr {
+                    if !out.iter().any(|seen| values_eq(seen, &v)) { out.push(v); }
                 }
+                Value::Array(out)
+            },
+            _ => Value::Nil,
+        }));
+        
+        // Bytes: string conversions are strict, so invalid UTF-8 yields nil rather than replacement chars
+        e.define("bytes_to_string", Value::NativeAction(|a| match a.first() {
+            Some(Value::Bytes(b)) => String::from_utf8(b.clone()).map(Value::String).unwrap_or(Value::Nil),
+            _ => Value::Nil,
+        }));
+        e.define("string_to_bytes", Value::NativeAction(|a| match a.first() {
+            Some(Value::String(s)) => Value::Bytes(s.as_bytes().to_vec()),
+            _ => Value::Nil,
+        }));
+        // Bit-level conversions; byte order is little-endian unless the flag is false
+        e.define("float_to_bits", Value::NativeAction(|a| match a.first() {
+            Some(Value::Float(f)) => Value::Int(f.to_bits() as i64),
+            _ => Value::Nil,
+        }));
+        e.define("bits_to_float", Value::NativeAction(|a| match a.first() {
+            Some(Value::Int(i)) => Value::Float(f64::from_bits(*i as u64)),
+            _ => Value::Nil,
+        }));
+        e.define("int_to_bytes", Value::NativeAction(|a| match (a.first(), a.get(1)) {
+            (Some(Value::Int(i)), None | Some(Value::Bool(true))) => Value::Bytes(i.to_le_bytes().to_vec()),
+            (Some(Value::Int(i)), Some(Value::Bool(false))) => Value::Bytes(i.to_be_bytes().to_vec()),
+            _ => Value::Nil,
+        }));
+        // Exactly 8 bytes, as produced by int_to_bytes
+        e.define("bytes_to_int", Value::NativeAction(|a| {
+            let Some(Value::Bytes(b)) = a.first() else { return Value::Nil };
+            let Ok(raw) = <[u8; 8]>::try_from(b.as_slice()) else { return Value::Nil };
+            match a.get(1) {
+                None | Some(Value::Bool(true)) => Value::Int(i64::from_le_bytes(raw)),
+                Some(Value::Bool(false)) => Value::Int(i64::from_be_bytes(raw)),
+                _ => Value::Nil,
             }
-            Value::Array(vec![])
+        }));
+        
+        // Numeric aggregates: nil for non-numeric input, and for empty input except sum (0)
+        e.define("sum", Value::NativeAction(|a| match a.first() {
+            Some(Value::Array(v)) => crate::stdlib::core::sum(v).unwrap_or(Value::Nil),
+            _ => Value::Nil,
+        }));
+        e.define("avg", Value::NativeAction(|a| match a.first() {
+            Some(Value::Array(v)) => crate::stdlib::core::avg(v).map(Value::Float).unwrap_or(Value::Nil),
+            _ => Value::Nil,
+        }));
+        e.define("array_min", Value::NativeAction(|a| match a.first() {
+            Some(Value::Array(v)) => crate::stdlib::core::array_min(v).unwrap_or(Value::Nil),
+            _ => Value::Nil,
+        }));
+        e.define("array_max", Value::NativeAction(|a| match a.first() {
+            Some(Value::Array(v)) => crate::stdlib::core::array_max(v).unwrap_or(Value::Nil),
+            _ => Value::Nil,
         }));
         
         // Additional map operations
-        e.define("map_has", Value::NativeAction(|a| {
-            if a.len() >= 2 {
-                if let (Value::Map(m), Value::String(k)) = (&a[0], &a[1]) {
-                    return Value::Bool(m.contains_key(k));
-                }
-            }
-            Value::Bool(false)
+        e.define("map_has", Value::FallibleAction(|a| match (a.first(), key_arg(&a)?) {
+            (Some(Value::Map(m)), Some(k)) => Ok(Value::Bool(m.contains_key(&k))),
+            _ => Ok(Value::Bool(false)),
         }));
-        e.define("map_remove", Value::NativeAction(|a| {
-            if a.len() >= 2 {
-                if let (Value::Map(mut m), Value::String(k)) = (a[0].clone(), &a[1]) {
-                    m.remove(k);
-                    return Value::Map(m);
-                }
+        e.define("map_remove", Value::FallibleAction(|a| {
+            if let (Some(Value::Map(m)), Some(k)) = (a.first(), key_arg(&a)?) {
+                let mut m = m.clone();
+                m.remove(&k);
+                return Ok(Value::Map(m));
             }
-            Value::Nil
+            Ok(Value::Nil)
         }));
+        // These call back into the action they are given
+        e.define("map_filter", Value::InterpreterAction(|i, a| i.map_with_action("map_filter", a)));
+        e.define("map_map", Value::InterpreterAction(|i, a| i.map_with_action("map_map", a)));
         e.define("map_keys", Value::NativeAction(|a| {
             if let Some(Value::Map(m)) = a.first() {
-                let keys: Vec<Value> = m.keys().map(|k| Value::String(k.clone())).collect();
+                let keys: Vec<Value> = m.keys().map(MapKey::to_value).collect();
                 return Value::Array(keys);
             }
             Value::Array(vec![])
@@ -209,6 +816,22 @@ impl Environment {
             }
             Value::String(String::new())
         }));
+        e.define("to_lower", Value::NativeAction(|a| map_string(&a, str::to_lowercase)));
+        e.define("to_upper", Value::NativeAction(|a| map_string(&a, str::to_uppercase)));
+        e.define("trim", Value::NativeAction(|a| map_string(&a, |s| s.trim().to_string())));
+        e.define("trim_start", Value::NativeAction(|a| map_string(&a, |s| s.trim_start().to_string())));
+        e.define("trim_end", Value::NativeAction(|a| map_string(&a, |s| s.trim_end().to_string())));
+        e.define("repeat_string", Value::FallibleAction(|a| match (a.first(), a.get(1)) {
+            (Some(Value::String(s)), Some(Value::Int(n))) => repeat_string(s, *n).map(Value::String),
+            _ => Ok(Value::String(String::new())),
+        }));
+        e.define("pad_left", Value::FallibleAction(|a| pad_string(&a, true)));
+        e.define("pad_right", Value::FallibleAction(|a| pad_string(&a, false)));
+        e.define("eq_ignore_case", Value::NativeAction(|a| match (a.first(), a.get(1)) {
+            // Unicode lowercasing rather than full case folding: "ß" does not match "SS"
+            (Some(Value::String(x)), Some(Value::String(y))) => Value::Bool(x.to_lowercase() == y.to_lowercase()),
+            _ => Value::Bool(false),
+        }));
         e.define("str_contains", Value::NativeAction(|a| {
             if a.len() >= 2 {
                 if let (Value::String(s), Value::String(sub)) = (&a[0], &a[1]) {
@@ -235,24 +858,24 @@ impl Environment {
             }
         }));
         e.define("min", Value::NativeAction(|a| {
-            if a.len() >= 2 {
-                match (&a[0], &a[1]) {
-                    (Value::Int(x), Value::Int(y)) => return Value::Int(*x.min(y)),
-                    (Value::Float(x), Value::Float(y)) => return Value::Float(x.min(*y)),
-                    _ => {}
-                }
+            match (a.first(), a.get(1)) {
+                (Some(Value::Int(x)), Some(Value::Int(y))) => Value::Int(*x.min(y)),
+                // Mixed int and float widen to float, as in arithmetic
+                _ => match (num_arg(a.first()), num_arg(a.get(1))) {
+                    (Some(x), Some(y)) => Value::Float(x.min(y)),
+                    _ => Value::Nil,
+                },
             }
-            Value::Int(0)
         }));
         e.define("max", Value::NativeAction(|a| {
-            if a.len() >= 2 {
-                match (&a[0], &a[1]) {
-                    (Value::Int(x), Value::Int(y)) => return Value::Int(*x.max(y)),
-                    (Value::Float(x), Value::Float(y)) => return Value::Float(x.max(*y)),
-                    _ => {}
-                }
+            match (a.first(), a.get(1)) {
+                (Some(Value::Int(x)), Some(Value::Int(y))) => Value::Int(*x.max(y)),
+                // Mixed int and float widen to float, as in arithmetic
+                _ => match (num_arg(a.first()), num_arg(a.get(1))) {
+                    (Some(x), Some(y)) => Value::Float(x.max(y)),
+                    _ => Value::Nil,
+                },
             }
-            Value::Int(0)
         }));
         e.define("sqrt", Value::NativeAction(|a| {
             match a.first() {
@@ -273,13 +896,13 @@ impl Environment {
             Value::Int(0)
         }));
         e.define("floor", Value::NativeAction(|a| {
-            if let Some(Value::Float(f)) = a.first() { Value::Int(f.floor() as i64) } else { Value::Int(0) }
+            if let Some(f) = num_arg(a.first()) { Value::Int(f.floor() as i64) } else { Value::Int(0) }
         }));
         e.define("ceil", Value::NativeAction(|a| {
-            if let Some(Value::Float(f)) = a.first() { Value::Int(f.ceil() as i64) } else { Value::Int(0) }
+            if let Some(f) = num_arg(a.first()) { Value::Int(f.ceil() as i64) } else { Value::Int(0) }
         }));
         e.define("round", Value::NativeAction(|a| {
-            if let Some(Value::Float(f)) = a.first() { Value::Int(f.round() as i64) } else { Value::Int(0) }
+            if let Some(f) = num_arg(a.first()) { Value::Int(f.round() as i64) } else { Value::Int(0) }
         }));
         
         // Type conversions
@@ -292,6 +915,32 @@ impl Environment {
                 _ => Value::Int(0)
             }
         }));
+        // Fixed-width conversions wrap like a C cast; values stay `Value::Int`
+        e.define("i8", Value::NativeAction(|a| int_convert(IntWidth::I8, &a)));
+        e.define("i16", Value::NativeAction(|a| int_convert(IntWidth::I16, &a)));
+        e.define("i32", Value::NativeAction(|a| int_convert(IntWidth::I32, &a)));
+        e.define("i64", Value::NativeAction(|a| int_convert(IntWidth::I64, &a)));
+        e.define("u8", Value::NativeAction(|a| int_convert(IntWidth::U8, &a)));
+        e.define("u16", Value::NativeAction(|a| int_convert(IntWidth::U16, &a)));
+        e.define("u32", Value::NativeAction(|a| int_convert(IntWidth::U32, &a)));
+        e.define("u64", Value::NativeAction(|a| int_convert(IntWidth::U64, &a)));
+        // Failures raise an error that `try` can catch and `reoxc test` reports
+        e.define("assert", Value::FallibleAction(|a| match a.first() {
+            Some(cond) if cond.is_truthy() => Ok(Value::Nil),
+            _ => Err(RuntimeError::new(match a.get(1) {
+                Some(msg) => format!("assertion failed: {}", msg),
+                None => "assertion failed".to_string(),
+            })),
+        }));
+        e.define("panic", Value::FallibleAction(|a| {
+            Err(RuntimeError::new(format!("panic: {}", a.first().cloned().unwrap_or(Value::Nil))))
+        }));
+        // Strict parsing: malformed input is an error rather than 0
+        e.define("parse_int", Value::FallibleAction(|a| match a.first() {
+            Some(Value::String(s)) => s.trim().parse().map(Value::Int)
+                .map_err(|_| RuntimeError::new(format!("cannot parse '{}' as int", s))),
+            other => Err(mismatch("string", other.unwrap_or(&Value::Nil))),
+        }));
         e.define("float", Value::NativeAction(|a| {
             match a.first() {
                 Some(Value::Int(i)) => Value::Float(*i as f64),
@@ -300,36 +949,65 @@ impl Environment {
                 _ => Value::Float(0.0)
             }
         }));
+        e.define("format", Value::FallibleAction(|a| match a.split_first() {
+            Some((Value::String(fmt), args)) => crate::stdlib::format::format(fmt, args)
+                .map(Value::String)
+                .map_err(RuntimeError::new),
+            _ => Ok(Value::Nil),
+        }));
+        // Regex natives return nil when the pattern is invalid
+        e.define("regex_match", Value::NativeAction(|a| match (a.first(), a.get(1)) {
+            (Some(Value::String(p)), Some(Value::String(t))) => crate::stdlib::regex::Regex::new(p)
+                .map(|re| Value::Bool(re.is_match(t)))
+                .unwrap_or(Value::Nil),
+            _ => Value::Nil,
+        }));
+        e.define("regex_find", Value::NativeAction(|a| match (a.first(), a.get(1)) {
+            (Some(Value::String(p)), Some(Value::String(t))) => crate::stdlib::regex::Regex::new(p)
+                .ok()
+                .and_then(|re| re.find(t))
+                .map(Value::String)
+                .unwrap_or(Value::Nil),
+            _ => Value::Nil,
+        }));
+        e.define("regex_replace", Value::NativeAction(|a| match (a.first(), a.get(1), a.get(2)) {
+            (Some(Value::String(p)), Some(Value::String(t)), Some(Value::String(r))) => crate::stdlib::regex::Regex::new(p)
+                .map(|re| Value::String(re.replace_all(t, r)))
+                .unwrap_or(Value::Nil),
+            _ => Value::Nil,
+        }));
         e.define("str", Value::NativeAction(|a| {
             if let Some(v) = a.first() { Value::String(format!("{}", v)) } else { Value::String(String::new()) }
         }));
+        e.define("to_string", Value::NativeAction(|a| {
+            if let Some(v) = a.first() { Value::String(v.to_string()) } else { Value::String(String::new()) }
+        }));
         e.define("bool", Value::NativeAction(|a| {
             if let Some(v) = a.first() { Value::Bool(v.is_truthy()) } else { Value::Bool(false) }
         }));
         
-        // AI
-        e.define("ai_generate", Value::NativeAction(crate::stdlib::ai::generate));
+        if prelude.ai { Self::define_ai(&mut e); }
         
         // ============ Animation Easing ============
         e.define("ease_linear", Value::NativeAction(|a| {
-            if let Some(Value::Float(t)) = a.first() {
+            if let Some(t) = num_arg(a.first()) {
                 Value::Float(t.clamp(0.0, 1.0))
             } else { Value::Float(0.0) }
         }));
         e.define("ease_in", Value::NativeAction(|a| {
-            if let Some(Value::Float(t)) = a.first() {
+            if let Some(t) = num_arg(a.first()) {
                 let t = t.clamp(0.0, 1.0);
                 Value::Float(t * t)
             } else { Value::Float(0.0) }
         }));
         e.define("ease_out", Value::NativeAction(|a| {
-            if let Some(Value::Float(t)) = a.first() {
+            if let Some(t) = num_arg(a.first()) {
                 let t = t.clamp(0.0, 1.0);
                 Value::Float(1.0 - (1.0 - t) * (1.0 - t))
             } else { Value::Float(0.0) }
         }));
         e.define("ease_in_out", Value::NativeAction(|a| {
-            if let Some(Value::Float(t)) = a.first() {
+            if let Some(t) = num_arg(a.first()) {
                 let t = t.clamp(0.0, 1.0);
                 if t < 0.5 {
                     Value::Float(2.0 * t * t)
@@ -339,9 +1017,9 @@ impl Environment {
             } else { Value::Float(0.0) }
         }));
         e.define("lerp", Value::NativeAction(|a| {
-            let a_val = if let Some(Value::Float(v)) = a.get(0) { *v } else { 0.0 };
-            let b_val = if let Some(Value::Float(v)) = a.get(1) { *v } else { 0.0 };
-            let t = if let Some(Value::Float(v)) = a.get(2) { v.clamp(0.0, 1.0) } else { 0.0 };
+            let a_val = num_arg(a.first()).unwrap_or(0.0);
+            let b_val = num_arg(a.get(1)).unwrap_or(0.0);
+            let t = num_arg(a.get(2)).map_or(0.0, |v| v.clamp(0.0, 1.0));
             Value::Float(a_val + (b_val - a_val) * t)
         }));
         
@@ -358,9 +1036,9 @@ impl Environment {
         
         // ============ HSL Color ============
         e.define("hsl", Value::NativeAction(|a| {
-            let h = if let Some(Value::Float(v)) = a.get(0) { *v } else { 0.0 };
-            let s = if let Some(Value::Float(v)) = a.get(1) { *v / 100.0 } else { 0.0 };
-            let l = if let Some(Value::Float(v)) = a.get(2) { *v / 100.0 } else { 0.0 };
+            let h = num_arg(a.first()).unwrap_or(0.0);
+            let s = num_arg(a.get(1)).unwrap_or(0.0) / 100.0;
+            let l = num_arg(a.get(2)).unwrap_or(0.0) / 100.0;
             
             let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
             let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
@@ -383,54 +1061,36 @@ impl Environment {
             }
         }));
         
-        // ============ System Module ============
-        // File I/O
-        e.define("file_read", Value::NativeAction(|a| {
-            if let Some(Value::String(path)) = a.first() {
-                match std::fs::read_to_string(path) {
-                    Ok(content) => Value::String(content),
-                    Err(_) => Value::String(String::new()),
-                }
-            } else { Value::String(String::new()) }
-        }));
-        e.define("file_write", Value::NativeAction(|a| {
-            if a.len() >= 2 {
-                if let (Value::String(path), Value::String(content)) = (&a[0], &a[1]) {
-                    return Value::Bool(std::fs::write(path, content).is_ok());
-                }
+        // ============ Color Blending ============
+        e.define("color_lerp", Value::NativeAction(|a| {
+            match (color_arg(a.first()), color_arg(a.get(1))) {
+                (Some(c1), Some(c2)) => crate::stdlib::ui::color_lerp(c1, c2, num_arg(a.get(2)).unwrap_or(0.0)),
+                _ => Value::Nil,
             }
-            Value::Bool(false)
-        }));
-        e.define("file_exists", Value::NativeAction(|a| {
-            if let Some(Value::String(path)) = a.first() {
-                Value::Bool(std::path::Path::new(path).exists())
-            } else { Value::Bool(false) }
-        }));
-        e.define("file_delete", Value::NativeAction(|a| {
-            if let Some(Value::String(path)) = a.first() {
-                Value::Bool(std::fs::remove_file(path).is_ok())
-            } else { Value::Bool(false) }
         }));
-        e.define("file_size", Value::NativeAction(|a| {
-            if let Some(Value::String(path)) = a.first() {
-                match std::fs::metadata(path) {
-                    Ok(m) => Value::Int(m.len() as i64),
-                    Err(_) => Value::Int(-1),
-                }
-            } else { Value::Int(-1) }
+        e.define("color_lighten", Value::NativeAction(|a| {
+            match color_arg(a.first()) {
+                Some(c) => crate::stdlib::ui::color_lighten(c, num_arg(a.get(1)).unwrap_or(0.0)),
+                None => Value::Nil,
+            }
         }));
-        e.define("dir_list", Value::NativeAction(|a| {
-            if let Some(Value::String(path)) = a.first() {
-                if let Ok(entries) = std::fs::read_dir(path) {
-                    let files: Vec<Value> = entries
-                        .filter_map(|e| e.ok())
-                        .map(|e| Value::String(e.file_name().to_string_lossy().into_owned()))
-                        .collect();
-                    return Value::Array(files);
-                }
+        e.define("color_darken", Value::NativeAction(|a| {
+            match color_arg(a.first()) {
+                Some(c) => crate::stdlib::ui::color_darken(c, num_arg(a.get(1)).unwrap_or(0.0)),
+                None => Value::Nil,
             }
-            Value::Array(vec![])
         }));
+        e.define("color_with_alpha", Value::NativeAction(color_with_alpha));
+        e.define("with_alpha", Value::NativeAction(color_with_alpha));
+        
+        // ============ System Module ============
+        if prelude.fs { Self::define_fs(&mut e); }
+        
+        // Delimited text (CSV / TSV)
+        e.define("csv_parse", Value::NativeAction(|a| delimited_parse(&a, ',')));
+        e.define("csv_write", Value::NativeAction(|a| delimited_write(&a, ',')));
+        e.define("tsv_parse", Value::NativeAction(|a| delimited_parse(&a, '\t')));
+        e.define("tsv_write", Value::NativeAction(|a| delimited_write(&a, '\t')));
         
         // Time
         e.define("time_now", Value::NativeAction(|_| {
@@ -445,6 +1105,17 @@ impl Environment {
                 Err(_) => Value::Int(0),
             }
         }));
+        e.define("duration_format", Value::NativeAction(|a| match a.first() {
+            Some(Value::Int(ms)) => Value::String(crate::stdlib::core::duration_format(*ms)),
+            _ => Value::Nil,
+        }));
+        e.define("now_iso", Value::NativeAction(|_| {
+            let millis = match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+                Ok(d) => d.as_millis() as i64,
+                Err(_) => 0,
+            };
+            Value::String(crate::stdlib::core::iso8601(millis))
+        }));
         e.define("time_sleep", Value::NativeAction(|a| {
             if let Some(Value::Int(ms)) = a.first() {
                 std::thread::sleep(std::time::Duration::from_millis(*ms as u64));
@@ -452,29 +1123,7 @@ impl Environment {
             Value::Nil
         }));
         
-        // Environment
-        e.define("env_get", Value::NativeAction(|a| {
-            if let Some(Value::String(name)) = a.first() {
-                match std::env::var(name) {
-                    Ok(val) => Value::String(val),
-                    Err(_) => Value::String(String::new()),
-                }
-            } else { Value::String(String::new()) }
-        }));
-        e.define("env_args", Value::NativeAction(|_| {
-            let args: Vec<Value> = std::env::args().map(Value::String).collect();
-            Value::Array(args)
-        }));
-        
-        // Process
-        e.define("process_exec", Value::NativeAction(|a| {
-            if let Some(Value::String(cmd)) = a.first() {
-                match std::process::Command::new("sh").arg("-c").arg(cmd).output() {
-                    Ok(out) => Value::String(String::from_utf8_lossy(&out.stdout).into_owned()),
-                    Err(_) => Value::String(String::new()),
-                }
-            } else { Value::String(String::new()) }
-        }));
+        if prelude.process { Self::define_process(&mut e); }
         
         // Random
         e.define("random_int", Value::NativeAction(|a| {
@@ -492,76 +1141,535 @@ impl Environment {
         }));
         
         // ============ Network Module ============
-        // HTTP (uses reqwest which is already a dependency)
-        e.define("http_get", Value::NativeAction(|a| {
-            if let Some(Value::String(url)) = a.first() {
-                match reqwest::blocking::get(url) {
-                    Ok(resp) => match resp.text() {
-                        Ok(text) => Value::String(text),
-                        Err(_) => Value::String(String::new()),
-                    },
-                    Err(_) => Value::String(String::new()),
-                }
-            } else { Value::String(String::new()) }
-        }));
+        if prelude.net { Self::define_net(&mut e); }
         
         e
     }
-    pub fn push(&mut self) { self.scopes.push(HashMap::new()); }
-    pub fn pop(&mut self) { if self.scopes.len() > 1 { self.scopes.pop(); } }
-    pub fn define(&mut self, n: &str, v: Value) { self.scopes.last_mut().map(|s| s.insert(n.into(), v)); }
-    pub fn get(&self, n: &str) -> Option<Value> { self.scopes.iter().rev().find_map(|s| s.get(n).cloned()) }
-    pub fn set(&mut self, n: &str, v: Value) -> bool {
-        for s in self.scopes.iter_mut().rev() { if s.contains_key(n) { s.insert(n.into(), v); return true; } }
-        false
-    }
-}
-impl Default for Environment { fn default() -> Self { Self::new() } }
 
-#[derive(Debug, Clone)]
-pub struct RuntimeError { pub message: String }
-impl RuntimeError { pub fn new(m: impl Into<String>) -> Self { Self { message: m.into() } } }
+    /// Text and UI generation through the AI runtime
+    fn define_ai(e: &mut Self) {
+        e.define("ai_generate", Value::NativeAction(crate::stdlib::ai::generate));
+        e.define("ai_ui", Value::NativeAction(crate::stdlib::ai::ui));
+        e.define("ai_ui_compile", Value::NativeAction(crate::stdlib::ai::ui_compile));
+    }
 
-pub struct Interpreter {
-    env: Environment,
-    structs: HashMap<String, StructDecl>,
+    /// File I/O: reads, writes, deletes and directory listings
+    fn define_fs(e: &mut Self) {
+        e.define("file_read", Value::FallibleAction(|a| match a.first() {
+            Some(Value::String(path)) => std::fs::read_to_string(path)
+                .map(Value::String)
+                .map_err(|err| RuntimeError::new(format!("cannot read '{}': {}", path, err))),
+            other => Err(mismatch("string", other.unwrap_or(&Value::Nil))),
+        }));
+        e.define("file_write", Value::NativeAction(|a| {
+            if a.len() >= 2 {
+                if let (Value::String(path), Value::String(content)) = (&a[0], &a[1]) {
+                    return Value::Bool(std::fs::write(path, content).is_ok());
+                }
+            }
+            Value::Bool(false)
+        }));
+        // Binary files round-trip through `bytes` without UTF-8 decoding
+        e.define("file_read_bytes", Value::FallibleAction(|a| match a.first() {
+            Some(Value::String(path)) => std::fs::read(path)
+                .map(Value::Bytes)
+                .map_err(|err| RuntimeError::new(format!("cannot read '{}': {}", path, err))),
+            other => Err(mismatch("string", other.unwrap_or(&Value::Nil))),
+        }));
+        e.define("file_write_bytes", Value::NativeAction(|a| {
+            if a.len() >= 2 {
+                if let (Value::String(path), Value::Bytes(data)) = (&a[0], &a[1]) {
+                    return Value::Bool(std::fs::write(path, data).is_ok());
+                }
+            }
+            Value::Bool(false)
+        }));
+        e.define("file_exists", Value::NativeAction(|a| {
+            if let Some(Value::String(path)) = a.first() {
+                Value::Bool(std::path::Path::new(path).exists())
+            } else { Value::Bool(false) }
+        }));
+        e.define("file_delete", Value::NativeAction(|a| {
+            if let Some(Value::String(path)) = a.first() {
+                Value::Bool(std::fs::remove_file(path).is_ok())
+            } else { Value::Bool(false) }
+        }));
+        e.define("file_size", Value::NativeAction(|a| {
+            if let Some(Value::String(path)) = a.first() {
+                match std::fs::metadata(path) {
+                    Ok(m) => Value::Int(m.len() as i64),
+                    Err(_) => Value::Int(-1),
+                }
+            } else { Value::Int(-1) }
+        }));
+        e.define("dir_list", Value::NativeAction(|a| {
+            if let Some(Value::String(path)) = a.first() {
+                if let Ok(entries) = std::fs::read_dir(path) {
+                    let files: Vec<Value> = entries
+                        .filter_map(|e| e.ok())
+                        .map(|e| Value::String(e.file_name().to_string_lossy().into_owned()))
+                        .collect();
+                    return Value::Array(files);
+                }
+            }
+            Value::Array(vec![])
+        }));
+    }
+
+    /// Environment variables, command-line arguments and shell commands
+    fn define_process(e: &mut Self) {
+        // Environment
+        e.define("env_get", Value::NativeAction(|a| {
+            if let Some(Value::String(name)) = a.first() {
+                match std::env::var(name) {
+                    Ok(val) => Value::String(val),
+                    Err(_) => Value::String(String::new()),
+                }
+            } else { Value::String(String::new()) }
+        }));
+        e.define("env_args", Value::NativeAction(|_| {
+            let args: Vec<Value> = std::env::args().map(Value::String).collect();
+            Value::Array(args)
+        }));
+        
+        // Process
+        e.define("process_exec", Value::NativeAction(|a| {
+            if let Some(Value::String(cmd)) = a.first() {
+                match std::process::Command::new("sh").arg("-c").arg(cmd).output() {
+                    Ok(out) => Value::String(String::from_utf8_lossy(&out.stdout).into_owned()),
+                    Err(_) => Value::String(String::new()),
+                }
+            } else { Value::String(String::new()) }
+        }));
+    }
+
+    /// HTTP (uses reqwest which is already a dependency); timeouts and
+    /// retries follow `REOX_HTTP_TIMEOUT_MS` / `REOX_HTTP_RETRIES`
+    fn define_net(e: &mut Self) {
+        e.define("http_get", Value::FallibleAction(|a| match a.first() {
+            Some(Value::String(url)) => crate::stdlib::http::get(url)
+                .map(Value::String)
+                .map_err(|err| RuntimeError::new(format!("http_get '{}' failed: {}", url, err))),
+            other => Err(mismatch("string", other.unwrap_or(&Value::Nil))),
+        }));
+    }
+
+    pub fn push(&mut self) { self.scopes.push(Rc::default()); }
+    pub fn pop(&mut self) { if self.scopes.len() > 1 { self.scopes.pop(); } }
+    pub fn define(&mut self, n: &str, v: Value) {
+        if let Some(s) = self.scopes.last_mut() { let s = Rc::make_mut(s); s.widths.remove(n); s.values.insert(n.into(), v); }
+    }
+    /// Define `n` with declared type `ty`; a fixed-width int keeps wrapping to its width on every store
+    pub fn define_typed(&mut self, n: &str, ty: &Type, v: Value) {
+        self.define(n, widen_to(ty, v));
+        if let (Type::FixedInt(w), Some(s)) = (ty, self.scopes.last_mut()) { Rc::make_mut(s).widths.insert(n.into(), *w); }
+    }
+    /// Width of variable `n` if it was declared with a fixed-width int type
+    pub fn width(&self, n: &str) -> Option<IntWidth> {
+        self.scopes.iter().rev().find(|s| s.values.contains_key(n)).and_then(|s| s.widths.get(n).copied())
+    }
+    /// Capture the current scopes; later writes copy the touched scope instead of altering the snapshot
+    pub fn snapshot(&self) -> ScopeSnapshot { ScopeSnapshot { scopes: self.scopes.clone() } }
+    /// Replace all scopes with a previously taken snapshot
+    pub fn restore(&mut self, snapshot: ScopeSnapshot) { self.scopes = snapshot.scopes; }
+    /// Names bound in the global scope, such as the natives
+    pub fn global_names(&self) -> impl Iterator<Item = &String> { self.scopes[0].values.keys() }
+    /// Snapshot of all non-global bindings, innermost scope winning
+    pub fn locals(&self) -> HashMap<String, Value> {
+        let mut out = HashMap::new();
+        for scope in self.scopes.iter().skip(1) { for (k, v) in scope.values.iter() { out.insert(k.clone(), v.clone()); } }
+        out
+    }
+    pub fn get(&self, n: &str) -> Option<Value> { self.lookup(n).cloned() }
+    /// Borrow the value of `n` without cloning it
+    pub fn lookup(&self, n: &str) -> Option<&Value> { self.scopes.iter().rev().find_map(|s| s.values.get(n)) }
+    pub fn set(&mut self, n: &str, v: Value) -> bool {
+        for s in self.scopes.iter_mut().rev() {
+            if s.values.contains_key(n) {
+                let v = match (s.widths.get(n), v) { (Some(w), Value::Int(i)) => Value::Int(w.wrap(i)), (_, v) => v };
+                Rc::make_mut(s).values.insert(n.into(), v);
+                return true;
+            }
+        }
+        false
+    }
+}
+impl Default for Environment { fn default() -> Self { Self::new(Prelude::default()) } }
+
+/// Non-local control flow, carried through the error channel so `?` unwinds it
+#[derive(Debug, Clone)]
+pub enum Flow { Break, Continue, Return(Box<Value>) }
+
+/// Maps a place's current value to the value to store there and the result
+type PlaceUpdate<'a> = dyn FnMut(&Interpreter, Value) -> Result<(Value, Value), RuntimeError> + 'a;
+
+#[derive(Debug, Clone)]
+pub struct RuntimeError { pub message: String, pub span: Option<Span>, pub flow: Option<Flow>, pub thrown: Option<Box<Value>> }
+impl RuntimeError {
+    pub fn new(m: impl Into<String>) -> Self { Self { message: m.into(), span: None, flow: None, thrown: None } }
+    pub fn with_span(m: impl Into<String>, span: Span) -> Self { Self { message: m.into(), span: Some(span), flow: None, thrown: None } }
+    /// The error raised by `throw value`; typed catches match against `value`
+    pub fn thrown(value: Value, span: Span) -> Self { Self { thrown: Some(Box::new(value.clone())), ..Self::with_span(value.to_string(), span) } }
+    pub fn display(&self) -> String {
+        match self.span {
+            Some(s) => format!("runtime error[{}:{}]: {}", s.line, s.column, self.message),
+            None => format!("runtime error: {}", self.message),
+        }
+    }
+    /// A control-flow signal; the message is what users see if nothing catches it
+    pub fn flow(f: Flow) -> Self {
+        let message = match f { Flow::Break => "'break' outside of a loop", Flow::Continue => "'continue' outside of a loop", Flow::Return(_) => "'return' outside of a function" };
+        Self { message: message.into(), span: None, flow: Some(f), thrown: None }
+    }
+}
+
+/// State handed to the step callback before a statement executes
+#[derive(Debug, Clone)]
+pub struct StepEvent {
+    pub span: Span,
+    pub locals: HashMap<String, Value>,
+    pub breakpoint: bool,
+}
+
+/// How execution proceeds after the step callback returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepAction {
+    /// Pause again before the next statement
+    Step,
+    /// Run until the next breakpoint
+    Continue,
+}
+
+pub type StepCallback = Box<dyn FnMut(&StepEvent) -> StepAction>;
+
+pub struct Interpreter {
+    env: Environment,
+    prelude: Prelude,
+    structs: HashMap<String, StructDecl>,
     functions: HashMap<String, FnDecl>,
+    newtypes: HashSet<String>,
+    step_callback: Option<StepCallback>,
+    stepping: bool,
+    breakpoints: HashSet<u32>,
+    defers: Vec<Vec<Deferred>>,
+    profiler: Option<Profiler>,
 }
 
+/// A registered `defer` block and the scope it captured when it was reached
+struct Deferred { start: usize, body: Rc<Block>, scope: ScopeSnapshot }
+
 impl Interpreter {
-    pub fn new() -> Self { Self { env: Environment::new(), structs: HashMap::new(), functions: HashMap::new() } }
+    /// An interpreter with the default, sandboxed prelude
+    pub fn new() -> Self { Self::with_prelude(Prelude::default()) }
+
+    /// An interpreter limited to the natives `prelude` allows; importing a
+    /// module outside it fails when the program loads
+    pub fn with_prelude(prelude: Prelude) -> Self {
+        Self { env: Environment::new(prelude), prelude, structs: HashMap::new(), functions: HashMap::new(), newtypes: HashSet::new(),
+               step_callback: None, stepping: false, breakpoints: HashSet::new(), defers: Vec::new(), profiler: None }
+    }
+
+    /// Time function calls (and, with `trace_memory`, estimate allocations) from now on
+    pub fn enable_profiling(&mut self, config: ProfilerConfig) {
+        self.profiler = Some(Profiler::new(config));
+    }
+
+    /// What has been profiled so far, if profiling is enabled
+    pub fn profile_summary(&self) -> Option<ProfilingSummary> {
+        self.profiler.as_ref().map(Profiler::summary)
+    }
+
+    /// The profile report in the configured format, if profiling is enabled
+    pub fn profile_report(&self) -> Option<String> {
+        self.profiler.as_ref().map(Profiler::report)
+    }
+
+    /// `map_to_struct(map, "Name")`: the map's string keys must be exactly the
+    /// struct's fields, with values of the declared primitive types
+    fn map_to_struct(&self, args: Vec<Value>) -> Result<Value, String> {
+        let mut args = args.into_iter();
+        let (Some(Value::Map(map)), Some(Value::String(name))) = (args.next(), args.next()) else {
+            return Err("map_to_struct expects a map and a struct name".to_string());
+        };
+        let decl = self.structs.get(&name).ok_or_else(|| format!("unknown struct '{}'", name))?;
+        let mut fields = HashMap::new();
+        for (key, value) in map {
+            let MapKey::String(key) = key else {
+                return Err(format!("struct '{}' field names must be strings, found '{}'", name, key));
+            };
+            let field = decl.fields.iter().find(|f| f.name == key)
+                .ok_or_else(|| format!("struct '{}' has no field '{}'", name, key))?;
+            let fits = match field.ty {
+                Type::Int | Type::FixedInt(_) => matches!(value, Value::Int(_)),
+                Type::Float => matches!(value, Value::Float(_) | Value::Int(_)),
+                Type::String => matches!(value, Value::String(_)),
+                Type::Bool => matches!(value, Value::Bool(_)),
+                _ => true,
+            };
+            if !fits {
+                return Err(format!("field '{}' of '{}' expects {}, found {}", key, name, field.ty, value.type_name()));
+            }
+            fields.insert(key, widen_to(&field.ty, value));
+        }
+        if let Some(missing) = decl.fields.iter().find(|f| !fields.contains_key(&f.name)) {
+            return Err(format!("missing field '{}' for struct '{}'", missing.name, name));
+        }
+        Ok(Value::Struct { name, fields })
+    }
+
+    /// `map_filter(map, pred)` keeps the entries for which `pred(key, value)` is
+    /// truthy; `map_map(map, f)` replaces each value with `f(key, value)`
+    fn map_with_action(&mut self, native: &str, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut args = args.into_iter();
+        let (Some(Value::Map(map)), Some(f)) = (args.next(), args.next()) else {
+            return Err(RuntimeError::new(format!("{} expects a map and an action", native)));
+        };
+        let mut out = HashMap::with_capacity(map.len());
+        for (key, value) in map {
+            let r = self.call_value(f.clone(), vec![key.to_value(), value.clone()])?;
+            if native == "map_map" {
+                out.insert(key, r);
+            } else if r.is_truthy() {
+                out.insert(key, value);
+            }
+        }
+        Ok(Value::Map(out))
+    }
+
+    /// Call an action or native passed as a value
+    fn call_value(&mut self, f: Value, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        match f {
+            Value::NativeAction(f) => Ok(f(args)),
+            Value::FallibleAction(f) => f(args),
+            Value::InterpreterAction(f) => f(self, args),
+            closure @ Value::Closure { .. } => self.call_closure(closure, args),
+            other => Err(RuntimeError::new(format!("expected an action, found {}", other.type_name()))),
+        }
+    }
+
+    /// Record a newly built value with the profiler; passes the value through
+    fn allocated(&mut self, v: Value) -> Value {
+        if let Some(p) = &mut self.profiler {
+            let bytes = v.heap_size();
+            if bytes > 0 { p.record_allocation(bytes); }
+        }
+        v
+    }
+
+    /// Enable step mode: `callback` runs before every statement until it returns
+    /// `StepAction::Continue`, after which it only runs at breakpoints.
+    pub fn step_mode(&mut self, callback: impl FnMut(&StepEvent) -> StepAction + 'static) {
+        self.step_callback = Some(Box::new(callback));
+        self.stepping = true;
+    }
+
+    /// Pause (invoke the step callback) before any statement on `line`
+    pub fn set_breakpoint(&mut self, line: u32) { self.breakpoints.insert(line); }
+    pub fn clear_breakpoint(&mut self, line: u32) { self.breakpoints.remove(&line); }
+
+    fn debug_hook(&mut self, s: &Stmt) {
+        if self.step_callback.is_none() { return; }
+        let span = s.span();
+        let breakpoint = self.breakpoints.contains(&span.line);
+        if !self.stepping && !breakpoint { return; }
+        let event = StepEvent { span, locals: self.env.locals(), breakpoint };
+        if let Some(cb) = self.step_callback.as_mut() { self.stepping = cb(&event) == StepAction::Step; }
+    }
     
     pub fn eval(&mut self, ast: &Ast) -> Result<Value, RuntimeError> {
+        self.load(ast)?;
+        if let Some(f) = self.functions.get("main").cloned() { self.call(&f, vec![]) } else { Ok(Value::Nil) }
+    }
+
+    /// Load `ast` and call the parameterless function `name` in place of `main`
+    pub fn eval_function(&mut self, ast: &Ast, name: &str) -> Result<Value, RuntimeError> {
+        self.load(ast)?;
+        match self.functions.get(name).cloned() {
+            Some(f) => self.call(&f, vec![]),
+            None => Err(RuntimeError::new(format!("unknown function '{}'", name))),
+        }
+    }
+
+    /// Register a program's declarations without running `main`
+    fn load(&mut self, ast: &Ast) -> Result<(), RuntimeError> {
         for d in &ast.declarations {
             match d { Decl::Struct(s) => { self.structs.insert(s.name.clone(), s.clone()); },
-                      Decl::Function(f) => { self.functions.insert(f.name.clone(), f.clone()); }, _ => {} }
+                      Decl::Function(f) => { self.functions.insert(f.name.clone(), f.clone()); },
+                      // Newtypes only exist for the typechecker; conversion is the identity at runtime
+                      Decl::TypeAlias(a) if a.distinct => { self.newtypes.insert(a.name.clone()); },
+                      Decl::Import(i) => {
+                          let module = i.path.join(".");
+                          if !self.prelude.permits(&module) {
+                              return Err(RuntimeError::with_span(format!("module '{}' is not available in this sandbox", module), i.span));
+                          }
+                      },
+                      _ => {} }
+        }
+        for d in &ast.declarations {
+            if let Decl::Const(c) = d { let v = self.expr(&c.value)?; self.env.define(&c.name, v); }
+        }
+        Ok(())
+    }
+
+    /// Evaluate one line of REPL input. Declarations are registered and statements run in
+    /// the global scope; a trailing bare expression's non-nil value is returned and bound to `_`.
+    pub fn eval_line(&mut self, line: &str) -> Result<Option<Value>, String> {
+        let mut src = line.trim().to_string();
+        if src.is_empty() { return Ok(None); }
+        // Let a bare expression be typed without its trailing semicolon
+        if !src.ends_with(';') && !src.ends_with('}') { src.push(';'); }
+        let tokens = crate::lexer::tokenize(&src).map_err(|e| e.display())?;
+        let is_decl = matches!(tokens.first().map(|t| &t.kind),
+            Some(TokenKind::Fn | TokenKind::Struct | TokenKind::Const | TokenKind::Import | TokenKind::Extern | TokenKind::Async | TokenKind::Typealias));
+        let mut parser = Parser::new(&tokens);
+        if is_decl {
+            let ast = parser.parse_program().map_err(|e| e.display())?;
+            self.load(&ast).map_err(|e| e.display())?;
+            return Ok(None);
+        }
+        let stmts = parser.parse_statements().map_err(|e| e.display())?;
+        let mut last = Value::Nil;
+        for s in &stmts { last = self.stmt(s).map_err(|e| e.display())?; }
+        match (stmts.last(), last) {
+            (Some(Stmt::Expr(_)), v) if !matches!(v, Value::Nil) => {
+                self.env.define("_", v.clone());
+                Ok(Some(v))
+            }
+            _ => Ok(None),
         }
-        if let Some(f) = self.functions.get("main").cloned() { self.call(&f, vec![]) } else { Ok(Value::Nil) }
     }
     
+    /// Evaluate a standalone expression such as `2 * 21` against the current
+    /// environment, so it sees globals and earlier REPL bindings
+    pub fn eval_expr(&mut self, source: &str) -> Result<Value, RuntimeError> {
+        let tokens = crate::lexer::tokenize(source).map_err(|e| match e.span {
+            Some(span) => RuntimeError::with_span(e.message, span),
+            None => RuntimeError::new(e.display()),
+        })?;
+        let expr = Parser::new(&tokens).parse_standalone_expression()
+            .map_err(|e| RuntimeError::with_span(e.message, e.span))?;
+        self.expr(&expr)
+    }
+
     fn call(&mut self, f: &FnDecl, a: Vec<Value>) -> Result<Value, RuntimeError> {
+        if let Some(p) = &mut self.profiler { p.enter_function(&f.name); }
         self.env.push();
-        for (i, p) in f.params.iter().enumerate() { self.env.define(&p.name, a.get(i).cloned().unwrap_or(Value::Nil)); }
+        for (i, p) in f.params.iter().enumerate() { self.env.define_typed(&p.name, &p.ty, a.get(i).cloned().unwrap_or(Value::Nil)); }
+        self.defers.push(Vec::new());
         let r = self.block(&f.body);
+        let r = self.run_defers(r);
         self.env.pop();
-        r
+        if let Some(p) = &mut self.profiler { p.exit_function(); }
+        let r = Self::finish_call(r);
+        match &f.return_type { Some(ty) => r.map(|v| widen_to(ty, v)), None => r }
     }
     
+    fn call_closure(&mut self, closure: Value, a: Vec<Value>) -> Result<Value, RuntimeError> {
+        let this = closure.clone();
+        let Value::Closure { params, body, env, name } = closure else { unreachable!("call_closure takes a closure") };
+        if let Some(p) = &mut self.profiler { p.enter_function("<action>"); }
+        let caller = env.map(|captured| { let caller = self.env.snapshot(); self.env.restore(captured); caller });
+        self.env.push();
+        if let Some(name) = &name { self.env.define(name, this); }
+        for (i, p) in params.iter().enumerate() { self.env.define(p, a.get(i).cloned().unwrap_or(Value::Nil)); }
+        self.defers.push(Vec::new());
+        let r = self.block(&body);
+        let r = self.run_defers(r);
+        self.env.pop();
+        if let Some(caller) = caller { self.env.restore(caller); }
+        if let Some(p) = &mut self.profiler { p.exit_function(); }
+        Self::finish_call(r)
+    }
+
+    /// Run the current call's defers, last registered first, each in the scope it
+    /// captured. An error from a defer replaces a normal result but not an earlier error.
+    fn run_defers(&mut self, mut r: Result<Value, RuntimeError>) -> Result<Value, RuntimeError> {
+        for d in self.defers.pop().unwrap_or_default().into_iter().rev() {
+            let caller = self.env.snapshot();
+            self.env.restore(d.scope);
+            let done = self.block(&d.body);
+            self.env.restore(caller);
+            if let Err(e) = done {
+                if !matches!(&r, Err(RuntimeError { flow: None, .. })) {
+                    r = Err(RuntimeError { flow: None, ..e });
+                }
+            }
+        }
+        r
+    }
+
+    /// Turn a `return` signal into the call's value; stray break/continue become plain errors
+    fn finish_call(r: Result<Value, RuntimeError>) -> Result<Value, RuntimeError> {
+        match r {
+            Err(RuntimeError { flow: Some(Flow::Return(v)), .. }) => Ok(*v),
+            Err(e) if e.flow.is_some() => Err(RuntimeError::new(e.message)),
+            other => other,
+        }
+    }
+
     fn block(&mut self, b: &Block) -> Result<Value, RuntimeError> {
         let mut r = Value::Nil;
-        for s in &b.statements { r = self.stmt(s)?; if matches!(s, Stmt::Return(_)) { return Ok(r); } }
+        for s in &b.statements { r = self.stmt(s)?; }
         Ok(r)
     }
+
+    /// Run one loop iteration; Ok(false) means the loop should stop
+    fn loop_body(&mut self, b: &Block) -> Result<bool, RuntimeError> {
+        match self.block(b) {
+            Ok(_) => Ok(true),
+            Err(RuntimeError { flow: Some(Flow::Break), .. }) => Ok(false),
+            Err(RuntimeError { flow: Some(Flow::Continue), .. }) => Ok(true),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Run the body of `for` loop `f` with its variable bound to `item`
+    fn for_iteration(&mut self, f: &ForStmt, item: Value) -> Result<bool, RuntimeError> {
+        self.env.push();
+        self.env.define(&f.var, item);
+        let keep_going = self.loop_body(&f.body);
+        self.env.pop();
+        keep_going
+    }
     
     fn stmt(&mut self, s: &Stmt) -> Result<Value, RuntimeError> {
+        self.debug_hook(s);
         match s {
             Stmt::Let(l) => { 
-                let v = l.init.as_ref().map(|e| self.expr(e)).transpose()?.unwrap_or(Value::Nil); 
-                self.env.define(&l.name, v); 
+                let v = match l.init.as_ref().map(|e| self.expr(e)).transpose()? {
+                    // `let f = action(..) { .. }` lets the body call `f`
+                    Some(Value::Closure { params, body, env, name: None }) if matches!(l.init, Some(Expr::Closure(..))) => {
+                        Value::Closure { params, body, env, name: Some(l.name.clone()) }
+                    }
+                    v => v.unwrap_or(Value::Nil),
+                };
+                match &l.ty { Some(ty) => self.env.define_typed(&l.name, ty, v), None => self.env.define(&l.name, v) }
                 Ok(Value::Nil) 
             },
+            Stmt::LetTuple(l) => {
+                let v = self.expr(&l.init)?;
+                let v = match &l.ty { Some(ty) => widen_to(ty, v), None => v };
+                match v {
+                    Value::Tuple(items) if items.len() == l.names.len() => {
+                        for (name, item) in l.names.iter().zip(items) { self.env.define(name, item); }
+                        Ok(Value::Nil)
+                    },
+                    Value::Tuple(items) => Err(RuntimeError::with_span(format!("cannot destructure a {}-element tuple into {} names", items.len(), l.names.len()), l.span)),
+                    other => Err(RuntimeError::with_span(format!("cannot destructure {} as a tuple", other.type_name()), l.span)),
+                }
+            },
+            // Local functions live in the enclosing scope like any binding and,
+            // as with named functions, run in the caller's scope when called
+            Stmt::FnDecl(f) => {
+                let params = f.params.iter().map(|p| p.name.clone()).collect();
+                self.env.define(&f.name, Value::Closure { params, body: Rc::new(f.body.clone()), env: None, name: None });
+                Ok(Value::Nil)
+            },
             Stmt::Expr(e) => self.expr(e),
-            Stmt::Return(r) => r.value.as_ref().map(|e| self.expr(e)).transpose().map(|v| v.unwrap_or(Value::Nil)),
+            Stmt::Return(r) => {
+                let v = r.value.as_ref().map(|e| self.expr(e)).transpose()?.unwrap_or(Value::Nil);
+                Err(RuntimeError::flow(Flow::Return(Box::new(v))))
+            },
             Stmt::If(i) => {
                 if self.expr(&i.condition)?.is_truthy() { 
                     self.block(&i.then_block) 
@@ -571,24 +1679,35 @@ impl Interpreter {
             },
             Stmt::While(w) => { 
                 while self.expr(&w.condition)?.is_truthy() { 
-                    self.block(&w.body)?; 
+                    if !self.loop_body(&w.body)? { break; }
                 } 
                 Ok(Value::Nil) 
             },
+            Stmt::Loop(l) => {
+                while self.loop_body(&l.body)? {}
+                Ok(Value::Nil)
+            },
             Stmt::For(f) => { 
-                if let Value::Array(a) = self.expr(&f.iterable)? { 
-                    for i in a { 
-                        self.env.push(); 
-                        self.env.define(&f.var, i); 
-                        self.block(&f.body)?; 
-                        self.env.pop(); 
-                    } 
-                } 
+                match self.expr(&f.iterable)? {
+                    Value::Array(a) => {
+                        for item in a {
+                            if !self.for_iteration(f, item)? { break; }
+                        }
+                    }
+                    // A bare int `n` counts 0, 1, ..., n-1: unlike the inclusive
+                    // range `0..n` it stops before n, so the body runs n times
+                    Value::Int(n) => {
+                        for i in 0..n {
+                            if !self.for_iteration(f, Value::Int(i))? { break; }
+                        }
+                    }
+                    _ => {}
+                }
                 Ok(Value::Nil) 
             },
             Stmt::Block(b) => self.block(b),
-            Stmt::Break(_) => Ok(Value::Nil), // Loop control handled at loop level
-            Stmt::Continue(_) => Ok(Value::Nil),
+            Stmt::Break(_) => Err(RuntimeError::flow(Flow::Break)),
+            Stmt::Continue(_) => Err(RuntimeError::flow(Flow::Continue)),
             // Swift-style guard statement
             Stmt::Guard(g) => {
                 if !self.expr(&g.condition)?.is_truthy() {
@@ -596,32 +1715,63 @@ impl Interpreter {
                 }
                 Ok(Value::Nil)
             },
-            // Defer - store for later execution (simplified: execute immediately at scope end)
+            // Defers are function-scoped: each runs once when the call returns, even if
+            // reached on several loop iterations, with the values from its first registration
             Stmt::Defer(d) => {
-                // In a full implementation, deferred blocks are collected and executed on scope exit
-                // For now, we just validate the block is valid
-                self.block(&d.body)?;
+                let scope = self.env.snapshot();
+                match self.defers.last_mut() {
+                    Some(frame) => {
+                        if !frame.iter().any(|x| x.start == d.span.start) {
+                            frame.push(Deferred { start: d.span.start, body: Rc::new(d.body.clone()), scope });
+                        }
+                    }
+                    // Outside any call (e.g. the REPL) there is no function exit to wait for
+                    None => { self.block(&d.body)?; }
+                }
                 Ok(Value::Nil)
             },
             // Try-catch exception handling
             Stmt::TryCatch(tc) => {
-                match self.block(&tc.try_block) {
+                let before = self.env.snapshot();
+                let result = match self.block(&tc.try_block) {
                     Ok(v) => Ok(v),
+                    // Control flow passes through try/catch untouched
+                    Err(e) if e.flow.is_some() => Err(e),
                     Err(e) => {
-                        self.env.push();
-                        if let Some(var) = &tc.catch_var {
-                            self.env.define(var, Value::String(e.message));
+                        // Runtime failures are caught as their message string
+                        let value = e.thrown.clone().map_or_else(|| Value::String(e.message.clone()), |v| *v);
+                        match tc.catches.iter().find(|c| c.ty.as_ref().is_none_or(|ty| value_has_type(&value, ty))) {
+                            None => Err(e),
+                            Some(clause) => {
+                                // A failed try leaves no trace of its variable mutations
+                                self.env.restore(before);
+                                self.env.push();
+                                if let Some(var) = &clause.var {
+                                    let bound = if clause.ty.is_some() { value } else { Value::String(e.message) };
+                                    self.env.define(var, bound);
+                                }
+                                let result = self.block(&clause.block);
+                                self.env.pop();
+                                result
+                            }
                         }
-                        let result = self.block(&tc.catch_block);
+                    }
+                };
+                // Finally runs on every exit; its own error or return wins
+                match &tc.finally_block {
+                    Some(f) => {
+                        self.env.push();
+                        let finished = self.block(f);
                         self.env.pop();
-                        result
+                        finished.and(result)
                     }
+                    None => result,
                 }
             },
             // Throw creates a runtime error
             Stmt::Throw(t) => {
-                let msg = self.expr(&t.value)?;
-                Err(RuntimeError::new(format!("{}", msg)))
+                let value = self.expr(&t.value)?;
+                Err(RuntimeError::thrown(value, t.span))
             },
         }
     }
@@ -631,45 +1781,85 @@ impl Interpreter {
             Expr::Literal(l) => Ok(match l { 
                 Literal::Int(i,_) => Value::Int(*i), 
                 Literal::Float(f,_) => Value::Float(*f), 
-                Literal::String(s,_) => Value::String(s.clone()), 
+                Literal::String(s,_) => self.allocated(Value::String(s.clone())), 
+                Literal::Char(c,_) => Value::Char(*c),
                 Literal::Bool(b,_) => Value::Bool(*b) 
             }),
-            Expr::Identifier(n, _) => self.env.get(n).ok_or_else(|| RuntimeError::new(format!("undefined: {}", n))),
+            Expr::Identifier(n, span) => {
+                if let Some(v) = self.env.get(n) { return Ok(v); }
+                // Named functions can be passed around as first-class values
+                self.functions.get(n)
+                    .map(|f| Value::Closure { params: f.params.iter().map(|p| p.name.clone()).collect(), body: Rc::new(f.body.clone()), env: None, name: None })
+                    .ok_or_else(|| RuntimeError::with_span(format!("undefined variable '{}'", n), *span))
+            },
             Expr::Binary(l, o, r, _) => { 
                 let lv = self.expr(l)?; 
                 let rv = self.expr(r)?; 
-                self.binop(lv, o, rv) 
+                let v = match (self.binop(lv, o, rv)?, self.int_width(l).or_else(|| self.int_width(r))) {
+                    (Value::Int(i), Some(w)) => Value::Int(w.wrap(i)),
+                    (v, _) => v,
+                };
+                Ok(self.allocated(v))
             },
             Expr::Unary(o, x, _) => { 
                 let v = self.expr(x)?; 
                 match o { 
                     UnaryOp::Neg => match v { 
-                        Value::Int(i) => Ok(Value::Int(-i)), 
+                        Value::Int(i) => Ok(Value::Int(self.int_width(x).map_or(-i, |w| w.wrap(-i)))), 
                         Value::Float(f) => Ok(Value::Float(-f)),
                         _ => Err(RuntimeError::new("cannot negate")) 
                     }, 
                     UnaryOp::Not => Ok(Value::Bool(!v.is_truthy())), 
                     UnaryOp::BitwiseNot => match v { 
-                        Value::Int(i) => Ok(Value::Int(!i)), 
+                        Value::Int(i) => Ok(Value::Int(self.int_width(x).map_or(!i, |w| w.wrap(!i)))), 
                         _ => Err(RuntimeError::new("bitwise not requires int")) 
                     } 
                 } 
             },
             Expr::Call(c, a, _) => {
-                if let Expr::Identifier(n, _) = c.as_ref() {
-                    let vs: Vec<Value> = a.iter().map(|x| self.expr(x)).collect::<Result<_,_>>()?;
-                    if let Some(Value::NativeAction(f)) = self.env.get(n) { return Ok(f(vs)); }
-                    if let Some(f) = self.functions.get(n).cloned() { return self.call(&f, vs); }
+                let vs: Vec<Value> = a.iter().map(|x| self.expr(x)).collect::<Result<_,_>>()?;
+                let callee = match c.as_ref() {
+                    Expr::Identifier(n, sp) => match self.env.get(n) {
+                        Some(v @ (Value::NativeAction(_) | Value::FallibleAction(_) | Value::InterpreterAction(_))) => {
+                            check_native_args(n, &vs).map_err(|mut e| { e.span = Some(*sp); e })?;
+                            v
+                        }
+                        Some(v) => v,
+                        None => match self.functions.get(n).cloned() {
+                            Some(f) => return self.call(&f, vs),
+                            None if self.newtypes.contains(n) => return Ok(vs.into_iter().next().unwrap_or(Value::Nil)),
+                            None => return Err(RuntimeError::new("unknown function")),
+                        },
+                    },
+                    other => self.expr(other)?,
+                };
+                match callee {
+                    Value::NativeAction(f) => { let v = f(vs); Ok(self.allocated(v)) },
+                    Value::FallibleAction(f) => {
+                        // Natives don't know where they were called from; point at the call
+                        let v = f(vs).map_err(|mut e| { e.span.get_or_insert(c.span()); e })?;
+                        Ok(self.allocated(v))
+                    },
+                    Value::InterpreterAction(f) => {
+                        let v = f(self, vs).map_err(|mut e| { e.span.get_or_insert(c.span()); e })?;
+                        Ok(self.allocated(v))
+                    },
+                    closure @ Value::Closure { .. } => self.call_closure(closure, vs),
+                    _ => match c.as_ref() {
+                        Expr::Member(_, field, _) => Err(RuntimeError::new(format!("field '{}' is not callable", field))),
+                        _ => Err(RuntimeError::new("unknown function")),
+                    },
                 }
-                Err(RuntimeError::new("unknown function"))
             },
-            Expr::Member(o, f, _) => { 
-                let ov = self.expr(o)?; 
-                if let Value::Struct{fields,..} = ov { 
-                    fields.get(f).cloned().ok_or_else(|| RuntimeError::new(format!("undefined field: {}", f))) 
-                } else { 
-                    Err(RuntimeError::new("member access on non-struct")) 
-                } 
+            // The receiver can be any expression, e.g. `make_point().x`
+            Expr::Member(o, f, span) => { 
+                match self.expr(o)? {
+                    Value::Struct { fields, .. } => fields.get(f).cloned().ok_or_else(|| RuntimeError::with_span(format!("undefined field: {}", f), *span)),
+                    Value::Color { r, g, b, a } => color_component(&mut [r, g, b, a], f)
+                        .map(|c| Value::Int(*c as i64))
+                        .ok_or_else(|| RuntimeError::with_span(format!("color has no component '{}'", f), *span)),
+                    other => Err(RuntimeError::with_span(format!("member access on non-struct {}", other.type_name()), *span)),
+                }
             },
             Expr::Index(a, i, _) => { 
                 let av = self.expr(a)?; 
@@ -678,115 +1868,89 @@ impl Interpreter {
                     (Value::Array(arr), Value::Int(idx)) => {
                         arr.get(*idx as usize).cloned().ok_or_else(|| RuntimeError::new("index out of bounds"))
                     },
-                    (Value::Map(m), Value::String(k)) => {
-                        Ok(m.get(k).cloned().unwrap_or(Value::Nil))
+                    (Value::Map(m), k) => {
+                        let k = MapKey::try_from(k).map_err(|e| RuntimeError::with_span(e, i.span()))?;
+                        Ok(m.get(&k).cloned().unwrap_or(Value::Nil))
+                    },
+                    (Value::Bytes(b), Value::Int(idx)) => {
+                        b.get(*idx as usize).map(|&x| Value::Int(x as i64)).ok_or_else(|| RuntimeError::new("index out of bounds"))
+                    },
+                    // Strings index by character, not byte
+                    (Value::String(s), Value::Int(idx)) => {
+                        usize::try_from(*idx).ok().and_then(|n| s.chars().nth(n)).map(Value::Char).ok_or_else(|| RuntimeError::new("index out of bounds"))
                     },
                     _ => Err(RuntimeError::new("invalid indexing"))
                 }
             },
-            Expr::Assign(t, v, _) => { 
-                let val = self.expr(v)?; 
-                if let Expr::Identifier(n, _) = t.as_ref() { 
-                    if self.env.set(n, val.clone()) { 
-                        Ok(val) 
-                    } else { 
-                        Err(RuntimeError::new("undefined variable")) 
-                    } 
-                } else { 
-                    Err(RuntimeError::new("invalid assignment target")) 
-                } 
+            Expr::Assign(t, v, _) => {
+                let val = self.expr(v)?;
+                self.update_place(t, &mut |_, _| Ok((val.clone(), val.clone())))
             },
-            Expr::ArrayLit(es, _) => Ok(Value::Array(es.iter().map(|x| self.expr(x)).collect::<Result<_,_>>()?)),
-            Expr::StructLit(n, fs, _) => { 
-                let mut m = HashMap::new(); 
-                for (k,v) in fs { m.insert(k.clone(), self.expr(v)?); } 
-                Ok(Value::Struct{name:n.clone(),fields:m}) 
+            Expr::ArrayLit(es, _) => { let v = Value::Array(es.iter().map(|x| self.expr(x)).collect::<Result<_,_>>()?); Ok(self.allocated(v)) },
+            Expr::Tuple(es, _) => { let v = Value::Tuple(es.iter().map(|x| self.expr(x)).collect::<Result<_,_>>()?); Ok(self.allocated(v)) },
+            Expr::StructLit(n, fs, base, _) => { 
+                let mut m = match base {
+                    Some(b) => match self.expr(b)? {
+                        Value::Struct { name, fields } if name == *n => fields,
+                        other => return Err(RuntimeError::with_span(
+                            format!("struct update base must be '{}', found {}", n, crate::stdlib::core::type_of(&other)),
+                            b.span(),
+                        )),
+                    },
+                    None => HashMap::new(),
+                };
+                for (k,v) in fs {
+                    let v = self.expr(v)?;
+                    let v = match self.structs.get(n).and_then(|s| s.fields.iter().find(|f| f.name == *k)) {
+                        Some(field) => widen_to(&field.ty, v),
+                        None => v,
+                    };
+                    m.insert(k.clone(), v);
+                } 
+                Ok(self.allocated(Value::Struct{name:n.clone(),fields:m}))
             },
             Expr::Match(x, arms, _) => { 
                 let v = self.expr(x)?; 
                 for arm in arms { 
                     if self.pat(&arm.pattern, &v) { 
-                        return self.expr(&arm.body); 
+                        let names = arm.pattern.binding_paths();
+                        if names.is_empty() { return self.expr(&arm.body); }
+                        self.env.push();
+                        for (name, path) in names {
+                            // Struct patterns bind fields; the pattern matched, so each path exists
+                            let bound = path.iter().fold(v.clone(), |value, field| match value {
+                                Value::Struct { mut fields, .. } => fields.remove(*field).unwrap_or(Value::Nil),
+                                _ => Value::Nil,
+                            });
+                            self.env.define(name, bound);
+                        }
+                        let r = self.expr(&arm.body);
+                        self.env.pop();
+                        return r;
                     } 
                 } 
                 Ok(Value::Nil) 
             },
             // Compound assignments: +=, -=, *=, /=, %=
             Expr::CompoundAssign(target, op, value, _) => {
-                if let Expr::Identifier(n, _) = target.as_ref() {
-                    let current = self.env.get(n).ok_or_else(|| RuntimeError::new("undefined"))?;
-                    let rhs = self.expr(value)?;
-                    let result = match op {
-                        CompoundOp::AddEq => self.binop(current, &BinOp::Add, rhs)?,
-                        CompoundOp::SubEq => self.binop(current, &BinOp::Sub, rhs)?,
-                        CompoundOp::MulEq => self.binop(current, &BinOp::Mul, rhs)?,
-                        CompoundOp::DivEq => self.binop(current, &BinOp::Div, rhs)?,
-                        CompoundOp::ModEq => self.binop(current, &BinOp::Mod, rhs)?,
-                    };
-                    self.env.set(n, result.clone());
-                    Ok(result)
-                } else {
-                    Err(RuntimeError::new("invalid compound assignment target"))
-                }
-            },
-            // Pre-increment: ++x
-            Expr::PreIncrement(target, _) => {
-                if let Expr::Identifier(n, _) = target.as_ref() {
-                    let current = self.env.get(n).ok_or_else(|| RuntimeError::new("undefined"))?;
-                    if let Value::Int(i) = current {
-                        let new_val = Value::Int(i + 1);
-                        self.env.set(n, new_val.clone());
-                        Ok(new_val)
-                    } else {
-                        Err(RuntimeError::new("increment requires int"))
-                    }
-                } else {
-                    Err(RuntimeError::new("invalid increment target"))
-                }
-            },
-            // Pre-decrement: --x
-            Expr::PreDecrement(target, _) => {
-                if let Expr::Identifier(n, _) = target.as_ref() {
-                    let current = self.env.get(n).ok_or_else(|| RuntimeError::new("undefined"))?;
-                    if let Value::Int(i) = current {
-                        let new_val = Value::Int(i - 1);
-                        self.env.set(n, new_val.clone());
-                        Ok(new_val)
-                    } else {
-                        Err(RuntimeError::new("decrement requires int"))
-                    }
-                } else {
-                    Err(RuntimeError::new("invalid decrement target"))
-                }
-            },
-            // Post-increment: x++
-            Expr::PostIncrement(target, _) => {
-                if let Expr::Identifier(n, _) = target.as_ref() {
-                    let current = self.env.get(n).ok_or_else(|| RuntimeError::new("undefined"))?;
-                    if let Value::Int(i) = current {
-                        self.env.set(n, Value::Int(i + 1));
-                        Ok(Value::Int(i)) // Return old value
-                    } else {
-                        Err(RuntimeError::new("increment requires int"))
-                    }
-                } else {
-                    Err(RuntimeError::new("invalid increment target"))
-                }
-            },
-            // Post-decrement: x--
-            Expr::PostDecrement(target, _) => {
-                if let Expr::Identifier(n, _) = target.as_ref() {
-                    let current = self.env.get(n).ok_or_else(|| RuntimeError::new("undefined"))?;
-                    if let Value::Int(i) = current {
-                        self.env.set(n, Value::Int(i - 1));
-                        Ok(Value::Int(i)) // Return old value
-                    } else {
-                        Err(RuntimeError::new("decrement requires int"))
-                    }
-                } else {
-                    Err(RuntimeError::new("invalid decrement target"))
-                }
+                let rhs = self.expr(value)?;
+                let op = match op {
+                    CompoundOp::AddEq => BinOp::Add,
+                    CompoundOp::SubEq => BinOp::Sub,
+                    CompoundOp::MulEq => BinOp::Mul,
+                    CompoundOp::DivEq => BinOp::Div,
+                    CompoundOp::ModEq => BinOp::Mod,
+                };
+                self.update_place(target, &mut |this, current| {
+                    let result = this.binop(current, &op, rhs.clone())?;
+                    Ok((result.clone(), result))
+                })
             },
+            // ++x / --x yield the updated value, x++ / x-- the old one
+            Expr::PreIncrement(target, span) => self.update_place(target, &mut |_, current| step(current, 1, *span).map(|v| (v.clone(), v))),
+            Expr::PreDecrement(target, span) => self.update_place(target, &mut |_, current| step(current, -1, *span).map(|v| (v.clone(), v))),
+            Expr::PostIncrement(target, span) => self.update_place(target, &mut |_, current| step(current.clone(), 1, *span).map(|v| (v, current))),
+            Expr::PostDecrement(target, span) => self.update_place(target, &mut |_, current| step(current.clone(), -1, *span).map(|v| (v, current))),
             // Nil literal
             Expr::Nil(_) => Ok(Value::Nil),
             // Null coalescing: a ?? b
@@ -813,6 +1977,8 @@ impl Interpreter {
             // Await: await expr (simplified, just evaluates the expr)
             Expr::Await(inner, _) => self.expr(inner),
             // Range expression: start..end generates array [start, start+1, ..., end]
+            Expr::Block(b, _) => { self.env.push(); let r = self.block(b); self.env.pop(); r },
+            Expr::Closure(params, _, body, _) => Ok(Value::Closure { params: params.iter().map(|p| p.name.clone()).collect(), body: Rc::new((**body).clone()), env: Some(self.env.snapshot()), name: None }),
             Expr::Range(start, end, _) => {
                 let s = self.expr(start)?;
                 let e = self.expr(end)?;
@@ -827,10 +1993,115 @@ impl Interpreter {
         }
     }
     
+    /// Read-modify-write the place `target` names (a variable, `obj.field`, or
+    /// `arr[i]`, nested arbitrarily). `f` maps the current value to the value to
+    /// store and the value of the whole expression; containers are written back
+    /// outwards since values are copied, and each index is evaluated once.
+    fn update_place(
+        &mut self,
+        target: &Expr,
+        f: &mut PlaceUpdate,
+    ) -> Result<Value, RuntimeError> {
+        match target {
+            Expr::Identifier(n, _) => {
+                let current = self.env.get(n).ok_or_else(|| RuntimeError::new("undefined variable"))?;
+                let (new, out) = f(self, current)?;
+                self.env.set(n, new);
+                Ok(out)
+            },
+            Expr::Member(o, field, _) => self.update_place(o, &mut |this, ov| match ov {
+                Value::Struct { name, mut fields } => {
+                    let current = fields.get(field).cloned().ok_or_else(|| RuntimeError::new(format!("undefined field: {}", field)))?;
+                    let (new, out) = f(this, current)?;
+                    fields.insert(field.clone(), new);
+                    Ok((Value::Struct { name, fields }, out))
+                },
+                // Components are stored clamped to 0-255
+                Value::Color { r, g, b, a } => {
+                    let mut rgba = [r, g, b, a];
+                    let slot = color_component(&mut rgba, field).ok_or_else(|| RuntimeError::new(format!("color has no component '{}'", field)))?;
+                    let (new, out) = f(this, Value::Int(*slot as i64))?;
+                    let Value::Int(n) = new else { return Err(mismatch("int", &new)) };
+                    *slot = n.clamp(0, 255) as u8;
+                    let [r, g, b, a] = rgba;
+                    Ok((Value::Color { r, g, b, a }, out))
+                },
+                _ => Err(RuntimeError::new("member access on non-struct")),
+            }),
+            Expr::Index(a, i, _) => {
+                let iv = self.expr(i)?;
+                let span = i.span();
+                self.update_place(a, &mut |this, av| match (av, &iv) {
+                    (Value::Array(mut arr), Value::Int(idx)) => {
+                        let slot = usize::try_from(*idx).ok().and_then(|idx| arr.get_mut(idx)).ok_or_else(|| RuntimeError::new("index out of bounds"))?;
+                        let (new, out) = f(this, slot.clone())?;
+                        *slot = new;
+                        Ok((Value::Array(arr), out))
+                    },
+                    (Value::Map(mut m), k) => {
+                        let k = MapKey::try_from(k).map_err(|e| RuntimeError::with_span(e, span))?;
+                        let (new, out) = f(this, m.get(&k).cloned().unwrap_or(Value::Nil))?;
+                        m.insert(k, new);
+                        Ok((Value::Map(m), out))
+                    },
+                    _ => Err(RuntimeError::new("invalid indexing")),
+                })
+            },
+            _ => Err(RuntimeError::new("invalid assignment target")),
+        }
+    }
+
     fn pat(&self, p: &Pattern, v: &Value) -> bool {
-        match p { Pattern::Wildcard | Pattern::Identifier(_) => true, Pattern::Literal(l) => match (l,v) { (Literal::Int(a,_), Value::Int(b)) => *a==*b, (Literal::Bool(a,_), Value::Bool(b)) => *a==*b, _ => false } }
+        match p {
+            Pattern::Wildcard | Pattern::Identifier(_) => true,
+            Pattern::Literal(l) => match (l,v) {
+                (Literal::Int(a,_), Value::Int(b)) => *a==*b,
+                // An int literal matches a float field it was widened into
+                (Literal::Int(a,_), Value::Float(b)) => *a as f64==*b,
+                (Literal::Float(a,_), Value::Float(b)) => *a==*b,
+                (Literal::Bool(a,_), Value::Bool(b)) => *a==*b,
+                (Literal::String(a,_), Value::String(b)) => a==b,
+                (Literal::Char(a,_), Value::Char(b)) => a==b,
+                _ => false
+            },
+            Pattern::Range(lo, hi) => matches!(v, Value::Int(n) if (*lo..=*hi).contains(n)),
+            Pattern::Binding { inner, .. } => self.pat(inner, v),
+            Pattern::Struct { name, fields: pats } => match v {
+                Value::Struct { name: n, fields } if n == name => pats.iter().all(|(field, p)| fields.get(field).is_some_and(|fv| self.pat(p, fv))),
+                _ => false,
+            },
+        }
     }
     
+    /// Width a fixed-width int expression wraps to, like the C it compiles to:
+    /// that of the fixed-width variables, conversions and calls it is built from
+    fn int_width(&self, e: &Expr) -> Option<IntWidth> {
+        match e {
+            Expr::Identifier(n, _) => self.env.width(n),
+            Expr::Call(callee, _, _) => match callee.as_ref() {
+                Expr::Identifier(n, _) => match self.functions.get(n) {
+                    Some(f) => match f.return_type { Some(Type::FixedInt(w)) => Some(w), _ => None },
+                    None => IntWidth::from_name(n),
+                },
+                _ => None,
+            },
+            Expr::Binary(l, BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod | BinOp::BitwiseAnd
+                | BinOp::BitwiseOr | BinOp::BitwiseXor | BinOp::ShiftLeft | BinOp::ShiftRight, r, _) => {
+                self.int_width(l).or_else(|| self.int_width(r))
+            }
+            Expr::Unary(UnaryOp::Neg | UnaryOp::BitwiseNot, x, _) => self.int_width(x),
+            Expr::Member(obj, field, _) => {
+                let Expr::Identifier(n, _) = obj.as_ref() else { return None };
+                let Some(Value::Struct { name, .. }) = self.env.lookup(n) else { return None };
+                match self.structs.get(name)?.fields.iter().find(|f| &f.name == field)?.ty {
+                    Type::FixedInt(w) => Some(w),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
     fn binop(&self, l: Value, o: &BinOp, r: Value) -> Result<Value, RuntimeError> {
         Ok(match o {
             BinOp::Add => match (l,r) { 
@@ -839,6 +2110,10 @@ impl Interpreter {
                 (Value::Int(a),Value::Float(b)) => Value::Float(a as f64 + b),
                 (Value::Float(a),Value::Int(b)) => Value::Float(a + b as f64),
                 (Value::String(a),Value::String(b)) => Value::String(a+&b), 
+                // Anything added to a string is formatted as `to_string` would
+                (Value::String(a),b) => Value::String(format!("{}{}", a, b)),
+                (a,Value::String(b)) => Value::String(format!("{}{}", a, b)),
+                (Value::Array(mut a),Value::Array(b)) => { a.extend(b); Value::Array(a) },
                 _ => return Err(RuntimeError::new("+")) 
             },
             BinOp::Sub => match (l,r) { 
@@ -853,75 +2128,1393 @@ impl Interpreter {
                 (Value::Float(a),Value::Float(b)) => Value::Float(a*b),
                 (Value::Int(a),Value::Float(b)) => Value::Float(a as f64 * b),
                 (Value::Float(a),Value::Int(b)) => Value::Float(a * b as f64),
+                (Value::String(s),Value::Int(n)) | (Value::Int(n),Value::String(s)) => Value::String(repeat_string(&s, n)?),
                 _ => return Err(RuntimeError::new("*")) 
             },
+            // Float division follows IEEE 754 (infinity or NaN); only int division by zero fails
             BinOp::Div => match (l,r) { 
-                (Value::Int(a),Value::Int(b)) if b!=0 => Value::Int(a/b), 
-                (Value::Float(a),Value::Float(b)) if b!=0.0 => Value::Float(a/b),
-                (Value::Int(a),Value::Float(b)) if b!=0.0 => Value::Float(a as f64 / b),
-                (Value::Float(a),Value::Int(b)) if b!=0 => Value::Float(a / b as f64),
+                (Value::Int(_),Value::Int(0)) => return Err(RuntimeError::new("division by zero")),
+                (Value::Int(a),Value::Int(b)) => Value::Int(a.wrapping_div(b)), 
+                (Value::Float(a),Value::Float(b)) => Value::Float(a/b),
+                (Value::Int(a),Value::Float(b)) => Value::Float(a as f64 / b),
+                (Value::Float(a),Value::Int(b)) => Value::Float(a / b as f64),
                 _ => return Err(RuntimeError::new("/")) 
             },
             BinOp::Mod => match (l,r) { 
-                (Value::Int(a),Value::Int(b)) if b!=0 => Value::Int(a%b), 
-                (Value::Float(a),Value::Float(b)) if b!=0.0 => Value::Float(a%b),
-                (Value::Int(a),Value::Float(b)) if b!=0.0 => Value::Float((a as f64) % b),
-                (Value::Float(a),Value::Int(b)) if b!=0 => Value::Float(a % (b as f64)),
+                (Value::Int(_),Value::Int(0)) => return Err(RuntimeError::new("division by zero")),
+                (Value::Int(a),Value::Int(b)) => Value::Int(a.wrapping_rem(b)), 
+                (Value::Float(a),Value::Float(b)) => Value::Float(a%b),
+                (Value::Int(a),Value::Float(b)) => Value::Float((a as f64) % b),
+                (Value::Float(a),Value::Int(b)) => Value::Float(a % (b as f64)),
                 _ => return Err(RuntimeError::new("%")) 
             },
-            BinOp::Eq => Value::Bool(self.eq(&l,&r)), 
-            BinOp::Ne => Value::Bool(!self.eq(&l,&r)),
-            BinOp::Lt => match (l,r) { 
-                (Value::Int(a),Value::Int(b)) => Value::Bool(a<b), 
-                (Value::Float(a),Value::Float(b)) => Value::Bool(a<b),
-                (Value::Int(a),Value::Float(b)) => Value::Bool((a as f64) < b),
-                (Value::Float(a),Value::Int(b)) => Value::Bool(a < (b as f64)),
-                _ => return Err(RuntimeError::new("<")) 
-            },
-            BinOp::Gt => match (l,r) { 
-                (Value::Int(a),Value::Int(b)) => Value::Bool(a>b), 
-                (Value::Float(a),Value::Float(b)) => Value::Bool(a>b),
-                (Value::Int(a),Value::Float(b)) => Value::Bool((a as f64) > b),
-                (Value::Float(a),Value::Int(b)) => Value::Bool(a > (b as f64)),
-                _ => return Err(RuntimeError::new(">")) 
-            },
-            BinOp::Le => match (l,r) { 
-                (Value::Int(a),Value::Int(b)) => Value::Bool(a<=b), 
-                (Value::Float(a),Value::Float(b)) => Value::Bool(a<=b),
-                (Value::Int(a),Value::Float(b)) => Value::Bool((a as f64) <= b),
-                (Value::Float(a),Value::Int(b)) => Value::Bool(a <= (b as f64)),
-                _ => return Err(RuntimeError::new("<=")) 
-            },
-            BinOp::Ge => match (l,r) { 
-                (Value::Int(a),Value::Int(b)) => Value::Bool(a>=b), 
-                (Value::Float(a),Value::Float(b)) => Value::Bool(a>=b),
-                (Value::Int(a),Value::Float(b)) => Value::Bool((a as f64) >= b),
-                (Value::Float(a),Value::Int(b)) => Value::Bool(a >= (b as f64)),
-                _ => return Err(RuntimeError::new(">=")) 
-            },
+            BinOp::Eq => Value::Bool(values_eq(&l,&r)), 
+            BinOp::Ne => Value::Bool(!values_eq(&l,&r)),
+            BinOp::Lt => Value::Bool(compare_values(&l,&r,"<")?.is_some_and(|o| o.is_lt())),
+            BinOp::Gt => Value::Bool(compare_values(&l,&r,">")?.is_some_and(|o| o.is_gt())),
+            BinOp::Le => Value::Bool(compare_values(&l,&r,"<=")?.is_some_and(|o| o.is_le())),
+            BinOp::Ge => Value::Bool(compare_values(&l,&r,">=")?.is_some_and(|o| o.is_ge())),
             BinOp::And => Value::Bool(l.is_truthy() && r.is_truthy()), 
             BinOp::Or => Value::Bool(l.is_truthy() || r.is_truthy()),
+            BinOp::In => match r {
+                Value::Array(items) => Value::Bool(items.iter().any(|v| values_eq(&l, v))),
+                Value::Map(m) => Value::Bool(MapKey::try_from(&l).is_ok_and(|k| m.contains_key(&k))),
+                Value::String(s) => match l { Value::String(sub) => Value::Bool(s.contains(&sub)), Value::Char(c) => Value::Bool(s.contains(c)), _ => return Err(RuntimeError::new("'in' on a string needs a string operand")) },
+                other => return Err(RuntimeError::new(format!("'in' requires an array, map or string, found {}", other.type_name()))),
+            },
             // Bitwise operators
             BinOp::BitwiseAnd => match (l,r) { (Value::Int(a),Value::Int(b)) => Value::Int(a&b), _ => return Err(RuntimeError::new("&")) },
             BinOp::BitwiseOr => match (l,r) { (Value::Int(a),Value::Int(b)) => Value::Int(a|b), _ => return Err(RuntimeError::new("|")) },
             BinOp::BitwiseXor => match (l,r) { (Value::Int(a),Value::Int(b)) => Value::Int(a^b), _ => return Err(RuntimeError::new("^")) },
-            BinOp::ShiftLeft => match (l,r) { (Value::Int(a),Value::Int(b)) => Value::Int(a<<b), _ => return Err(RuntimeError::new("<<")) },
-            BinOp::ShiftRight => match (l,r) { (Value::Int(a),Value::Int(b)) => Value::Int(a>>b), _ => return Err(RuntimeError::new(">>")) },
+            // Shift amounts outside 0..64 are an error rather than a Rust overflow panic
+            BinOp::ShiftLeft => match (l,r) { (Value::Int(a),Value::Int(b)) => Value::Int(a << shift_amount(b)?), _ => return Err(RuntimeError::new("<<")) },
+            BinOp::ShiftRight => match (l,r) { (Value::Int(a),Value::Int(b)) => Value::Int(a >> shift_amount(b)?), _ => return Err(RuntimeError::new(">>")) },
         })
     }
-    
-    fn eq(&self, a: &Value, b: &Value) -> bool { 
-        match (a,b) { 
-            (Value::Nil,Value::Nil) => true, 
-            (Value::Bool(a),Value::Bool(b)) => a==b, 
-            (Value::Int(a),Value::Int(b)) => a==b, 
-            (Value::Float(a),Value::Float(b)) => (a - b).abs() < f64::EPSILON,
-            (Value::String(a),Value::String(b)) => a==b, 
-            _ => false 
-        } 
-    }
 }
 
 impl Default for Interpreter { fn default() -> Self { Self::new() } }
 
 pub fn eval(ast: &Ast) -> Result<Value, RuntimeError> { Interpreter::new().eval(ast) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(src: &str) -> Value {
+        let tokens = crate::lexer::tokenize(src).expect("lex error");
+        let ast = crate::parser::parse(&tokens);
+        eval(&ast).expect("runtime error")
+    }
+
+    /// `run` with every native group, for tests of filesystem and process natives
+    fn run_all(src: &str) -> Value {
+        let tokens = crate::lexer::tokenize(src).expect("lex error");
+        Interpreter::with_prelude(Prelude::ALL).eval(&crate::parser::parse(&tokens)).expect("runtime error")
+    }
+
+    #[test]
+    fn test_color_lerp_native_mid_gray() {
+        let v = run("fn main() { return color_lerp(rgb(0, 0, 0), rgb(255, 255, 255), 0.5); }");
+        match v {
+            Value::Color { r, g, b, a } => assert_eq!((r, g, b, a), (127, 127, 127, 255)),
+            other => panic!("expected color, got {}", other),
+        }
+    }
+
+    #[test]
+    fn test_color_lighten_darken_with_alpha() {
+        let v = run("fn main() { return color_lighten(rgb(0, 0, 0), 1.0); }");
+        assert!(matches!(v, Value::Color { r: 255, g: 255, b: 255, a: 255 }));
+        let v = run("fn main() { return color_darken(rgb(200, 100, 50), 0.5); }");
+        assert!(matches!(v, Value::Color { r: 100, g: 50, b: 25, a: 255 }));
+        let v = run("fn main() { return color_with_alpha(rgb(10, 20, 30), 128); }");
+        assert!(matches!(v, Value::Color { r: 10, g: 20, b: 30, a: 128 }));
+    }
+
+    #[test]
+    fn test_const_sizes_array() {
+        let v = run(r#"
+            const fn square(n: int) -> int { return n * n; }
+            const SIZE: int = square(4);
+            fn main() {
+                let mut buf = [];
+                let mut i = 0;
+                while (i < SIZE) { buf = push(buf, i); i = i + 1; }
+                return len(buf);
+            }
+        "#);
+        assert!(matches!(v, Value::Int(16)));
+    }
+
+    #[test]
+    fn test_format_float_whole_and_repeating() {
+        assert_eq!(format_float(1.0), "1.0");
+        assert_eq!(format_float(-3.0), "-3.0");
+        assert_eq!(format_float(0.1 + 0.2), "0.3");
+        assert_eq!(format_float(1.0 / 3.0), "0.333333333333");
+        assert_eq!(Value::Float(2.5).to_string(), "2.5");
+    }
+
+    #[test]
+    fn test_to_string_native_uses_float_format() {
+        let v = run("fn main() { return to_string(1.0); }");
+        assert!(matches!(v, Value::String(ref s) if s == "1.0"));
+        let v = run("fn main() { return to_string(0.1 + 0.2); }");
+        assert!(matches!(v, Value::String(ref s) if s == "0.3"));
+    }
+
+    #[test]
+    fn test_for_over_int_iterates_from_zero() {
+        let v = run(r#"
+            fn main() {
+                let mut seen = [];
+                for i in 3 { seen = push(seen, i); }
+                return seen;
+            }
+        "#);
+        assert_eq!(v.to_string(), "[0,1,2]");
+    }
+
+    #[test]
+    fn test_step_mode_visits_each_statement() {
+        use std::{cell::RefCell, rc::Rc};
+        let src = "fn main() {\n    let a = 1;\n    let b = 2;\n    let c = a + b;\n}";
+        let ast = crate::parser::parse(&crate::lexer::tokenize(src).unwrap());
+        let events: Rc<RefCell<Vec<StepEvent>>> = Rc::new(RefCell::new(Vec::new()));
+        let sink = events.clone();
+        let mut interp = Interpreter::new();
+        interp.step_mode(move |e| { sink.borrow_mut().push(e.clone()); StepAction::Step });
+        interp.eval(&ast).unwrap();
+
+        let events = events.borrow();
+        let lines: Vec<u32> = events.iter().map(|e| e.span.line).collect();
+        assert_eq!(lines, vec![2, 3, 4]);
+        assert!(events[2].locals.contains_key("a") && events[2].locals.contains_key("b"));
+        assert!(!events[2].locals.contains_key("c"));
+    }
+
+    #[test]
+    fn test_breakpoint_pauses_after_continue() {
+        use std::{cell::RefCell, rc::Rc};
+        let src = "fn main() {\n    let a = 1;\n    let b = 2;\n    let c = a + b;\n}";
+        let ast = crate::parser::parse(&crate::lexer::tokenize(src).unwrap());
+        let lines: Rc<RefCell<Vec<(u32, bool)>>> = Rc::new(RefCell::new(Vec::new()));
+        let sink = lines.clone();
+        let mut interp = Interpreter::new();
+        interp.step_mode(move |e| { sink.borrow_mut().push((e.span.line, e.breakpoint)); StepAction::Continue });
+        interp.set_breakpoint(4);
+        interp.eval(&ast).unwrap();
+        assert_eq!(*lines.borrow(), vec![(2, false), (4, true)]);
+    }
+
+    #[test]
+    fn test_uninitialized_let_is_nil_until_assigned() {
+        assert!(matches!(run("fn main() { let x: int; return x; }"), Value::Nil));
+        assert!(matches!(run("fn main() { let x: int; x = 5; return x; }"), Value::Int(5)));
+    }
+
+    #[test]
+    fn test_closures_and_function_values() {
+        let v = run(r#"
+            fn apply(f: fn(int) -> int, x: int) -> int { return f(x); }
+            fn inc(n: int) -> int { return n + 1; }
+            fn main() {
+                let double = action(n: int) -> int { return n * 2; };
+                return apply(double, 20) + apply(inc, 1);
+            }
+        "#);
+        assert!(matches!(v, Value::Int(42)));
+    }
+
+    #[test]
+    fn test_value_conversions_report_mismatch() {
+        assert!(matches!(Value::from(7i64), Value::Int(7)));
+        assert!(matches!(Value::from("hi"), Value::String(ref s) if s == "hi"));
+        assert_eq!(f64::try_from(Value::Int(2)).unwrap(), 2.0);
+        let err = bool::try_from(Value::Int(1)).unwrap_err();
+        assert_eq!(err.message, "expected bool, found int");
+    }
+
+    #[test]
+    fn test_return_inside_match_arm_block() {
+        let src = r#"
+            fn check(x: int) -> int {
+                match x {
+                    0 => { return 100; }
+                    _ => {}
+                }
+                return 1;
+            }
+            fn main() { return check(0) * 10 + check(5); }
+        "#;
+        assert!(matches!(run(src), Value::Int(1001)));
+    }
+
+    #[test]
+    fn test_break_and_continue_inside_match_arms() {
+        let v = run(r#"
+            fn main() {
+                let mut total = 0;
+                for i in 10 {
+                    match i {
+                        2 => { continue; }
+                        5 => { break; }
+                        _ => { total += i; }
+                    }
+                }
+                return total;
+            }
+        "#);
+        // 0 + 1 + 3 + 4
+        assert!(matches!(v, Value::Int(8)));
+    }
+
+    #[test]
+    fn test_bare_return_from_match_arm_skips_rest() {
+        let v = run(r#"
+            fn main() {
+                let mut hits = 0;
+                let x = 0;
+                match x {
+                    0 => { return; }
+                    _ => {}
+                }
+                hits = 1;
+                return hits;
+            }
+        "#);
+        assert!(matches!(v, Value::Nil));
+    }
+
+    #[test]
+    fn test_out_of_range_shift_is_error() {
+        let src = "fn main() { return 1 << 70; }";
+        let ast = crate::parser::parse(&crate::lexer::tokenize(src).unwrap());
+        let err = eval(&ast).unwrap_err();
+        assert_eq!(err.message, "shift amount 70 out of range 0..64");
+        let src = "fn main() { return 8 >> -1; }";
+        let ast = crate::parser::parse(&crate::lexer::tokenize(src).unwrap());
+        assert!(eval(&ast).is_err());
+        assert!(matches!(run("fn main() { return 1 << 63 >> 62; }"), Value::Int(-2)));
+    }
+
+    #[test]
+    fn test_undefined_variable_error_has_location() {
+        let src = "fn main() {\n    let a = 1;\n    return a + missing;\n}";
+        let ast = crate::parser::parse(&crate::lexer::tokenize(src).unwrap());
+        let err = eval(&ast).unwrap_err();
+        assert_eq!(err.span.map(|s| s.line), Some(3));
+        assert!(err.display().starts_with("runtime error[3:"));
+        assert!(err.display().ends_with("undefined variable 'missing'"));
+    }
+
+    #[test]
+    fn test_loop_terminates_via_break() {
+        let v = run(r#"
+            fn main() {
+                let mut n = 0;
+                let mut done = false;
+                loop {
+                    if done { break; }
+                    n += 1;
+                    done = n == 5;
+                }
+                return n;
+            }
+        "#);
+        assert!(matches!(v, Value::Int(5)));
+    }
+
+    #[test]
+    fn test_string_repeat_operator() {
+        assert!(matches!(run(r#"fn main() { return "ab" * 3; }"#), Value::String(ref s) if s == "ababab"));
+        assert!(matches!(run(r#"fn main() { return 2 * "-"; }"#), Value::String(ref s) if s == "--"));
+        let ast = crate::parser::parse(&crate::lexer::tokenize(r#"fn main() { return "x" * -1; }"#).unwrap());
+        assert!(eval(&ast).is_err());
+        let ast = crate::parser::parse(&crate::lexer::tokenize(r#"fn main() { return "xy" * 9223372036854775807; }"#).unwrap());
+        assert!(eval(&ast).is_err());
+    }
+
+    #[test]
+    fn test_string_concat_formats_other_operand() {
+        let v = run(r#"fn main() { return ["x=" + 5, 1.5 + "s", "ok: " + true + '!', "" + [1, 2], 1 + 2 + "a", "a" + 1 + 2]; }"#);
+        assert_eq!(v.to_string(), "[x=5,1.5s,ok: true!,[1,2],3a,a12]");
+        assert!(matches!(run("fn main() { return 2 + 3; }"), Value::Int(5)));
+        assert!(matches!(run("fn main() { return 2 + 0.5; }"), Value::Float(f) if f == 2.5));
+    }
+
+    #[test]
+    fn test_array_concat_and_in() {
+        match run("fn main() { return [1, 2] + [3]; }") {
+            Value::Array(v) => assert_eq!(v.iter().map(|x| match x { Value::Int(i) => *i, _ => -1 }).collect::<Vec<_>>(), vec![1, 2, 3]),
+            other => panic!("expected array, got {}", other),
+        }
+        assert!(matches!(run("fn main() { return 2 in [1, 2, 3]; }"), Value::Bool(true)));
+        assert!(matches!(run("fn main() { return 5 in [1, 2, 3]; }"), Value::Bool(false)));
+        assert!(matches!(run("fn main() { return [1] in [[0], [1]]; }"), Value::Bool(true)));
+        assert!(matches!(run(r#"fn main() { return "ell" in "hello"; }"#), Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_closure_captures_value_at_creation() {
+        let v = run(r#"
+            fn main() {
+                let mut x = 1;
+                let f = action() -> int { return x; };
+                x = 5;
+                return f() * 10 + x;
+            }
+        "#);
+        assert!(matches!(v, Value::Int(15)));
+    }
+
+    #[test]
+    fn test_let_bound_closure_calls_itself() {
+        let v = run(r#"
+            fn main() {
+                let base = 1;
+                let fact = action(n: int) -> int {
+                    if n <= 1 { return base; }
+                    return n * fact(n - 1);
+                };
+                let f = fact;
+                return f(5);
+            }
+        "#);
+        assert!(matches!(v, Value::Int(120)));
+    }
+
+    #[test]
+    fn test_failed_try_rolls_back_mutations() {
+        let v = run(r#"
+            fn main() {
+                let mut x = 1;
+                try {
+                    x = 2;
+                    throw "fail";
+                } catch e {
+                    x = x + 10;
+                }
+                return x;
+            }
+        "#);
+        assert!(matches!(v, Value::Int(11)));
+    }
+
+    #[test]
+    fn test_sandboxed_prelude_omits_process_and_fs() {
+        let env = Environment::new(Prelude::default());
+        assert!(matches!(env.get("print"), Some(Value::NativeAction(_))));
+        assert!(env.get("process_exec").is_none());
+        assert!(env.get("file_write").is_none());
+        assert!(env.get("http_get").is_some());
+        assert!(Environment::new(Prelude::ALL).get("process_exec").is_some());
+        assert_eq!(Interpreter::new().prelude, Prelude::default());
+
+        let load = |src: &str| {
+            let tokens = crate::lexer::tokenize(src).unwrap();
+            Interpreter::with_prelude(Prelude::default()).eval(&crate::parser::parse(&tokens))
+        };
+        let err = load("import system;\nfn main() { return 1; }").unwrap_err();
+        assert_eq!(err.message, "module 'system' is not available in this sandbox");
+        assert!(matches!(load("import prelude;\nimport net;\nfn main() { return 1; }"), Ok(Value::Int(1))));
+        let err = load(r#"fn main() { return process_exec("ls"); }"#).unwrap_err();
+        assert_eq!(err.message, "unknown function");
+    }
+
+    #[test]
+    fn test_snapshot_is_isolated_from_later_writes() {
+        let mut env = Environment::new(Prelude::ALL);
+        env.define("x", Value::Int(1));
+        let snap = env.snapshot();
+        env.set("x", Value::Int(2));
+        env.push();
+        env.define("y", Value::Int(3));
+        env.restore(snap);
+        assert!(matches!(env.get("x"), Some(Value::Int(1))));
+        assert!(env.get("y").is_none());
+    }
+
+    #[test]
+    fn test_match_binding_with_range() {
+        let v = run(r#"
+            fn classify(x: int) -> int {
+                return match x {
+                    n @ 1..=5 => n * 100,
+                    10..20 => 2,
+                    other => other,
+                };
+            }
+            fn main() { return classify(3) + classify(15) + classify(7); }
+        "#);
+        assert!(matches!(v, Value::Int(309)));
+    }
+
+    #[test]
+    fn test_match_struct_pattern() {
+        let v = run(r#"
+            struct Point { x: float, y: int }
+            struct Line { from: Point, label: string }
+            fn describe(p: Point) -> int {
+                return match p {
+                    Point { x: 0, y } => y,
+                    Point { y: 1..=9 } => -1,
+                    _ => 0,
+                };
+            }
+            fn main() {
+                let line = Line { from: Point { x: 0, y: 7 }, label: "a" };
+                let nested = match line {
+                    Line { label: "b" } => 0,
+                    Line { from: Point { y: n }, label } => n,
+                };
+                return [describe(Point { x: 0, y: 42 }), describe(Point { x: 2.5, y: 3 }),
+                        describe(Point { x: 1, y: 50 }), nested];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[42,-1,0,7]");
+    }
+
+    #[test]
+    fn test_time_format_natives() {
+        assert!(matches!(run("fn main() { return duration_format(90000); }"), Value::String(ref s) if s == "1m 30s"));
+        match run("fn main() { return now_iso(); }") {
+            Value::String(s) => assert!(s.len() == 24 && s.ends_with('Z') && &s[10..11] == "T"),
+            other => panic!("expected string, got {}", other),
+        }
+    }
+
+    #[test]
+    fn test_eval_expr_uses_current_environment() {
+        let mut i = Interpreter::new();
+        assert!(matches!(i.eval_expr("2 * 21"), Ok(Value::Int(42))));
+        i.eval_line("let x = 5;").unwrap();
+        assert!(matches!(i.eval_expr("x + 1"), Ok(Value::Int(6))));
+
+        let err = i.eval_expr("1 2").unwrap_err();
+        assert_eq!(err.message, "expected end of expression, found IntLit(2)");
+        assert_eq!(err.span.map(|s| s.column), Some(3));
+        assert!(i.eval_expr("1 +").is_err());
+    }
+
+    #[test]
+    fn test_repl_prints_expressions_and_binds_underscore() {
+        let mut i = Interpreter::new();
+        let v = i.eval_line("1 + 1").unwrap().expect("expression should produce a value");
+        assert_eq!(v.to_string(), "2");
+        assert!(matches!(i.eval_line("_ * 10").unwrap(), Some(Value::Int(20))));
+        assert!(i.eval_line("let x = 5;").unwrap().is_none());
+        assert!(i.eval_line("fn double(n: int) -> int { return n * 2; }").unwrap().is_none());
+        assert!(matches!(i.eval_line("double(x)").unwrap(), Some(Value::Int(10))));
+        assert!(i.eval_line("print(1)").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_csv_natives_round_trip() {
+        let v = run(r#"
+            fn main() {
+                let text = csv_write([["id", "note"], [1, "a, b\nc"]]);
+                let rows = csv_parse(text);
+                return rows[1][1];
+            }
+        "#);
+        assert!(matches!(v, Value::String(ref s) if s == "a, b\nc"));
+        assert!(matches!(run(r#"fn main() { return tsv_parse("x\ty\n")[0][1]; }"#), Value::String(ref s) if s == "y"));
+    }
+
+    #[test]
+    fn test_newtype_conversion_is_identity() {
+        let v = run(r#"
+            typealias UserId = int distinct;
+            fn main() { return UserId(41) + 1; }
+        "#);
+        assert!(matches!(v, Value::Int(42)));
+    }
+
+    #[test]
+    fn test_aggregate_natives() {
+        assert!(matches!(run("fn main() { return sum([1, 2, 3]); }"), Value::Int(6)));
+        assert!(matches!(run("fn main() { return avg([2.0, 4.0]); }"), Value::Float(f) if f == 3.0));
+        assert!(matches!(run("fn main() { return array_max([3, 9, 4]); }"), Value::Int(9)));
+        assert!(matches!(run("fn main() { return array_min([]); }"), Value::Nil));
+    }
+
+    #[test]
+    fn test_bytes_read_write_without_corruption() {
+        let dir = std::env::temp_dir();
+        let src = dir.join(format!("reox_bytes_in_{}.bin", std::process::id()));
+        let dst = dir.join(format!("reox_bytes_out_{}.bin", std::process::id()));
+        let data = [0xffu8, 0xfe, 0x00, 0x41, 0xc3];
+        std::fs::write(&src, data).unwrap();
+
+        let v = run_all(&format!(r#"
+            fn main() {{
+                let b = file_read_bytes("{}");
+                file_write_bytes("{}", b);
+                return [len(b), b[0], b[3], bytes_to_string(b)];
+            }}
+        "#, src.display(), dst.display()));
+        let written = std::fs::read(&dst).unwrap();
+        let _ = std::fs::remove_file(&src);
+        let _ = std::fs::remove_file(&dst);
+
+        assert_eq!(written, data);
+        match v {
+            Value::Array(items) => {
+                assert!(matches!(items[..], [Value::Int(5), Value::Int(255), Value::Int(65), Value::Nil]));
+            }
+            other => panic!("expected array, got {}", other),
+        }
+        assert!(matches!(run(r#"fn main() { return bytes_to_string(string_to_bytes("héllo")); }"#), Value::String(ref s) if s == "héllo"));
+    }
+
+    #[test]
+    fn test_failing_natives_raise_catchable_errors() {
+        let missing = std::env::temp_dir().join(format!("reox_missing_{}.txt", std::process::id()));
+        let v = run_all(&format!(r#"
+            fn main() {{
+                let mut caught = [];
+                try {{
+                    let text = file_read("{}");
+                    caught = push(caught, text);
+                }} catch e {{
+                    caught = push(caught, "cannot read" in e);
+                }}
+                try {{ parse_int("12a"); }} catch e {{ caught = push(caught, e); }}
+                return push(caught, parse_int(" 42 "));
+            }}
+        "#, missing.display()));
+        assert_eq!(v.to_string(), "[true,cannot parse '12a' as int,42]");
+
+        let tokens = crate::lexer::tokenize(r#"fn main() { return file_read(1); }"#).unwrap();
+        let err = Interpreter::with_prelude(Prelude::ALL).eval(&crate::parser::parse(&tokens)).unwrap_err();
+        assert_eq!(err.message, "expected string, found int");
+    }
+
+    #[test]
+    fn test_closure_in_struct_field_is_callable() {
+        let v = run(r#"
+            struct Counter { step: int, next: fn(int) -> int }
+            fn main() {
+                let step = 5;
+                let c = Counter { step: step, next: action(n: int) -> int { return n + step; } };
+                return c.next(c.next(1));
+            }
+        "#);
+        assert!(matches!(v, Value::Int(11)));
+    }
+
+    #[test]
+    fn test_native_and_nested_field_callbacks() {
+        let v = run(r#"
+            struct Inner { handler: fn(string) -> string }
+            struct App { inner: Inner, fix: fn(int) -> int }
+            fn main() {
+                let app = App { inner: Inner { handler: action(s: string) -> string { return s + "!"; } }, fix: abs };
+                return [app.inner.handler("hi"), app.fix(-3)];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[hi!,3]");
+    }
+
+    #[test]
+    fn test_calling_non_closure_field_errors() {
+        let tokens = crate::lexer::tokenize(r#"
+            struct Counter { step: int }
+            fn main() { let c = Counter { step: 1 }; return c.step(); }
+        "#).unwrap();
+        let err = eval(&crate::parser::parse(&tokens)).unwrap_err();
+        assert!(err.message.contains("field 'step' is not callable"));
+    }
+
+    #[test]
+    fn test_fixed_width_conversions_wrap() {
+        let v = run("fn main() { return [u8(256), u8(-1), i8(200), i16(70000), u32(-1)]; }");
+        let Value::Array(items) = v else { panic!("expected array") };
+        let ints: Vec<i64> = items.iter().map(|v| match v { Value::Int(n) => *n, _ => panic!() }).collect();
+        assert_eq!(ints, vec![0, 255, -56, 4464, 4294967295]);
+    }
+
+    #[test]
+    fn test_fixed_width_arithmetic_wraps() {
+        let v = run(r#"
+            struct Pixel { r: u8 }
+            fn next(b: u8) -> u8 { return b + 1; }
+            fn main() {
+                let x: u8 = 255;
+                let mut c: i8 = 127;
+                c += 1;
+                let mut n: u16 = 0;
+                n--;
+                let p = Pixel { r: 255 };
+                return [x + 1, x * 2, ~x, next(x), c, n, i8(100) + i8(100), x + 1 > 255, p.r + 1];
+            }
+        "#);
+        let Value::Array(items) = v else { panic!("expected array") };
+        let shown: Vec<String> = items.iter().map(|v| v.to_string()).collect();
+        assert_eq!(shown, ["0", "254", "0", "0", "-128", "65535", "-56", "false", "0"]);
+    }
+
+    #[test]
+    fn test_clone_is_independent_of_original() {
+        let v = run(r#"
+            fn main() {
+                let a = [1, 2, [3]];
+                let b = clone(a);
+                b = push(b, 4);
+                let m = map_set(map_new(), "k", a);
+                let n = clone(m);
+                n = map_set(n, "k", b);
+                return [len(a), len(b), len(map_get(m, "k")), len(map_get(n, "k"))];
+            }
+        "#);
+        let Value::Array(lens) = v else { panic!("expected array") };
+        assert!(matches!(lens.as_slice(), [Value::Int(3), Value::Int(4), Value::Int(3), Value::Int(4)]));
+    }
+
+    #[test]
+    fn test_format_native() {
+        let v = run(r#"fn main() { return format("{:.2}|{:x}|{:>4}", 3.14159, 255, "id"); }"#);
+        assert!(matches!(v, Value::String(ref s) if s == "3.14|ff|  id"));
+        let v = run(r#"
+            fn main() {
+                try { format("{:x}", "no"); } catch e { return e; }
+            }
+        "#);
+        assert!(matches!(v, Value::String(ref s) if s == "'x' format requires a number, found string"));
+    }
+
+    #[test]
+    fn test_optional_natives() {
+        let v = run(r#"
+            fn main() {
+                let m = map_set(map_new(), "a", 1);
+                let hit = map_get(m, "a");
+                let miss = map_get(m, "b");
+                return [is_nil(miss), is_nil(hit), is_some(hit), is_some(miss),
+                        unwrap(hit), unwrap_or(miss, 7), unwrap_or(hit, 7)];
+            }
+        "#);
+        let Value::Array(items) = v else { panic!("expected array") };
+        assert!(matches!(items.as_slice(), [
+            Value::Bool(true), Value::Bool(false), Value::Bool(true), Value::Bool(false),
+            Value::Int(1), Value::Int(7), Value::Int(1),
+        ]));
+    }
+
+    #[test]
+    fn test_unwrap_nil_reports_location() {
+        let tokens = crate::lexer::tokenize("fn main() {\n    let x = nil;\n    return unwrap(x);\n}").unwrap();
+        let err = eval(&crate::parser::parse(&tokens)).unwrap_err();
+        assert_eq!(err.display(), "runtime error[3:12]: called 'unwrap' on nil");
+
+        // The native fails the same way when called through another name
+        let tokens = crate::lexer::tokenize("fn main() {\n    let get = unwrap;\n    return get(nil);\n}").unwrap();
+        let err = eval(&crate::parser::parse(&tokens)).unwrap_err();
+        assert_eq!(err.display(), "runtime error[3:12]: called 'unwrap' on nil");
+    }
+
+    #[test]
+    fn test_case_and_trim_natives() {
+        let v = run(r#"
+            fn main() {
+                return [eq_ignore_case("Hello", "hello"), eq_ignore_case("ÉCOLE", "école"),
+                        eq_ignore_case("a", "b"), trim("  x  "), trim_start("  x  "),
+                        trim_end("  x  "), to_upper("straße"), to_lower("ÀB")];
+            }
+        "#);
+        let Value::Array(items) = v else { panic!("expected array") };
+        let shown: Vec<String> = items.iter().map(|v| v.to_string()).collect();
+        assert_eq!(shown, vec!["true", "true", "false", "x", "x  ", "  x", "STRASSE", "àb"]);
+    }
+
+    #[test]
+    fn test_local_function() {
+        let v = run(r#"
+            fn main() {
+                let base = 10;
+                fn fact(n: int) -> int {
+                    if n <= 1 { return 1; }
+                    return n * fact(n - 1);
+                }
+                fn offset(x: int) -> int { return x + base; }
+                return [fact(5), offset(2)];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[120,12]");
+    }
+
+    #[test]
+    fn test_repeat_and_pad_natives() {
+        let v = run(r#"
+            fn main() {
+                return [repeat_string("ab", 3), repeat_string("x", 0),
+                        pad_left("42", 5, "0"), pad_right("né", 4, "."), pad_left("abc", 2, "*"),
+                        pad_right("a", 3), pad_left("a", -4)];
+            }
+        "#);
+        let Value::Array(items) = v else { panic!("expected array") };
+        let shown: Vec<String> = items.iter().map(|v| v.to_string()).collect();
+        assert_eq!(shown, vec!["ababab", "", "00042", "né..", "abc", "a  ", "a"]);
+
+        let err_of = |src: &str| eval(&crate::parser::parse(&crate::lexer::tokenize(src).unwrap())).unwrap_err().message;
+        assert_eq!(err_of(r#"fn main() { return repeat_string("x", -2); }"#), "cannot repeat a string -2 times");
+        assert_eq!(
+            err_of(r#"fn main() { return repeat_string("ab", 9223372036854775807); }"#),
+            format!("repeated string would exceed {} bytes", MAX_REPEAT_BYTES)
+        );
+        assert_eq!(
+            err_of(r#"fn main() { return pad_left("x", 9223372036854775807); }"#),
+            format!("padded string would exceed {} bytes", MAX_REPEAT_BYTES)
+        );
+    }
+
+    #[test]
+    fn test_defer_in_loop_runs_once_at_function_end() {
+        use std::{cell::RefCell, rc::Rc};
+        let src = "fn main() {\n    let mut i = 0;\n    while i < 4 {\n        i += 1;\n        if i == 2 { continue; }\n        defer {\n            let seen = i;\n        }\n        if i == 3 { break; }\n    }\n    return i;\n}";
+        let ast = crate::parser::parse(&crate::lexer::tokenize(src).unwrap());
+        let events: Rc<RefCell<Vec<(u32, Option<Value>)>>> = Rc::new(RefCell::new(Vec::new()));
+        let sink = events.clone();
+        let mut interp = Interpreter::new();
+        interp.step_mode(move |e| { sink.borrow_mut().push((e.span.line, e.locals.get("i").cloned())); StepAction::Step });
+        assert!(matches!(interp.eval(&ast).unwrap(), Value::Int(3)));
+
+        // The body (line 7) runs after `return` (line 11), once, seeing `i` as first registered
+        let events = events.borrow();
+        let after_return: Vec<(u32, String)> = events.iter()
+            .skip_while(|(line, _)| *line != 11)
+            .map(|(line, i)| (*line, i.as_ref().map(|v| v.to_string()).unwrap_or_default()))
+            .collect();
+        assert_eq!(after_return, vec![(11, "3".to_string()), (7, "1".to_string())]);
+        assert_eq!(events.iter().filter(|(line, _)| *line == 7).count(), 1);
+    }
+
+    #[test]
+    fn test_regex_natives() {
+        let v = run(r#"
+            fn main() {
+                return [regex_match("^[a-z]+[0-9]?$", "abc7"), regex_match("^[a-z]+$", "ab1"),
+                        regex_find("[0-9]+", "v12.5"), regex_find("x", "abc"),
+                        regex_replace("o+", "foo boo", "0"), regex_match("[a-", "a")];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[true,false,12,nil,f0 b0,nil]");
+    }
+
+    #[test]
+    fn test_stderr_stream_goes_to_its_sink() {
+        use std::{cell::RefCell, io::Write, rc::Rc};
+        struct Shared(Rc<RefCell<Vec<u8>>>);
+        impl Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> { self.0.borrow_mut().extend_from_slice(buf); Ok(buf.len()) }
+            fn flush(&mut self) -> std::io::Result<()> { Ok(()) }
+        }
+        let (err, out) = (Rc::new(RefCell::new(Vec::new())), Rc::new(RefCell::new(Vec::new())));
+        crate::stdlib::io::set_sink(Stream::Stderr, Some(Box::new(Shared(err.clone())))).unwrap();
+        crate::stdlib::io::set_sink(Stream::Stdout, Some(Box::new(Shared(out.clone())))).unwrap();
+        let v = run(r#"
+            fn main() {
+                let err = stderr();
+                stream_write(err, "warning: ");
+                stream_write(err, 42);
+                print("hello", 1);
+                return [err, stream_write(stdin(), "x")];
+            }
+        "#);
+        crate::stdlib::io::set_sink(Stream::Stderr, None).unwrap();
+        crate::stdlib::io::set_sink(Stream::Stdout, None).unwrap();
+
+        assert_eq!(v.to_string(), "[<stderr>,false]");
+        assert_eq!(String::from_utf8(err.borrow().clone()).unwrap(), "warning: 42");
+        assert_eq!(String::from_utf8(out.borrow().clone()).unwrap(), "hello 1 \n");
+    }
+
+    #[test]
+    fn test_int_and_tuple_map_keys() {
+        let v = run(r#"
+            struct P { x: int, y: int }
+            fn main() {
+                let mut m = map_new();
+                m = map_set(m, 7, "seven");
+                m = map_set(m, [1, "a"], "pair");
+                m = map_set(m, P { x: 1, y: 2 }, "point");
+                return [map_get(m, 7), m[[1, "a"]], map_get(m, P { y: 2, x: 1 }),
+                        map_get(m, "7"), [1, "a"] in m, map_has(m, [1, "b"])];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[seven,pair,point,nil,true,false]");
+    }
+
+    #[test]
+    fn test_unhashable_map_key_is_rejected() {
+        let tokens = crate::lexer::tokenize("fn main() {\n    let m = map_new();\n    return map_set(m, 1.5, 0);\n}").unwrap();
+        let err = eval(&crate::parser::parse(&tokens)).unwrap_err();
+        assert_eq!(err.display(), "runtime error[3:12]: unhashable map key of type 'float'");
+
+        let tokens = crate::lexer::tokenize("fn main() {\n    let m = map_new();\n    return m[nil];\n}").unwrap();
+        let err = eval(&crate::parser::parse(&tokens)).unwrap_err();
+        assert_eq!(err.message, "unhashable map key of type 'nil'");
+
+        let tokens = crate::lexer::tokenize("fn main() {\n    let mut m = map_new();\n    m[(1, action() { return 1; })] = 0;\n}").unwrap();
+        let err = eval(&crate::parser::parse(&tokens)).unwrap_err();
+        assert_eq!(err.display(), "runtime error[3:8]: unhashable map key of type 'action'");
+        let tokens = crate::lexer::tokenize("fn main() { return map_set(map_new(), rgb(1, 2, 3), 0); }").unwrap();
+        assert_eq!(eval(&crate::parser::parse(&tokens)).unwrap_err().message, "unhashable map key of type 'color'");
+    }
+
+    #[test]
+    fn test_tuple_and_char_map_keys() {
+        let v = run(r#"
+            fn main() {
+                let mut grid = map_new();
+                grid[(0, 1)] = "a";
+                grid[(2, "x")] = "b";
+                grid['c'] = "c";
+                let keys = map_keys(map_remove(map_remove(grid, (2, "x")), 'c'));
+                return [grid[(0, 1)], grid[(2, "x")], grid['c'], grid[[0, 1]], map_has(grid, (1, 0)), keys];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[a,b,c,nil,false,[(0,1)]]");
+    }
+
+    #[test]
+    fn test_first_last_and_is_empty() {
+        let v = run(r#"
+            fn main() {
+                let a = [3, 5, 8];
+                let e: [int] = [];
+                return [arr_first(a), arr_last(a), arr_first(e), arr_last(e), is_empty(a), is_empty(e), is_empty(""), is_empty(map_new())];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[3,8,nil,nil,false,true,true,true]");
+    }
+
+    #[test]
+    fn test_pop_leaves_array_unchanged() {
+        let v = run(r#"
+            fn main() {
+                let a = [1, nil];
+                let top = pop(a);
+                let rest = array_slice(a, 0, len(a) - 1);
+                return [top, len(a), rest, is_empty(rest), pop(rest)];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[nil,2,[1],false,1]");
+    }
+
+    #[test]
+    fn test_for_continue_skips_rest_of_iteration() {
+        let v = run(r#"
+            fn main() {
+                let i = "outer";
+                let mut seen = [];
+                for i in [1, 2, 3, 4] {
+                    let doubled = i * 2;
+                    if i % 2 == 0 { continue; }
+                    seen = push(seen, doubled);
+                }
+                return [seen, i];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[[2,6],outer]");
+    }
+
+    #[test]
+    fn test_for_break_exits_early() {
+        let v = run(r#"
+            fn main() {
+                let i = -1;
+                let mut last = 0;
+                let mut iterations = 0;
+                for i in 100 {
+                    iterations += 1;
+                    if i == 3 { break; }
+                    last = i;
+                }
+                return [iterations, last, i];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[4,2,-1]");
+    }
+
+    #[test]
+    fn test_struct_update_keeps_other_fields() {
+        let v = run(r#"
+            struct Point { x: int, y: int }
+            fn main() {
+                let p = Point { x: 1, y: 2 };
+                let q = Point { ..p, x: 9 };
+                return [q.x, q.y, p.x];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[9,2,1]");
+    }
+
+    #[test]
+    fn test_match_negative_literals() {
+        let v = run(r#"
+            fn describe(n: int) -> string {
+                return match n { -1 => "neg one", -9..=-2 => "negative", _ => "other" };
+            }
+            fn main() {
+                let f = match -2.5 { -2.5 => "float", _ => "other" };
+                return [describe(-1), describe(-4), describe(1), f];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[neg one,negative,other,float]");
+    }
+
+    #[test]
+    fn test_bit_reinterpretation() {
+        let v = run(r#"
+            fn main() {
+                let bits = float_to_bits(3.14);
+                let le = int_to_bytes(258, true);
+                let be = int_to_bytes(258, false);
+                return [bits_to_float(bits), bits == float_to_bits(3.14), le[0], le[1], be[6], be[7],
+                        bytes_to_int(le, true), bytes_to_int(be, false), bytes_to_int(be, true) == 258,
+                        bytes_to_int(int_to_bytes(-7, false), false), bytes_to_int(string_to_bytes("short"), true)];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[3.14,true,2,1,1,2,258,258,false,-7,nil]");
+        assert!(matches!(run("fn main() { return float_to_bits(1.0); }"), Value::Int(0x3ff0_0000_0000_0000)));
+    }
+
+    #[test]
+    fn test_profiling_tracks_allocations() {
+        let src = r#"
+            fn build(n: int) -> [int] {
+                let mut xs = [];
+                for i in n { xs = push(xs, i); }
+                return xs;
+            }
+            fn main() { return len(build(1000)); }
+        "#;
+        let ast = crate::parser::parse(&crate::lexer::tokenize(src).unwrap());
+        let mut interp = Interpreter::new();
+        interp.enable_profiling(ProfilerConfig { trace_memory: true, ..Default::default() });
+        assert!(matches!(interp.eval(&ast), Ok(Value::Int(1000))));
+
+        let summary = interp.profile_summary().unwrap();
+        assert!(summary.total_allocations >= 1000);
+        assert!(summary.total_bytes_allocated >= 999 * std::mem::size_of::<Value>() as u64);
+        let names: Vec<&str> = summary.functions.iter().map(|f| f.name.as_str()).collect();
+        assert!(names.contains(&"main") && names.contains(&"build"));
+
+        // Without trace_memory only calls are timed
+        let mut interp = Interpreter::new();
+        interp.enable_profiling(ProfilerConfig::default());
+        interp.eval(&ast).unwrap();
+        assert_eq!(interp.profile_summary().unwrap().total_allocations, 0);
+        assert!(Interpreter::new().profile_summary().is_none());
+    }
+
+    #[test]
+    fn test_struct_map_round_trip() {
+        let v = run(r#"
+            struct Point { x: int, y: float }
+            fn main() {
+                let m = struct_to_map(Point { x: 3, y: 4.5 });
+                let p = map_to_struct(m, "Point");
+                let q = map_to_struct(map_set(map_set(map_new(), "x", 1), "y", 2), "Point");
+                return [map_get(m, "x"), map_get(m, "y"), p.x, p.y, q.y, len(m)];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[3,4.5,3,4.5,2.0,2]");
+    }
+
+    #[test]
+    fn test_map_to_struct_rejects_bad_fields() {
+        let err = |body: &str| {
+            let src = format!("struct Point {{ x: int, y: int }}\nfn main() {{ {} }}", body);
+            let ast = crate::parser::parse(&crate::lexer::tokenize(&src).unwrap());
+            eval(&ast).unwrap_err().message
+        };
+        assert_eq!(err(r#"map_to_struct(map_set(map_new(), "x", 1), "Point");"#), "missing field 'y' for struct 'Point'");
+        assert_eq!(
+            err(r#"map_to_struct(map_set(map_set(map_set(map_new(), "x", 1), "y", 2), "z", 3), "Point");"#),
+            "struct 'Point' has no field 'z'"
+        );
+        assert_eq!(err(r#"map_to_struct(map_set(map_set(map_new(), "x", "1"), "y", 2), "Point");"#), "field 'x' of 'Point' expects int, found string");
+        assert_eq!(err(r#"map_to_struct(map_new(), "Nope");"#), "unknown struct 'Nope'");
+    }
+
+    #[test]
+    fn test_map_natives_work_through_aliases() {
+        let v = run(r#"
+            struct Point { x: int, y: int }
+            fn main() {
+                let set = map_set;
+                let to_struct = map_to_struct;
+                let p = to_struct(set(set(map_new(), "x", 1), "y", 2), "Point");
+                return [p.x, p.y];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[1,2]");
+
+        let tokens = crate::lexer::tokenize("fn main() {\n    let has = map_has;\n    return has(map_new(), [1.5]);\n}").unwrap();
+        let err = eval(&crate::parser::parse(&tokens)).unwrap_err();
+        assert_eq!(err.display(), "runtime error[3:12]: unhashable map key of type 'float'");
+    }
+
+    #[test]
+    fn test_typed_catch_dispatches_to_matching_clause() {
+        let v = run(r#"
+            struct ParseError { line: int }
+            struct NetworkError { code: int }
+            fn fetch() { throw NetworkError { code: 503 }; }
+            fn main() {
+                let mut out = [];
+                try {
+                    fetch();
+                } catch e: ParseError {
+                    out = push(out, e.line);
+                } catch e: NetworkError {
+                    out = push(out, e.code);
+                }
+                try {
+                    throw "offline";
+                } catch e: NetworkError {
+                    out = push(out, -1);
+                } catch e {
+                    out = push(out, e);
+                }
+                return out;
+            }
+        "#);
+        assert_eq!(v.to_string(), "[503,offline]");
+    }
+
+    #[test]
+    fn test_unmatched_typed_catch_propagates() {
+        let src = r#"
+            struct NetworkError { code: int }
+            fn main() {
+                try { throw 42; } catch e: NetworkError { return 0; }
+                return 1;
+            }
+        "#;
+        let ast = crate::parser::parse(&crate::lexer::tokenize(src).unwrap());
+        assert_eq!(eval(&ast).unwrap_err().message, "42");
+    }
+
+    #[test]
+    fn test_compound_assign_through_fields_and_indices() {
+        let v = run(r#"
+            struct Point { x: int, y: int }
+            struct Line { start: Point, end: Point }
+            fn main() {
+                let mut p = Point { x: 1, y: 2 };
+                p.x += 10;
+                let mut line = Line { start: p, end: Point { x: 0, y: 0 } };
+                line.end.y -= 5;
+                let mut arr = [3, 4];
+                arr[1] *= 2;
+                arr[0] = arr[0]++ + 100;
+                return [p.x, line.start.x, line.end.y, arr[0], arr[1]];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[11,11,-5,103,8]");
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        let v = run(r#"
+            struct User { name: string, tags: [string] }
+            fn main() {
+                let u = User { name: "ada", tags: ["x", "y"] };
+                let n = 2;
+                return ["sum=${1+2}", "${u.name} has ${len(u.tags)} tags: ${u.tags}", "${n * 1.5}${"!"}", "cost: \${n}", "${"in ${u.name}"}"];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[sum=3,ada has 2 tags: [x,y],3.0!,cost: ${n},in ada]");
+    }
+
+    #[test]
+    fn test_field_access_on_call_result() {
+        let v = run(r#"
+            struct Point { x: int, y: int }
+            struct Line { start: Point, end: Point }
+            fn make_point(x: int) -> Point { return Point { x: x, y: x * 2 }; }
+            fn make_line() -> Line { return Line { start: make_point(1), end: make_point(5) }; }
+            fn main() {
+                return [make_point(3).x, make_point(3).y + make_line().end.y, [make_point(7)][0].x, rgb(1, 2, 3).g];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[3,16,7,2]");
+
+        let tokens = crate::lexer::tokenize("fn five() -> int { return 5; }\nfn main() { return five().x; }").unwrap();
+        let err = eval(&crate::parser::parse(&tokens)).unwrap_err();
+        assert_eq!(err.message, "member access on non-struct int");
+        assert_eq!(err.span.map(|s| s.line), Some(2));
+    }
+
+    #[test]
+    fn test_increment_array_element_and_nested_index() {
+        let v = run(r#"
+            fn main() {
+                let mut arr = [1, 2];
+                arr[0]++;
+                let mut grid = [[0, 0], [0, 0]];
+                let mut i = 0;
+                grid[1][i++] += 7;
+                return [arr[0], ++arr[1], grid[1][0], grid[1][1], i];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[2,3,7,0,1]");
+    }
+
+    #[test]
+    fn test_pre_and_post_increment_results() {
+        let v = run(r#"
+            fn main() {
+                let mut x = 1;
+                let y = x++;
+                let after_post = x;
+                let z = ++x;
+                let mut f = 1.5;
+                let g = f--;
+                --f;
+                return [y, after_post, z, x, g, f];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[1,2,3,3,1.5,-0.5]");
+
+        let tokens = crate::lexer::tokenize(r#"fn main() { let mut s = "a"; s++; }"#).unwrap();
+        let err = eval(&crate::parser::parse(&tokens)).unwrap_err();
+        assert_eq!(err.message, "cannot increment string");
+        let tokens = crate::lexer::tokenize("fn main() { return 1++; }").unwrap();
+        assert_eq!(eval(&crate::parser::parse(&tokens)).unwrap_err().message, "invalid assignment target");
+    }
+
+    #[test]
+    fn test_finally_runs_on_every_exit() {
+        let v = run(r#"
+            fn early(log: [string]) -> [string] {
+                let mut out = log;
+                try {
+                    return push(out, "returned");
+                } finally {
+                    print("cleanup");
+                }
+                return push(out, "unreachable");
+            }
+            fn main() {
+                let mut log = [];
+                try {
+                    log = push(log, "ok");
+                } catch e {
+                    log = push(log, "never");
+                } finally {
+                    log = push(log, "finally 1");
+                }
+                try {
+                    throw "boom";
+                } catch e {
+                    log = push(log, e);
+                } finally {
+                    log = push(log, "finally 2");
+                }
+                return early(log);
+            }
+        "#);
+        assert_eq!(v.to_string(), "[ok,finally 1,boom,finally 2,returned]");
+    }
+
+    #[test]
+    fn test_finally_runs_before_uncaught_error_propagates() {
+        let v = run(r#"
+            fn main() {
+                let mut seen = [];
+                try {
+                    try { throw "inner"; } finally { seen = push(seen, "finally"); }
+                } catch e {
+                    seen = push(seen, e);
+                }
+                try {
+                    try { throw "inner"; } finally { throw "cleanup failed"; }
+                } catch e {
+                    seen = push(seen, e);
+                }
+                return seen;
+            }
+        "#);
+        // The outer catch rolls back the first finally's mutation, but not its error
+        assert_eq!(v.to_string(), "[inner,cleanup failed]");
+    }
+
+    #[test]
+    fn test_declared_float_types_widen_int_values() {
+        let v = run(r#"
+            struct Point { x: float, y: float }
+            fn half(n: float) -> float { return n / 2; }
+            fn main() {
+                let xs: [float] = [1, 2, 3];
+                let p = Point { x: 1, y: 2.5 };
+                return [xs[0] / 2, p.x / 2, half(3)];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[0.5,0.5,1.5]");
+    }
+
+    #[test]
+    fn test_reverse_flatten_unique_slice() {
+        let v = run(r#"
+            fn main() {
+                return [
+                    reverse([1, 2, 3]),
+                    flatten([[1], [2, 3]]),
+                    unique([1, 1, 2]),
+                    unique([[1], 2, [1], 2.0, 2]),  // int and float are distinct
+                    slice([1, 2, 3, 4], 1, 3),
+                    slice([1, 2, 3], 2, 1),
+                    slice([1, 2, 3], -5, 10),
+                ];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[[3,2,1],[1,2,3],[1,2],[[1],2,2.0],[2,3],[],[1,2,3]]");
+    }
+
+    #[test]
+    fn test_native_arity_and_types_are_checked() {
+        let err_of = |src: &str| {
+            let tokens = crate::lexer::tokenize(src).unwrap();
+            eval(&crate::parser::parse(&tokens)).unwrap_err()
+        };
+        let err = err_of("fn main() { return rgb(); }");
+        assert_eq!(err.message, "rgb expects 3 int arguments, got 0");
+        assert_eq!(err.span.map(|s| s.column), Some(20));
+        assert_eq!(err_of("fn main() { return rgba(1, 2); }").message, "rgba expects 4 int arguments, got 2");
+        assert_eq!(err_of(r#"fn main() { return rgb(1, "2", 3); }"#).message, "rgb argument 2 must be int, found string");
+        assert_eq!(err_of("fn main() { return sqrt(); }").message, "sqrt expects 1 number argument, got 0");
+        assert_eq!(err_of(r#"fn main() { return pad_left("x"); }"#).message, "pad_left expects 2 to 3 arguments, got 1");
+
+        // Caught like any other runtime error, and optional arguments may be left out
+        let v = run(r#"
+            fn main() {
+                let mut out = [pad_left("7", 3, "0"), pad_right("ab", 3)];
+                try { with_alpha(1, 2); } catch e { out = push(out, e); }
+                return out;
+            }
+        "#);
+        assert_eq!(v.to_string(), "[007,ab ,with_alpha argument 1 must be color, found int]");
+    }
+
+    #[test]
+    fn test_math_natives_widen_int_arguments() {
+        let v = run(r#"
+            fn main() {
+                return [min(1, 2.5), max(1, 2.5), min(3, 2), floor(7), round(2), lerp(0, 10, 0.5), ease_in(1)];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[1.0,2.5,2,7,2,5.0,1.0]");
+        assert!(matches!(run("fn main() { return hsl(0, 100, 50); }"), Value::Color { r: 255, g: 0, b: 0, a: 255 }));
+    }
+
+    #[test]
+    fn test_else_if_chain_picks_first_matching_branch() {
+        let v = run(r#"
+            fn grade(score: int) -> string {
+                if score >= 90 {
+                    return "A";
+                } else if score >= 75 {
+                    return "B";
+                } else if score >= 50 {
+                    return "C";
+                } else {
+                    return "F";
+                }
+            }
+            fn main() { return [grade(95), grade(80), grade(75), grade(60), grade(10)]; }
+        "#);
+        assert_eq!(v.to_string(), "[A,B,B,C,F]");
+    }
+
+    #[test]
+    fn test_float_arithmetic_widens_ints() {
+        assert!(matches!(run("fn main() -> float { return 1.5 * 2.0 + 1; }"), Value::Float(f) if f == 4.0));
+        assert!(matches!(run("fn main() -> float { return 7 / 2.0 - 0.5 % 2; }"), Value::Float(f) if f == 3.0));
+
+        let v = run("fn main() { return [1.0 / 0.0, -1 / 0.0, 0.0 / 0.0, 5.5 % 0, 7 / 2, -7 % 3]; }");
+        assert_eq!(v.to_string(), "[inf,-inf,NaN,NaN,3,-1]");
+
+        let tokens = crate::lexer::tokenize("fn main() { return 1 / 0; }").unwrap();
+        let err = eval(&crate::parser::parse(&tokens)).unwrap_err();
+        assert_eq!(err.message, "division by zero");
+    }
+
+    #[test]
+    fn test_string_relational_operators() {
+        let v = run(r#"
+            fn main() {
+                let mut names = ["pear", "apple", "fig", "Zebra", "app"];
+                let mut i = 1;
+                while i < len(names) {
+                    let mut j = i;
+                    while j > 0 {
+                        if names[j] >= names[j - 1] { break; }
+                        let tmp = names[j];
+                        names[j] = names[j - 1];
+                        names[j - 1] = tmp;
+                        j -= 1;
+                    }
+                    i += 1;
+                }
+                return [names, "a" < "b", "b" <= "a", "abc" >= "abc", "b" > "abc", 1.5 < 2, 2 >= 2.0];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[[Zebra,app,apple,fig,pear],true,false,true,true,true,true]");
+
+        let tokens = crate::lexer::tokenize(r#"fn main() { return "a" < 1; }"#).unwrap();
+        let err = eval(&crate::parser::parse(&tokens)).unwrap_err();
+        assert_eq!(err.message, "cannot compare string and int with '<'");
+    }
+
+    #[test]
+    fn test_char_literals_and_string_indexing() {
+        let v = run(r#"
+            fn main() {
+                let s = "héllo";
+                let c = s[1];
+                let shape = match c { 'é' => "accent", _ => "plain" };
+                return [c, c == 'é', 'a' < 'b', 'l' in s, shape, '\n' == '\n'];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[é,true,true,true,accent,true]");
+    }
+
+    #[test]
+    fn test_color_component_access() {
+        let v = run(r#"
+            fn main() {
+                let mut c = rgb(255, 128, 64);
+                let faded = with_alpha(c, 10);
+                let before = [c.r, c.g, c.b, c.a, faded.a];
+                c.a -= 55;
+                c.r += 10;
+                return push(before, [c.a, c.r]);
+            }
+        "#);
+        assert_eq!(v.to_string(), "[255,128,64,255,10,[200,255]]");
+    }
+
+    #[test]
+    fn test_map_filter_and_map_map() {
+        let v = run(r#"
+            fn main() {
+                let scores = map_set(map_set(map_set(map_new(), "ann", 91), "bob", 42), "cy", 77);
+                let passed = map_filter(scores, action(name: string, score: int) -> bool { return score > 60; });
+                let curved = map_map(passed, action(name: string, score: int) -> int { return score + 5; });
+                return [len(passed), map_has(passed, "bob"), map_get(curved, "ann"), map_get(curved, "cy")];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[2,false,96,82]");
+
+        // The natives are values like any other, so an alias calls the same one
+        let v = run(r#"
+            fn main() {
+                let keep = map_filter;
+                let m = keep(map_set(map_set(map_new(), "a", 1), "b", 2), action(k: string, v: int) -> bool { return v > 1; });
+                return map_keys(m);
+            }
+        "#);
+        assert_eq!(v.to_string(), "[b]");
+    }
+
+    #[test]
+    fn test_let_destructures_tuples() {
+        let v = run(r#"
+            fn div_mod(a: int, b: int) -> (int, int) { return (a / b, a % b); }
+            fn main() {
+                let (x, y) = (1, 2);
+                let (q, r) = div_mod(17, 5);
+                let pair = (x + y, "three");
+                let (n, s) = pair;
+                return [x, y, q, r, n, s, pair];
+            }
+        "#);
+        assert_eq!(v.to_string(), "[1,2,3,2,3,three,(3,three)]");
+    }
+}
@@ -14,6 +14,8 @@ pub struct LexError {
     pub message: String,
     pub line: u32,
     pub column: u32,
+    /// Full extent of the offending text, when known
+    pub span: Option<Span>,
 }
 
 impl LexError {
@@ -22,6 +24,17 @@ impl LexError {
             message: message.into(),
             line,
             column,
+            span: None,
+        }
+    }
+
+    /// Create an error covering a whole token (e.g. a malformed literal)
+    pub fn with_span(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            message: message.into(),
+            line: span.line,
+            column: span.column,
+            span: Some(span),
         }
     }
 
@@ -38,6 +51,11 @@ pub struct Lexer<'a> {
     line: u32,
     column: u32,
     current_pos: usize,
+    /// Emit comments as tokens instead of skipping them
+    keep_comments: bool,
+    /// One entry per open `${` in a string literal: the depth of `{` nesting
+    /// within it, so the `}` that resumes the string can be told apart
+    interpolations: Vec<usize>,
 }
 
 impl<'a> Lexer<'a> {
@@ -48,9 +66,16 @@ impl<'a> Lexer<'a> {
             line: 1,
             column: 1,
             current_pos: 0,
+            keep_comments: false,
+            interpolations: Vec::new(),
         }
     }
 
+    /// Lexer that reports comments as `LineComment`/`BlockComment` tokens
+    pub fn with_comments(source: &'a str) -> Self {
+        Self { keep_comments: true, ..Self::new(source) }
+    }
+
     /// Advance to next character
     fn advance(&mut self) -> Option<(usize, char)> {
         if let Some((pos, ch)) = self.chars.next() {
@@ -79,15 +104,19 @@ impl<'a> Lexer<'a> {
         iter.peek().map(|(_, ch)| *ch)
     }
 
-    /// Skip whitespace and comments
-    fn skip_whitespace_and_comments(&mut self) {
+    /// Skip whitespace and comments, returning the first comment when
+    /// comments are kept
+    fn skip_whitespace_and_comments(&mut self) -> Result<Option<Token>, LexError> {
         loop {
             match self.peek() {
                 Some(' ') | Some('\t') | Some('\r') | Some('\n') => {
                     self.advance();
                 }
                 Some('/') => {
-                    if self.peek_next() == Some('/') {
+                    let start_line = self.line;
+                    let start_col = self.column;
+                    let start_pos = self.chars.peek().map(|(pos, _)| *pos).unwrap_or(self.current_pos);
+                    let kind = if self.peek_next() == Some('/') {
                         // Line comment
                         self.advance(); // /
                         self.advance(); // /
@@ -97,6 +126,8 @@ impl<'a> Lexer<'a> {
                             }
                             self.advance();
                         }
+                        let end_pos = self.end_of_consumed();
+                        TokenKind::LineComment(self.source[start_pos + 2..end_pos].to_string())
                     } else if self.peek_next() == Some('*') {
                         // Block comment
                         self.advance(); // /
@@ -112,17 +143,35 @@ impl<'a> Lexer<'a> {
                                     self.advance();
                                     depth += 1;
                                 }
-                                None => break,
+                                None => {
+                                    // Point at the opening `/*`; the span runs to end of input
+                                    let span = Span::new(start_line, start_col, start_pos, self.source.len());
+                                    return Err(LexError::with_span("unterminated block comment", span));
+                                }
                                 _ => {}
                             }
                         }
+                        let end_pos = self.end_of_consumed();
+                        TokenKind::BlockComment(self.source[start_pos + 2..end_pos - 2].to_string())
                     } else {
                         break;
+                    };
+                    if self.keep_comments {
+                        let span = Span::new(start_line, start_col, start_pos, self.end_of_consumed());
+                        return Ok(Some(Token::new(kind, span)));
                     }
                 }
                 _ => break,
             }
         }
+        Ok(None)
+    }
+
+    /// Byte offset just past the last consumed character
+    fn end_of_consumed(&self) -> usize {
+        self.source[self.current_pos..].chars().next()
+            .map(|ch| self.current_pos + ch.len_utf8())
+            .unwrap_or(self.current_pos)
     }
 
     /// Scan an identifier or keyword
@@ -148,21 +197,24 @@ impl<'a> Lexer<'a> {
         Token::new(kind, span)
     }
 
-    /// Scan a number literal (supports decimal and hex with 0x prefix)
+    /// Scan a number literal: decimal, or hex/binary/octal with a `0x`/`0b`/`0o`
+    /// prefix. `_` may separate digits and is dropped before parsing
     fn scan_number(&mut self, start_pos: usize, start_line: u32, start_col: u32) -> Result<Token, LexError> {
         let mut end_pos = start_pos;
         let mut is_float = false;
-        let mut is_hex = false;
+        let prefix = match self.source[start_pos..].get(..2) {
+            Some("0x") | Some("0X") => Some((16, "hex")),
+            Some("0b") | Some("0B") => Some((2, "binary")),
+            Some("0o") | Some("0O") => Some((8, "octal")),
+            _ => None,
+        };
 
-        // Check for hex prefix (0x or 0X)
-        if self.source[start_pos..].starts_with("0x") || self.source[start_pos..].starts_with("0X") {
-            is_hex = true;
-            self.advance(); // consume 'x' or 'X'
+        if let Some((radix, _)) = prefix {
+            self.advance(); // consume the radix letter
             end_pos = self.current_pos;
-            
-            // Parse hex digits
+
             while let Some(ch) = self.peek() {
-                if ch.is_ascii_hexdigit() {
+                if ch.is_digit(radix) || ch == '_' {
                     if let Some((pos, _)) = self.advance() {
                         end_pos = pos;
                     }
@@ -173,7 +225,7 @@ impl<'a> Lexer<'a> {
         } else {
             // Decimal number
             while let Some(ch) = self.peek() {
-                if ch.is_ascii_digit() {
+                if ch.is_ascii_digit() || ch == '_' {
                     if let Some((pos, _)) = self.advance() {
                         end_pos = pos;
                     }
@@ -197,36 +249,61 @@ impl<'a> Lexer<'a> {
             }
         }
 
+        // Swallow trailing identifier characters so a malformed literal
+        // like `0xGG` or `12ab` is reported as one token
+        while let Some(ch) = self.peek() {
+            if ch.is_alphanumeric() || ch == '_' {
+                if let Some((pos, _)) = self.advance() {
+                    end_pos = pos;
+                }
+            } else {
+                break;
+            }
+        }
+
         let text = &self.source[start_pos..=end_pos];
         let span = Span::new(start_line, start_col, start_pos, end_pos + 1);
+        let digits_start = if prefix.is_some() { 2 } else { 0 };
+        let radix = prefix.map_or(10, |(radix, _)| radix);
+
+        // Each `_` must sit between two digits: no `0x_1`, `5_`, `1__0` or `1_.5`
+        let bytes = text.as_bytes();
+        let misplaced = (digits_start..bytes.len()).any(|i| {
+            bytes[i] == b'_'
+                && !(i > digits_start
+                    && (bytes[i - 1] as char).is_digit(radix)
+                    && bytes.get(i + 1).is_some_and(|&b| (b as char).is_digit(radix)))
+        });
+        if misplaced {
+            return Err(LexError::with_span(
+                format!("misplaced digit separator in number literal: {}", text),
+                span,
+            ));
+        }
+        let digits = text[digits_start..].replace('_', "");
 
-        if is_hex {
-            // Parse hex literal (skip 0x prefix)
-            let hex_digits = &text[2..];
-            match i64::from_str_radix(hex_digits, 16) {
+        if let Some((radix, name)) = prefix {
+            match i64::from_str_radix(&digits, radix) {
                 Ok(val) => Ok(Token::new(TokenKind::IntLit(val), span)),
-                Err(_) => Err(LexError::new(
-                    format!("invalid hex literal: {}", text),
-                    start_line,
-                    start_col,
+                Err(_) => Err(LexError::with_span(
+                    format!("invalid {} literal: {}", name, text),
+                    span,
                 )),
             }
         } else if is_float {
-            match text.parse::<f64>() {
+            match digits.parse::<f64>() {
                 Ok(val) => Ok(Token::new(TokenKind::FloatLit(val), span)),
-                Err(_) => Err(LexError::new(
+                Err(_) => Err(LexError::with_span(
                     format!("invalid float literal: {}", text),
-                    start_line,
-                    start_col,
+                    span,
                 )),
             }
         } else {
-            match text.parse::<i64>() {
+            match digits.parse::<i64>() {
                 Ok(val) => Ok(Token::new(TokenKind::IntLit(val), span)),
-                Err(_) => Err(LexError::new(
+                Err(_) => Err(LexError::with_span(
                     format!("invalid integer literal: {}", text),
-                    start_line,
-                    start_col,
+                    span,
                 )),
             }
         }
@@ -234,41 +311,28 @@ impl<'a> Lexer<'a> {
 
 
     /// Scan a string literal
-    fn scan_string(&mut self, start_pos: usize, start_line: u32, start_col: u32) -> Result<Token, LexError> {
+    /// Scan string text after an opening quote, or after the `}` closing an
+    /// interpolation when `resumed`, up to the closing quote or the next `${`
+    fn scan_string(&mut self, start_pos: usize, start_line: u32, start_col: u32, resumed: bool) -> Result<Token, LexError> {
         let mut value = String::new();
-        let mut end_pos = start_pos;
+        let end_pos;
+        let interpolates;
 
         loop {
             match self.advance() {
                 Some((pos, '"')) => {
                     end_pos = pos;
+                    interpolates = false;
                     break;
                 }
-                Some((_, '\\')) => {
-                    // Escape sequence
-                    match self.advance() {
-                        Some((_, 'n')) => value.push('\n'),
-                        Some((_, 't')) => value.push('\t'),
-                        Some((_, 'r')) => value.push('\r'),
-                        Some((_, '\\')) => value.push('\\'),
-                        Some((_, '"')) => value.push('"'),
-                        Some((_, '0')) => value.push('\0'),
-                        Some((_, ch)) => {
-                            return Err(LexError::new(
-                                format!("invalid escape sequence: \\{}", ch),
-                                self.line,
-                                self.column,
-                            ));
-                        }
-                        None => {
-                            return Err(LexError::new(
-                                "unexpected end of file in escape sequence",
-                                self.line,
-                                self.column,
-                            ));
-                        }
-                    }
+                Some((pos, '$')) if self.peek() == Some('{') => {
+                    self.advance();
+                    self.interpolations.push(0);
+                    end_pos = pos + 1;
+                    interpolates = true;
+                    break;
                 }
+                Some((_, '\\')) => value.push(self.scan_escape()?),
                 Some((_, '\n')) => {
                     return Err(LexError::new(
                         "unterminated string literal",
@@ -290,18 +354,113 @@ impl<'a> Lexer<'a> {
         }
 
         let span = Span::new(start_line, start_col, start_pos, end_pos + 1);
-        Ok(Token::new(TokenKind::StringLit(value), span))
+        let kind = match (resumed, interpolates) {
+            (false, false) => TokenKind::StringLit(value),
+            (false, true) => TokenKind::InterpStart(value),
+            (true, true) => TokenKind::InterpPart(value),
+            (true, false) => TokenKind::InterpEnd(value),
+        };
+        Ok(Token::new(kind, span))
+    }
+
+    /// Scan a raw string after its `r`: `r"..."` or `r#"..."#`, ending at a quote
+    /// followed by as many `#` as opened it. Nothing inside is an escape.
+    fn scan_raw_string(&mut self, start_pos: usize, start_line: u32, start_col: u32) -> Result<Token, LexError> {
+        let mut hashes = 0;
+        while self.peek() == Some('#') {
+            self.advance();
+            hashes += 1;
+        }
+        self.advance(); // opening quote
+        let body_start = self.current_pos + 1;
+        loop {
+            match self.advance() {
+                Some((pos, '"')) if self.source[pos + 1..].bytes().take_while(|&b| b == b'#').count() >= hashes => {
+                    for _ in 0..hashes {
+                        self.advance();
+                    }
+                    let value = self.source[body_start..pos].to_string();
+                    let span = Span::new(start_line, start_col, start_pos, pos + 1 + hashes);
+                    return Ok(Token::new(TokenKind::StringLit(value), span));
+                }
+                Some(_) => {}
+                None => {
+                    return Err(LexError::new(
+                        "unterminated raw string literal",
+                        start_line,
+                        start_col,
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Decode the escape sequence after a `\\` in a string or char literal
+    fn scan_escape(&mut self) -> Result<char, LexError> {
+        match self.advance() {
+            Some((_, 'n')) => Ok('\n'),
+            Some((_, 't')) => Ok('\t'),
+            Some((_, 'r')) => Ok('\r'),
+            Some((_, '\\')) => Ok('\\'),
+            Some((_, '"')) => Ok('"'),
+            Some((_, '\'')) => Ok('\''),
+            Some((_, '$')) => Ok('$'),
+            Some((_, '0')) => Ok('\0'),
+            Some((_, ch)) => Err(LexError::new(
+                format!("invalid escape sequence: \\{}", ch),
+                self.line,
+                self.column,
+            )),
+            None => Err(LexError::new(
+                "unexpected end of file in escape sequence",
+                self.line,
+                self.column,
+            )),
+        }
+    }
+
+    /// Scan a char literal such as `'a'` or `'\\n'` (the opening quote is consumed)
+    fn scan_char(&mut self, start_pos: usize, start_line: u32, start_col: u32) -> Result<Token, LexError> {
+        let unterminated = || LexError::new("unterminated char literal", start_line, start_col);
+        let value = match self.advance() {
+            Some((_, '\\')) => self.scan_escape()?,
+            Some((_, '\'')) => return Err(LexError::new("empty char literal", start_line, start_col)),
+            Some((_, '\n')) | None => return Err(unterminated()),
+            Some((_, ch)) => ch,
+        };
+        match self.advance() {
+            Some((end_pos, '\'')) => {
+                let span = Span::new(start_line, start_col, start_pos, end_pos + 1);
+                Ok(Token::new(TokenKind::CharLit(value), span))
+            }
+            Some((_, '\n')) | None => Err(unterminated()),
+            Some(_) => {
+                // `'ab'`: report it whole rather than as a char and a stray quote
+                while let Some(ch) = self.peek() {
+                    if ch == '\n' { return Err(unterminated()); }
+                    self.advance();
+                    if ch == '\'' { break; }
+                }
+                Err(LexError::new("char literal must contain exactly one character", start_line, start_col))
+            }
+        }
     }
 
     /// Get next token
     fn next_token(&mut self) -> Result<Token, LexError> {
-        self.skip_whitespace_and_comments();
+        if let Some(comment) = self.skip_whitespace_and_comments()? {
+            return Ok(comment);
+        }
 
         let start_line = self.line;
         let start_col = self.column;
 
         match self.advance() {
-            None => Ok(Token::eof(self.current_pos)),
+            None if !self.interpolations.is_empty() => {
+                self.interpolations.clear();
+                Err(LexError::new("unterminated string interpolation", start_line, start_col))
+            }
+            None => Ok(Token::eof(self.source.len())),
 
             Some((pos, ch)) => {
                 let span = Span::new(start_line, start_col, pos, pos + 1);
@@ -310,8 +469,23 @@ impl<'a> Lexer<'a> {
                     // Single character tokens
                     '(' => Ok(Token::new(TokenKind::LParen, span)),
                     ')' => Ok(Token::new(TokenKind::RParen, span)),
-                    '{' => Ok(Token::new(TokenKind::LBrace, span)),
-                    '}' => Ok(Token::new(TokenKind::RBrace, span)),
+                    '{' => {
+                        if let Some(depth) = self.interpolations.last_mut() {
+                            *depth += 1;
+                        }
+                        Ok(Token::new(TokenKind::LBrace, span))
+                    }
+                    '}' => match self.interpolations.last_mut() {
+                        Some(0) => {
+                            self.interpolations.pop();
+                            self.scan_string(pos, start_line, start_col, true)
+                        }
+                        Some(depth) => {
+                            *depth -= 1;
+                            Ok(Token::new(TokenKind::RBrace, span))
+                        }
+                        None => Ok(Token::new(TokenKind::RBrace, span)),
+                    },
                     '[' => Ok(Token::new(TokenKind::LBracket, span)),
                     ']' => Ok(Token::new(TokenKind::RBracket, span)),
                     ',' => Ok(Token::new(TokenKind::Comma, span)),
@@ -506,7 +680,11 @@ impl<'a> Lexer<'a> {
                     }
 
                     // String literals
-                    '"' => self.scan_string(pos, start_line, start_col),
+                    '"' => self.scan_string(pos, start_line, start_col, false),
+                    '\'' => self.scan_char(pos, start_line, start_col),
+                    'r' if self.source[pos + 1..].trim_start_matches('#').starts_with('"') => {
+                        self.scan_raw_string(pos, start_line, start_col)
+                    }
 
                     // Numbers
                     '0'..='9' => self.scan_number(pos, start_line, start_col),
@@ -527,21 +705,151 @@ impl<'a> Lexer<'a> {
     }
 }
 
-/// Tokenize source code into a vector of tokens
+/// Tokenize source code into a vector of tokens, failing on the first error
 pub fn tokenize(source: &str) -> Result<Vec<Token>, LexError> {
-    let mut lexer = Lexer::new(source);
+    first_error(collect_tokens(Lexer::new(source)))
+}
+
+/// Tokenize the whole source, skipping past each malformed token so every
+/// error is reported at once. The tokens are what lexed cleanly.
+pub fn tokenize_all(source: &str) -> (Vec<Token>, Vec<LexError>) {
+    collect_tokens(Lexer::new(source))
+}
+
+/// Tokenize keeping comments as `LineComment`/`BlockComment` tokens, for
+/// tooling such as linters and formatters. The parser does not accept them.
+pub fn tokenize_with_trivia(source: &str) -> Result<Vec<Token>, LexError> {
+    first_error(collect_tokens(Lexer::with_comments(source)))
+}
+
+/// Kind of a trivia piece
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    Whitespace,
+    LineComment,
+    BlockComment,
+}
+
+/// Whitespace or a comment between two tokens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub start: usize,
+}
+
+/// A token with the source text around it.
+///
+/// Trailing trivia runs to the end of the token's line (excluding the line
+/// break); everything else before the next token is that token's leading trivia.
+/// The final `Eof` token carries whatever trails the last real token's line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenWithTrivia {
+    pub leading: Vec<Trivia>,
+    pub token: Token,
+    /// The token's source text
+    pub text: String,
+    pub trailing: Vec<Trivia>,
+}
+
+impl TokenWithTrivia {
+    /// Append the token and its trivia, exactly as written, to `out`
+    pub fn write_source(&self, out: &mut String) {
+        for trivia in &self.leading {
+            out.push_str(&trivia.text);
+        }
+        out.push_str(&self.text);
+        for trivia in &self.trailing {
+            out.push_str(&trivia.text);
+        }
+    }
+}
+
+/// Tokenize for editors: every byte of `source` ends up in exactly one token's
+/// text or trivia, so concatenating them reproduces the source.
+pub fn tokenize_lossless(source: &str) -> Result<Vec<TokenWithTrivia>, LexError> {
+    let mut result: Vec<TokenWithTrivia> = Vec::new();
+    let mut pending: Vec<Trivia> = Vec::new();
+    let mut pos = 0;
+    let push_whitespace = |pending: &mut Vec<Trivia>, start: usize, end: usize| {
+        if start < end {
+            pending.push(Trivia { kind: TriviaKind::Whitespace, text: source[start..end].to_string(), start });
+        }
+    };
+
+    for token in tokenize_with_trivia(source)? {
+        push_whitespace(&mut pending, pos, token.span.start);
+        pos = token.span.end;
+        let comment = match token.kind {
+            TokenKind::LineComment(_) => Some(TriviaKind::LineComment),
+            TokenKind::BlockComment(_) => Some(TriviaKind::BlockComment),
+            _ => None,
+        };
+        if let Some(kind) = comment {
+            let span = token.span;
+            pending.push(Trivia { kind, text: source[span.start..span.end].to_string(), start: span.start });
+            continue;
+        }
+
+        // Pieces up to the first line break trail the previous token
+        if let Some(prev) = result.last_mut() {
+            let mut pieces = std::mem::take(&mut pending).into_iter();
+            for piece in pieces.by_ref() {
+                match piece.text.find('\n').filter(|_| piece.kind == TriviaKind::Whitespace) {
+                    Some(0) => {
+                        pending.push(piece);
+                        break;
+                    }
+                    Some(i) => {
+                        let (same_line, rest) = piece.text.split_at(i);
+                        prev.trailing.push(Trivia { kind: TriviaKind::Whitespace, text: same_line.to_string(), start: piece.start });
+                        pending.push(Trivia { kind: TriviaKind::Whitespace, text: rest.to_string(), start: piece.start + i });
+                        break;
+                    }
+                    None => prev.trailing.push(piece),
+                }
+            }
+            pending.extend(pieces);
+        }
+
+        let text = source[token.span.start..token.span.end].to_string();
+        result.push(TokenWithTrivia { leading: std::mem::take(&mut pending), token, text, trailing: Vec::new() });
+    }
+    Ok(result)
+}
+
+fn collect_tokens(mut lexer: Lexer) -> (Vec<Token>, Vec<LexError>) {
     let mut tokens = Vec::new();
+    let mut errors = Vec::new();
 
     loop {
-        let token = lexer.next_token()?;
-        let is_eof = token.kind == TokenKind::Eof;
-        tokens.push(token);
-        if is_eof {
-            break;
+        let before = lexer.chars.peek().map(|&(pos, _)| pos);
+        match lexer.next_token() {
+            Ok(token) => {
+                let is_eof = token.kind == TokenKind::Eof;
+                tokens.push(token);
+                if is_eof {
+                    break;
+                }
+            }
+            Err(e) => {
+                errors.push(e);
+                // Resume after the bad token; always make progress
+                if lexer.chars.peek().map(|&(pos, _)| pos) == before {
+                    lexer.advance();
+                }
+            }
         }
     }
 
-    Ok(tokens)
+    (tokens, errors)
+}
+
+fn first_error((tokens, errors): (Vec<Token>, Vec<LexError>)) -> Result<Vec<Token>, LexError> {
+    match errors.into_iter().next() {
+        Some(e) => Err(e),
+        None => Ok(tokens),
+    }
 }
 
 #[cfg(test)]
@@ -632,6 +940,49 @@ mod tests {
         assert_eq!(tokens[0].kind, TokenKind::StringLit("hello\nworld\ttab".to_string()));
     }
 
+    #[test]
+    fn test_string_interpolation_tokens() {
+        let kinds: Vec<_> = tokenize(r#""a${x}b${ {1} }c" "\${x}""#).unwrap().into_iter().map(|t| t.kind).collect();
+        assert_eq!(kinds, [
+            TokenKind::InterpStart("a".to_string()),
+            TokenKind::Ident("x".to_string()),
+            TokenKind::InterpPart("b".to_string()),
+            TokenKind::LBrace,
+            TokenKind::IntLit(1),
+            TokenKind::RBrace,
+            TokenKind::InterpEnd("c".to_string()),
+            TokenKind::StringLit("${x}".to_string()),
+            TokenKind::Eof,
+        ]);
+        assert_eq!(tokenize(r#""open ${x"#).unwrap_err().message, "unterminated string interpolation");
+    }
+
+    #[test]
+    fn test_raw_strings() {
+        let tokens = tokenize(r#"r"C:\temp\file" r"a\nb" r"""#).unwrap();
+        assert_eq!(tokens[0].kind, TokenKind::StringLit(r"C:\temp\file".to_string()));
+        assert_eq!(tokens[1].kind, TokenKind::StringLit(r"a\nb".to_string()));
+        assert_eq!(tokens[2].kind, TokenKind::StringLit(String::new()));
+
+        let tokens = tokenize(r###"r#"contains "quotes""# r##"ends "# here"## rx"###).unwrap();
+        assert_eq!(tokens[0].kind, TokenKind::StringLit(r#"contains "quotes""#.to_string()));
+        assert_eq!(tokens[1].kind, TokenKind::StringLit(r##"ends "# here"##.to_string()));
+        assert_eq!(tokens[2].kind, TokenKind::Ident("rx".to_string()));
+
+        assert_eq!(tokenize(r##"r#"open"##).unwrap_err().message, "unterminated raw string literal");
+    }
+
+    #[test]
+    fn test_char_literals() {
+        let tokens = tokenize(r"'a' '\n' '\t' '\'' '\\' 'é'").unwrap();
+        let chars: Vec<_> = tokens.iter().take(6).map(|t| t.kind.clone()).collect();
+        assert_eq!(chars, ['a', '\n', '\t', '\'', '\\', 'é'].map(TokenKind::CharLit));
+
+        assert_eq!(tokenize("'ab'").unwrap_err().message, "char literal must contain exactly one character");
+        assert_eq!(tokenize("''").unwrap_err().message, "empty char literal");
+        assert_eq!(tokenize("'a").unwrap_err().message, "unterminated char literal");
+    }
+
     #[test]
     fn test_identifiers() {
         let tokens = tokenize("foo bar_baz _private camelCase").unwrap();
@@ -687,12 +1038,69 @@ mod tests {
         assert_eq!(tokens[2].kind, TokenKind::LBrace);
     }
 
+    #[test]
+    fn test_digit_separators() {
+        let tokens = tokenize("1_000 0xFF_FF 0b1010_0101 0o7_7 1_000.250_5 0b11").unwrap();
+        assert_eq!(tokens[0].kind, TokenKind::IntLit(1000));
+        assert_eq!(tokens[1].kind, TokenKind::IntLit(0xFFFF));
+        assert_eq!(tokens[2].kind, TokenKind::IntLit(0b1010_0101));
+        assert_eq!(tokens[3].kind, TokenKind::IntLit(0o77));
+        assert_eq!(tokens[4].kind, TokenKind::FloatLit(1000.2505));
+        assert_eq!(tokens[5].kind, TokenKind::IntLit(3));
+
+        for bad in ["5_", "1__0", "0x_FF", "0xFF_", "1_.5"] {
+            let err = tokenize(bad).unwrap_err();
+            assert!(err.message.starts_with("misplaced digit separator"), "{}: {}", bad, err.message);
+        }
+        // A leading underscore makes an identifier, not a number
+        assert_eq!(tokenize("_5").unwrap()[0].kind, TokenKind::Ident("_5".into()));
+        assert!(tokenize("0b102").unwrap_err().message.starts_with("invalid binary literal"));
+    }
+
     #[test]
     fn test_error_unterminated_string() {
         let result = tokenize("\"unterminated");
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_tokenize_all_reports_every_error() {
+        let (tokens, errors) = tokenize_all("let a = 1 $ 2;\nlet b = \"ok\";\nlet c = `;");
+        let found: Vec<_> = errors.iter().map(|e| (e.line, e.message.as_str())).collect();
+        assert_eq!(found, [(1, "unexpected character: '$'"), (3, "unexpected character: '`'")]);
+        assert!(tokens.iter().any(|t| t.kind == TokenKind::StringLit("ok".to_string())));
+        assert_eq!(tokens.last().map(|t| &t.kind), Some(&TokenKind::Eof));
+
+        let (_, errors) = tokenize_all("\"open\nlet x = 0x;");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(tokenize("a $ b `").unwrap_err().message, "unexpected character: '$'");
+    }
+
+    #[test]
+    fn test_error_unterminated_block_comment() {
+        let err = tokenize("/* unclosed").unwrap_err();
+        assert_eq!(err.message, "unterminated block comment");
+        assert_eq!((err.line, err.column), (1, 1));
+
+        let source = "let x = 1;\n  /* outer /* inner */\n still open";
+        let err = tokenize_with_trivia(source).unwrap_err();
+        assert_eq!((err.line, err.column), (2, 3));
+        let span = err.span.expect("comment errors should carry a span");
+        assert_eq!((span.start, span.end), (13, source.len()));
+    }
+
+    #[test]
+    fn test_error_span_covers_malformed_number() {
+        let err = tokenize("let x = 0xGG;").unwrap_err();
+        let span = err.span.expect("number errors should carry a span");
+        assert_eq!((span.start, span.end), (8, 12));
+        assert_eq!((err.line, err.column), (1, 9));
+
+        let err = tokenize("99999999999999999999").unwrap_err();
+        let span = err.span.unwrap();
+        assert_eq!((span.start, span.end), (0, 20));
+    }
+
     // === Ambiguous Token Tests (Critical for longest-match rule) ===
     
     #[test]
@@ -793,7 +1201,7 @@ mod tests {
     
     #[test]
     fn test_swift_keywords() {
-        let tokens = tokenize("guard defer throw try catch where typealias nil").unwrap();
+        let tokens = tokenize("guard defer throw try catch where typealias nil finally").unwrap();
         assert_eq!(tokens[0].kind, TokenKind::Guard);
         assert_eq!(tokens[1].kind, TokenKind::Defer);
         assert_eq!(tokens[2].kind, TokenKind::Throw);
@@ -802,5 +1210,44 @@ mod tests {
         assert_eq!(tokens[5].kind, TokenKind::Where);
         assert_eq!(tokens[6].kind, TokenKind::Typealias);
         assert_eq!(tokens[7].kind, TokenKind::Nil);
+        assert_eq!(tokens[8].kind, TokenKind::Finally);
+    }
+
+    #[test]
+    fn test_tokenize_with_trivia_keeps_comments() {
+        let tokens = tokenize_with_trivia("x // hi").unwrap();
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(tokens[0].kind, TokenKind::Ident("x".to_string()));
+        assert_eq!(tokens[1].kind, TokenKind::LineComment(" hi".to_string()));
+        assert_eq!(tokens[1].span, Span::new(1, 3, 2, 7));
+        assert_eq!(tokens[2].kind, TokenKind::Eof);
+
+        let tokens = tokenize_with_trivia("/* a /* b */ */\ny").unwrap();
+        assert_eq!(tokens[0].kind, TokenKind::BlockComment(" a /* b */ ".to_string()));
+        assert_eq!(tokens[0].span, Span::new(1, 1, 0, 15));
+        assert_eq!(tokens[1].span.line, 2);
+
+        // Normal tokenization still drops them
+        assert_eq!(tokenize("x // hi").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_lossless_tokens_round_trip_source() {
+        let source = "// header\nfn main() {  /* inline */\n\tlet s = \"héllo\"; // note\r\n\n    return 1; }\n  ";
+        let tokens = tokenize_lossless(source).unwrap();
+        let mut out = String::new();
+        for token in &tokens {
+            token.write_source(&mut out);
+        }
+        assert_eq!(out, source);
+
+        assert_eq!(tokens[0].leading[0].kind, TriviaKind::LineComment);
+        assert_eq!(tokens[0].text, "fn");
+        let brace = &tokens[4];
+        assert_eq!(brace.token.kind, TokenKind::LBrace);
+        let kinds: Vec<_> = brace.trailing.iter().map(|t| t.kind).collect();
+        assert_eq!(kinds, [TriviaKind::Whitespace, TriviaKind::BlockComment]);
+        assert_eq!(tokens[5].leading[0].text, "\n\t");
+        assert_eq!(tokens.last().unwrap().token.kind, TokenKind::Eof);
     }
 }
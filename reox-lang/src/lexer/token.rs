@@ -16,6 +16,30 @@ impl Span {
     pub fn new(line: u32, column: u32, start: usize, end: usize) -> Self {
         Self { line, column, start, end }
     }
+
+    /// Source text covered by this span
+    pub fn snippet<'a>(&self, source: &'a str) -> Option<&'a str> {
+        source.get(self.start..self.end)
+    }
+
+    /// The line containing this span, underlined with carets
+    pub fn excerpt(&self, source: &str) -> Option<String> {
+        let text = source.lines().nth(self.line.checked_sub(1)? as usize)?;
+        let indent = (self.column as usize).saturating_sub(1);
+        let width = self.snippet(source)
+            .map(|s| s.lines().next().unwrap_or("").chars().count())
+            .unwrap_or(0)
+            .max(1);
+        let gutter = self.line.to_string();
+        Some(format!(
+            "{} | {}\n{} | {}{}",
+            gutter,
+            text,
+            " ".repeat(gutter.len()),
+            " ".repeat(indent),
+            "^".repeat(width)
+        ))
+    }
 }
 
 impl Default for Span {
@@ -34,9 +58,12 @@ pub enum TokenKind {
     If,
     Else,
     While,
+    Loop,
     For,
     In,
     Return,
+    Break,
+    Continue,
     Struct,
     Match,
     Import,
@@ -66,6 +93,7 @@ pub enum TokenKind {
     Throw,      // throw exception
     Try,        // try block
     Catch,      // catch block
+    Finally,    // finally block
     Where,      // where clause
     Typealias,  // type alias
     Protocol,   // protocol/interface
@@ -94,6 +122,13 @@ pub enum TokenKind {
     IntLit(i64),
     FloatLit(f64),
     StringLit(String),
+    CharLit(char),
+    /// `"text ${`: the text before the first interpolated expression
+    InterpStart(String),
+    /// `} text ${`: the text between two interpolated expressions
+    InterpPart(String),
+    /// `} text"`: the text after the last interpolated expression
+    InterpEnd(String),
 
     // Operators
     Plus,       // +
@@ -155,6 +190,10 @@ pub enum TokenKind {
     Hash,       // # (for system directives)
     DotDot,     // .. (range)
 
+    // Trivia (only produced by `tokenize_with_trivia`)
+    LineComment(String),   // // text
+    BlockComment(String),  // /* text */
+
     // Special
     Eof,
 }
@@ -170,9 +209,12 @@ impl TokenKind {
                 | TokenKind::If
                 | TokenKind::Else
                 | TokenKind::While
+                | TokenKind::Loop
                 | TokenKind::For
                 | TokenKind::In
                 | TokenKind::Return
+                | TokenKind::Break
+                | TokenKind::Continue
                 | TokenKind::Struct
                 | TokenKind::Match
                 | TokenKind::Import
@@ -200,6 +242,7 @@ impl TokenKind {
                 | TokenKind::Throw
                 | TokenKind::Try
                 | TokenKind::Catch
+                | TokenKind::Finally
                 | TokenKind::Where
                 | TokenKind::Typealias
                 | TokenKind::Protocol
@@ -226,9 +269,12 @@ impl TokenKind {
             "if" => Some(TokenKind::If),
             "else" => Some(TokenKind::Else),
             "while" => Some(TokenKind::While),
+            "loop" => Some(TokenKind::Loop),
             "for" => Some(TokenKind::For),
             "in" => Some(TokenKind::In),
             "return" => Some(TokenKind::Return),
+            "break" => Some(TokenKind::Break),
+            "continue" => Some(TokenKind::Continue),
             "struct" => Some(TokenKind::Struct),
             "match" => Some(TokenKind::Match),
             "import" => Some(TokenKind::Import),
@@ -261,6 +307,7 @@ impl TokenKind {
             "throw" => Some(TokenKind::Throw),
             "try" => Some(TokenKind::Try),
             "catch" => Some(TokenKind::Catch),
+            "finally" => Some(TokenKind::Finally),
             "where" => Some(TokenKind::Where),
             "typealias" => Some(TokenKind::Typealias),
             "protocol" => Some(TokenKind::Protocol),
@@ -319,4 +366,13 @@ mod tests {
         assert!(!TokenKind::Plus.is_keyword());
         assert!(!TokenKind::Eof.is_keyword());
     }
+
+    #[test]
+    fn test_span_excerpt() {
+        let source = "let a = 1;\nlet bad = 0xGG;\n";
+        let span = Span::new(2, 11, 21, 25);
+        assert_eq!(span.snippet(source), Some("0xGG"));
+        assert_eq!(span.excerpt(source).unwrap(), "2 | let bad = 0xGG;\n  |           ^^^^");
+        assert_eq!(Span::new(9, 1, 0, 0).excerpt(source), None);
+    }
 }
@@ -10,6 +10,9 @@ pub mod interpreter;
 pub mod stdlib;
 pub mod cli;
 pub mod templates;
+pub mod explain;
+pub mod doc;
+pub mod formatter;
 
 // Re-export main types for convenience
 pub use lexer::{Token, TokenKind, Span, tokenize, LexError};
@@ -3,14 +3,7 @@
 
 #![allow(unused_imports)]
 
-mod lexer;
-mod parser;
-mod typechecker;
-mod codegen;
-mod cli;
-mod interpreter;
-mod stdlib;
-mod templates;
+use reoxc::{lexer, parser, typechecker, codegen, cli, interpreter, templates, explain, doc, formatter, profiler};
 
 use std::env;
 use std::process;
@@ -50,6 +43,15 @@ fn handle_command(cmd: cli::CliCommand) -> Result<(), String> {
         cli::CliCommand::New { name, template } => {
             new_project(&name, &template)
         }
+        cli::CliCommand::Repl => repl(),
+        cli::CliCommand::Explain(code) => {
+            print!("{}", explain::explain(&code)?);
+            Ok(())
+        }
+        cli::CliCommand::Doc { input, output } => generate_docs(&input, output.as_deref()),
+        cli::CliCommand::Check { input, syntax_only, warnings_as_errors } => check(&input, syntax_only, warnings_as_errors),
+        cli::CliCommand::Test { input } => run_tests(&input),
+        cli::CliCommand::Fmt { input, indent, brace_style } => format_file(&input, indent, brace_style),
         cli::CliCommand::Help => {
             cli::print_usage();
             Ok(())
@@ -65,6 +67,122 @@ fn handle_command(cmd: cli::CliCommand) -> Result<(), String> {
     }
 }
 
+fn generate_docs(input: &str, output: Option<&str>) -> Result<(), String> {
+    let source = std::fs::read_to_string(input)
+        .map_err(|e| format!("failed to read '{}': {}", input, e))?;
+    let stem = Path::new(input)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    let markdown = doc::generate(&source, stem)?;
+
+    let output_path = output.map(str::to_string).unwrap_or_else(|| format!("{}.md", stem));
+    std::fs::write(&output_path, markdown)
+        .map_err(|e| format!("failed to write '{}': {}", output_path, e))?;
+    println!("Wrote {}", output_path);
+    Ok(())
+}
+
+fn check(input: &str, syntax_only: bool, warnings_as_errors: bool) -> Result<(), String> {
+    let source = std::fs::read_to_string(input)
+        .map_err(|e| format!("failed to read '{}': {}", input, e))?;
+    let ast = parser::parse_source(&source)?;
+    if ast.declarations.is_empty() && !source.is_empty() {
+        if warnings_as_errors {
+            return Err(parser::NO_DECLARATIONS.to_string());
+        }
+        eprintln!("warning: {}", parser::NO_DECLARATIONS);
+    }
+
+    if !syntax_only {
+        // Programs may be run rather than compiled, so the interpreter's natives count as defined
+        let mut checker = typechecker::TypeChecker::with_natives(interpreter::builtin_names());
+        let diagnostics = checker.check_program(&ast).unwrap_or_else(|all| all);
+        for diagnostic in &diagnostics {
+            eprintln!("{}", diagnostic.display());
+        }
+        let errors = diagnostics.iter().filter(|d| d.severity == typechecker::Severity::Error).count();
+        if errors > 0 {
+            return Err(format!("{} error(s) found in '{}'", errors, input));
+        }
+        let warnings = diagnostics.len() - errors;
+        if warnings_as_errors && warnings > 0 {
+            return Err(format!("{} warning(s) treated as errors", warnings));
+        }
+    }
+    println!("{}: ok", input);
+    Ok(())
+}
+
+/// Run each `@test` function in a fresh interpreter, so one failure neither
+/// stops the rest nor leaks state into them
+fn run_tests(input: &str) -> Result<(), String> {
+    let source = std::fs::read_to_string(input)
+        .map_err(|e| format!("failed to read '{}': {}", input, e))?;
+    let ast = parser::parse_source(&source)?;
+    let tests: Vec<&parser::FnDecl> = ast.declarations.iter()
+        .filter_map(|d| match d {
+            parser::Decl::Function(f) if f.is_test() => Some(f),
+            _ => None,
+        })
+        .collect();
+
+    println!("running {} test(s) in {}", tests.len(), input);
+    let mut failed = 0;
+    for test in &tests {
+        let result = if test.params.is_empty() {
+            interpreter::Interpreter::with_prelude(interpreter::Prelude::ALL).eval_function(&ast, &test.name).map(|_| ())
+        } else {
+            Err(interpreter::RuntimeError::with_span("test functions take no parameters", test.span))
+        };
+        match result {
+            Ok(()) => println!("test {} ... ok", test.name),
+            Err(e) => {
+                failed += 1;
+                println!("test {} ... FAILED", test.name);
+                println!("    {}", e.display());
+            }
+        }
+    }
+
+    let status = if failed == 0 { "ok" } else { "FAILED" };
+    println!("test result: {}. {} passed; {} failed", status, tests.len() - failed, failed);
+    if failed > 0 {
+        return Err(format!("{} test(s) failed in '{}'", failed, input));
+    }
+    Ok(())
+}
+
+fn format_file(
+    input: &str,
+    indent: Option<cli::IndentStyle>,
+    brace_style: Option<cli::BraceStyle>,
+) -> Result<(), String> {
+    let source = std::fs::read_to_string(input)
+        .map_err(|e| format!("failed to read '{}': {}", input, e))?;
+
+    // Project settings from reox.toml next to the file, then CLI overrides
+    let mut options = formatter::FormatOptions::default();
+    let config_path = Path::new(input).with_file_name("reox.toml");
+    if let Ok(config) = std::fs::read_to_string(&config_path) {
+        options.apply_config(&config)?;
+    }
+    if let Some(indent) = indent {
+        options.indent = indent;
+    }
+    if let Some(brace_style) = brace_style {
+        options.brace_style = brace_style;
+    }
+
+    let formatted = formatter::format_source(&source, &options).map_err(|e| e.display())?;
+    if formatted != source {
+        std::fs::write(input, formatted)
+            .map_err(|e| format!("failed to write '{}': {}", input, e))?;
+        println!("Formatted {}", input);
+    }
+    Ok(())
+}
+
 fn init_project(template_name: &str, name: Option<&str>) -> Result<(), String> {
     let template = templates::Template::from_str(template_name)
         .ok_or_else(|| format!(
@@ -127,6 +245,28 @@ fn new_project(name: &str, template_name: &str) -> Result<(), String> {
     Ok(())
 }
 
+fn repl() -> Result<(), String> {
+    use std::io::{BufRead, Write};
+
+    println!("reoxc {} interactive mode (Ctrl-D to exit)", env!("CARGO_PKG_VERSION"));
+    let mut interp = interpreter::Interpreter::with_prelude(interpreter::Prelude::ALL);
+    let stdin = std::io::stdin();
+    loop {
+        print!("reox> ");
+        std::io::stdout().flush().map_err(|e| e.to_string())?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            println!();
+            return Ok(());
+        }
+        match interp.eval_line(&line) {
+            Ok(Some(value)) => println!("{}", value),
+            Ok(None) => {}
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+}
+
 fn run(args: &cli::Args) -> Result<(), String> {
     // Read source file
     let source = std::fs::read_to_string(&args.input)
@@ -136,14 +276,56 @@ fn run(args: &cli::Args) -> Result<(), String> {
     let tokens = lexer::tokenize(&source)
         .map_err(|e| e.display())?;
 
+    no_declarations_check(&source, &tokens, args)?;
+
     // Parse
     let ast = parser::parse(&tokens);
 
+    // Strict mode still fails on warnings before anything runs
+    if args.warnings_as_errors {
+        typechecker::check_with(&ast, true)?;
+    }
+
     // Run interpreter
-    if let Err(e) = interpreter::eval(&ast) {
-        return Err(format!("runtime error: {}", e.message));
+    let mut interp = interpreter::Interpreter::with_prelude(interpreter::Prelude::ALL);
+    if args.profile {
+        interp.enable_profiling(profiler::ProfilerConfig {
+            trace_memory: args.trace_memory,
+            min_total_ms: args.profile_min_time,
+            min_calls: args.profile_min_calls,
+            output_format: args.profile_format,
+            ..Default::default()
+        });
     }
+    let result = interp.eval(&ast);
+    if let Some(report) = interp.profile_report() {
+        eprint!("{}", report);
+    }
+    let returns_int = ast.declarations.iter().any(|d| matches!(
+        d,
+        parser::Decl::Function(f) if f.name == "main" && f.return_type == Some(parser::Type::Int)
+    ));
+    match result {
+        // `main() -> int` sets the exit status; like C, only its low 8 bits reach the parent
+        Ok(interpreter::Value::Int(code)) if returns_int => {
+            let _ = std::io::Write::flush(&mut std::io::stdout());
+            process::exit((code & 0xFF) as i32)
+        }
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.display()),
+    }
+}
 
+/// Warn about input with nothing but whitespace and comments; building an
+/// executable from it, or running in strict mode, is an error instead
+fn no_declarations_check(source: &str, tokens: &[lexer::Token], args: &cli::Args) -> Result<(), String> {
+    if !parser::lacks_declarations(source, tokens) {
+        return Ok(());
+    }
+    if (args.emit == cli::EmitType::Exe && !args.run) || args.warnings_as_errors {
+        return Err(parser::NO_DECLARATIONS.to_string());
+    }
+    eprintln!("warning: {}", parser::NO_DECLARATIONS);
     Ok(())
 }
 
@@ -156,24 +338,32 @@ fn compile(args: &cli::Args) -> Result<(), String> {
     let tokens = lexer::tokenize(&source)
         .map_err(|e| e.display())?;
 
+    no_declarations_check(&source, &tokens, args)?;
+
     // Parse
     let ast = parser::parse(&tokens);
 
     // Type check
-    let checked_ast = typechecker::check(&ast);
+    let checked_ast = typechecker::check_with(&ast, args.warnings_as_errors)?;
 
     // Generate code
+    let extension = if args.emit == cli::EmitType::Llvm { "ll" } else { "c" };
     let output_path = args.output.clone()
         .unwrap_or_else(|| {
             let stem = std::path::Path::new(&args.input)
                 .file_stem()
                 .and_then(|s| s.to_str())
                 .unwrap_or("output");
-            format!("{}.c", stem)
+            format!("{}.{}", stem, extension)
         });
 
-    codegen::generate(&checked_ast, &output_path)
-        .map_err(|e| format!("code generation failed: {}", e))?;
+    if args.emit == cli::EmitType::Llvm {
+        codegen::llvm_ir::generate(&checked_ast, &output_path)
+            .map_err(|e| format!("code generation failed: {}", e))?;
+    } else {
+        codegen::generate(&checked_ast, &output_path, args.target, args.runtime)
+            .map_err(|e| format!("code generation failed: {}", e))?;
+    }
 
     println!("compiled: {} -> {}", args.input, output_path);
 
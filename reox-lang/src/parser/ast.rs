@@ -18,6 +18,8 @@ pub enum Decl {
     Struct(StructDecl),
     Import(ImportDecl),
     Extern(ExternDecl),
+    Const(ConstDecl),
+    TypeAlias(TypeAliasDecl),
 }
 
 /// Function declaration
@@ -28,9 +30,37 @@ pub struct FnDecl {
     pub return_type: Option<Type>,
     pub body: Block,
     pub is_async: bool,
+    pub is_const: bool,
+    pub attributes: Vec<Attribute>,
+    /// Text of the `///` comments directly above the declaration
+    pub doc: Option<String>,
     pub span: Span,
 }
 
+/// Declaration attribute such as `@deprecated("use foo2 instead")`
+#[derive(Debug, Clone)]
+pub struct Attribute {
+    pub name: String,
+    pub args: Vec<Expr>,
+    pub span: Span,
+}
+
+impl FnDecl {
+    /// Marked `@test`, so `reoxc test` runs it
+    pub fn is_test(&self) -> bool {
+        self.attributes.iter().any(|a| a.name == "test")
+    }
+
+    /// `Some(message)` if marked `@deprecated`; the message is empty when none was given
+    pub fn deprecation(&self) -> Option<String> {
+        let attr = self.attributes.iter().find(|a| a.name == "deprecated")?;
+        Some(match attr.args.first() {
+            Some(Expr::Literal(Literal::String(msg, _))) => msg.clone(),
+            _ => String::new(),
+        })
+    }
+}
+
 /// Function parameter
 #[derive(Debug, Clone)]
 pub struct Param {
@@ -44,6 +74,8 @@ pub struct Param {
 pub struct StructDecl {
     pub name: String,
     pub fields: Vec<Field>,
+    /// Text of the `///` comments directly above the declaration
+    pub doc: Option<String>,
     pub span: Span,
 }
 
@@ -72,6 +104,25 @@ pub struct ExternDecl {
     pub span: Span,
 }
 
+/// Constant declaration (`const NAME: T = expr;`)
+#[derive(Debug, Clone)]
+pub struct ConstDecl {
+    pub name: String,
+    pub ty: Type,
+    pub value: Expr,
+    pub span: Span,
+}
+
+/// Type alias (`typealias Name = T;`); with `distinct` it declares a newtype
+/// that is not interchangeable with `T` (`typealias UserId = int distinct;`)
+#[derive(Debug, Clone)]
+pub struct TypeAliasDecl {
+    pub name: String,
+    pub ty: Type,
+    pub distinct: bool,
+    pub span: Span,
+}
+
 /// Type annotation
 #[derive(Debug, Clone, PartialEq)]
 pub enum Type {
@@ -82,6 +133,125 @@ pub enum Type {
     Void,
     Named(String),
     Array(Box<Type>),
+    Function(Vec<Type>, Box<Type>), // fn(int, int) -> int
+    Box(Box<Type>),                 // Box<T>: heap indirection, allows recursive structs
+    FixedInt(IntWidth),             // i8 .. u64
+    Optional(Box<Type>),            // T? or maybe T
+    Tuple(Vec<Type>),               // (int, string)
+}
+
+impl std::fmt::Display for Type {
+    /// Formats the type as it is written in source
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::String => write!(f, "string"),
+            Type::Bool => write!(f, "bool"),
+            Type::Void => write!(f, "void"),
+            Type::Named(name) => write!(f, "{}", name),
+            Type::Array(inner) => write!(f, "[{}]", inner),
+            Type::Function(params, ret) => {
+                let params: Vec<String> = params.iter().map(|p| p.to_string()).collect();
+                write!(f, "fn({}) -> {}", params.join(", "), ret)
+            }
+            Type::Box(inner) => write!(f, "Box<{}>", inner),
+            Type::FixedInt(width) => write!(f, "{}", width.name()),
+            Type::Optional(inner) => write!(f, "{}?", inner),
+            Type::Tuple(elems) => {
+                let elems: Vec<String> = elems.iter().map(|e| e.to_string()).collect();
+                write!(f, "({})", elems.join(", "))
+            }
+        }
+    }
+}
+
+/// Fixed-width integer types; plain `int` is 64-bit signed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IntWidth {
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+}
+
+impl IntWidth {
+    pub const ALL: [IntWidth; 8] = [
+        IntWidth::I8, IntWidth::I16, IntWidth::I32, IntWidth::I64,
+        IntWidth::U8, IntWidth::U16, IntWidth::U32, IntWidth::U64,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|w| w.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            IntWidth::I8 => "i8",
+            IntWidth::I16 => "i16",
+            IntWidth::I32 => "i32",
+            IntWidth::I64 => "i64",
+            IntWidth::U8 => "u8",
+            IntWidth::U16 => "u16",
+            IntWidth::U32 => "u32",
+            IntWidth::U64 => "u64",
+        }
+    }
+
+    pub fn c_type(self) -> &'static str {
+        match self {
+            IntWidth::I8 => "int8_t",
+            IntWidth::I16 => "int16_t",
+            IntWidth::I32 => "int32_t",
+            IntWidth::I64 => "int64_t",
+            IntWidth::U8 => "uint8_t",
+            IntWidth::U16 => "uint16_t",
+            IntWidth::U32 => "uint32_t",
+            IntWidth::U64 => "uint64_t",
+        }
+    }
+
+    /// Smallest and largest representable values
+    pub fn range(self) -> (i128, i128) {
+        match self {
+            IntWidth::I8 => (i8::MIN as i128, i8::MAX as i128),
+            IntWidth::I16 => (i16::MIN as i128, i16::MAX as i128),
+            IntWidth::I32 => (i32::MIN as i128, i32::MAX as i128),
+            IntWidth::I64 => (i64::MIN as i128, i64::MAX as i128),
+            IntWidth::U8 => (0, u8::MAX as i128),
+            IntWidth::U16 => (0, u16::MAX as i128),
+            IntWidth::U32 => (0, u32::MAX as i128),
+            IntWidth::U64 => (0, u64::MAX as i128),
+        }
+    }
+
+    pub fn contains(self, n: i128) -> bool {
+        let (min, max) = self.range();
+        min <= n && n <= max
+    }
+
+    /// Whether every value of `other` is representable (an implicit widening)
+    pub fn widens_from(self, other: IntWidth) -> bool {
+        let (min, max) = other.range();
+        self.contains(min) && self.contains(max)
+    }
+
+    /// Wrap an `int` into this width, as a C cast would
+    pub fn wrap(self, n: i64) -> i64 {
+        match self {
+            IntWidth::I8 => n as i8 as i64,
+            IntWidth::I16 => n as i16 as i64,
+            IntWidth::I32 => n as i32 as i64,
+            IntWidth::I64 | IntWidth::U64 => n,
+            IntWidth::U8 => n as u8 as i64,
+            IntWidth::U16 => n as u16 as i64,
+            IntWidth::U32 => n as u32 as i64,
+        }
+    }
 }
 
 /// A block of statements
@@ -95,10 +265,12 @@ pub struct Block {
 #[derive(Debug, Clone)]
 pub enum Stmt {
     Let(LetStmt),
+    LetTuple(LetTupleStmt),
     Expr(Expr),
     Return(ReturnStmt),
     If(IfStmt),
     While(WhileStmt),
+    Loop(LoopStmt),
     For(ForStmt),
     Block(Block),
     Break(Span),
@@ -108,6 +280,31 @@ pub enum Stmt {
     Defer(DeferStmt),
     TryCatch(TryCatchStmt),
     Throw(ThrowStmt),
+    /// Function declared inside a block, visible after its definition
+    FnDecl(FnDecl),
+}
+
+impl Stmt {
+    /// Source location of the statement
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Let(l) => l.span,
+            Stmt::LetTuple(l) => l.span,
+            Stmt::Expr(e) => e.span(),
+            Stmt::Return(r) => r.span,
+            Stmt::If(i) => i.span,
+            Stmt::While(w) => w.span,
+            Stmt::Loop(l) => l.span,
+            Stmt::For(f) => f.span,
+            Stmt::Block(b) => b.span,
+            Stmt::Break(span) | Stmt::Continue(span) => *span,
+            Stmt::Guard(g) => g.span,
+            Stmt::Defer(d) => d.span,
+            Stmt::TryCatch(t) => t.span,
+            Stmt::Throw(t) => t.span,
+            Stmt::FnDecl(f) => f.span,
+        }
+    }
 }
 
 /// Let statement (variable declaration)
@@ -120,6 +317,16 @@ pub struct LetStmt {
     pub span: Span,
 }
 
+/// Destructuring let: `let (a, b) = pair;` binds one name per tuple element
+#[derive(Debug, Clone)]
+pub struct LetTupleStmt {
+    pub names: Vec<String>,
+    pub mutable: bool,
+    pub ty: Option<Type>,
+    pub init: Expr,
+    pub span: Span,
+}
+
 /// Return statement
 #[derive(Debug, Clone)]
 pub struct ReturnStmt {
@@ -144,6 +351,13 @@ pub struct WhileStmt {
     pub span: Span,
 }
 
+/// Infinite loop, exited with `break` or `return`
+#[derive(Debug, Clone)]
+pub struct LoopStmt {
+    pub body: Block,
+    pub span: Span,
+}
+
 /// For loop
 #[derive(Debug, Clone)]
 pub struct ForStmt {
@@ -172,8 +386,19 @@ pub struct DeferStmt {
 #[derive(Debug, Clone)]
 pub struct TryCatchStmt {
     pub try_block: Block,
-    pub catch_var: Option<String>,
-    pub catch_block: Block,
+    /// Tried in order; only the last clause may be untyped
+    pub catches: Vec<CatchClause>,
+    /// Runs after the try and any catch, however they exit
+    pub finally_block: Option<Block>,
+    pub span: Span,
+}
+
+/// One `catch e: Type { ... }` clause; `ty` is `None` for a catch-all
+#[derive(Debug, Clone)]
+pub struct CatchClause {
+    pub var: Option<String>,
+    pub ty: Option<Type>,
+    pub block: Block,
     pub span: Span,
 }
 
@@ -195,8 +420,9 @@ pub enum Expr {
     Member(Box<Expr>, String, Span),
     Index(Box<Expr>, Box<Expr>, Span),
     Assign(Box<Expr>, Box<Expr>, Span),
-    StructLit(String, Vec<(String, Expr)>, Span),
+    StructLit(String, Vec<(String, Expr)>, Option<Box<Expr>>, Span), // Name { ..base, field: value }
     ArrayLit(Vec<Expr>, Span),
+    Tuple(Vec<Expr>, Span),                        // (a, b)
     Match(Box<Expr>, Vec<MatchArm>, Span),
     // Swift/C++ style expressions
     CompoundAssign(Box<Expr>, CompoundOp, Box<Expr>, Span),
@@ -211,6 +437,43 @@ pub enum Expr {
     Await(Box<Expr>, Span),                        // await expr
     // Range expressions
     Range(Box<Expr>, Box<Expr>, Span),             // start..end (inclusive)
+    // Block used as an expression (match arm bodies); value is its trailing expression
+    Block(Box<Block>, Span),
+    // Closures
+    Closure(Vec<Param>, Option<Type>, Box<Block>, Span), // action(x: int) -> int { ... }
+}
+
+impl Expr {
+    /// Source location of the expression
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Literal(lit) => lit.span(),
+            Expr::Identifier(_, span)
+            | Expr::Binary(_, _, _, span)
+            | Expr::Unary(_, _, span)
+            | Expr::Call(_, _, span)
+            | Expr::Member(_, _, span)
+            | Expr::Index(_, _, span)
+            | Expr::Assign(_, _, span)
+            | Expr::StructLit(_, _, _, span)
+            | Expr::ArrayLit(_, span)
+            | Expr::Tuple(_, span)
+            | Expr::Match(_, _, span)
+            | Expr::CompoundAssign(_, _, _, span)
+            | Expr::PreIncrement(_, span)
+            | Expr::PreDecrement(_, span)
+            | Expr::PostIncrement(_, span)
+            | Expr::PostDecrement(_, span)
+            | Expr::NullCoalesce(_, _, span)
+            | Expr::OptionalChain(_, _, span)
+            | Expr::TrailingClosure(_, _, span)
+            | Expr::Nil(span)
+            | Expr::Await(_, span)
+            | Expr::Range(_, _, span)
+            | Expr::Block(_, span)
+            | Expr::Closure(_, _, _, span) => *span,
+        }
+    }
 }
 
 /// Literal values
@@ -219,9 +482,23 @@ pub enum Literal {
     Int(i64, Span),
     Float(f64, Span),
     String(String, Span),
+    Char(char, Span),
     Bool(bool, Span),
 }
 
+impl Literal {
+    /// Source location of the literal
+    pub fn span(&self) -> Span {
+        match self {
+            Literal::Int(_, span)
+            | Literal::Float(_, span)
+            | Literal::String(_, span)
+            | Literal::Char(_, span)
+            | Literal::Bool(_, span) => *span,
+        }
+    }
+}
+
 /// Compound assignment operators
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CompoundOp {
@@ -257,6 +534,8 @@ pub enum BinOp {
     BitwiseXor,
     ShiftLeft,
     ShiftRight,
+    // Membership (`x in collection`)
+    In,
 }
 
 impl BinOp {
@@ -269,7 +548,7 @@ impl BinOp {
             BinOp::BitwiseXor => 4,
             BinOp::BitwiseAnd => 5,
             BinOp::Eq | BinOp::Ne => 6,
-            BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge => 7,
+            BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge | BinOp::In => 7,
             BinOp::ShiftLeft | BinOp::ShiftRight => 8,
             BinOp::Add | BinOp::Sub => 9,
             BinOp::Mul | BinOp::Div | BinOp::Mod => 10,
@@ -299,5 +578,34 @@ pub enum Pattern {
     Literal(Literal),
     Identifier(String),
     Wildcard,
+    Range(i64, i64),                                 // 1..5 or 1..=5 (both inclusive)
+    Binding { name: String, inner: Box<Pattern> },   // n @ 1..=5
+    /// `Point { x: 0, y }`; fields left out match anything, `y` alone binds the field
+    Struct { name: String, fields: Vec<(String, Pattern)> },
+}
+
+impl Pattern {
+    /// Names bound by the pattern, outermost first, each with the field path
+    /// from the matched value to what it binds (empty for the value itself)
+    pub fn binding_paths(&self) -> Vec<(&str, Vec<&str>)> {
+        match self {
+            Pattern::Identifier(name) => vec![(name, Vec::new())],
+            Pattern::Binding { name, inner } => {
+                let mut names = vec![(name.as_str(), Vec::new())];
+                names.extend(inner.binding_paths());
+                names
+            }
+            Pattern::Struct { fields, .. } => fields
+                .iter()
+                .flat_map(|(field, pattern)| {
+                    pattern.binding_paths().into_iter().map(move |(name, mut path)| {
+                        path.insert(0, field.as_str());
+                        (name, path)
+                    })
+                })
+                .collect(),
+            Pattern::Literal(_) | Pattern::Wildcard | Pattern::Range(..) => Vec::new(),
+        }
+    }
 }
 
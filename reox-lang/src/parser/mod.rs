@@ -6,6 +6,8 @@ mod ast;
 
 pub use ast::*;
 
+use std::collections::HashMap;
+
 use crate::lexer::{Token, TokenKind, Span};
 
 /// Parser error
@@ -35,11 +37,35 @@ impl ParseError {
 pub struct Parser<'a> {
     tokens: &'a [Token],
     current: usize,
+    /// Set while parsing `if`/`while`/`for`/`match` heads, where `ident {` starts the body
+    no_struct_literal: bool,
+    /// Current expression nesting depth, bounded by `max_nesting`
+    depth: usize,
+    max_nesting: usize,
+    /// A `>>` closed a nested `Box<Box<T>>`; its second `>` is still owed
+    pending_gt: bool,
 }
 
+/// Default limit on nested expressions before parsing fails instead of overflowing the stack.
+/// Sized to fit the 8 MiB main-thread stack even in unoptimized builds.
+pub const DEFAULT_MAX_NESTING: usize = 128;
+
 impl<'a> Parser<'a> {
     pub fn new(tokens: &'a [Token]) -> Self {
-        Self { tokens, current: 0 }
+        Self {
+            tokens,
+            current: 0,
+            no_struct_literal: false,
+            depth: 0,
+            max_nesting: DEFAULT_MAX_NESTING,
+            pending_gt: false,
+        }
+    }
+
+    /// Override the expression nesting limit
+    pub fn with_max_nesting(mut self, max_nesting: usize) -> Self {
+        self.max_nesting = max_nesting;
+        self
     }
 
     // === Utility Methods ===
@@ -54,6 +80,10 @@ impl<'a> Parser<'a> {
         &self.peek().kind
     }
 
+    fn peek_next_kind(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.current + 1).map(|t| &t.kind)
+    }
+
     fn is_at_end(&self) -> bool {
         matches!(self.peek_kind(), TokenKind::Eof)
     }
@@ -102,7 +132,74 @@ impl<'a> Parser<'a> {
         Ok(Program { declarations })
     }
 
+    /// Parse a bare statement list (REPL input) up to end of input
+    pub fn parse_statements(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let mut statements = Vec::new();
+        while !self.is_at_end() {
+            statements.push(self.parse_statement()?);
+        }
+        Ok(statements)
+    }
+
+    /// Parse a single expression that must span the whole input
+    pub fn parse_standalone_expression(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.parse_expression()?;
+        if !self.is_at_end() {
+            return Err(ParseError::new(
+                format!("expected end of expression, found {:?}", self.peek_kind()),
+                self.peek().span,
+            ));
+        }
+        Ok(expr)
+    }
+
+    /// Parse the rest of `"head ${a} mid ${b} tail"` after its `InterpStart`
+    /// token into `"head " + to_string(a) + " mid " + to_string(b) + " tail"`,
+    /// so every piece is a string before it is concatenated
+    fn parse_interpolation(&mut self, head: String, start: Span) -> Result<Expr, ParseError> {
+        let append = |result: Option<Expr>, piece: Expr| match result {
+            Some(left) => Expr::Binary(Box::new(left), BinOp::Add, Box::new(piece), start),
+            None => piece,
+        };
+        let mut result = (!head.is_empty()).then_some(Expr::Literal(Literal::String(head, start)));
+        loop {
+            let inner = self.parse_expression()?;
+            let span = inner.span();
+            let text = Expr::Call(Box::new(Expr::Identifier("to_string".to_string(), span)), vec![inner], span);
+            result = Some(append(result, text));
+            let token = self.peek().clone();
+            let (text, last) = match token.kind {
+                TokenKind::InterpPart(text) => (text, false),
+                TokenKind::InterpEnd(text) => (text, true),
+                _ => {
+                    return Err(ParseError::new(
+                        format!("expected '}}' to close string interpolation, found {:?}", token.kind),
+                        token.span,
+                    ))
+                }
+            };
+            self.advance();
+            if !text.is_empty() {
+                result = Some(append(result, Expr::Literal(Literal::String(text, token.span))));
+            }
+            if last {
+                return Ok(result.expect("an interpolation has at least one expression"));
+            }
+        }
+    }
+
     fn parse_declaration(&mut self) -> Result<Decl, ParseError> {
+        if self.check(&TokenKind::At) {
+            let attributes = self.parse_attributes()?;
+            let span = self.peek().span;
+            return match self.parse_declaration()? {
+                Decl::Function(mut f) => {
+                    f.attributes = attributes;
+                    Ok(Decl::Function(f))
+                }
+                _ => Err(ParseError::new("attributes are only supported on functions", span)),
+            };
+        }
         match self.peek_kind() {
             TokenKind::Fn => self.parse_fn_decl(false).map(Decl::Function),
             TokenKind::Async => {
@@ -119,6 +216,17 @@ impl<'a> Parser<'a> {
             TokenKind::Struct => self.parse_struct_decl().map(Decl::Struct),
             TokenKind::Import => self.parse_import_decl().map(Decl::Import),
             TokenKind::Extern => self.parse_extern_decl().map(Decl::Extern),
+            TokenKind::Typealias => self.parse_typealias_decl().map(Decl::TypeAlias),
+            TokenKind::Const => {
+                if matches!(self.peek_next_kind(), Some(TokenKind::Fn)) {
+                    self.advance(); // consume 'const'
+                    let mut f = self.parse_fn_decl(false)?;
+                    f.is_const = true;
+                    Ok(Decl::Function(f))
+                } else {
+                    self.parse_const_decl().map(Decl::Const)
+                }
+            }
             _ => Err(ParseError::new(
                 format!("expected declaration, found {:?}", self.peek_kind()),
                 self.peek().span,
@@ -126,6 +234,27 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Parse `@name` or `@name(args...)` attributes preceding a declaration
+    fn parse_attributes(&mut self) -> Result<Vec<Attribute>, ParseError> {
+        let mut attributes = Vec::new();
+        while self.check(&TokenKind::At) {
+            let span = self.advance().span;
+            let name = self.parse_identifier()?;
+            let mut args = Vec::new();
+            if self.match_token(&[TokenKind::LParen]) {
+                while !self.check(&TokenKind::RParen) {
+                    args.push(self.parse_expression()?);
+                    if !self.match_token(&[TokenKind::Comma]) {
+                        break;
+                    }
+                }
+                self.consume(&TokenKind::RParen, "expected ')' after attribute arguments")?;
+            }
+            attributes.push(Attribute { name, args, span });
+        }
+        Ok(attributes)
+    }
+
     fn parse_fn_decl(&mut self, is_async: bool) -> Result<FnDecl, ParseError> {
         let start_span = self.peek().span;
         self.consume(&TokenKind::Fn, "expected 'fn'")?;
@@ -150,6 +279,50 @@ impl<'a> Parser<'a> {
             return_type,
             body,
             is_async,
+            is_const: false,
+            attributes: Vec::new(),
+            doc: None,
+            span: start_span,
+        })
+    }
+
+    fn parse_const_decl(&mut self) -> Result<ConstDecl, ParseError> {
+        let start_span = self.peek().span;
+        self.consume(&TokenKind::Const, "expected 'const'")?;
+
+        let name = self.parse_identifier()?;
+        self.consume(&TokenKind::Colon, "expected ':' after constant name")?;
+        let ty = self.parse_type()?;
+        self.consume(&TokenKind::Eq, "expected '=' in constant declaration")?;
+        let value = self.parse_expression()?;
+        self.consume(&TokenKind::Semicolon, "expected ';' after constant declaration")?;
+
+        Ok(ConstDecl {
+            name,
+            ty,
+            value,
+            span: start_span,
+        })
+    }
+
+    fn parse_typealias_decl(&mut self) -> Result<TypeAliasDecl, ParseError> {
+        let start_span = self.peek().span;
+        self.consume(&TokenKind::Typealias, "expected 'typealias'")?;
+
+        let name = self.parse_identifier()?;
+        self.consume(&TokenKind::Eq, "expected '=' in type alias")?;
+        let ty = self.parse_type()?;
+        // `distinct` is contextual, not a reserved word
+        let distinct = matches!(self.peek_kind(), TokenKind::Ident(ref s) if s == "distinct");
+        if distinct {
+            self.advance();
+        }
+        self.consume(&TokenKind::Semicolon, "expected ';' after type alias")?;
+
+        Ok(TypeAliasDecl {
+            name,
+            ty,
+            distinct,
             span: start_span,
         })
     }
@@ -198,6 +371,7 @@ impl<'a> Parser<'a> {
         Ok(StructDecl {
             name,
             fields,
+            doc: None,
             span: start_span,
         })
     }
@@ -258,6 +432,18 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_type(&mut self) -> Result<Type, ParseError> {
+        // `maybe T` is the long form of `T?`
+        if self.match_token(&[TokenKind::Maybe]) {
+            return Ok(Type::Optional(Box::new(self.parse_type()?)));
+        }
+        let mut ty = self.parse_base_type()?;
+        while !self.pending_gt && self.match_token(&[TokenKind::Question]) {
+            ty = Type::Optional(Box::new(ty));
+        }
+        Ok(ty)
+    }
+
+    fn parse_base_type(&mut self) -> Result<Type, ParseError> {
         match self.peek_kind().clone() {
             TokenKind::Int => {
                 self.advance();
@@ -279,9 +465,16 @@ impl<'a> Parser<'a> {
                 self.advance();
                 Ok(Type::Void)
             }
+            TokenKind::Ident(name) if name == "Box" && self.peek_next_kind() == Some(&TokenKind::Lt) => {
+                self.advance(); // Box
+                self.advance(); // <
+                let inner = self.parse_type()?;
+                self.consume_type_close()?;
+                Ok(Type::Box(Box::new(inner)))
+            }
             TokenKind::Ident(name) => {
                 self.advance();
-                Ok(Type::Named(name))
+                Ok(IntWidth::from_name(&name).map(Type::FixedInt).unwrap_or(Type::Named(name)))
             }
             TokenKind::LBracket => {
                 self.advance();
@@ -289,6 +482,36 @@ impl<'a> Parser<'a> {
                 self.consume(&TokenKind::RBracket, "expected ']'")?;
                 Ok(Type::Array(Box::new(inner)))
             }
+            // `(int, string)`; a single parenthesized type is just that type
+            TokenKind::LParen => {
+                self.advance();
+                let mut elems = vec![self.parse_type()?];
+                while self.match_token(&[TokenKind::Comma]) && !self.check(&TokenKind::RParen) {
+                    elems.push(self.parse_type()?);
+                }
+                self.consume(&TokenKind::RParen, "expected ')' in tuple type")?;
+                Ok(if elems.len() == 1 { elems.remove(0) } else { Type::Tuple(elems) })
+            }
+            TokenKind::Fn => {
+                self.advance();
+                self.consume(&TokenKind::LParen, "expected '(' after 'fn' in function type")?;
+                let mut params = Vec::new();
+                if !self.check(&TokenKind::RParen) {
+                    loop {
+                        params.push(self.parse_type()?);
+                        if !self.match_token(&[TokenKind::Comma]) {
+                            break;
+                        }
+                    }
+                }
+                self.consume(&TokenKind::RParen, "expected ')' in function type")?;
+                let ret = if self.match_token(&[TokenKind::Arrow]) {
+                    self.parse_type()?
+                } else {
+                    Type::Void
+                };
+                Ok(Type::Function(params, Box::new(ret)))
+            }
             _ => Err(ParseError::new(
                 format!("expected type, found {:?}", self.peek_kind()),
                 self.peek().span,
@@ -296,6 +519,40 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// `(expr)` is grouping; a comma makes it a tuple `(a, b)`
+    fn parse_paren_contents(&mut self) -> Result<Expr, ParseError> {
+        let first = self.parse_expression()?;
+        if !self.check(&TokenKind::Comma) {
+            return Ok(first);
+        }
+        let span = first.span();
+        let mut elements = vec![first];
+        while self.match_token(&[TokenKind::Comma]) && !self.check(&TokenKind::RParen) {
+            elements.push(self.parse_expression()?);
+        }
+        Ok(Expr::Tuple(elements, span))
+    }
+
+    /// Consume the `>` closing a generic type, splitting `>>` when nested
+    fn consume_type_close(&mut self) -> Result<(), ParseError> {
+        if self.pending_gt {
+            self.pending_gt = false;
+            self.advance();
+            return Ok(());
+        }
+        match self.peek_kind() {
+            TokenKind::Gt => {
+                self.advance();
+                Ok(())
+            }
+            TokenKind::ShiftRight => {
+                self.pending_gt = true;
+                Ok(())
+            }
+            _ => Err(ParseError::new("expected '>' to close type", self.peek().span)),
+        }
+    }
+
     fn parse_block(&mut self) -> Result<Block, ParseError> {
         let span = self.peek().span;
         self.consume(&TokenKind::LBrace, "expected '{'")?;
@@ -314,8 +571,11 @@ impl<'a> Parser<'a> {
         match self.peek_kind() {
             TokenKind::Let => self.parse_let_stmt(),
             TokenKind::Return => self.parse_return_stmt(),
+            TokenKind::Break => self.parse_break_stmt(),
+            TokenKind::Continue => self.parse_continue_stmt(),
             TokenKind::If => self.parse_if_stmt(),
             TokenKind::While => self.parse_while_stmt(),
+            TokenKind::Loop => self.parse_loop_stmt(),
             TokenKind::For => self.parse_for_stmt(),
             TokenKind::LBrace => Ok(Stmt::Block(self.parse_block()?)),
             // Swift/C++ style statements
@@ -323,6 +583,10 @@ impl<'a> Parser<'a> {
             TokenKind::Defer => self.parse_defer_stmt(),
             TokenKind::Try => self.parse_try_catch_stmt(),
             TokenKind::Throw => self.parse_throw_stmt(),
+            // Local function: `fn name(...)`; `fn(...)` stays a closure expression
+            TokenKind::Fn if matches!(self.peek_next_kind(), Some(TokenKind::Ident(_))) => {
+                self.parse_fn_decl(false).map(Stmt::FnDecl)
+            }
             _ => self.parse_expr_stmt(),
         }
     }
@@ -332,6 +596,9 @@ impl<'a> Parser<'a> {
         self.consume(&TokenKind::Let, "expected 'let'")?;
 
         let mutable = self.match_token(&[TokenKind::Mut]);
+        if self.check(&TokenKind::LParen) {
+            return self.parse_let_tuple(mutable, span);
+        }
         let name = self.parse_identifier()?;
 
         let ty = if self.match_token(&[TokenKind::Colon]) {
@@ -357,6 +624,33 @@ impl<'a> Parser<'a> {
         }))
     }
 
+    /// The rest of `let (a, b): (int, int) = pair;` after `let` / `let mut`
+    fn parse_let_tuple(&mut self, mutable: bool, span: Span) -> Result<Stmt, ParseError> {
+        self.consume(&TokenKind::LParen, "expected '('")?;
+        let mut names = Vec::new();
+        while !self.check(&TokenKind::RParen) && !self.is_at_end() {
+            names.push(self.parse_identifier()?);
+            if !self.match_token(&[TokenKind::Comma]) {
+                break;
+            }
+        }
+        self.consume(&TokenKind::RParen, "expected ')' after destructured names")?;
+        if names.len() < 2 {
+            return Err(ParseError::new("tuple destructuring needs at least two names", span));
+        }
+
+        let ty = if self.match_token(&[TokenKind::Colon]) {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        self.consume(&TokenKind::Eq, "expected '=': a destructuring let needs an initializer")?;
+        let init = self.parse_expression()?;
+        self.consume(&TokenKind::Semicolon, "expected ';' after variable declaration")?;
+
+        Ok(Stmt::LetTuple(LetTupleStmt { names, mutable, ty, init, span }))
+    }
+
     fn parse_return_stmt(&mut self) -> Result<Stmt, ParseError> {
         let span = self.peek().span;
         self.consume(&TokenKind::Return, "expected 'return'")?;
@@ -376,11 +670,17 @@ impl<'a> Parser<'a> {
         let span = self.peek().span;
         self.consume(&TokenKind::If, "expected 'if'")?;
 
-        let condition = self.parse_expression()?;
+        let condition = self.parse_condition()?;
         let then_block = self.parse_block()?;
 
         let else_block = if self.match_token(&[TokenKind::Else]) {
-            Some(self.parse_block()?)
+            if self.check(&TokenKind::If) {
+                // `else if` is an else block holding just the nested if
+                let nested = self.parse_if_stmt()?;
+                Some(Block { span: nested.span(), statements: vec![nested] })
+            } else {
+                Some(self.parse_block()?)
+            }
         } else {
             None
         };
@@ -397,7 +697,7 @@ impl<'a> Parser<'a> {
         let span = self.peek().span;
         self.consume(&TokenKind::While, "expected 'while'")?;
 
-        let condition = self.parse_expression()?;
+        let condition = self.parse_condition()?;
         let body = self.parse_block()?;
 
         Ok(Stmt::While(WhileStmt {
@@ -407,13 +707,22 @@ impl<'a> Parser<'a> {
         }))
     }
 
+    fn parse_loop_stmt(&mut self) -> Result<Stmt, ParseError> {
+        let span = self.peek().span;
+        self.consume(&TokenKind::Loop, "expected 'loop'")?;
+
+        let body = self.parse_block()?;
+
+        Ok(Stmt::Loop(LoopStmt { body, span }))
+    }
+
     fn parse_for_stmt(&mut self) -> Result<Stmt, ParseError> {
         let span = self.peek().span;
         self.consume(&TokenKind::For, "expected 'for'")?;
 
         let var = self.parse_identifier()?;
         self.consume(&TokenKind::In, "expected 'in'")?;
-        let iterable = self.parse_expression()?;
+        let iterable = self.parse_condition()?;
         let body = self.parse_block()?;
 
         Ok(Stmt::For(ForStmt {
@@ -426,17 +735,30 @@ impl<'a> Parser<'a> {
 
     fn parse_expr_stmt(&mut self) -> Result<Stmt, ParseError> {
         let expr = self.parse_expression()?;
-        self.consume(&TokenKind::Semicolon, "expected ';' after expression")?;
+        // A match used as a statement ends with its closing brace
+        if matches!(expr, Expr::Match(..)) {
+            self.match_token(&[TokenKind::Semicolon]);
+        } else {
+            self.consume(&TokenKind::Semicolon, "expected ';' after expression")?;
+        }
         Ok(Stmt::Expr(expr))
     }
 
+    /// Parse the head expression of a control-flow construct followed by a block
+    fn parse_condition(&mut self) -> Result<Expr, ParseError> {
+        let outer = std::mem::replace(&mut self.no_struct_literal, true);
+        let result = self.parse_expression();
+        self.no_struct_literal = outer;
+        result
+    }
+
     // === Swift/C++ Style Statement Parsing ===
 
     fn parse_guard_stmt(&mut self) -> Result<Stmt, ParseError> {
         // guard condition else { ... }
         let span = self.peek().span;
         self.consume(&TokenKind::Guard, "expected 'guard'")?;
-        let condition = self.parse_expression()?;
+        let condition = self.parse_condition()?;
         self.consume(&TokenKind::Else, "expected 'else' after guard condition")?;
         let else_block = self.parse_block()?;
         Ok(Stmt::Guard(GuardStmt { condition, else_block, span }))
@@ -451,17 +773,51 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_try_catch_stmt(&mut self) -> Result<Stmt, ParseError> {
-        // try { ... } catch e { ... }  (single catch only for v1)
+        // try { ... } catch e: NetworkError { ... } catch e { ... } finally { ... }
         let span = self.peek().span;
         self.consume(&TokenKind::Try, "expected 'try'")?;
         let try_block = self.parse_block()?;
-        self.consume(&TokenKind::Catch, "expected 'catch'")?;
-        let catch_var = match self.peek_kind() {
-            TokenKind::Ident(_) => Some(self.parse_identifier()?),
-            _ => None,
+        let mut catches: Vec<CatchClause> = Vec::new();
+        while self.check(&TokenKind::Catch) || (catches.is_empty() && !self.check(&TokenKind::Finally)) {
+            let clause_span = self.peek().span;
+            if catches.last().is_some_and(|c| c.ty.is_none()) {
+                return Err(ParseError::new("an untyped catch must be the last catch clause", clause_span));
+            }
+            self.consume(&TokenKind::Catch, "expected 'catch'")?;
+            let var = match self.peek_kind() {
+                TokenKind::Ident(_) => Some(self.parse_identifier()?),
+                _ => None,
+            };
+            let ty = if var.is_some() && self.check(&TokenKind::Colon) {
+                self.advance();
+                Some(self.parse_type()?)
+            } else {
+                None
+            };
+            let block = self.parse_block()?;
+            catches.push(CatchClause { var, ty, block, span: clause_span });
+        }
+        let finally_block = if self.check(&TokenKind::Finally) {
+            self.advance();
+            Some(self.parse_block()?)
+        } else {
+            None
         };
-        let catch_block = self.parse_block()?;
-        Ok(Stmt::TryCatch(TryCatchStmt { try_block, catch_var, catch_block, span }))
+        Ok(Stmt::TryCatch(TryCatchStmt { try_block, catches, finally_block, span }))
+    }
+
+    fn parse_break_stmt(&mut self) -> Result<Stmt, ParseError> {
+        let span = self.peek().span;
+        self.consume(&TokenKind::Break, "expected 'break'")?;
+        self.consume(&TokenKind::Semicolon, "expected ';' after 'break'")?;
+        Ok(Stmt::Break(span))
+    }
+
+    fn parse_continue_stmt(&mut self) -> Result<Stmt, ParseError> {
+        let span = self.peek().span;
+        self.consume(&TokenKind::Continue, "expected 'continue'")?;
+        self.consume(&TokenKind::Semicolon, "expected ';' after 'continue'")?;
+        Ok(Stmt::Continue(span))
     }
 
     fn parse_throw_stmt(&mut self) -> Result<Stmt, ParseError> {
@@ -601,12 +957,13 @@ impl<'a> Parser<'a> {
     fn parse_comparison(&mut self) -> Result<Expr, ParseError> {
         let mut left = self.parse_shift()?;
 
-        while self.match_token(&[TokenKind::Lt, TokenKind::Gt, TokenKind::LtEq, TokenKind::GtEq]) {
+        while self.match_token(&[TokenKind::Lt, TokenKind::Gt, TokenKind::LtEq, TokenKind::GtEq, TokenKind::In]) {
             let op = match self.tokens.get(self.current - 1).map(|t| &t.kind) {
                 Some(TokenKind::Lt) => BinOp::Lt,
                 Some(TokenKind::Gt) => BinOp::Gt,
                 Some(TokenKind::LtEq) => BinOp::Le,
                 Some(TokenKind::GtEq) => BinOp::Ge,
+                Some(TokenKind::In) => BinOp::In,
                 _ => unreachable!(),
             };
             let span = self.peek().span;
@@ -670,6 +1027,18 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        // Every nested sub-expression (parens, prefix operators, literals, closures)
+        // passes through here, so this bounds recursion for the whole ladder
+        if self.depth >= self.max_nesting {
+            return Err(ParseError::new("expression nesting too deep", self.peek().span));
+        }
+        self.depth += 1;
+        let result = self.parse_unary_inner();
+        self.depth -= 1;
+        result
+    }
+
+    fn parse_unary_inner(&mut self) -> Result<Expr, ParseError> {
         // Await expression: await expr
         if self.match_token(&[TokenKind::Await]) {
             let span = self.peek().span;
@@ -785,9 +1154,27 @@ impl<'a> Parser<'a> {
                 Ok(Expr::Literal(Literal::Float(n, token.span)))
             }
             TokenKind::StringLit(s) => {
+                let mut s = s.clone();
+                let mut span = token.span;
+                self.advance();
+                // C-style adjacent literals ("a" "b") merge at parse time. The merge is
+                // purely textual, so a "{...}" placeholder reads the same as in one literal.
+                while let TokenKind::StringLit(next) = self.peek_kind() {
+                    s.push_str(next);
+                    span.end = self.peek().span.end;
+                    self.advance();
+                }
+                Ok(Expr::Literal(Literal::String(s, span)))
+            }
+            TokenKind::CharLit(c) => {
+                let c = *c;
+                self.advance();
+                Ok(Expr::Literal(Literal::Char(c, token.span)))
+            }
+            TokenKind::InterpStart(s) => {
                 let s = s.clone();
                 self.advance();
-                Ok(Expr::Literal(Literal::String(s, token.span)))
+                self.parse_interpolation(s, token.span)
             }
             TokenKind::True => {
                 self.advance();
@@ -802,27 +1189,40 @@ impl<'a> Parser<'a> {
                 self.advance();
 
                 // Check for struct literal
-                if self.check(&TokenKind::LBrace) {
+                if !self.no_struct_literal && self.check(&TokenKind::LBrace) {
                     self.advance();
                     let mut fields = Vec::new();
+                    let mut base = None;
                     while !self.check(&TokenKind::RBrace) && !self.is_at_end() {
-                        let field_name = self.parse_identifier()?;
-                        self.consume(&TokenKind::Colon, "expected ':'")?;
-                        let value = self.parse_expression()?;
-                        fields.push((field_name, value));
+                        if self.check(&TokenKind::DotDot) {
+                            let span = self.advance().span;
+                            if base.is_some() {
+                                return Err(ParseError::new("struct literal can only have one '..' base", span));
+                            }
+                            base = Some(Box::new(self.parse_expression()?));
+                        } else {
+                            let field_name = self.parse_identifier()?;
+                            self.consume(&TokenKind::Colon, "expected ':'")?;
+                            let value = self.parse_expression()?;
+                            fields.push((field_name, value));
+                        }
                         if !self.match_token(&[TokenKind::Comma]) {
                             break;
                         }
                     }
                     self.consume(&TokenKind::RBrace, "expected '}'")?;
-                    return Ok(Expr::StructLit(name, fields, token.span));
+                    return Ok(Expr::StructLit(name, fields, base, token.span));
                 }
 
                 Ok(Expr::Identifier(name, token.span))
             }
             TokenKind::LParen => {
                 self.advance();
-                let expr = self.parse_expression()?;
+                // Parentheses make struct literals unambiguous again
+                let outer = std::mem::replace(&mut self.no_struct_literal, false);
+                let expr = self.parse_paren_contents();
+                self.no_struct_literal = outer;
+                let expr = expr?;
                 self.consume(&TokenKind::RParen, "expected ')'")?;
                 Ok(expr)
             }
@@ -842,9 +1242,27 @@ impl<'a> Parser<'a> {
                 self.advance();
                 Ok(Expr::Nil(token.span))
             }
+            TokenKind::Action => {
+                self.advance();
+                // Parameter list is optional: `action { ... }`
+                let params = if self.match_token(&[TokenKind::LParen]) {
+                    let params = self.parse_param_list()?;
+                    self.consume(&TokenKind::RParen, "expected ')' after closure parameters")?;
+                    params
+                } else {
+                    Vec::new()
+                };
+                let return_type = if self.match_token(&[TokenKind::Arrow]) {
+                    Some(self.parse_type()?)
+                } else {
+                    None
+                };
+                let body = self.parse_block()?;
+                Ok(Expr::Closure(params, return_type, Box::new(body), token.span))
+            }
             TokenKind::Match => {
                 self.advance();
-                let scrutinee = self.parse_expression()?;
+                let scrutinee = self.parse_condition()?;
                 self.consume(&TokenKind::LBrace, "expected '{' after match expression")?;
                 
                 let mut arms = Vec::new();
@@ -885,13 +1303,10 @@ impl<'a> Parser<'a> {
         
         // Body can be a single expression or a block
         let body = if self.check(&TokenKind::LBrace) {
-            // Block body - parse statements and use last as value
+            // Block body keeps all statements so return/break inside it still apply
+            let block_span = self.peek().span;
             let block = self.parse_block()?;
-            if let Some(Stmt::Expr(expr)) = block.statements.last() {
-                expr.clone()
-            } else {
-                Expr::Nil(span)
-            }
+            Expr::Block(Box::new(block), block_span)
         } else {
             self.parse_expression()?
         };
@@ -899,20 +1314,68 @@ impl<'a> Parser<'a> {
         Ok(MatchArm { pattern, body, span })
     }
 
+    /// A numeric literal in pattern position, folding a leading `-` into it
+    fn parse_signed_number(&mut self) -> Result<Literal, ParseError> {
+        let start = self.peek().span;
+        let negative = self.match_token(&[TokenKind::Minus]);
+        let token = self.peek().clone();
+        let lit = match token.kind {
+            TokenKind::IntLit(n) => Literal::Int(if negative { n.wrapping_neg() } else { n }, start),
+            TokenKind::FloatLit(f) => Literal::Float(if negative { -f } else { f }, start),
+            _ => return Err(ParseError::new(format!("expected number after '-', found {:?}", token.kind), token.span)),
+        };
+        self.advance();
+        Ok(lit)
+    }
+
+    /// Parse the `{ field: pattern, field }` part of a struct pattern
+    fn parse_struct_pattern(&mut self, name: String) -> Result<Pattern, ParseError> {
+        self.consume(&TokenKind::LBrace, "expected '{' in struct pattern")?;
+        let mut fields = Vec::new();
+        while !self.check(&TokenKind::RBrace) && !self.is_at_end() {
+            let field = self.parse_identifier()?;
+            let pattern = if self.match_token(&[TokenKind::Colon]) {
+                self.parse_pattern()?
+            } else {
+                Pattern::Identifier(field.clone())
+            };
+            fields.push((field, pattern));
+            if !self.match_token(&[TokenKind::Comma]) {
+                break;
+            }
+        }
+        self.consume(&TokenKind::RBrace, "expected '}' after struct pattern")?;
+        Ok(Pattern::Struct { name, fields })
+    }
+
     fn parse_pattern(&mut self) -> Result<Pattern, ParseError> {
         let token = self.peek().clone();
         
         match &token.kind {
-            TokenKind::IntLit(n) => {
-                let n = *n;
-                self.advance();
-                Ok(Pattern::Literal(Literal::Int(n, token.span)))
+            TokenKind::IntLit(_) | TokenKind::FloatLit(_) | TokenKind::Minus => {
+                let lit = self.parse_signed_number()?;
+                if let Literal::Int(n, _) = lit {
+                    if self.match_token(&[TokenKind::DotDot]) {
+                        self.match_token(&[TokenKind::Eq]);
+                        let end = self.peek().span;
+                        let Ok(Literal::Int(hi, _)) = self.parse_signed_number() else {
+                            return Err(ParseError::new("expected integer after '..' in range pattern", end));
+                        };
+                        return Ok(Pattern::Range(n, hi));
+                    }
+                }
+                Ok(Pattern::Literal(lit))
             }
             TokenKind::StringLit(s) => {
                 let s = s.clone();
                 self.advance();
                 Ok(Pattern::Literal(Literal::String(s, token.span)))
             }
+            TokenKind::CharLit(c) => {
+                let c = *c;
+                self.advance();
+                Ok(Pattern::Literal(Literal::Char(c, token.span)))
+            }
             TokenKind::True => {
                 self.advance();
                 Ok(Pattern::Literal(Literal::Bool(true, token.span)))
@@ -926,6 +1389,11 @@ impl<'a> Parser<'a> {
                 self.advance();
                 if name == "_" {
                     Ok(Pattern::Wildcard)
+                } else if self.check(&TokenKind::LBrace) {
+                    self.parse_struct_pattern(name)
+                } else if self.match_token(&[TokenKind::At]) {
+                    let inner = self.parse_pattern()?;
+                    Ok(Pattern::Binding { name, inner: Box::new(inner) })
                 } else {
                     Ok(Pattern::Identifier(name))
                 }
@@ -953,6 +1421,65 @@ pub fn parse(tokens: &[Token]) -> Ast {
     }
 }
 
+/// Attach `///` doc comments to the functions and structs they precede.
+///
+/// `trivia` is the same source tokenized with `tokenize_with_trivia`. A doc
+/// block belongs to the declaration starting on the line right after it,
+/// counting a leading attribute such as `@deprecated` as the start.
+pub fn attach_doc_comments(program: &mut Program, trivia: &[Token]) {
+    let mut docs: HashMap<u32, String> = HashMap::new();
+    let mut block: Vec<&str> = Vec::new();
+    let mut last_line = 0;
+    for token in trivia {
+        match &token.kind {
+            TokenKind::LineComment(text) if text.starts_with('/') && !text.starts_with("//") => {
+                if !block.is_empty() && token.span.line != last_line + 1 {
+                    block.clear();
+                }
+                let text = &text[1..];
+                block.push(text.strip_prefix(' ').unwrap_or(text).trim_end());
+                last_line = token.span.line;
+            }
+            TokenKind::LineComment(_) | TokenKind::BlockComment(_) => block.clear(),
+            _ => {
+                if !block.is_empty() && token.span.line == last_line + 1 {
+                    docs.insert(token.span.line, block.join("\n"));
+                }
+                block.clear();
+            }
+        }
+    }
+
+    for decl in &mut program.declarations {
+        match decl {
+            Decl::Function(f) => {
+                let line = f.attributes.first().map_or(f.span.line, |a| a.span.line);
+                f.doc = docs.remove(&line);
+            }
+            Decl::Struct(s) => s.doc = docs.remove(&s.span.line),
+            _ => {}
+        }
+    }
+}
+
+/// Lex and parse `source`, stopping at the first syntax error
+pub fn parse_source(source: &str) -> Result<Program, String> {
+    let (tokens, errors) = crate::lexer::tokenize_all(source);
+    if !errors.is_empty() {
+        return Err(errors.iter().map(|e| e.display()).collect::<Vec<_>>().join("\n"));
+    }
+    Parser::new(&tokens).parse_program().map_err(|e| e.display())
+}
+
+/// Reported for input that is not empty but holds only whitespace and comments
+pub const NO_DECLARATIONS: &str = "no declarations found in input";
+
+/// Whether a non-empty `source` lexed to nothing but end of input. Judged from
+/// the tokens rather than the parsed program, which is also empty after a parse error.
+pub fn lacks_declarations(source: &str, tokens: &[Token]) -> bool {
+    !source.is_empty() && tokens.iter().all(|t| t.kind == TokenKind::Eof)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1038,6 +1565,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_else_if_chain() {
+        let tokens = tokenize("fn main() { if a { x(); } else if b { y(); } else { z(); } }").unwrap();
+        let ast = parse(&tokens);
+        let Decl::Function(f) = &ast.declarations[0] else { panic!("expected function") };
+        let Stmt::If(outer) = &f.body.statements[0] else { panic!("expected if") };
+        assert!(matches!(outer.condition, Expr::Identifier(ref n, _) if n == "a"));
+        let else_block = outer.else_block.as_ref().expect("else branch");
+        assert_eq!(else_block.statements.len(), 1);
+        let Stmt::If(inner) = &else_block.statements[0] else { panic!("expected nested if") };
+        assert!(matches!(inner.condition, Expr::Identifier(ref n, _) if n == "b"));
+        assert_eq!(inner.then_block.statements.len(), 1);
+        assert_eq!(inner.else_block.as_ref().map(|b| b.statements.len()), Some(1));
+    }
+
     #[test]
     fn test_parse_expression_precedence() {
         let tokens = tokenize("fn main() { let x = 1 + 2 * 3; }").unwrap();
@@ -1105,6 +1647,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_break_and_continue_statements() {
+        let tokens = tokenize("fn main() { while x > 0 { break; continue; } }").unwrap();
+        let ast = parse(&tokens);
+        match &ast.declarations[0] {
+            Decl::Function(f) => {
+                assert_eq!(f.body.statements.len(), 1);
+                match &f.body.statements[0] {
+                    Stmt::While(w) => {
+                        assert!(matches!(w.body.statements[0], Stmt::Break(span) if span.column == 27));
+                        assert!(matches!(w.body.statements[1], Stmt::Continue(span) if span.column == 34));
+                    }
+                    _ => panic!("expected while statement"),
+                }
+            }
+            _ => panic!("expected function"),
+        }
+
+        let tokens = tokenize("fn main() { loop { break } }").unwrap();
+        let err = Parser::new(&tokens).parse_program().unwrap_err();
+        assert!(err.message.starts_with("expected ';' after 'break'"));
+    }
+
     #[test]
     fn test_parse_defer_statement() {
         let tokens = tokenize("fn main() { defer { cleanup(); } }").unwrap();
@@ -1133,8 +1698,10 @@ mod tests {
                 match &f.body.statements[0] {
                     Stmt::TryCatch(t) => {
                         assert!(!t.try_block.statements.is_empty());
-                        assert_eq!(t.catch_var, Some("e".to_string()));
-                        assert!(!t.catch_block.statements.is_empty());
+                        assert_eq!(t.catches.len(), 1);
+                        assert_eq!(t.catches[0].var, Some("e".to_string()));
+                        assert!(t.catches[0].ty.is_none());
+                        assert!(!t.catches[0].block.statements.is_empty());
                     }
                     _ => panic!("expected try/catch statement"),
                 }
@@ -1286,6 +1853,76 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_const_fn_and_const_decl() {
+        let source = r#"
+            const fn square(n: int) -> int {
+                return n * n;
+            }
+            const SIZE: int = square(4);
+        "#;
+        let tokens = tokenize(source).unwrap();
+        let ast = parse(&tokens);
+
+        assert_eq!(ast.declarations.len(), 2);
+        match &ast.declarations[0] {
+            Decl::Function(f) => assert!(f.is_const && !f.is_async),
+            _ => panic!("expected const fn"),
+        }
+        match &ast.declarations[1] {
+            Decl::Const(c) => {
+                assert_eq!(c.name, "SIZE");
+                assert_eq!(c.ty, Type::Int);
+            }
+            _ => panic!("expected const declaration"),
+        }
+    }
+
+    #[test]
+    fn test_adjacent_string_literals_merge() {
+        let tokens = tokenize("let s = \"Hello, \"\n    \"world\";").unwrap();
+        let stmts = Parser::new(&tokens).parse_statements().unwrap();
+        match &stmts[0] {
+            Stmt::Let(l) => match &l.init {
+                Some(Expr::Literal(Literal::String(s, _))) => assert_eq!(s, "Hello, world"),
+                other => panic!("expected one string literal, got {:?}", other),
+            },
+            other => panic!("expected let, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_string_interpolation_desugars_to_concatenation() {
+        let tokens = tokenize(r#"let s = "sum=${a + b}!";"#).unwrap();
+        let stmts = Parser::new(&tokens).parse_statements().unwrap();
+        let Stmt::Let(l) = &stmts[0] else { panic!("expected let") };
+        let Some(Expr::Binary(head, BinOp::Add, tail, _)) = &l.init else { panic!("expected concatenation") };
+        assert!(matches!(tail.as_ref(), Expr::Literal(Literal::String(s, _)) if s == "!"));
+        let Expr::Binary(prefix, BinOp::Add, text, _) = head.as_ref() else { panic!("expected concatenation") };
+        assert!(matches!(prefix.as_ref(), Expr::Literal(Literal::String(s, _)) if s == "sum="));
+        let Expr::Call(callee, args, _) = text.as_ref() else { panic!("expected to_string call") };
+        assert!(matches!(callee.as_ref(), Expr::Identifier(name, _) if name == "to_string"));
+        assert!(matches!(args.as_slice(), [Expr::Binary(_, BinOp::Add, _, _)]));
+
+        let tokens = tokenize(r#"let s = "${a b}";"#).unwrap();
+        let err = Parser::new(&tokens).parse_statements().unwrap_err();
+        assert!(err.message.starts_with("expected '}' to close string interpolation"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_parse_typealias() {
+        let tokens = tokenize("typealias Score = int; typealias UserId = int distinct;").unwrap();
+        let ast = Parser::new(&tokens).parse_program().unwrap();
+        match (&ast.declarations[0], &ast.declarations[1]) {
+            (Decl::TypeAlias(a), Decl::TypeAlias(b)) => {
+                assert_eq!((a.name.as_str(), a.distinct), ("Score", false));
+                assert_eq!((b.name.as_str(), b.distinct), ("UserId", true));
+                assert_eq!(b.ty, Type::Int);
+            }
+            _ => panic!("expected type aliases"),
+        }
+    }
+
     #[test]
     fn test_parse_await_expression() {
         let source = r#"
@@ -1318,4 +1955,265 @@ mod tests {
             _ => panic!("expected async function"),
         }
     }
+
+    #[test]
+    fn test_parse_function_type_annotation() {
+        let source = r#"
+            fn apply(f: fn(int) -> int, x: int) -> int {
+                return f(x);
+            }
+        "#;
+        let tokens = tokenize(source).unwrap();
+        let ast = parse(&tokens);
+
+        match &ast.declarations[0] {
+            Decl::Function(f) => {
+                assert_eq!(
+                    f.params[0].ty,
+                    Type::Function(vec![Type::Int], Box::new(Type::Int))
+                );
+                assert_eq!(f.params[1].ty, Type::Int);
+            }
+            _ => panic!("expected function"),
+        }
+    }
+
+    #[test]
+    fn test_parse_closure_expression() {
+        let source = r#"
+            fn main() {
+                let double = action(n: int) -> int { return n * 2; };
+            }
+        "#;
+        let tokens = tokenize(source).unwrap();
+        let ast = parse(&tokens);
+
+        match &ast.declarations[0] {
+            Decl::Function(f) => match &f.body.statements[0] {
+                Stmt::Let(l) => match &l.init {
+                    Some(Expr::Closure(params, ret, _, _)) => {
+                        assert_eq!(params.len(), 1);
+                        assert_eq!(ret, &Some(Type::Int));
+                    }
+                    other => panic!("expected closure, got {:?}", other),
+                },
+                _ => panic!("expected let"),
+            },
+            _ => panic!("expected function"),
+        }
+    }
+
+    #[test]
+    fn test_identifier_before_block_is_not_struct_literal() {
+        let source = r#"
+            fn main() {
+                while running {
+                    if done { break; } else { continue; }
+                }
+                match x {
+                    0 => { return; }
+                    _ => {}
+                }
+            }
+        "#;
+        let tokens = tokenize(source).unwrap();
+        let mut parser = Parser::new(&tokens);
+        let program = parser.parse_program().unwrap();
+
+        match &program.declarations[0] {
+            Decl::Function(f) => {
+                assert!(matches!(&f.body.statements[0], Stmt::While(w) if matches!(w.condition, Expr::Identifier(..))));
+                match &f.body.statements[1] {
+                    Stmt::Expr(Expr::Match(_, arms, _)) => {
+                        assert!(matches!(&arms[0].body, Expr::Block(b, _) if matches!(b.statements[0], Stmt::Return(_))));
+                    }
+                    other => panic!("expected match statement, got {:?}", other),
+                }
+            }
+            _ => panic!("expected function"),
+        }
+    }
+
+    #[test]
+    fn test_deeply_nested_parens_report_error() {
+        // Run on a main-thread-sized stack; test threads default to 2 MiB
+        let handle = std::thread::Builder::new()
+            .stack_size(8 << 20)
+            .spawn(|| {
+                let depth = 100_000;
+                let source = format!("fn main() {{ let x = {}1{}; }}", "(".repeat(depth), ")".repeat(depth));
+                let tokens = tokenize(&source).unwrap();
+                Parser::new(&tokens).parse_program().unwrap_err().message
+            })
+            .unwrap();
+        assert_eq!(handle.join().unwrap(), "expression nesting too deep");
+
+        // Moderate nesting still parses, and the limit is configurable
+        let source = format!("fn main() {{ let x = {}1{}; }}", "(".repeat(50), ")".repeat(50));
+        let tokens = tokenize(&source).unwrap();
+        assert!(Parser::new(&tokens).parse_program().is_ok());
+        assert!(Parser::new(&tokens).with_max_nesting(10).parse_program().is_err());
+    }
+
+    #[test]
+    fn test_parse_loop_statement() {
+        let source = "fn main() { loop { if done { break; } } }";
+        let tokens = tokenize(source).unwrap();
+        let ast = parse(&tokens);
+
+        match &ast.declarations[0] {
+            Decl::Function(f) => match &f.body.statements[0] {
+                Stmt::Loop(l) => assert!(matches!(l.body.statements[0], Stmt::If(_))),
+                other => panic!("expected loop, got {:?}", other),
+            },
+            _ => panic!("expected function"),
+        }
+    }
+
+    #[test]
+    fn test_parse_box_type() {
+        let tokens = tokenize("struct Node { next: Box<Node>, deep: Box<Box<Node>>, n: int }").unwrap();
+        let ast = Parser::new(&tokens).parse_program().unwrap();
+        match &ast.declarations[0] {
+            Decl::Struct(s) => {
+                let node = || Box::new(Type::Named("Node".to_string()));
+                assert_eq!(s.fields[0].ty, Type::Box(node()));
+                assert_eq!(s.fields[1].ty, Type::Box(Box::new(Type::Box(node()))));
+                assert_eq!(s.fields[2].ty, Type::Int);
+            }
+            _ => panic!("expected struct"),
+        }
+    }
+
+    #[test]
+    fn test_parse_optional_types() {
+        let tokens = tokenize("fn f(a: int?, b: maybe [int], c: Box<Node>?) {}").unwrap();
+        let ast = Parser::new(&tokens).parse_program().unwrap();
+        match &ast.declarations[0] {
+            Decl::Function(f) => {
+                let types: Vec<&Type> = f.params.iter().map(|p| &p.ty).collect();
+                assert_eq!(types, vec![
+                    &Type::Optional(Box::new(Type::Int)),
+                    &Type::Optional(Box::new(Type::Array(Box::new(Type::Int)))),
+                    &Type::Optional(Box::new(Type::Box(Box::new(Type::Named("Node".to_string()))))),
+                ]);
+            }
+            _ => panic!("expected function"),
+        }
+    }
+
+    #[test]
+    fn test_parse_struct_update() {
+        let tokens = tokenize("fn main() { let q = Point { ..p, x: 9 }; }").unwrap();
+        let program = Parser::new(&tokens).parse_program().unwrap();
+        let Decl::Function(f) = &program.declarations[0] else { panic!("expected function") };
+        let Stmt::Let(l) = &f.body.statements[0] else { panic!("expected let") };
+        match l.init.as_ref() {
+            Some(Expr::StructLit(name, fields, Some(base), _)) => {
+                assert_eq!(name, "Point");
+                assert_eq!(fields.len(), 1);
+                assert!(matches!(base.as_ref(), Expr::Identifier(n, _) if n == "p"));
+            }
+            other => panic!("expected struct update, got {:?}", other),
+        }
+
+        let tokens = tokenize("fn main() { let q = Point { ..p, ..r }; }").unwrap();
+        assert!(Parser::new(&tokens).parse_program().is_err());
+    }
+
+    #[test]
+    fn test_parse_negative_patterns() {
+        let tokens = tokenize("fn f(n: int) { match n { -1 => 0, -5..=-2 => 1, -0.5 => 2, _ => 3 } }").unwrap();
+        let program = Parser::new(&tokens).parse_program().unwrap();
+        let Decl::Function(f) = &program.declarations[0] else { panic!("expected function") };
+        let Stmt::Expr(Expr::Match(_, arms, _)) = &f.body.statements[0] else { panic!("expected match") };
+        assert!(matches!(arms[0].pattern, Pattern::Literal(Literal::Int(-1, _))));
+        assert!(matches!(arms[1].pattern, Pattern::Range(-5, -2)));
+        assert!(matches!(arms[2].pattern, Pattern::Literal(Literal::Float(f, _)) if f == -0.5));
+
+        let tokens = tokenize("fn f(n: int) { match n { -x => 0 } }").unwrap();
+        assert!(Parser::new(&tokens).parse_program().is_err());
+    }
+
+    #[test]
+    fn test_parse_struct_pattern() {
+        let tokens = tokenize("fn f(p: Point) { match p { Point { x: 0, y } => y, Point {} => 1, _ => 2 } }").unwrap();
+        let program = Parser::new(&tokens).parse_program().unwrap();
+        let Decl::Function(f) = &program.declarations[0] else { panic!("expected function") };
+        let Stmt::Expr(Expr::Match(_, arms, _)) = &f.body.statements[0] else { panic!("expected match") };
+        let Pattern::Struct { name, fields } = &arms[0].pattern else { panic!("expected struct pattern") };
+        assert_eq!(name, "Point");
+        assert!(matches!(&fields[..], [(x, Pattern::Literal(Literal::Int(0, _))), (y, Pattern::Identifier(b))]
+            if x == "x" && y == "y" && b == "y"));
+        assert_eq!(arms[0].pattern.binding_paths(), vec![("y", vec!["y"])]);
+        assert!(matches!(&arms[1].pattern, Pattern::Struct { fields, .. } if fields.is_empty()));
+    }
+
+    #[test]
+    fn test_parse_source_ignores_type_errors() {
+        let ill_typed = "fn main() { let x: int = \"text\"; return missing(x); }";
+        assert!(parse_source(ill_typed).is_ok());
+        let err = parse_source("fn main() { let x = ; }").unwrap_err();
+        assert!(err.starts_with("error[1:"));
+        assert!(parse_source("let s = \"open").is_err());
+    }
+
+    #[test]
+    fn test_parse_typed_catch_clauses() {
+        let tokens = tokenize("fn main() { try { f(); } catch e: NetworkError { a(); } catch e: [int] { b(); } catch { c(); } }").unwrap();
+        let ast = parse(&tokens);
+        let Decl::Function(f) = &ast.declarations[0] else { panic!("expected function") };
+        let Stmt::TryCatch(t) = &f.body.statements[0] else { panic!("expected try/catch statement") };
+        let types: Vec<Option<String>> = t.catches.iter().map(|c| c.ty.as_ref().map(|ty| ty.to_string())).collect();
+        assert_eq!(types, [Some("NetworkError".to_string()), Some("[int]".to_string()), None]);
+        assert_eq!(t.catches[2].var, None);
+
+        let tokens = tokenize("fn main() { try { f(); } catch e { a(); } catch e: NetworkError { b(); } }").unwrap();
+        let err = Parser::new(&tokens).parse_program().unwrap_err();
+        assert!(err.message.contains("untyped catch must be the last"));
+    }
+
+    #[test]
+    fn test_parse_finally_clause() {
+        let tokens = tokenize("fn main() { try { f(); } catch e { a(); } finally { b(); } try { g(); } finally { c(); } }").unwrap();
+        let ast = parse(&tokens);
+        let Decl::Function(f) = &ast.declarations[0] else { panic!("expected function") };
+        let Stmt::TryCatch(t) = &f.body.statements[0] else { panic!("expected try/catch statement") };
+        assert_eq!(t.catches.len(), 1);
+        assert!(t.finally_block.as_ref().is_some_and(|b| b.statements.len() == 1));
+        let Stmt::TryCatch(t) = &f.body.statements[1] else { panic!("expected try/finally statement") };
+        assert!(t.catches.is_empty());
+        assert!(t.finally_block.is_some());
+    }
+
+    #[test]
+    fn test_comment_and_whitespace_only_input() {
+        let comments = "// settings moved to config.rx\n/* nothing\n   here */\n/// doc for nothing\n";
+        let tokens = tokenize(comments).unwrap();
+        assert!(lacks_declarations(comments, &tokens));
+        assert!(parse_source(comments).unwrap().declarations.is_empty());
+
+        let blank = "  \n\t\n";
+        assert!(lacks_declarations(blank, &tokenize(blank).unwrap()));
+        assert!(parse_source(blank).unwrap().declarations.is_empty());
+
+        assert!(!lacks_declarations("", &tokenize("").unwrap()));
+        let code = "fn main() { }";
+        assert!(!lacks_declarations(code, &tokenize(code).unwrap()));
+    }
+
+    #[test]
+    fn test_parse_tuple_destructuring_let() {
+        let tokens = tokenize("fn split() -> (int, string) { let (x, y) = (1, \"a\"); let g = (x); return (x, y,); }").unwrap();
+        let ast = parse(&tokens);
+        let Decl::Function(f) = &ast.declarations[0] else { panic!("expected function") };
+        assert_eq!(f.return_type.as_ref().map(|t| t.to_string()).as_deref(), Some("(int, string)"));
+        let Stmt::LetTuple(l) = &f.body.statements[0] else { panic!("expected destructuring let") };
+        assert_eq!(l.names, ["x", "y"]);
+        assert!(matches!(&l.init, Expr::Tuple(elems, _) if elems.len() == 2));
+        let Stmt::Let(g) = &f.body.statements[1] else { panic!("expected let") };
+        assert!(matches!(g.init, Some(Expr::Identifier(..))));
+        let Stmt::Return(r) = &f.body.statements[2] else { panic!("expected return") };
+        assert!(matches!(&r.value, Some(Expr::Tuple(elems, _)) if elems.len() == 2));
+    }
 }
@@ -18,10 +18,16 @@ pub struct ProfilerConfig {
     pub trace_calls: bool,
     /// Enable memory allocation tracking
     pub trace_memory: bool,
-    /// Sample rate (1 = every call, 10 = every 10th call)
+    /// Sample rate (1 = every call, 10 = every 10th call).
+    /// Every call is counted, but only sampled calls are timed; a function's
+    /// total time is then estimated as its average sampled time times its calls.
     pub sample_rate: u32,
     /// Output format
     pub output_format: OutputFormat,
+    /// Leave functions with less total time than this out of reports
+    pub min_total_ms: f64,
+    /// Leave functions called fewer times than this out of reports
+    pub min_calls: u64,
 }
 
 impl Default for ProfilerConfig {
@@ -31,6 +37,8 @@ impl Default for ProfilerConfig {
             trace_memory: false,
             sample_rate: 1,
             output_format: OutputFormat::Text,
+            min_total_ms: 0.0,
+            min_calls: 0,
         }
     }
 }
@@ -41,16 +49,22 @@ pub enum OutputFormat {
     Text,
     Json,
     Flamegraph,
+    Speedscope,
 }
 
-/// A single profiling event
-#[derive(Debug, Clone)]
-pub struct ProfileEvent {
+/// Whether a timeline event opens or closes a call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelineEventKind {
+    Open,
+    Close,
+}
+
+/// One edge of a timed call, relative to when profiling started
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineEvent {
+    pub kind: TimelineEventKind,
     pub name: String,
-    pub start_time: Instant,
-    pub duration: Duration,
-    pub call_count: u64,
-    pub children: Vec<String>,
+    pub at: Duration,
 }
 
 /// Function statistics
@@ -58,6 +72,8 @@ pub struct ProfileEvent {
 pub struct FunctionStats {
     pub name: String,
     pub call_count: u64,
+    /// Calls that were timed; below `call_count` when sampling
+    pub sampled_calls: u64,
     pub total_time: Duration,
     pub self_time: Duration,
     pub min_time: Duration,
@@ -70,6 +86,7 @@ impl FunctionStats {
         Self {
             name: name.to_string(),
             call_count: 0,
+            sampled_calls: 0,
             total_time: Duration::ZERO,
             self_time: Duration::ZERO,
             min_time: Duration::MAX,
@@ -80,6 +97,7 @@ impl FunctionStats {
 
     pub fn record(&mut self, duration: Duration) {
         self.call_count += 1;
+        self.sampled_calls += 1;
         self.total_time += duration;
         self.min_time = self.min_time.min(duration);
         self.max_time = self.max_time.max(duration);
@@ -92,7 +110,12 @@ impl FunctionStats {
 pub struct Profiler {
     config: ProfilerConfig,
     functions: HashMap<String, FunctionStats>,
-    call_stack: Vec<(String, Instant)>,
+    /// Calls entered per function, sampled or not
+    entered: HashMap<String, u64>,
+    /// Open calls; the start time is `None` for calls skipped by sampling
+    call_stack: Vec<(String, Option<Instant>)>,
+    /// Open/close events of every timed call, in order
+    timeline: Vec<TimelineEvent>,
     start_time: Instant,
     total_allocations: u64,
     total_bytes_allocated: u64,
@@ -103,7 +126,9 @@ impl Profiler {
         Self {
             config,
             functions: HashMap::new(),
+            entered: HashMap::new(),
             call_stack: Vec::new(),
+            timeline: Vec::new(),
             start_time: Instant::now(),
             total_allocations: 0,
             total_bytes_allocated: 0,
@@ -115,7 +140,18 @@ impl Profiler {
         if !self.config.trace_calls {
             return;
         }
-        self.call_stack.push((name.to_string(), Instant::now()));
+        let count = self.entered.entry(name.to_string()).or_insert(0);
+        *count += 1;
+        let sampled = (*count - 1).is_multiple_of(self.config.sample_rate.max(1) as u64);
+        let start = sampled.then(Instant::now);
+        if let Some(start) = start {
+            self.timeline.push(TimelineEvent {
+                kind: TimelineEventKind::Open,
+                name: name.to_string(),
+                at: start - self.start_time,
+            });
+        }
+        self.call_stack.push((name.to_string(), start));
     }
 
     /// End profiling a function
@@ -124,16 +160,21 @@ impl Profiler {
             return;
         }
         
-        if let Some((name, start)) = self.call_stack.pop() {
-            let duration = start.elapsed();
-            
-            let stats = self.functions
-                .entry(name)
-                .or_insert_with(|| FunctionStats::new(""));
-            stats.record(duration);
+        if let Some((name, Some(start))) = self.call_stack.pop() {
+            let end = Instant::now();
+            self.record_call(&name, end - start);
+            self.timeline.push(TimelineEvent { kind: TimelineEventKind::Close, name, at: end - self.start_time });
         }
     }
 
+    /// Record one completed call of `name` that took `duration`
+    pub fn record_call(&mut self, name: &str, duration: Duration) {
+        self.functions
+            .entry(name.to_string())
+            .or_insert_with(|| FunctionStats::new(name))
+            .record(duration);
+    }
+
     /// Record a memory allocation
     pub fn record_allocation(&mut self, bytes: u64) {
         if !self.config.trace_memory {
@@ -146,15 +187,35 @@ impl Profiler {
     /// Get profiling summary
     pub fn summary(&self) -> ProfilingSummary {
         let mut functions: Vec<_> = self.functions.values().cloned().collect();
-        functions.sort_by(|a, b| b.total_time.cmp(&a.total_time));
+        // Scale sampled timings up to every call
+        for f in &mut functions {
+            let calls = self.entered.get(&f.name).copied().unwrap_or(0);
+            if calls > f.call_count {
+                f.call_count = calls;
+                f.total_time = f.avg_time * calls as u32;
+            }
+        }
+        // Slowest first; ties broken by name so reports are deterministic
+        functions.sort_by(|a, b| b.total_time.cmp(&a.total_time).then_with(|| a.name.cmp(&b.name)));
+        let before = functions.len();
+        functions.retain(|f| {
+            f.call_count >= self.config.min_calls && f.total_time.as_secs_f64() * 1000.0 >= self.config.min_total_ms
+        });
 
         ProfilingSummary {
             total_time: self.start_time.elapsed(),
+            omitted_functions: before - functions.len(),
             functions,
+            timeline: self.timeline.clone(),
             total_allocations: self.total_allocations,
             total_bytes_allocated: self.total_bytes_allocated,
         }
     }
+
+    /// The summary rendered in the configured output format
+    pub fn report(&self) -> String {
+        format_report(&self.summary(), self.config.output_format)
+    }
 }
 
 /// Profiling summary for reporting
@@ -162,6 +223,82 @@ impl Profiler {
 pub struct ProfilingSummary {
     pub total_time: Duration,
     pub functions: Vec<FunctionStats>,
+    /// Functions left out of `functions` by the config thresholds
+    pub omitted_functions: usize,
+    pub timeline: Vec<TimelineEvent>,
     pub total_allocations: u64,
     pub total_bytes_allocated: u64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_orders_ties_by_name() {
+        let mut profiler = Profiler::new(ProfilerConfig::default());
+        for name in ["zeta", "alpha", "slow", "mid"] {
+            let ms = match name {
+                "slow" => 9,
+                "mid" => 5,
+                _ => 2,
+            };
+            profiler.record_call(name, Duration::from_millis(ms));
+        }
+        let names: Vec<String> = profiler.summary().functions.into_iter().map(|f| f.name).collect();
+        assert_eq!(names, ["slow", "mid", "alpha", "zeta"]);
+    }
+
+    #[test]
+    fn test_exit_records_function_name() {
+        let mut profiler = Profiler::new(ProfilerConfig::default());
+        profiler.enter_function("render_frame");
+        profiler.exit_function();
+        let summary = profiler.summary();
+        assert_eq!(summary.functions.len(), 1);
+        assert_eq!(summary.functions[0].name, "render_frame");
+        assert_eq!(summary.functions[0].call_count, 1);
+    }
+
+    #[test]
+    fn test_sampling_times_every_nth_call() {
+        let config = ProfilerConfig { sample_rate: 2, ..ProfilerConfig::default() };
+        let mut profiler = Profiler::new(config);
+        let mut timed = 0;
+        for _ in 0..5 {
+            profiler.enter_function("tick");
+            timed += profiler.call_stack.last().is_some_and(|(_, start)| start.is_some()) as u32;
+            profiler.exit_function();
+        }
+        assert_eq!(timed, 3);
+
+        let stats = &profiler.summary().functions[0];
+        assert_eq!(stats.call_count, 5);
+        assert_eq!(stats.sampled_calls, 3);
+        assert_eq!(stats.total_time, stats.avg_time * 5);
+    }
+
+    #[test]
+    fn test_threshold_omits_trivial_functions() {
+        let config = ProfilerConfig { min_total_ms: 1.0, min_calls: 2, ..ProfilerConfig::default() };
+        let mut profiler = Profiler::new(config);
+        profiler.record_call("hot", Duration::from_millis(3));
+        profiler.record_call("hot", Duration::from_millis(3));
+        profiler.record_call("once", Duration::from_millis(50));
+        profiler.record_call("tiny", Duration::from_micros(10));
+        profiler.record_call("tiny", Duration::from_micros(10));
+
+        let summary = profiler.summary();
+        let names: Vec<&str> = summary.functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["hot"]);
+        assert_eq!(summary.omitted_functions, 2);
+
+        let text = reporter::generate_text_report(&summary);
+        assert!(text.contains("hot"));
+        assert!(!text.contains("tiny") && !text.contains("once"));
+        assert!(text.contains("... 2 functions below threshold omitted"));
+        let json = reporter::generate_json_report(&summary);
+        assert!(!json.contains("\"tiny\""));
+        assert!(json.contains("\"omitted_functions\": 2"));
+    }
+}
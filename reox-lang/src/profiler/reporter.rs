@@ -3,7 +3,7 @@
 
 #![allow(dead_code, unused_imports)]
 
-use super::{ProfilingSummary, FunctionStats, OutputFormat};
+use super::{ProfilingSummary, FunctionStats, OutputFormat, TimelineEventKind};
 use std::fmt::Write;
 
 /// Generate a text report
@@ -33,6 +33,9 @@ pub fn generate_text_report(summary: &ProfilingSummary) -> String {
                  self_ms,
                  avg_us).unwrap();
     }
+    if summary.omitted_functions > 0 {
+        writeln!(output, "... {} functions below threshold omitted", summary.omitted_functions).unwrap();
+    }
 
     if summary.total_allocations > 0 {
         writeln!(output, "").unwrap();
@@ -69,6 +72,7 @@ pub fn generate_json_report(summary: &ProfilingSummary) -> String {
     }
 
     writeln!(output, "  ],").unwrap();
+    writeln!(output, "  \"omitted_functions\": {},", summary.omitted_functions).unwrap();
     writeln!(output, "  \"memory\": {{").unwrap();
     writeln!(output, "    \"allocations\": {},", summary.total_allocations).unwrap();
     writeln!(output, "    \"bytes\": {}", summary.total_bytes_allocated).unwrap();
@@ -93,13 +97,76 @@ pub fn generate_flamegraph_output(summary: &ProfilingSummary) -> String {
     output
 }
 
+/// Generate a Speedscope profile (https://www.speedscope.app) with one evented
+/// profile built from the call timeline
+pub fn generate_speedscope(summary: &ProfilingSummary) -> String {
+    // Frames in order of first appearance, then functions with no timed calls
+    let mut frames: Vec<&str> = Vec::new();
+    let names = summary.timeline.iter().map(|e| e.name.as_str())
+        .chain(summary.functions.iter().map(|f| f.name.as_str()));
+    for name in names {
+        if !frames.contains(&name) {
+            frames.push(name);
+        }
+    }
+    let micros = |d: std::time::Duration| d.as_secs_f64() * 1_000_000.0;
+    let end = summary.timeline.last().map_or(0.0, |e| micros(e.at));
+
+    let mut output = String::new();
+    writeln!(output, "{{").unwrap();
+    writeln!(output, "  \"$schema\": \"https://www.speedscope.app/file-format-schema.json\",").unwrap();
+    writeln!(output, "  \"exporter\": \"reoxc\",").unwrap();
+    writeln!(output, "  \"name\": \"REOX profile\",").unwrap();
+    writeln!(output, "  \"activeProfileIndex\": 0,").unwrap();
+    let frame_list: Vec<String> = frames.iter()
+        .map(|name| format!("{{\"name\": \"{}\"}}", escape_json(name)))
+        .collect();
+    writeln!(output, "  \"shared\": {{ \"frames\": [{}] }},", frame_list.join(", ")).unwrap();
+    writeln!(output, "  \"profiles\": [{{").unwrap();
+    writeln!(output, "    \"type\": \"evented\",").unwrap();
+    writeln!(output, "    \"name\": \"main thread\",").unwrap();
+    writeln!(output, "    \"unit\": \"microseconds\",").unwrap();
+    writeln!(output, "    \"startValue\": 0,").unwrap();
+    writeln!(output, "    \"endValue\": {:.3},", end).unwrap();
+    writeln!(output, "    \"events\": [").unwrap();
+    for (i, event) in summary.timeline.iter().enumerate() {
+        let comma = if i + 1 < summary.timeline.len() { "," } else { "" };
+        let kind = match event.kind {
+            TimelineEventKind::Open => "O",
+            TimelineEventKind::Close => "C",
+        };
+        let frame = frames.iter().position(|f| *f == event.name).unwrap_or(0);
+        writeln!(output, "      {{\"type\": \"{}\", \"frame\": {}, \"at\": {:.3}}}{}",
+                 kind, frame, micros(event.at), comma).unwrap();
+    }
+    writeln!(output, "    ]").unwrap();
+    writeln!(output, "  }}]").unwrap();
+    writeln!(output, "}}").unwrap();
+
+    output
+}
+
 /// Format report based on output format
 pub fn format_report(summary: &ProfilingSummary, format: OutputFormat) -> String {
     match format {
         OutputFormat::Text => generate_text_report(summary),
         OutputFormat::Json => generate_json_report(summary),
         OutputFormat::Flamegraph => generate_flamegraph_output(summary),
+        OutputFormat::Speedscope => generate_speedscope(summary),
+    }
+}
+
+fn escape_json(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
     }
+    out
 }
 
 fn truncate(s: &str, max_len: usize) -> String {
@@ -123,6 +190,7 @@ mod tests {
                 FunctionStats {
                     name: "main".to_string(),
                     call_count: 1,
+                    sampled_calls: 1,
                     total_time: Duration::from_millis(100),
                     self_time: Duration::from_millis(50),
                     min_time: Duration::from_millis(100),
@@ -130,6 +198,8 @@ mod tests {
                     avg_time: Duration::from_millis(100),
                 },
             ],
+            omitted_functions: 0,
+            timeline: Vec::new(),
             total_allocations: 10,
             total_bytes_allocated: 1024,
         };
@@ -138,4 +208,24 @@ mod tests {
         assert!(report.contains("main"));
         assert!(report.contains("100.000"));
     }
+
+    #[test]
+    fn test_speedscope_output() {
+        let mut profiler = super::super::Profiler::new(Default::default());
+        profiler.enter_function("main");
+        profiler.enter_function("draw \"ui\"");
+        profiler.exit_function();
+        profiler.exit_function();
+
+        let output = format_report(&profiler.summary(), OutputFormat::Speedscope);
+        assert!(output.contains("\"$schema\": \"https://www.speedscope.app/file-format-schema.json\""));
+        assert!(output.contains("\"frames\": [{\"name\": \"main\"}, {\"name\": \"draw \\\"ui\\\"\"}]"));
+        assert!(output.contains("\"type\": \"evented\""));
+        let events: Vec<&str> = output.lines().filter(|l| l.contains("\"frame\":")).collect();
+        assert_eq!(events.len(), 4);
+        assert!(events[0].contains("\"type\": \"O\", \"frame\": 0"));
+        assert!(events[1].contains("\"type\": \"O\", \"frame\": 1"));
+        assert!(events[2].contains("\"type\": \"C\", \"frame\": 1"));
+        assert!(events[3].contains("\"type\": \"C\", \"frame\": 0"));
+    }
 }
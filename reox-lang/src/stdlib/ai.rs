@@ -1,5 +1,5 @@
 use crate::interpreter::Value;
-use reqwest::blocking::Client;
+use crate::stdlib::http::{classify, RetryPolicy};
 use serde_json::json;
 
 pub fn generate(args: Vec<Value>) -> Value {
@@ -31,7 +31,16 @@ pub fn generate(args: Vec<Value>) -> Value {
         return Value::String("Error: API Key not provided".to_string());
     }
 
-    let client = Client::new();
+    match request(model, prompt, &api_key) {
+        Ok(text) => Value::String(text),
+        Err(e) => Value::String(e),
+    }
+}
+
+/// Send `prompt` to `model`; errors are the `Error...` strings `ai_generate` returns
+fn request(model: &str, prompt: &str, api_key: &str) -> Result<String, String> {
+    let policy = RetryPolicy::from_env();
+    let client = policy.client().map_err(|e| format!("Error: {}", e))?;
     let url = format!(
         "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
         model, api_key
@@ -43,24 +52,97 @@ pub fn generate(args: Vec<Value>) -> Value {
         }]
     });
 
-    match client.post(&url).json(&body).send() {
+    // Timeouts and 5xx responses are retried with backoff before giving up
+    match policy.run(|| classify(client.post(&url).json(&body).send())) {
         Ok(res) => {
             if res.status().is_success() {
                 match res.json::<serde_json::Value>() {
                     Ok(json) => {
                         if let Some(text) = json["candidates"][0]["content"]["parts"][0]["text"].as_str() {
-                            Value::String(text.to_string())
+                            Ok(text.to_string())
                         } else {
-                            Value::String(format!("Error: Unexpected response format: {}", json))
+                            Err(format!("Error: Unexpected response format: {}", json))
                         }
                     }
-                    Err(e) => Value::String(format!("Error parsing JSON: {}", e)),
+                    Err(e) => Err(format!("Error parsing JSON: {}", e)),
                 }
             } else {
-                Value::String(format!("Error: API request failed with status {}", res.status()))
+                Err(format!("Error: API request failed with status {}", res.status()))
             }
         }
-        Err(e) => Value::String(format!("Error sending request: {}", e)),
+        Err(e) => Err(format!("Error sending request: {}", e)),
+    }
+}
+
+/// Model used by the `ai_ui*` natives unless `REOX_AI_MODEL` names another
+const DEFAULT_MODEL: &str = "gemini-1.5-flash";
+
+/// Generate REOX UI source for a description, with markdown fences removed
+fn generate_ui(description: &str) -> Result<String, String> {
+    let api_key = std::env::var("GEMINI_API_KEY").unwrap_or_default();
+    if api_key.is_empty() {
+        return Err("Error: API Key not provided".to_string());
+    }
+    let model = std::env::var("REOX_AI_MODEL").unwrap_or_else(|_| DEFAULT_MODEL.to_string());
+    request(&model, &ai_ui(description), &api_key).map(|text| extract_code(&text))
+}
+
+/// `ai_ui(description)`: generated REOX UI code, or an `Error...` string
+pub fn ui(args: Vec<Value>) -> Value {
+    match args.first() {
+        Some(Value::String(description)) => match generate_ui(description) {
+            Ok(code) => Value::String(code),
+            Err(e) => Value::String(e),
+        },
+        _ => Value::String("Error: Description must be a string".to_string()),
+    }
+}
+
+/// `ai_ui_compile(description)`: the problems found in the generated code, as an
+/// array of messages; empty when it lexes, parses and type checks cleanly
+pub fn ui_compile(args: Vec<Value>) -> Value {
+    let problems = match args.first() {
+        Some(Value::String(description)) => match generate_ui(description) {
+            Ok(code) => check_source(&code),
+            Err(e) => vec![e],
+        },
+        _ => vec!["Error: Description must be a string".to_string()],
+    };
+    Value::Array(problems.into_iter().map(Value::String).collect())
+}
+
+/// The body of the first fenced code block in a markdown reply, or the whole
+/// reply when it has no fences
+pub fn extract_code(reply: &str) -> String {
+    let Some(open) = reply.find("```") else {
+        return reply.trim().to_string();
+    };
+    // Skip the info string (e.g. "reox") on the opening fence line
+    let body = &reply[open + 3..];
+    let body = body.find('\n').map_or("", |i| &body[i + 1..]);
+    let body = body.find("```").map_or(body, |close| &body[..close]);
+    body.trim_end().to_string()
+}
+
+/// Lex, parse and type check `source`, returning every error; the natives
+/// the interpreter provides and the compiler's UI widgets count as defined
+pub fn check_source(source: &str) -> Vec<String> {
+    let tokens = match crate::lexer::tokenize(source) {
+        Ok(tokens) => tokens,
+        Err(e) => return vec![e.display()],
+    };
+    let program = match crate::parser::Parser::new(&tokens).parse_program() {
+        Ok(program) => program,
+        Err(e) => return vec![e.display()],
+    };
+    let mut checker = crate::typechecker::TypeChecker::with_natives(crate::interpreter::builtin_names());
+    match checker.check_program(&program) {
+        Ok(_) => Vec::new(),
+        Err(diagnostics) => diagnostics
+            .iter()
+            .filter(|d| d.severity == crate::typechecker::Severity::Error)
+            .map(|d| d.display())
+            .collect(),
     }
 }
 
@@ -96,14 +178,13 @@ pub fn ai_fix(error_message: &str, code_context: &str) -> String {
     format!("AI_FIX: {}", prompt)
 }
 
-/// Generate UI component from description
-/// ai_ui("a login form with email and password fields") -> REOX UI code
+/// Prompt asking for a UI component matching the description; the `ai_ui`
+/// native sends it and returns the code from the reply
 pub fn ai_ui(description: &str) -> String {
-    let prompt = format!(
+    format!(
         "Generate REOX UI code for: {}. Use vstack, hstack, text, button, input components. Return only the code:",
         description
-    );
-    format!("AI_UI: {}", prompt)
+    )
 }
 
 /// Check if code contains potential issues
@@ -115,3 +196,54 @@ pub fn ai_review(code: &str) -> String {
     );
     format!("AI_REVIEW: {}", prompt)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_code_from_fences() {
+        let reply = "Here you go:\n```reox\nfn main() {\n    print(1);\n}\n```\nEnjoy!";
+        assert_eq!(extract_code(reply), "fn main() {\n    print(1);\n}");
+        assert_eq!(extract_code("  fn main() { }\n"), "fn main() { }");
+        assert_eq!(extract_code("```\nlet x = 1;"), "let x = 1;");
+    }
+
+    #[test]
+    fn test_malformed_code_is_reported() {
+        assert!(check_source("fn main() { print(\"ok\"); }").is_empty());
+        let problems = check_source("fn main() { let x: int = ; }");
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("error["));
+        assert!(!check_source("fn main() { let x: int = \"no\"; print(x); }").is_empty());
+    }
+
+    #[test]
+    fn test_ui_code_using_natives_checks_cleanly() {
+        let code = r#"
+            fn counter(start: int) {
+                let label = text("Count: " + start);
+                let b = button("OK");
+                let v = vstack([label, b]);
+                let root = window("Counter", 320, 200);
+                print(v, root);
+            }
+        "#;
+        assert_eq!(check_source(code), Vec::<String>::new());
+        let problems = check_source("fn main() { let v = vstak([]); }");
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("undefined variable 'vstak'"), "{:?}", problems);
+    }
+
+    /// Talks to the real model; runs only when `REOX_AI_LIVE_TESTS` and `GEMINI_API_KEY` are set
+    #[test]
+    fn test_ui_compile_live() {
+        if std::env::var("REOX_AI_LIVE_TESTS").is_err() || std::env::var("GEMINI_API_KEY").is_err() {
+            return;
+        }
+        let Value::Array(problems) = ui_compile(vec![Value::String("a button labelled OK".to_string())]) else {
+            panic!("ai_ui_compile should return an array");
+        };
+        assert!(problems.iter().all(|p| matches!(p, Value::String(_))));
+    }
+}
@@ -8,6 +8,7 @@ pub fn len(val: &Value) -> i64 {
     match val {
         Value::Array(arr) => arr.len() as i64,
         Value::String(s) => s.len() as i64,
+        Value::Bytes(b) => b.len() as i64,
         Value::Map(m) => m.len() as i64,
         _ => 0,
     }
@@ -21,11 +22,15 @@ pub fn type_of(val: &Value) -> String {
         Value::Int(_) => "int".to_string(),
         Value::Float(_) => "float".to_string(),
         Value::String(_) => "string".to_string(),
+        Value::Char(_) => "char".to_string(),
+        Value::Bytes(_) => "bytes".to_string(),
         Value::Array(_) => "array".to_string(),
+        Value::Tuple(_) => "tuple".to_string(),
         Value::Map(_) => "map".to_string(),
         Value::Color { .. } => "color".to_string(),
         Value::Struct { name, .. } => format!("struct:{}", name),
-        Value::NativeAction { .. } => "action".to_string(),
+        Value::NativeAction { .. } | Value::FallibleAction { .. } | Value::InterpreterAction { .. } | Value::Closure { .. } => "action".to_string(),
+        Value::Stream(_) => "stream".to_string(),
     }
 }
 
@@ -110,6 +115,109 @@ pub fn round(x: f64) -> i64 {
     x.round() as i64
 }
 
+/// Numeric view of an int or float element
+fn as_number(val: &Value) -> Option<f64> {
+    match val {
+        Value::Int(i) => Some(*i as f64),
+        Value::Float(f) => Some(*f),
+        _ => None,
+    }
+}
+
+/// Sum of a numeric array: int if every element is an int, float otherwise.
+/// Empty arrays sum to 0; non-numeric elements or int overflow give `None`.
+pub fn sum(items: &[Value]) -> Option<Value> {
+    if items.iter().all(|v| matches!(v, Value::Int(_))) {
+        let mut total: i64 = 0;
+        for v in items {
+            if let Value::Int(i) = v {
+                total = total.checked_add(*i)?;
+            }
+        }
+        return Some(Value::Int(total));
+    }
+    let mut total = 0.0;
+    for v in items {
+        total += as_number(v)?;
+    }
+    Some(Value::Float(total))
+}
+
+/// Arithmetic mean as a float; `None` for empty or non-numeric arrays
+pub fn avg(items: &[Value]) -> Option<f64> {
+    if items.is_empty() {
+        return None;
+    }
+    let mut total = 0.0;
+    for v in items {
+        total += as_number(v)?;
+    }
+    Some(total / items.len() as f64)
+}
+
+/// Smallest element, keeping its int/float type; `None` for empty or non-numeric arrays
+pub fn array_min(items: &[Value]) -> Option<Value> {
+    extreme(items, |candidate, best| candidate < best)
+}
+
+/// Largest element, keeping its int/float type; `None` for empty or non-numeric arrays
+pub fn array_max(items: &[Value]) -> Option<Value> {
+    extreme(items, |candidate, best| candidate > best)
+}
+
+fn extreme(items: &[Value], better: fn(f64, f64) -> bool) -> Option<Value> {
+    let mut best: Option<(&Value, f64)> = None;
+    for v in items {
+        let n = as_number(v)?;
+        if best.is_none_or(|(_, b)| better(n, b)) {
+            best = Some((v, n));
+        }
+    }
+    best.map(|(v, _)| v.clone())
+}
+
+/// Format a millisecond count as e.g. "1h 2m 3s"; zero units are skipped
+pub fn duration_format(millis: i64) -> String {
+    const UNITS: [(&str, u64); 5] = [("d", 86_400_000), ("h", 3_600_000), ("m", 60_000), ("s", 1_000), ("ms", 1)];
+    if millis == 0 {
+        return "0s".to_string();
+    }
+    let mut rest = millis.unsigned_abs();
+    let mut parts = Vec::new();
+    for (suffix, size) in UNITS {
+        if rest >= size {
+            parts.push(format!("{}{}", rest / size, suffix));
+            rest %= size;
+        }
+    }
+    let sign = if millis < 0 { "-" } else { "" };
+    format!("{}{}", sign, parts.join(" "))
+}
+
+/// Format milliseconds since the Unix epoch as an ISO-8601 UTC timestamp
+pub fn iso8601(epoch_millis: i64) -> String {
+    let secs = epoch_millis.div_euclid(1000);
+    let millis = epoch_millis.rem_euclid(1000);
+    let days = secs.div_euclid(86_400);
+    let tod = secs.rem_euclid(86_400);
+
+    // Civil date from day count (Howard Hinnant's days_from_civil inverse)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year, month, day, tod / 3600, tod % 3600 / 60, tod % 60, millis
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -146,6 +254,37 @@ mod tests {
         assert!((clamp(15.0, 0.0, 10.0) - 10.0).abs() < 0.001);
     }
     
+    #[test]
+    fn test_aggregates() {
+        let ints = [Value::Int(1), Value::Int(2), Value::Int(3)];
+        assert!(matches!(sum(&ints), Some(Value::Int(6))));
+        assert!(matches!(sum(&[]), Some(Value::Int(0))));
+        assert!(matches!(sum(&[Value::Int(1), Value::Float(0.5)]), Some(Value::Float(f)) if f == 1.5));
+        assert_eq!(avg(&[Value::Float(2.0), Value::Float(4.0)]), Some(3.0));
+        assert_eq!(avg(&[]), None);
+        assert!(matches!(array_min(&ints), Some(Value::Int(1))));
+        assert!(matches!(array_max(&[Value::Int(2), Value::Float(2.5)]), Some(Value::Float(f)) if f == 2.5));
+        assert!(array_max(&[]).is_none());
+        assert!(sum(&[Value::Int(1), Value::String("x".into())]).is_none());
+        assert!(sum(&[Value::Int(i64::MAX), Value::Int(1)]).is_none());
+    }
+
+    #[test]
+    fn test_duration_format() {
+        assert_eq!(duration_format(90_000), "1m 30s");
+        assert_eq!(duration_format(0), "0s");
+        assert_eq!(duration_format(1_500), "1s 500ms");
+        assert_eq!(duration_format(90_061_000), "1d 1h 1m 1s");
+        assert_eq!(duration_format(-2_000), "-2s");
+    }
+
+    #[test]
+    fn test_iso8601() {
+        assert_eq!(iso8601(0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(iso8601(951_782_400_123), "2000-02-29T00:00:00.123Z");
+        assert_eq!(iso8601(1_700_000_000_000), "2023-11-14T22:13:20.000Z");
+    }
+
     #[test]
     fn test_math() {
         assert!((abs(-5.0) - 5.0).abs() < 0.001);
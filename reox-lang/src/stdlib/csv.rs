@@ -0,0 +1,114 @@
+// REOX Standard Library - CSV Module
+// RFC 4180 delimited text parsing and writing (CSV and TSV)
+
+/// Parse delimited text into records of fields.
+/// Quoted fields may contain the delimiter, newlines and doubled quotes (`""`).
+pub fn parse(text: &str, delim: char) -> Result<Vec<Vec<String>>, String> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() && !quoted => {
+                in_quotes = true;
+                quoted = true;
+            }
+            '"' => return Err(format!("unexpected quote in unquoted field (record {})", rows.len() + 1)),
+            c if c == delim => {
+                row.push(std::mem::take(&mut field));
+                quoted = false;
+            }
+            '\r' | '\n' => {
+                if c == '\r' && chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+                quoted = false;
+            }
+            _ if quoted => return Err(format!("unexpected text after closing quote (record {})", rows.len() + 1)),
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err("unterminated quoted field".to_string());
+    }
+    // A trailing line break does not start another record
+    if !field.is_empty() || quoted || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// Write records as delimited text, quoting fields only where needed
+pub fn write(rows: &[Vec<String>], delim: char) -> String {
+    let mut out = String::new();
+    for row in rows {
+        let fields: Vec<String> = row.iter().map(|f| quote(f, delim)).collect();
+        out.push_str(&fields.join(&delim.to_string()));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn quote(field: &str, delim: char) -> String {
+    if field.contains([delim, '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(fields: &[&str]) -> Vec<String> {
+        fields.iter().map(|f| f.to_string()).collect()
+    }
+
+    #[test]
+    fn test_round_trip_quoted_comma_and_newline() {
+        let rows = vec![row(&["name", "note"]), row(&["Smith, J.", "line one\nline \"two\""])];
+        let text = write(&rows, ',');
+        assert_eq!(text, "name,note\r\n\"Smith, J.\",\"line one\nline \"\"two\"\"\"\r\n");
+        assert_eq!(parse(&text, ',').unwrap(), rows);
+    }
+
+    #[test]
+    fn test_tsv_round_trip() {
+        let rows = vec![row(&["a\tb", "c,d"]), row(&["", "x"])];
+        let text = write(&rows, '\t');
+        assert_eq!(text, "\"a\tb\"\tc,d\r\n\tx\r\n");
+        assert_eq!(parse(&text, '\t').unwrap(), rows);
+    }
+
+    #[test]
+    fn test_parse_lf_and_trailing_empty_field() {
+        assert_eq!(parse("a,b,\n1,2,3", ',').unwrap(), vec![row(&["a", "b", ""]), row(&["1", "2", "3"])]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("\"open", ',').is_err());
+        assert!(parse("ab\"c", ',').is_err());
+        assert!(parse("\"a\"b", ',').is_err());
+    }
+}
@@ -0,0 +1,243 @@
+// REOX Standard Library - Format Strings
+// `format("{:>8.2}", x)` style formatting for the `format` native
+// Zero external dependencies
+
+use crate::interpreter::Value;
+
+/// Largest width or precision a specifier may ask for
+const MAX_WIDTH: usize = u16::MAX as usize;
+
+/// Parsed `{:...}` specifier: `[[fill]align][0][width][.precision][type]`
+#[derive(Debug, Clone, PartialEq)]
+struct Spec {
+    fill: char,
+    align: Option<char>,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    kind: Option<char>,
+}
+
+impl Default for Spec {
+    fn default() -> Self {
+        Self { fill: ' ', align: None, zero: false, width: 0, precision: None, kind: None }
+    }
+}
+
+/// Substitute `args` into `fmt`.
+///
+/// `{}` takes the next argument and `{N}` argument N; `{{`/`}}` are literal
+/// braces. After a `:` the specifier supports fill/alignment (`<`, `>`, `^`),
+/// zero padding, width, precision and the types `x`, `X`, `b`, `o` and `e`.
+pub fn format(fmt: &str, args: &[Value]) -> Result<String, String> {
+    let mut out = String::new();
+    let mut chars = fmt.chars().peekable();
+    let mut next_arg = 0;
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let mut field = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => field.push(c),
+                        None => return Err("unterminated '{' in format string".to_string()),
+                    }
+                }
+                let (position, spec) = field.split_once(':').unwrap_or((&field, ""));
+                let index = if position.is_empty() {
+                    next_arg += 1;
+                    next_arg - 1
+                } else {
+                    position.parse::<usize>()
+                        .map_err(|_| format!("invalid argument position '{}'", position))?
+                };
+                let value = args.get(index)
+                    .ok_or_else(|| format!("missing argument {} for format string", index))?;
+                out.push_str(&render(value, &parse_spec(spec)?)?);
+            }
+            '}' => return Err("unmatched '}' in format string".to_string()),
+            _ => out.push(ch),
+        }
+    }
+
+    Ok(out)
+}
+
+fn parse_spec(spec: &str) -> Result<Spec, String> {
+    let mut result = Spec::default();
+    let chars: Vec<char> = spec.chars().collect();
+    let mut i = 0;
+
+    let is_align = |c: char| matches!(c, '<' | '>' | '^');
+    if chars.len() >= 2 && is_align(chars[1]) {
+        result.fill = chars[0];
+        result.align = Some(chars[1]);
+        i = 2;
+    } else if chars.first().is_some_and(|&c| is_align(c)) {
+        result.align = Some(chars[0]);
+        i = 1;
+    }
+
+    if chars.get(i) == Some(&'0') {
+        result.zero = true;
+        i += 1;
+    }
+
+    let start = i;
+    while chars.get(i).is_some_and(|c| c.is_ascii_digit()) {
+        i += 1;
+    }
+    if i > start {
+        result.width = bounded(&chars[start..i], "width", spec)?;
+    }
+
+    if chars.get(i) == Some(&'.') {
+        i += 1;
+        let start = i;
+        while chars.get(i).is_some_and(|c| c.is_ascii_digit()) {
+            i += 1;
+        }
+        if i == start {
+            return Err(format!("missing precision in '{{:{}}}'", spec));
+        }
+        result.precision = Some(bounded(&chars[start..i], "precision", spec)?);
+    }
+
+    match chars.get(i) {
+        None => {}
+        Some(&c) if matches!(c, 'x' | 'X' | 'b' | 'o' | 'e') && i + 1 == chars.len() => result.kind = Some(c),
+        Some(_) => return Err(format!("unsupported format specifier '{{:{}}}'", spec)),
+    }
+
+    Ok(result)
+}
+
+/// Parse the width or precision `digits`, rejecting values above `MAX_WIDTH`
+fn bounded(digits: &[char], what: &str, spec: &str) -> Result<usize, String> {
+    digits.iter().collect::<String>().parse().ok()
+        .filter(|&n| n <= MAX_WIDTH)
+        .ok_or_else(|| format!("{} in '{{:{}}}' exceeds {}", what, spec, MAX_WIDTH))
+}
+
+fn render(value: &Value, spec: &Spec) -> Result<String, String> {
+    let (body, numeric) = match (value, spec.kind) {
+        (Value::Int(n), Some('x')) => (format!("{:x}", n), true),
+        (Value::Int(n), Some('X')) => (format!("{:X}", n), true),
+        (Value::Int(n), Some('b')) => (format!("{:b}", n), true),
+        (Value::Int(n), Some('o')) => (format!("{:o}", n), true),
+        (Value::Int(n), Some('e')) => (exponent(*n as f64, spec.precision), true),
+        (Value::Float(f), Some('e')) => (exponent(*f, spec.precision), true),
+        (Value::Int(n), None) => (n.to_string(), true),
+        (Value::Float(f), None) => match spec.precision {
+            Some(p) => (format!("{:.*}", p, f), true),
+            None => (value.to_string(), true),
+        },
+        (_, Some(kind)) => {
+            return Err(format!("'{}' format requires a number, found {}", kind, value.type_name()));
+        }
+        (other, None) => {
+            let text = other.to_string();
+            // Precision truncates non-numeric values
+            let text = match spec.precision {
+                Some(p) => text.chars().take(p).collect(),
+                None => text,
+            };
+            (text, false)
+        }
+    };
+    Ok(pad(body, spec, numeric))
+}
+
+fn exponent(f: f64, precision: Option<usize>) -> String {
+    match precision {
+        Some(p) => format!("{:.*e}", p, f),
+        None => format!("{:e}", f),
+    }
+}
+
+/// Apply width, fill and alignment; numbers default to the right, text to the left
+fn pad(body: String, spec: &Spec, numeric: bool) -> String {
+    let len = body.chars().count();
+    if len >= spec.width {
+        return body;
+    }
+    let gap = spec.width - len;
+
+    if spec.zero && numeric && spec.align.is_none() {
+        let (sign, digits) = match body.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", body.as_str()),
+        };
+        return format!("{}{}{}", sign, "0".repeat(gap), digits);
+    }
+
+    let fill = |n: usize| spec.fill.to_string().repeat(n);
+    match spec.align.unwrap_or(if numeric { '>' } else { '<' }) {
+        '<' => format!("{}{}", body, fill(gap)),
+        '^' => format!("{}{}{}", fill(gap / 2), body, fill(gap - gap / 2)),
+        _ => format!("{}{}", fill(gap), body),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fmt1(spec: &str, v: Value) -> String {
+        format(spec, &[v]).unwrap()
+    }
+
+    #[test]
+    fn test_precision_and_radix() {
+        assert_eq!(fmt1("{:.2}", Value::Float(3.14159)), "3.14");
+        assert_eq!(fmt1("{:x}", Value::Int(255)), "ff");
+        assert_eq!(fmt1("{:X}", Value::Int(255)), "FF");
+        assert_eq!(fmt1("{:08b}", Value::Int(5)), "00000101");
+        assert_eq!(fmt1("{:o}", Value::Int(8)), "10");
+        assert_eq!(fmt1("{:.1e}", Value::Float(1234.5)), "1.2e3");
+    }
+
+    #[test]
+    fn test_width_and_alignment() {
+        assert_eq!(fmt1("[{:5}]", Value::Int(42)), "[   42]");
+        assert_eq!(fmt1("[{:5}]", Value::String("ab".into())), "[ab   ]");
+        assert_eq!(fmt1("[{:>5}]", Value::String("ab".into())), "[   ab]");
+        assert_eq!(fmt1("[{:*^6}]", Value::String("ab".into())), "[**ab**]");
+        assert_eq!(fmt1("[{:05}]", Value::Int(-42)), "[-0042]");
+        assert_eq!(fmt1("[{:.3}]", Value::String("abcdef".into())), "[abc]");
+    }
+
+    #[test]
+    fn test_positions_and_escapes() {
+        let args = [Value::String("a".into()), Value::Int(1)];
+        assert_eq!(format("{1}{0}{} {{}}", &args).unwrap(), "1aa {}");
+        assert_eq!(format("{} and {}", &args).unwrap(), "a and 1");
+    }
+
+    #[test]
+    fn test_format_errors() {
+        assert!(format("{}", &[]).unwrap_err().contains("missing argument 0"));
+        assert!(format("{:q}", &[Value::Int(1)]).unwrap_err().contains("unsupported"));
+        assert!(format("{:x}", &[Value::String("s".into())]).unwrap_err().contains("requires a number"));
+        assert!(format("{", &[]).is_err());
+        assert!(format("}", &[]).is_err());
+    }
+
+    #[test]
+    fn test_width_and_precision_are_capped() {
+        assert_eq!(fmt1("{:.65535}", Value::Float(1.0)).len(), 65537);
+        assert!(format("{:.99999999}", &[Value::Float(1.0)]).unwrap_err().contains("precision in '{:.99999999}' exceeds 65535"));
+        assert!(format("{:999999999999}", &[Value::Int(1)]).unwrap_err().contains("width in '{:999999999999}' exceeds 65535"));
+        assert!(format("{:.99999999999999999999999}", &[Value::Float(1.0)]).unwrap_err().contains("precision"));
+    }
+}
@@ -0,0 +1,186 @@
+// REOX Standard Library - HTTP Module
+// Timeout and retry policy shared by http_get and the AI natives
+
+use reqwest::blocking::{Client, Response};
+use std::time::Duration;
+
+/// Request timeout unless `REOX_HTTP_TIMEOUT_MS` sets another
+const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+/// Retries after the first attempt unless `REOX_HTTP_RETRIES` sets another
+const DEFAULT_RETRIES: u32 = 2;
+/// Upper bound on `REOX_HTTP_RETRIES`, so a typo cannot stall a script for hours
+const MAX_RETRIES: u32 = 10;
+/// Delay before the first retry; it doubles for each one after
+const BASE_BACKOFF_MS: u64 = 250;
+
+/// Why an attempt failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum Failure {
+    /// Worth retrying: timeouts, refused connections and 5xx responses
+    Transient(String),
+    /// Retrying would fail the same way
+    Fatal(String),
+}
+
+/// How long a request may take and how often it is retried
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    pub timeout: Duration,
+    pub retries: u32,
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_millis(DEFAULT_TIMEOUT_MS),
+            retries: DEFAULT_RETRIES,
+            backoff: Duration::from_millis(BASE_BACKOFF_MS),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The default policy with `REOX_HTTP_TIMEOUT_MS` and `REOX_HTTP_RETRIES`
+    /// applied; unparsable values are ignored
+    pub fn from_env() -> Self {
+        let var = |name| std::env::var(name).ok();
+        Self::from_vars(var("REOX_HTTP_TIMEOUT_MS").as_deref(), var("REOX_HTTP_RETRIES").as_deref())
+    }
+
+    fn from_vars(timeout_ms: Option<&str>, retries: Option<&str>) -> Self {
+        let mut policy = Self::default();
+        if let Some(ms) = timeout_ms.and_then(|v| v.trim().parse().ok()).filter(|&ms: &u64| ms > 0) {
+            policy.timeout = Duration::from_millis(ms);
+        }
+        if let Some(n) = retries.and_then(|v| v.trim().parse::<u32>().ok()) {
+            policy.retries = n.min(MAX_RETRIES);
+        }
+        policy
+    }
+
+    /// A blocking client that gives up on a request after `timeout`
+    pub fn client(&self) -> Result<Client, String> {
+        Client::builder()
+            .timeout(self.timeout)
+            .build()
+            .map_err(|e| format!("cannot create HTTP client: {}", e))
+    }
+
+    /// Delay before retry number `retry` (0-based)
+    fn delay(&self, retry: u32) -> Duration {
+        self.backoff.saturating_mul(1 << retry.min(16))
+    }
+
+    /// Run `attempt` until it succeeds, fails fatally, or the retries run out
+    pub fn run<T>(&self, attempt: impl FnMut() -> Result<T, Failure>) -> Result<T, String> {
+        self.run_with_sleep(attempt, std::thread::sleep)
+    }
+
+    fn run_with_sleep<T>(
+        &self,
+        mut attempt: impl FnMut() -> Result<T, Failure>,
+        mut sleep: impl FnMut(Duration),
+    ) -> Result<T, String> {
+        let mut retry = 0;
+        loop {
+            match attempt() {
+                Ok(value) => return Ok(value),
+                Err(Failure::Fatal(message)) => return Err(message),
+                Err(Failure::Transient(message)) if retry >= self.retries => {
+                    return Err(match retry {
+                        0 => message,
+                        _ => format!("{} (gave up after {} attempts)", message, retry + 1),
+                    });
+                }
+                Err(Failure::Transient(_)) => {
+                    sleep(self.delay(retry));
+                    retry += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Sort the outcome of sending a request into a response or a [`Failure`].
+/// Responses with 4xx statuses are returned for the caller to judge.
+pub fn classify(result: reqwest::Result<Response>) -> Result<Response, Failure> {
+    match result {
+        Ok(res) if res.status().is_server_error() => {
+            Err(Failure::Transient(format!("server responded with status {}", res.status())))
+        }
+        Ok(res) => Ok(res),
+        Err(e) if e.is_timeout() || e.is_connect() => Err(Failure::Transient(e.to_string())),
+        Err(e) => Err(Failure::Fatal(e.to_string())),
+    }
+}
+
+/// GET `url` under the policy from the environment, returning the body text
+pub fn get(url: &str) -> Result<String, String> {
+    let policy = RetryPolicy::from_env();
+    let client = policy.client()?;
+    policy.run(|| {
+        classify(client.get(url).send())?
+            .text()
+            .map_err(|e| Failure::Fatal(e.to_string()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(retries: u32) -> RetryPolicy {
+        RetryPolicy { retries, ..RetryPolicy::default() }
+    }
+
+    #[test]
+    fn test_retries_transient_failures_with_backoff() {
+        let mut calls = 0;
+        let mut delays = Vec::new();
+        let result = policy(3).run_with_sleep(
+            || {
+                calls += 1;
+                if calls < 3 { Err(Failure::Transient("503".to_string())) } else { Ok("body") }
+            },
+            |d| delays.push(d.as_millis()),
+        );
+        assert_eq!(result, Ok("body"));
+        assert_eq!(calls, 3);
+        assert_eq!(delays, [250, 500]);
+    }
+
+    #[test]
+    fn test_gives_up_after_retries_and_stops_on_fatal() {
+        let mut calls = 0;
+        let result: Result<(), _> = policy(2).run_with_sleep(
+            || {
+                calls += 1;
+                Err(Failure::Transient("timed out".to_string()))
+            },
+            |_| {},
+        );
+        assert_eq!(result, Err("timed out (gave up after 3 attempts)".to_string()));
+        assert_eq!(calls, 3);
+
+        let mut calls = 0;
+        let result: Result<(), _> = policy(2).run_with_sleep(
+            || {
+                calls += 1;
+                Err(Failure::Fatal("invalid URL".to_string()))
+            },
+            |_| panic!("fatal errors are not retried"),
+        );
+        assert_eq!(result, Err("invalid URL".to_string()));
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_policy_from_vars() {
+        let p = RetryPolicy::from_vars(Some("1500"), Some("5"));
+        assert_eq!((p.timeout, p.retries), (Duration::from_millis(1500), 5));
+        let p = RetryPolicy::from_vars(Some("soon"), Some("999"));
+        assert_eq!((p.timeout, p.retries), (Duration::from_millis(DEFAULT_TIMEOUT_MS), MAX_RETRIES));
+        assert_eq!(RetryPolicy::from_vars(None, None), RetryPolicy::default());
+    }
+}
@@ -1,10 +1,75 @@
 // REOX Standard Library - IO Module
 // Provides file and console I/O operations
 
+use std::cell::RefCell;
 use std::fs;
 use std::io::{self, BufRead, Write};
 use std::path::Path;
 
+/// A standard stream handle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdin,
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    pub fn name(self) -> &'static str {
+        match self {
+            Stream::Stdin => "stdin",
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+        }
+    }
+}
+
+thread_local! {
+    // Replacement writers for stdout and stderr; `None` writes to the process stream
+    static STDOUT_SINK: RefCell<Option<Box<dyn Write>>> = const { RefCell::new(None) };
+    static STDERR_SINK: RefCell<Option<Box<dyn Write>>> = const { RefCell::new(None) };
+}
+
+/// Redirect an output stream to `sink` on this thread; `None` restores the process stream
+pub fn set_sink(stream: Stream, sink: Option<Box<dyn Write>>) -> Result<(), String> {
+    match stream {
+        Stream::Stdout => STDOUT_SINK.with(|s| *s.borrow_mut() = sink),
+        Stream::Stderr => STDERR_SINK.with(|s| *s.borrow_mut() = sink),
+        Stream::Stdin => return Err("stdin is not an output stream".to_string()),
+    }
+    Ok(())
+}
+
+/// Write `text` to an output stream, through its sink when one is set
+pub fn write_stream(stream: Stream, text: &str) -> Result<(), String> {
+    let write = |sink: &RefCell<Option<Box<dyn Write>>>, fallback: &mut dyn Write| {
+        match sink.borrow_mut().as_mut() {
+            Some(w) => w.write_all(text.as_bytes()).and_then(|_| w.flush()),
+            None => fallback.write_all(text.as_bytes()).and_then(|_| fallback.flush()),
+        }
+        .map_err(|e| e.to_string())
+    };
+    match stream {
+        Stream::Stdout => STDOUT_SINK.with(|s| write(s, &mut io::stdout())),
+        Stream::Stderr => STDERR_SINK.with(|s| write(s, &mut io::stderr())),
+        Stream::Stdin => Err("cannot write to stdin".to_string()),
+    }
+}
+
+/// Read one line from an input stream without its line ending; `None` at end of input
+pub fn read_stream_line(stream: Stream) -> Result<Option<String>, String> {
+    if stream != Stream::Stdin {
+        return Err(format!("cannot read from {}", stream.name()));
+    }
+    let mut line = String::new();
+    if io::stdin().lock().read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+        return Ok(None);
+    }
+    let trimmed = line.trim_end_matches(['\n', '\r']).len();
+    line.truncate(trimmed);
+    Ok(Some(line))
+}
+
 /// Read entire file contents as string
 pub fn read_file(path: &str) -> Result<String, String> {
     fs::read_to_string(path).map_err(|e| e.to_string())
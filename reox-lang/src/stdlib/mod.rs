@@ -2,5 +2,9 @@
 
 pub mod core;    // Utility functions: len, type_of, range, math
 pub mod io;      // File and console I/O
+pub mod csv;     // CSV/TSV parsing and writing
+pub mod format;  // format() strings with width/precision specifiers
+pub mod regex;   // Backtracking regex engine for the regex_* natives
+pub mod http;    // Timeouts and retry with backoff for network natives
 pub mod ui;      // Color and animation utilities
 pub mod ai;      // AI/LLM integration
@@ -0,0 +1,247 @@
+// REOX Standard Library - Regular Expressions
+// A small backtracking engine for the `regex_*` natives
+// Zero external dependencies
+
+/// What a single pattern element matches
+#[derive(Debug, Clone, PartialEq)]
+enum Atom {
+    Char(char),
+    Any,
+    Class { negated: bool, ranges: Vec<(char, char)> },
+}
+
+impl Atom {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Atom::Char(expected) => *expected == c,
+            Atom::Any => c != '\n',
+            Atom::Class { negated, ranges } => {
+                ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != *negated
+            }
+        }
+    }
+}
+
+/// How many times an atom may repeat: `(min, max)`, `None` meaning unbounded
+type Repeat = (usize, Option<usize>);
+
+/// A compiled pattern.
+///
+/// Supports literals, `.`, the greedy quantifiers `*`, `+` and `?`, character
+/// classes (`[abc]`, `[a-z]`, `[^0-9]`), the escapes `\d`, `\w`, `\s` (and
+/// their negations `\D`, `\W`, `\S`) and the anchors `^` and `$`.
+/// Groups and alternation are not supported.
+#[derive(Debug, Clone)]
+pub struct Regex {
+    nodes: Vec<(Atom, Repeat)>,
+    anchored_start: bool,
+    anchored_end: bool,
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let mut chars: Vec<char> = pattern.chars().collect();
+        let anchored_start = chars.first() == Some(&'^');
+        if anchored_start {
+            chars.remove(0);
+        }
+        let anchored_end = chars.last() == Some(&'$') && !ends_with_escape(&chars[..chars.len() - 1]);
+        if anchored_end {
+            chars.pop();
+        }
+
+        let mut nodes = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let atom = match chars[i] {
+                '.' => Atom::Any,
+                '[' => {
+                    let (class, next) = parse_class(&chars, i + 1)?;
+                    i = next - 1;
+                    class
+                }
+                '\\' => {
+                    i += 1;
+                    let c = *chars.get(i).ok_or("pattern ends with a trailing '\\'")?;
+                    escape(c)
+                }
+                c @ ('*' | '+' | '?') => return Err(format!("'{}' has nothing to repeat", c)),
+                c => Atom::Char(c),
+            };
+            i += 1;
+            let repeat = match chars.get(i) {
+                Some('*') => (0, None),
+                Some('+') => (1, None),
+                Some('?') => (0, Some(1)),
+                _ => (1, Some(1)),
+            };
+            if repeat != (1, Some(1)) {
+                i += 1;
+            }
+            nodes.push((atom, repeat));
+        }
+
+        Ok(Self { nodes, anchored_start, anchored_end })
+    }
+
+    /// Whether the pattern matches anywhere in `text`
+    pub fn is_match(&self, text: &str) -> bool {
+        let text: Vec<char> = text.chars().collect();
+        self.find_at(&text, 0).is_some()
+    }
+
+    /// The leftmost match in `text`
+    pub fn find(&self, text: &str) -> Option<String> {
+        let text: Vec<char> = text.chars().collect();
+        self.find_at(&text, 0).map(|(start, end)| text[start..end].iter().collect())
+    }
+
+    /// Replace every non-overlapping match with `replacement`
+    pub fn replace_all(&self, text: &str, replacement: &str) -> String {
+        let text: Vec<char> = text.chars().collect();
+        let mut out = String::new();
+        let mut pos = 0;
+        while let Some((start, end)) = self.find_at(&text, pos) {
+            out.extend(&text[pos..start]);
+            out.push_str(replacement);
+            if end == start {
+                // Step past an empty match so the search always advances
+                if let Some(&c) = text.get(end) {
+                    out.push(c);
+                }
+                pos = end + 1;
+            } else {
+                pos = end;
+            }
+            if pos > text.len() || self.anchored_start {
+                break;
+            }
+        }
+        if pos < text.len() {
+            out.extend(&text[pos..]);
+        }
+        out
+    }
+
+    /// Start and end (in chars) of the leftmost match at or after `from`
+    fn find_at(&self, text: &[char], from: usize) -> Option<(usize, usize)> {
+        if self.anchored_start {
+            return if from == 0 { self.match_here(0, text, 0).map(|end| (0, end)) } else { None };
+        }
+        (from..=text.len()).find_map(|start| self.match_here(0, text, start).map(|end| (start, end)))
+    }
+
+    /// Match nodes `node..` at `pos`, returning where the match ends
+    fn match_here(&self, node: usize, text: &[char], pos: usize) -> Option<usize> {
+        let Some((atom, (min, max))) = self.nodes.get(node) else {
+            return if !self.anchored_end || pos == text.len() { Some(pos) } else { None };
+        };
+        let mut count = 0;
+        while max.is_none_or(|m| count < m) && text.get(pos + count).is_some_and(|&c| atom.matches(c)) {
+            count += 1;
+        }
+        // Greedy: try the longest run first, then back off
+        (*min..=count).rev().find_map(|n| self.match_here(node + 1, text, pos + n))
+    }
+}
+
+/// Whether `chars` ends in an unescaped backslash
+fn ends_with_escape(chars: &[char]) -> bool {
+    chars.iter().rev().take_while(|&&c| c == '\\').count() % 2 == 1
+}
+
+fn escape(c: char) -> Atom {
+    let class = |negated, ranges: &[(char, char)]| Atom::Class { negated, ranges: ranges.to_vec() };
+    const DIGIT: &[(char, char)] = &[('0', '9')];
+    const WORD: &[(char, char)] = &[('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')];
+    const SPACE: &[(char, char)] = &[(' ', ' '), ('\t', '\t'), ('\n', '\n'), ('\r', '\r')];
+    match c {
+        'd' => class(false, DIGIT),
+        'D' => class(true, DIGIT),
+        'w' => class(false, WORD),
+        'W' => class(true, WORD),
+        's' => class(false, SPACE),
+        'S' => class(true, SPACE),
+        'n' => Atom::Char('\n'),
+        't' => Atom::Char('\t'),
+        other => Atom::Char(other),
+    }
+}
+
+/// Parse a class body starting after `[`; returns the class and the index after `]`
+fn parse_class(chars: &[char], mut i: usize) -> Result<(Atom, usize), String> {
+    let negated = chars.get(i) == Some(&'^');
+    if negated {
+        i += 1;
+    }
+    let mut ranges = Vec::new();
+    let mut first = true;
+    loop {
+        let c = *chars.get(i).ok_or("unterminated character class")?;
+        if c == ']' && !first {
+            return Ok((Atom::Class { negated, ranges }, i + 1));
+        }
+        first = false;
+        let lo = if c == '\\' {
+            i += 1;
+            let escaped = *chars.get(i).ok_or("unterminated character class")?;
+            if let Atom::Class { ranges: extra, negated: false } = escape(escaped) {
+                ranges.extend(extra);
+                i += 1;
+                continue;
+            }
+            escaped
+        } else {
+            c
+        };
+        if chars.get(i + 1) == Some(&'-') && chars.get(i + 2).is_some_and(|&c| c != ']') {
+            let hi = chars[i + 2];
+            if hi < lo {
+                return Err(format!("invalid class range '{}-{}'", lo, hi));
+            }
+            ranges.push((lo, hi));
+            i += 3;
+        } else {
+            ranges.push((lo, lo));
+            i += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn re(pattern: &str) -> Regex {
+        Regex::new(pattern).unwrap()
+    }
+
+    #[test]
+    fn test_match_and_find() {
+        assert!(re("h.llo").is_match("say hello"));
+        assert!(re("^ab+c?$").is_match("abbb"));
+        assert!(!re("^ab+c?$").is_match("ac"));
+        assert!(re("colou?r").is_match("color"));
+        assert_eq!(re("[0-9]+").find("order 66 now").as_deref(), Some("66"));
+        assert_eq!(re(r"\w+@\w+\.com").find("mail bob@site.com!").as_deref(), Some("bob@site.com"));
+        assert_eq!(re("[^a-z ]+").find("abc DEF").as_deref(), Some("DEF"));
+        assert_eq!(re("x*").find("abc").as_deref(), Some(""));
+        assert!(re(r"\$5$").is_match("costs $5"));
+    }
+
+    #[test]
+    fn test_replace_all() {
+        assert_eq!(re(r"\d+").replace_all("a1b22c333", "#"), "a#b#c#");
+        assert_eq!(re(r"\s+").replace_all("too   many  spaces", " "), "too many spaces");
+        assert_eq!(re("^a").replace_all("aaa", "b"), "baa");
+        assert_eq!(re("x*").replace_all("ab", "-"), "-a-b-");
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        assert!(Regex::new("*a").is_err());
+        assert!(Regex::new("[abc").is_err());
+        assert!(Regex::new("[z-a]").is_err());
+        assert!(Regex::new("a\\").is_err());
+    }
+}
@@ -127,6 +127,16 @@ pub fn color_lerp(c1: (u8, u8, u8, u8), c2: (u8, u8, u8, u8), t: f64) -> crate::
     crate::interpreter::Value::Color { r, g, b, a }
 }
 
+/// Blend a color toward white, keeping its alpha
+pub fn color_lighten(c: (u8, u8, u8, u8), amount: f64) -> crate::interpreter::Value {
+    color_lerp(c, (255, 255, 255, c.3), amount)
+}
+
+/// Blend a color toward black, keeping its alpha
+pub fn color_darken(c: (u8, u8, u8, u8), amount: f64) -> crate::interpreter::Value {
+    color_lerp(c, (0, 0, 0, c.3), amount)
+}
+
 // ============== View Modifier System ==============
 
 /// ViewModifier for SwiftUI-like chainable styling
@@ -890,6 +900,29 @@ mod tests {
         }
     }
     
+    #[test]
+    fn test_color_lerp_midpoint() {
+        if let crate::interpreter::Value::Color { r, g, b, a } = color_lerp((0, 0, 0, 255), (255, 255, 255, 255), 0.5) {
+            assert_eq!((r, g, b, a), (127, 127, 127, 255));
+        } else {
+            panic!("Expected Color");
+        }
+    }
+    
+    #[test]
+    fn test_color_lighten_darken() {
+        if let crate::interpreter::Value::Color { r, g, b, a } = color_lighten((100, 0, 200, 128), 1.0) {
+            assert_eq!((r, g, b, a), (255, 255, 255, 128));
+        } else {
+            panic!("Expected Color");
+        }
+        if let crate::interpreter::Value::Color { r, g, b, a } = color_darken((100, 0, 200, 128), 0.5) {
+            assert_eq!((r, g, b, a), (50, 0, 100, 128));
+        } else {
+            panic!("Expected Color");
+        }
+    }
+    
     #[test]
     fn test_ease_linear() {
         assert!((ease_linear(0.0) - 0.0).abs() < 0.001);
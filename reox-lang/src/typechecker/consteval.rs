@@ -0,0 +1,485 @@
+// REOX Compiler - Compile-Time Constant Evaluator
+// Executes const expressions and const fn bodies during type checking
+// Zero external dependencies
+
+use std::collections::HashMap;
+
+use crate::lexer::Span;
+use crate::parser::{BinOp, Block, CompoundOp, Expr, FnDecl, Literal, Stmt, UnaryOp};
+
+/// Maximum nesting of const fn calls before evaluation is aborted
+const MAX_CALL_DEPTH: usize = 64;
+
+/// Maximum number of evaluated statements/loop iterations
+const MAX_STEPS: usize = 100_000;
+
+/// A value known at compile time
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+}
+
+impl ConstValue {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ConstValue::Int(_) => "int",
+            ConstValue::Float(_) => "float",
+            ConstValue::Bool(_) => "bool",
+            ConstValue::String(_) => "string",
+        }
+    }
+
+    /// The literal expression for this value, so a folded const can replace its initializer
+    pub fn to_expr(&self, span: Span) -> Expr {
+        Expr::Literal(match self {
+            ConstValue::Int(v) => Literal::Int(*v, span),
+            ConstValue::Float(v) => Literal::Float(*v, span),
+            ConstValue::Bool(v) => Literal::Bool(*v, span),
+            ConstValue::String(v) => Literal::String(v.clone(), span),
+        })
+    }
+}
+
+/// Result of executing a statement inside a const fn
+enum Control {
+    Next,
+    Break,
+    Continue,
+    Return(ConstValue),
+}
+
+/// Evaluates constant expressions, calling into `const fn`s as needed
+pub struct ConstEvaluator<'a> {
+    const_fns: &'a HashMap<String, FnDecl>,
+    consts: &'a HashMap<String, ConstValue>,
+    scopes: Vec<HashMap<String, ConstValue>>,
+    depth: usize,
+    steps: usize,
+}
+
+impl<'a> ConstEvaluator<'a> {
+    pub fn new(const_fns: &'a HashMap<String, FnDecl>, consts: &'a HashMap<String, ConstValue>) -> Self {
+        Self {
+            const_fns,
+            consts,
+            scopes: Vec::new(),
+            depth: 0,
+            steps: 0,
+        }
+    }
+
+    /// Evaluate an expression to a constant value
+    pub fn eval_expr(&mut self, expr: &Expr) -> Result<ConstValue, String> {
+        match expr {
+            Expr::Literal(lit) => Ok(match lit {
+                Literal::Int(v, _) => ConstValue::Int(*v),
+                Literal::Float(v, _) => ConstValue::Float(*v),
+                Literal::String(v, _) => ConstValue::String(v.clone()),
+                Literal::Bool(v, _) => ConstValue::Bool(*v),
+                Literal::Char(..) => return Err("char values are not supported in constant expressions".to_string()),
+            }),
+            Expr::Identifier(name, _) => self.lookup(name)
+                .ok_or_else(|| format!("'{}' is not a compile-time constant", name)),
+            Expr::Binary(left, op, right, _) => {
+                // Short-circuit logical operators like the runtime does
+                if matches!(op, BinOp::And | BinOp::Or) {
+                    let l = self.eval_bool(left)?;
+                    if (*op == BinOp::And && !l) || (*op == BinOp::Or && l) {
+                        return Ok(ConstValue::Bool(l));
+                    }
+                    return Ok(ConstValue::Bool(self.eval_bool(right)?));
+                }
+                let l = self.eval_expr(left)?;
+                let r = self.eval_expr(right)?;
+                binary(&l, op, &r)
+            }
+            Expr::Unary(op, operand, _) => {
+                let v = self.eval_expr(operand)?;
+                match (op, v) {
+                    (UnaryOp::Neg, ConstValue::Int(i)) => i.checked_neg()
+                        .map(ConstValue::Int)
+                        .ok_or_else(|| "integer overflow in constant expression".to_string()),
+                    (UnaryOp::Neg, ConstValue::Float(f)) => Ok(ConstValue::Float(-f)),
+                    (UnaryOp::Not, ConstValue::Bool(b)) => Ok(ConstValue::Bool(!b)),
+                    (UnaryOp::BitwiseNot, ConstValue::Int(i)) => Ok(ConstValue::Int(!i)),
+                    (op, v) => Err(format!("cannot apply {:?} to {} in constant expression", op, v.type_name())),
+                }
+            }
+            Expr::Call(callee, args, _) => {
+                let name = match callee.as_ref() {
+                    Expr::Identifier(name, _) => name,
+                    _ => return Err("only direct calls to const fns are allowed in constant expressions".to_string()),
+                };
+                if name == "len" && !self.const_fns.contains_key(name) {
+                    return self.eval_len(args);
+                }
+                let f = self.const_fns.get(name)
+                    .ok_or_else(|| format!("cannot call non-const function '{}' in a constant expression", name))?;
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.push(self.eval_expr(arg)?);
+                }
+                self.call(f, values)
+            }
+            _ => Err("expression is not allowed in a constant context".to_string()),
+        }
+    }
+
+    /// `len()` of a string or array literal, counted the same way as the runtime builtin
+    fn eval_len(&mut self, args: &[Expr]) -> Result<ConstValue, String> {
+        let [arg] = args else {
+            return Err(format!("'len' expects 1 argument, found {}", args.len()));
+        };
+        let len = match arg {
+            Expr::ArrayLit(elems, _) => {
+                for elem in elems {
+                    self.eval_expr(elem)?;
+                }
+                elems.len()
+            }
+            _ => match self.eval_expr(arg)? {
+                ConstValue::String(s) => s.len(),
+                other => return Err(format!("cannot take 'len' of {} in constant expression", other.type_name())),
+            },
+        };
+        Ok(ConstValue::Int(len as i64))
+    }
+
+    fn eval_bool(&mut self, expr: &Expr) -> Result<bool, String> {
+        match self.eval_expr(expr)? {
+            ConstValue::Bool(b) => Ok(b),
+            other => Err(format!("expected bool in constant expression, found {}", other.type_name())),
+        }
+    }
+
+    fn call(&mut self, f: &FnDecl, args: Vec<ConstValue>) -> Result<ConstValue, String> {
+        if args.len() != f.params.len() {
+            return Err(format!(
+                "const fn '{}' expects {} arguments, found {}",
+                f.name,
+                f.params.len(),
+                args.len()
+            ));
+        }
+        if self.depth >= MAX_CALL_DEPTH {
+            return Err(format!("const fn '{}' exceeded the maximum call depth", f.name));
+        }
+
+        let mut frame = HashMap::new();
+        for (param, value) in f.params.iter().zip(args) {
+            frame.insert(param.name.clone(), value);
+        }
+
+        // Each call only sees its own parameters and locals plus global consts
+        let saved = std::mem::replace(&mut self.scopes, vec![frame]);
+        self.depth += 1;
+        let result = self.exec_block(&f.body);
+        self.depth -= 1;
+        self.scopes = saved;
+
+        match result? {
+            Control::Return(v) => Ok(v),
+            Control::Break | Control::Continue => Err(format!("'break' or 'continue' outside of a loop in const fn '{}'", f.name)),
+            Control::Next => Err(format!("const fn '{}' did not return a value", f.name)),
+        }
+    }
+
+    fn exec_block(&mut self, block: &Block) -> Result<Control, String> {
+        self.scopes.push(HashMap::new());
+        let mut result = Ok(Control::Next);
+        for stmt in &block.statements {
+            match self.exec_stmt(stmt) {
+                Ok(Control::Next) => {}
+                other => {
+                    result = other;
+                    break;
+                }
+            }
+        }
+        self.scopes.pop();
+        result
+    }
+
+    fn exec_stmt(&mut self, stmt: &Stmt) -> Result<Control, String> {
+        self.steps += 1;
+        if self.steps > MAX_STEPS {
+            return Err("constant evaluation exceeded the step limit".to_string());
+        }
+
+        match stmt {
+            Stmt::Let(l) => {
+                let init = l.init.as_ref()
+                    .ok_or_else(|| format!("'{}' must be initialized in a const fn", l.name))?;
+                let value = self.eval_expr(init)?;
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.insert(l.name.clone(), value);
+                }
+                Ok(Control::Next)
+            }
+            Stmt::Return(r) => match &r.value {
+                Some(v) => Ok(Control::Return(self.eval_expr(v)?)),
+                None => Err("const fn must return a value".to_string()),
+            },
+            Stmt::If(i) => {
+                if self.eval_bool(&i.condition)? {
+                    self.exec_block(&i.then_block)
+                } else if let Some(else_block) = &i.else_block {
+                    self.exec_block(else_block)
+                } else {
+                    Ok(Control::Next)
+                }
+            }
+            Stmt::While(w) => {
+                while self.eval_bool(&w.condition)? {
+                    match self.exec_block(&w.body)? {
+                        Control::Return(v) => return Ok(Control::Return(v)),
+                        Control::Break => break,
+                        Control::Continue | Control::Next => {}
+                    }
+                    self.steps += 1;
+                    if self.steps > MAX_STEPS {
+                        return Err("constant evaluation exceeded the step limit".to_string());
+                    }
+                }
+                Ok(Control::Next)
+            }
+            Stmt::Block(b) => self.exec_block(b),
+            Stmt::Break(_) => Ok(Control::Break),
+            Stmt::Continue(_) => Ok(Control::Continue),
+            Stmt::Expr(Expr::Assign(target, value, _)) => {
+                let value = self.eval_expr(value)?;
+                self.assign(target, value)?;
+                Ok(Control::Next)
+            }
+            Stmt::Expr(Expr::CompoundAssign(target, op, value, _)) => {
+                let current = self.eval_expr(target)?;
+                let rhs = self.eval_expr(value)?;
+                let op = match op {
+                    CompoundOp::AddEq => BinOp::Add,
+                    CompoundOp::SubEq => BinOp::Sub,
+                    CompoundOp::MulEq => BinOp::Mul,
+                    CompoundOp::DivEq => BinOp::Div,
+                    CompoundOp::ModEq => BinOp::Mod,
+                };
+                let result = binary(&current, &op, &rhs)?;
+                self.assign(target, result)?;
+                Ok(Control::Next)
+            }
+            Stmt::Expr(e) => {
+                self.eval_expr(e)?;
+                Ok(Control::Next)
+            }
+            _ => Err("statement is not allowed in a const fn".to_string()),
+        }
+    }
+
+    fn assign(&mut self, target: &Expr, value: ConstValue) -> Result<(), String> {
+        let name = match target {
+            Expr::Identifier(name, _) => name,
+            _ => return Err("only local variables can be assigned in a const fn".to_string()),
+        };
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(slot) = scope.get_mut(name) {
+                *slot = value;
+                return Ok(());
+            }
+        }
+        Err(format!("cannot assign to '{}' in a const fn", name))
+    }
+
+    fn lookup(&self, name: &str) -> Option<ConstValue> {
+        self.scopes.iter().rev()
+            .find_map(|s| s.get(name))
+            .or_else(|| self.consts.get(name))
+            .cloned()
+    }
+}
+
+fn binary(l: &ConstValue, op: &BinOp, r: &ConstValue) -> Result<ConstValue, String> {
+    use ConstValue::*;
+    let overflow = || "integer overflow in constant expression".to_string();
+    Ok(match (l, r) {
+        (Int(a), Int(b)) => match op {
+            BinOp::Add => Int(a.checked_add(*b).ok_or_else(overflow)?),
+            BinOp::Sub => Int(a.checked_sub(*b).ok_or_else(overflow)?),
+            BinOp::Mul => Int(a.checked_mul(*b).ok_or_else(overflow)?),
+            BinOp::Div | BinOp::Mod if *b == 0 => {
+                return Err("division by zero in constant expression".to_string())
+            }
+            BinOp::Div => Int(a.checked_div(*b).ok_or_else(overflow)?),
+            BinOp::Mod => Int(a.checked_rem(*b).ok_or_else(overflow)?),
+            BinOp::Eq => Bool(a == b),
+            BinOp::Ne => Bool(a != b),
+            BinOp::Lt => Bool(a < b),
+            BinOp::Gt => Bool(a > b),
+            BinOp::Le => Bool(a <= b),
+            BinOp::Ge => Bool(a >= b),
+            BinOp::BitwiseAnd => Int(a & b),
+            BinOp::BitwiseOr => Int(a | b),
+            BinOp::BitwiseXor => Int(a ^ b),
+            BinOp::ShiftLeft | BinOp::ShiftRight if !(0..64).contains(b) => {
+                return Err(format!("shift amount {} out of range 0..64", b))
+            }
+            BinOp::ShiftLeft => Int(a << b),
+            BinOp::ShiftRight => Int(a >> b),
+            BinOp::And | BinOp::Or | BinOp::In => return Err(unsupported(l, op, r)),
+        },
+        (Int(_) | Float(_), Int(_) | Float(_)) => {
+            let (a, b) = (as_float(l), as_float(r));
+            match op {
+                BinOp::Add => Float(a + b),
+                BinOp::Sub => Float(a - b),
+                BinOp::Mul => Float(a * b),
+                BinOp::Div => Float(a / b),
+                BinOp::Mod => Float(a % b),
+                BinOp::Eq => Bool((a - b).abs() < f64::EPSILON),
+                BinOp::Ne => Bool((a - b).abs() >= f64::EPSILON),
+                BinOp::Lt => Bool(a < b),
+                BinOp::Gt => Bool(a > b),
+                BinOp::Le => Bool(a <= b),
+                BinOp::Ge => Bool(a >= b),
+                _ => return Err(unsupported(l, op, r)),
+            }
+        }
+        (String(a), String(b)) => match op {
+            BinOp::Add => String(format!("{}{}", a, b)),
+            BinOp::In => Bool(b.contains(a.as_str())),
+            BinOp::Eq => Bool(a == b),
+            BinOp::Ne => Bool(a != b),
+            _ => return Err(unsupported(l, op, r)),
+        },
+        (Bool(a), Bool(b)) => match op {
+            BinOp::Eq => Bool(a == b),
+            BinOp::Ne => Bool(a != b),
+            _ => return Err(unsupported(l, op, r)),
+        },
+        _ => return Err(unsupported(l, op, r)),
+    })
+}
+
+fn as_float(v: &ConstValue) -> f64 {
+    match v {
+        ConstValue::Int(i) => *i as f64,
+        ConstValue::Float(f) => *f,
+        _ => 0.0,
+    }
+}
+
+fn unsupported(l: &ConstValue, op: &BinOp, r: &ConstValue) -> String {
+    format!(
+        "cannot apply {:?} to {} and {} in constant expression",
+        op,
+        l.type_name(),
+        r.type_name()
+    )
+}
+
+/// Collect operations in a const fn body that cannot run at compile time.
+/// `is_const_fn` reports whether a called function is itself a `const fn`.
+pub fn find_non_const_ops(block: &Block, is_const_fn: &dyn Fn(&str) -> bool) -> Vec<(String, Span)> {
+    let mut found = Vec::new();
+    visit_block(block, is_const_fn, &mut found);
+    found
+}
+
+fn visit_block(block: &Block, is_const_fn: &dyn Fn(&str) -> bool, found: &mut Vec<(String, Span)>) {
+    for stmt in &block.statements {
+        match stmt {
+            Stmt::Let(l) => {
+                if let Some(init) = &l.init {
+                    visit_expr(init, is_const_fn, found);
+                }
+            }
+            Stmt::LetTuple(l) => found.push(("tuple destructuring is not allowed in a const fn".to_string(), l.span)),
+            Stmt::Expr(e) => visit_expr(e, is_const_fn, found),
+            Stmt::Return(r) => {
+                if let Some(v) = &r.value {
+                    visit_expr(v, is_const_fn, found);
+                }
+            }
+            Stmt::If(i) => {
+                visit_expr(&i.condition, is_const_fn, found);
+                visit_block(&i.then_block, is_const_fn, found);
+                if let Some(else_block) = &i.else_block {
+                    visit_block(else_block, is_const_fn, found);
+                }
+            }
+            Stmt::While(w) => {
+                visit_expr(&w.condition, is_const_fn, found);
+                visit_block(&w.body, is_const_fn, found);
+            }
+            Stmt::Block(b) => visit_block(b, is_const_fn, found),
+            Stmt::For(f) => found.push(("'for' loops are not allowed in a const fn".to_string(), f.span)),
+            Stmt::Loop(l) => found.push(("'loop' is not allowed in a const fn".to_string(), l.span)),
+            Stmt::Defer(d) => found.push(("'defer' is not allowed in a const fn".to_string(), d.span)),
+            Stmt::TryCatch(t) => found.push(("'try' is not allowed in a const fn".to_string(), t.span)),
+            Stmt::Throw(t) => found.push(("'throw' is not allowed in a const fn".to_string(), t.span)),
+            Stmt::Guard(g) => found.push(("'guard' is not allowed in a const fn".to_string(), g.span)),
+            Stmt::FnDecl(f) => found.push(("local functions are not allowed in a const fn".to_string(), f.span)),
+            Stmt::Break(_) | Stmt::Continue(_) => {}
+        }
+    }
+}
+
+fn visit_expr(expr: &Expr, is_const_fn: &dyn Fn(&str) -> bool, found: &mut Vec<(String, Span)>) {
+    match expr {
+        Expr::Call(callee, args, span) => {
+            match callee.as_ref() {
+                Expr::Identifier(name, _) if is_const_fn(name) || name == "len" => {}
+                Expr::Identifier(name, _) => found.push((
+                    format!("const fn cannot call non-const function '{}'", name),
+                    *span,
+                )),
+                _ => found.push(("const fn can only call other const fns directly".to_string(), *span)),
+            }
+            for arg in args {
+                visit_expr(arg, is_const_fn, found);
+            }
+        }
+        Expr::Binary(l, _, r, _)
+        | Expr::Assign(l, r, _)
+        | Expr::CompoundAssign(l, _, r, _)
+        | Expr::Index(l, r, _)
+        | Expr::NullCoalesce(l, r, _)
+        | Expr::Range(l, r, _) => {
+            visit_expr(l, is_const_fn, found);
+            visit_expr(r, is_const_fn, found);
+        }
+        Expr::Unary(_, e, _)
+        | Expr::Member(e, _, _)
+        | Expr::OptionalChain(e, _, _)
+        | Expr::PreIncrement(e, _)
+        | Expr::PreDecrement(e, _)
+        | Expr::PostIncrement(e, _)
+        | Expr::PostDecrement(e, _) => visit_expr(e, is_const_fn, found),
+        Expr::ArrayLit(elems, _) | Expr::Tuple(elems, _) => {
+            for elem in elems {
+                visit_expr(elem, is_const_fn, found);
+            }
+        }
+        Expr::StructLit(_, fields, base, _) => {
+            if let Some(base) = base {
+                visit_expr(base, is_const_fn, found);
+            }
+            for (_, value) in fields {
+                visit_expr(value, is_const_fn, found);
+            }
+        }
+        Expr::Match(scrutinee, arms, _) => {
+            visit_expr(scrutinee, is_const_fn, found);
+            for arm in arms {
+                visit_expr(&arm.body, is_const_fn, found);
+            }
+        }
+        Expr::Block(b, _) => visit_block(b, is_const_fn, found),
+        Expr::Await(_, span) => found.push(("'await' is not allowed in a const fn".to_string(), *span)),
+        Expr::Closure(_, _, _, span) => found.push(("closures are not allowed in a const fn".to_string(), *span)),
+        Expr::TrailingClosure(_, _, span) => {
+            found.push(("trailing closures are not allowed in a const fn".to_string(), *span))
+        }
+        Expr::Literal(_) | Expr::Identifier(..) | Expr::Nil(_) => {}
+    }
+}
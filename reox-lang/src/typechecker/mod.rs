@@ -5,36 +5,77 @@
 #![allow(dead_code, unused_imports, unused_variables)]
 
 mod types;
+mod consteval;
 
 pub use types::*;
+pub use consteval::{ConstEvaluator, ConstValue};
 
 use crate::parser::{
     Ast, Decl, Stmt, Expr, Literal, BinOp, UnaryOp,
-    FnDecl, StructDecl, ExternDecl, Block, Type, LetStmt,
+    FnDecl, StructDecl, ExternDecl, ConstDecl, TypeAliasDecl, Block, Type, LetStmt, LetTupleStmt,
     ReturnStmt, IfStmt, WhileStmt, ForStmt, GuardStmt, DeferStmt,
-    TryCatchStmt, ThrowStmt, CompoundOp,
+    TryCatchStmt, ThrowStmt, CompoundOp, IntWidth, Pattern,
 };
 use crate::lexer::Span;
 
-/// Type check error
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Lint-style finding; compilation continues unless warnings are promoted
+    Warning,
+    Error,
+}
+
+/// Type checker diagnostic
 #[derive(Debug, Clone)]
-pub struct TypeError {
+pub struct Diagnostic {
+    pub severity: Severity,
     pub message: String,
     pub line: u32,
     pub column: u32,
+    /// Error code with a long-form explanation (`reoxc explain <code>`)
+    pub code: Option<&'static str>,
 }
 
-impl TypeError {
+/// Type check error (a diagnostic with `Severity::Error`)
+pub type TypeError = Diagnostic;
+
+impl Diagnostic {
     pub fn new(message: impl Into<String>, span: &Span) -> Self {
         Self {
+            severity: Severity::Error,
             message: message.into(),
             line: span.line,
             column: span.column,
+            code: None,
+        }
+    }
+
+    /// Tag the diagnostic with an error code from `explain::EXPLANATIONS`
+    pub fn with_code(self, code: &'static str) -> Self {
+        Self { code: Some(code), ..self }
+    }
+
+    pub fn warning(message: impl Into<String>, span: &Span) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::new(message, span)
         }
     }
 
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
     pub fn display(&self) -> String {
-        format!("type error[{}:{}]: {}", self.line, self.column, self.message)
+        let kind = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "type error",
+        };
+        match self.code {
+            Some(code) => format!("{}[{}:{}]: {} [{}]", kind, self.line, self.column, self.message, code),
+            None => format!("{}[{}:{}]: {}", kind, self.line, self.column, self.message),
+        }
     }
 }
 
@@ -42,7 +83,18 @@ impl TypeError {
 pub struct TypeChecker {
     symbols: SymbolTable,
     errors: Vec<TypeError>,
+    warnings: Vec<Diagnostic>,
     current_return_type: Option<ResolvedType>,
+    const_fns: std::collections::HashMap<String, FnDecl>,
+    consts: std::collections::HashMap<String, ConstValue>,
+    aliases: std::collections::HashMap<String, ResolvedType>,
+    /// Interpreter natives a program may call; their arguments and results are unchecked
+    natives: std::collections::HashSet<String>,
+    /// Scope index where each enclosing action's parameters start; bindings
+    /// from lower scopes are captured by value
+    closure_scopes: Vec<usize>,
+    /// Name of the `let` whose initializer is the action being checked
+    closure_name: Option<String>,
 }
 
 impl TypeChecker {
@@ -50,12 +102,45 @@ impl TypeChecker {
         Self {
             symbols: SymbolTable::new(),
             errors: Vec::new(),
+            warnings: Vec::new(),
             current_return_type: None,
+            const_fns: std::collections::HashMap::new(),
+            consts: std::collections::HashMap::new(),
+            aliases: std::collections::HashMap::new(),
+            natives: std::collections::HashSet::new(),
+            closure_scopes: Vec::new(),
+            closure_name: None,
         }
     }
 
-    /// Type check the entire AST
-    pub fn check_program(&mut self, ast: &Ast) -> Result<(), Vec<TypeError>> {
+    /// A checker for programs run by the interpreter, which resolves `natives`
+    /// unless the program declares the same name
+    pub fn with_natives(natives: impl IntoIterator<Item = String>) -> Self {
+        Self { natives: natives.into_iter().collect(), ..Self::new() }
+    }
+
+    /// Value of a top-level `const`, if it was evaluated successfully
+    pub fn const_value(&self, name: &str) -> Option<&ConstValue> {
+        self.consts.get(name)
+    }
+
+    /// Warnings reported by the last `check_program` run
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+
+    /// Type check the entire AST.
+    ///
+    /// On success returns the warnings; on failure returns every
+    /// diagnostic, errors first.
+    pub fn check_program(&mut self, ast: &Ast) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+        // Aliases come first so every signature below can use them
+        for decl in &ast.declarations {
+            if let Decl::TypeAlias(a) = decl {
+                self.register_type_alias(a);
+            }
+        }
+
         // First pass: collect all struct and function declarations
         for decl in &ast.declarations {
             match decl {
@@ -63,6 +148,17 @@ impl TypeChecker {
                 Decl::Function(f) => self.register_function(f),
                 Decl::Extern(e) => self.register_extern(e),
                 Decl::Import(_) => {} // Skip imports for now
+                Decl::Const(_) => {} // Evaluated in declaration order below
+                Decl::TypeAlias(_) => {} // Registered above
+            }
+        }
+
+        self.check_recursive_structs(ast);
+
+        // Evaluate constants once every function signature is known
+        for decl in &ast.declarations {
+            if let Decl::Const(c) = decl {
+                self.check_const(c);
             }
         }
 
@@ -70,20 +166,70 @@ impl TypeChecker {
         for decl in &ast.declarations {
             if let Decl::Function(f) = decl {
                 self.check_function(f);
+                if f.is_const {
+                    self.check_const_fn(f);
+                }
             }
         }
 
         if self.errors.is_empty() {
-            Ok(())
+            Ok(self.warnings.clone())
         } else {
-            Err(self.errors.clone())
+            let mut all = self.errors.clone();
+            all.extend(self.warnings.iter().cloned());
+            Err(all)
+        }
+    }
+
+    /// Resolve a parser type, substituting declared type aliases
+    fn resolve_type(&self, ty: &Type) -> ResolvedType {
+        match ty {
+            Type::Named(name) => self.aliases.get(name).cloned()
+                .unwrap_or_else(|| match name.as_str() {
+                    // The builtin `color` unless a struct takes the name
+                    "color" if self.symbols.lookup_struct(name).is_none() => ResolvedType::Color,
+                    "char" if self.symbols.lookup_struct(name).is_none() => ResolvedType::Char,
+                    _ => ResolvedType::Struct(name.clone()),
+                }),
+            Type::Array(inner) => ResolvedType::Array(Box::new(self.resolve_type(inner))),
+            Type::Box(inner) => self.resolve_type(inner),
+            Type::Optional(inner) => ResolvedType::Optional(Box::new(self.resolve_type(inner))),
+            Type::Tuple(elems) => ResolvedType::Tuple(elems.iter().map(|e| self.resolve_type(e)).collect()),
+            Type::Function(params, ret) => ResolvedType::Function {
+                params: params.iter().map(|p| self.resolve_type(p)).collect(),
+                ret: Box::new(self.resolve_type(ret)),
+            },
+            _ => ResolvedType::from_parser_type(ty),
         }
     }
 
+    fn register_type_alias(&mut self, a: &TypeAliasDecl) {
+        if self.aliases.contains_key(&a.name) {
+            self.errors.push(TypeError::new(format!("type '{}' is already defined", a.name), &a.span));
+            return;
+        }
+        let base = self.resolve_type(&a.ty);
+        if !a.distinct {
+            self.aliases.insert(a.name.clone(), base);
+            return;
+        }
+        let newtype = ResolvedType::Newtype { name: a.name.clone(), base: Box::new(base.clone()) };
+        self.aliases.insert(a.name.clone(), newtype.clone());
+        // `Name(value)` is the explicit conversion from the base type
+        let _ = self.symbols.define(Symbol {
+            name: a.name.clone(),
+            ty: ResolvedType::Function { params: vec![base], ret: Box::new(newtype) },
+            mutable: false,
+            kind: SymbolKind::Function,
+            initialized: true,
+            deprecated: None,
+        });
+    }
+
     fn register_struct(&mut self, s: &StructDecl) {
         let mut fields = std::collections::HashMap::new();
         for field in &s.fields {
-            let ty = ResolvedType::from_parser_type(&field.ty);
+            let ty = self.resolve_type(&field.ty);
             fields.insert(field.name.clone(), ty);
         }
 
@@ -91,23 +237,80 @@ impl TypeChecker {
             name: s.name.clone(),
             fields,
         }) {
-            self.errors.push(TypeError {
-                message: e,
-                line: s.span.line,
-                column: s.span.column,
-            });
+            self.errors.push(TypeError::new(e, &s.span));
+        }
+    }
+
+    /// Report structs that contain themselves by value, which would have infinite size
+    fn check_recursive_structs(&mut self, ast: &Ast) {
+        let structs: std::collections::HashMap<&str, &StructDecl> = ast.declarations.iter()
+            .filter_map(|d| match d {
+                Decl::Struct(s) => Some((s.name.as_str(), s)),
+                _ => None,
+            })
+            .collect();
+        let mut reported = std::collections::HashSet::new();
+
+        for decl in &ast.declarations {
+            let Decl::Struct(s) = decl else { continue };
+            if reported.contains(&s.name) {
+                continue;
+            }
+            let mut path = vec![s.name.clone()];
+            if self.find_struct_cycle(s, &structs, &mut path) {
+                self.errors.push(TypeError::new(
+                    format!(
+                        "recursive struct '{}' has infinite size ({}); wrap the recursive field in Box<...>",
+                        s.name,
+                        path.join(" -> ")
+                    ),
+                    &s.span,
+                ));
+                reported.extend(path);
+            }
+        }
+    }
+
+    /// Extend `path` until it leads back to its first struct through by-value fields
+    fn find_struct_cycle(
+        &self,
+        current: &StructDecl,
+        structs: &std::collections::HashMap<&str, &StructDecl>,
+        path: &mut Vec<String>,
+    ) -> bool {
+        for field in &current.fields {
+            // Box, arrays and functions are pointers in the generated code
+            let embedded = match self.resolve_type(&field.ty) {
+                ResolvedType::Struct(name) if matches!(field.ty, Type::Named(_)) => name,
+                _ => continue,
+            };
+            if embedded == path[0] {
+                path.push(embedded);
+                return true;
+            }
+            if path.contains(&embedded) {
+                continue;
+            }
+            if let Some(next) = structs.get(embedded.as_str()) {
+                path.push(embedded);
+                if self.find_struct_cycle(next, structs, path) {
+                    return true;
+                }
+                path.pop();
+            }
         }
+        false
     }
 
     fn register_function(&mut self, f: &FnDecl) {
         let params: Vec<ResolvedType> = f.params
             .iter()
-            .map(|p| ResolvedType::from_parser_type(&p.ty))
+            .map(|p| self.resolve_type(&p.ty))
             .collect();
 
         let ret = f.return_type
             .as_ref()
-            .map(ResolvedType::from_parser_type)
+            .map(|t| self.resolve_type(t))
             .unwrap_or(ResolvedType::Void);
 
         let fn_type = ResolvedType::Function {
@@ -116,11 +319,11 @@ impl TypeChecker {
         };
 
         if let Err(e) = self.symbols.define_function(f.name.clone(), fn_type.clone()) {
-            self.errors.push(TypeError {
-                message: e,
-                line: f.span.line,
-                column: f.span.column,
-            });
+            self.errors.push(TypeError::new(e, &f.span));
+        }
+
+        if f.is_const {
+            self.const_fns.insert(f.name.clone(), f.clone());
         }
 
         // Also add to symbol table for lookup
@@ -129,18 +332,86 @@ impl TypeChecker {
             ty: fn_type,
             mutable: false,
             kind: SymbolKind::Function,
+            initialized: true,
+            deprecated: f.deprecation(),
         });
     }
 
+    fn check_const(&mut self, c: &ConstDecl) {
+        let declared = self.resolve_type(&c.ty);
+        let inferred = self.infer_expr_type(&c.value);
+        let inferred = self.coerce_literal(&declared, &c.value, inferred);
+        if !declared.is_assignable_from(&inferred) {
+            self.errors.push(TypeError::new(
+                format!(
+                    "type mismatch: expected '{}', found '{}'",
+                    declared.display_name(),
+                    inferred.display_name()
+                ),
+                &c.span,
+            ).with_code("E0003"));
+        }
+
+        if let Err(e) = self.symbols.define(Symbol {
+            name: c.name.clone(),
+            ty: declared,
+            mutable: false,
+            kind: SymbolKind::Variable,
+            initialized: true,
+            deprecated: None,
+        }) {
+            self.errors.push(TypeError::new(e, &c.span));
+        }
+
+        let result = ConstEvaluator::new(&self.const_fns, &self.consts).eval_expr(&c.value);
+        match result {
+            Ok(value) => {
+                self.consts.insert(c.name.clone(), value);
+            }
+            Err(e) => self.errors.push(TypeError::new(
+                format!("initializer of const '{}' is not a compile-time constant: {}", c.name, e),
+                &c.span,
+            ).with_code("E0008")),
+        }
+    }
+
+    /// `len(x)` accepts a string or an array and yields an int
+    fn check_len_call(&mut self, args: &[Expr], span: &Span) -> ResolvedType {
+        if args.len() != 1 {
+            self.errors.push(TypeError::new(
+                format!("'len' expects 1 argument, found {}", args.len()),
+                span,
+            ).with_code("E0005"));
+        }
+        for arg in args {
+            let ty = self.infer_expr_type(arg);
+            if !matches!(ty, ResolvedType::String | ResolvedType::Array(_) | ResolvedType::Error | ResolvedType::Any) {
+                self.errors.push(TypeError::new(
+                    format!("cannot take 'len' of '{}'", ty.display_name()),
+                    span,
+                ));
+            }
+        }
+        ResolvedType::Int
+    }
+
+    fn check_const_fn(&mut self, f: &FnDecl) {
+        let const_fns = &self.const_fns;
+        let violations = consteval::find_non_const_ops(&f.body, &|name| const_fns.contains_key(name));
+        for (message, span) in violations {
+            self.errors.push(TypeError::new(format!("in const fn '{}': {}", f.name, message), &span));
+        }
+    }
+
     fn register_extern(&mut self, e: &ExternDecl) {
         let params: Vec<ResolvedType> = e.params
             .iter()
-            .map(|p| ResolvedType::from_parser_type(&p.ty))
+            .map(|p| self.resolve_type(&p.ty))
             .collect();
 
         let ret = e.return_type
             .as_ref()
-            .map(ResolvedType::from_parser_type)
+            .map(|t| self.resolve_type(t))
             .unwrap_or(ResolvedType::Void);
 
         let fn_type = ResolvedType::Function {
@@ -149,11 +420,7 @@ impl TypeChecker {
         };
 
         if let Err(e_msg) = self.symbols.define_function(e.name.clone(), fn_type.clone()) {
-            self.errors.push(TypeError {
-                message: e_msg,
-                line: e.span.line,
-                column: e.span.column,
-            });
+            self.errors.push(TypeError::new(e_msg, &e.span));
         }
 
         let _ = self.symbols.define(Symbol {
@@ -161,6 +428,8 @@ impl TypeChecker {
             ty: fn_type,
             mutable: false,
             kind: SymbolKind::Function,
+            initialized: true,
+            deprecated: None,
         });
     }
 
@@ -169,82 +438,159 @@ impl TypeChecker {
 
         // Add parameters to scope
         for param in &f.params {
-            let ty = ResolvedType::from_parser_type(&param.ty);
+            let ty = self.resolve_type(&param.ty);
             let _ = self.symbols.define(Symbol {
                 name: param.name.clone(),
                 ty,
                 mutable: false,
                 kind: SymbolKind::Parameter,
+                initialized: true,
+                deprecated: None,
             });
         }
 
         // Set expected return type
         self.current_return_type = f.return_type
             .as_ref()
-            .map(|t| ResolvedType::from_parser_type(t));
+            .map(|t| self.resolve_type(t));
 
         // Check function body
         self.check_block(&f.body);
 
         self.current_return_type = None;
-        self.symbols.pop_scope();
+        self.pop_scope();
+    }
+
+    /// A function declared inside a block is visible from its definition
+    /// (including its own body) to the end of the enclosing scope
+    fn check_local_function(&mut self, f: &FnDecl) {
+        let params = f.params.iter().map(|p| self.resolve_type(&p.ty)).collect();
+        let ret = f.return_type
+            .as_ref()
+            .map(|t| self.resolve_type(t))
+            .unwrap_or(ResolvedType::Void);
+
+        if let Err(e) = self.symbols.define(Symbol {
+            name: f.name.clone(),
+            ty: ResolvedType::Function { params, ret: Box::new(ret) },
+            mutable: false,
+            kind: SymbolKind::Function,
+            initialized: true,
+            deprecated: f.deprecation(),
+        }) {
+            self.errors.push(TypeError::new(e, &f.span));
+        }
+
+        let outer_return_type = self.current_return_type.take();
+        self.check_function(f);
+        self.current_return_type = outer_return_type;
     }
 
     fn check_block(&mut self, block: &Block) {
+        let mut diverged = false;
         for stmt in &block.statements {
+            if diverged {
+                self.warnings.push(Diagnostic::warning("unreachable statement", &stmt.span()));
+                diverged = false;
+            }
             self.check_statement(stmt);
+            if matches!(stmt, Stmt::Return(_) | Stmt::Break(_) | Stmt::Continue(_) | Stmt::Throw(_)) {
+                diverged = true;
+            }
+        }
+    }
+
+    /// Leave a scope, warning about `let` bindings that were never read
+    fn pop_scope(&mut self) {
+        for (name, span) in self.symbols.pop_scope() {
+            if !name.starts_with('_') {
+                self.warnings.push(Diagnostic::warning(format!("unused variable '{}'", name), &span));
+            }
         }
     }
 
     fn check_statement(&mut self, stmt: &Stmt) {
         match stmt {
             Stmt::Let(l) => self.check_let(l),
+            Stmt::LetTuple(l) => self.check_let_tuple(l),
             Stmt::Return(r) => self.check_return(r),
             Stmt::If(i) => self.check_if(i),
             Stmt::While(w) => self.check_while(w),
+            Stmt::Loop(l) => {
+                let pending = self.symbols.uninitialized_names();
+                self.symbols.push_scope();
+                self.check_block(&l.body);
+                self.pop_scope();
+                self.reset_uninitialized(&pending);
+            }
             Stmt::For(f) => self.check_for(f),
             Stmt::Expr(e) => { self.infer_expr_type(e); }
             Stmt::Block(b) => {
                 self.symbols.push_scope();
                 self.check_block(b);
-                self.symbols.pop_scope();
+                self.pop_scope();
             }
             Stmt::Break(_) | Stmt::Continue(_) => {}
+            Stmt::FnDecl(f) => self.check_local_function(f),
             // Swift/C++ style statements
             Stmt::Guard(g) => {
                 let cond_type = self.infer_expr_type(&g.condition);
                 if cond_type != ResolvedType::Bool {
-                    self.errors.push(TypeError {
-                        message: format!("guard condition must be bool, found '{}'", cond_type.display_name()),
-                        line: g.span.line,
-                        column: g.span.column,
-                    });
+                    self.errors.push(TypeError::new(format!("guard condition must be bool, found '{}'", cond_type.display_name()), &g.span).with_code("E0007"));
                 }
+                let pending = self.symbols.uninitialized_names();
                 self.symbols.push_scope();
+                self.narrow_by_condition(&g.condition, false);
                 self.check_block(&g.else_block);
-                self.symbols.pop_scope();
+                self.pop_scope();
+                self.reset_uninitialized(&pending);
+                // The else block must exit, so the condition holds from here on
+                self.narrow_by_condition(&g.condition, true);
             }
             Stmt::Defer(d) => {
+                let pending = self.symbols.uninitialized_names();
                 self.symbols.push_scope();
                 self.check_block(&d.body);
-                self.symbols.pop_scope();
+                self.pop_scope();
+                self.reset_uninitialized(&pending);
             }
             Stmt::TryCatch(t) => {
+                // Either block may stop partway, so neither definitely assigns
+                let pending = self.symbols.uninitialized_names();
                 self.symbols.push_scope();
                 self.check_block(&t.try_block);
-                self.symbols.pop_scope();
+                self.pop_scope();
                 
-                self.symbols.push_scope();
-                if let Some(var) = &t.catch_var {
-                    let _ = self.symbols.define(Symbol {
-                        name: var.clone(),
-                        ty: ResolvedType::String, // Error type
-                        mutable: false,
-                        kind: SymbolKind::Variable,
-                    });
+                for clause in &t.catches {
+                    self.symbols.push_scope();
+                    if let Some(var) = &clause.var {
+                        // Untyped catches receive the error message
+                        let ty = clause.ty.as_ref().map_or(ResolvedType::String, |ty| self.resolve_type(ty));
+                        let _ = self.symbols.define(Symbol {
+                            name: var.clone(),
+                            ty,
+                            mutable: false,
+                            kind: SymbolKind::Variable,
+                            initialized: true,
+                            deprecated: None,
+                        });
+                    }
+                    self.check_block(&clause.block);
+                    self.pop_scope();
+                    self.reset_uninitialized(&pending);
+                }
+
+                // Finally may run before the try assigned anything, but always runs
+                if let Some(finally) = &t.finally_block {
+                    let after = self.symbols.uninitialized_names();
+                    self.reset_uninitialized(&pending);
+                    self.symbols.push_scope();
+                    self.check_block(finally);
+                    self.pop_scope();
+                    for name in pending.iter().filter(|n| !after.contains(n)) {
+                        self.symbols.set_initialized(name, true);
+                    }
                 }
-                self.check_block(&t.catch_block);
-                self.symbols.pop_scope();
             }
             Stmt::Throw(t) => {
                 self.infer_expr_type(&t.value);
@@ -253,67 +599,148 @@ impl TypeChecker {
     }
 
     fn check_let(&mut self, l: &LetStmt) {
-        let declared_type = l.ty.as_ref().map(|t| ResolvedType::from_parser_type(t));
+        let declared_type = l.ty.as_ref().map(|t| self.resolve_type(t));
+        if let Some(Expr::Closure(..)) = &l.init {
+            self.closure_name = Some(l.name.clone());
+        }
         
-        let inferred_type = l.init.as_ref().map(|e| self.infer_expr_type(e));
+        let inferred_type = l.init.as_ref().map(|e| {
+            let ty = self.infer_expr_type(e);
+            let ty = self.reject_void(ty, e);
+            match &declared_type {
+                Some(decl) => self.coerce_literal(decl, e, ty),
+                None => ty,
+            }
+        });
 
         let final_type = match (&declared_type, &inferred_type) {
             (Some(decl), Some(infer)) => {
                 if !decl.is_assignable_from(infer) {
-                    self.errors.push(TypeError {
-                        message: format!(
+                    self.errors.push(TypeError::new(format!(
                             "type mismatch: expected '{}', found '{}'",
                             decl.display_name(),
                             infer.display_name()
-                        ),
-                        line: l.span.line,
-                        column: l.span.column,
-                    });
+                        ), &l.span).with_code("E0003"));
                 }
                 decl.clone()
             }
             (Some(decl), None) => decl.clone(),
             (None, Some(infer)) => infer.clone(),
             (None, None) => {
-                self.errors.push(TypeError {
-                    message: "cannot infer type without initializer".to_string(),
-                    line: l.span.line,
-                    column: l.span.column,
-                });
+                self.errors.push(TypeError::new("cannot infer type without initializer".to_string(), &l.span));
                 ResolvedType::Error
             }
         };
 
-        if let Err(e) = self.symbols.define(Symbol {
-            name: l.name.clone(),
-            ty: final_type,
-            mutable: l.mutable,
+        self.define_let_binding(&l.name, final_type, l.mutable, l.init.is_some(), l.span);
+    }
+
+    fn define_let_binding(&mut self, name: &str, ty: ResolvedType, mutable: bool, initialized: bool, span: Span) {
+        if self.symbols.shadows_outer(name) {
+            self.warnings.push(Diagnostic::warning(
+                format!("variable '{}' shadows a binding from an outer scope", name),
+                &span,
+            ));
+        }
+
+        if let Err(e) = self.symbols.define_local(Symbol {
+            name: name.to_string(),
+            ty,
+            mutable,
             kind: SymbolKind::Variable,
-        }) {
-            self.errors.push(TypeError {
-                message: e,
-                line: l.span.line,
-                column: l.span.column,
-            });
+            initialized,
+            deprecated: None,
+        }, span) {
+            self.errors.push(TypeError::new(e, &span));
+        }
+    }
+
+    /// `let (a, b) = pair;` needs a tuple with exactly one element per name
+    fn check_let_tuple(&mut self, l: &LetTupleStmt) {
+        let declared_type = l.ty.as_ref().map(|t| self.resolve_type(t));
+        let ty = self.infer_expr_type(&l.init);
+        let ty = self.reject_void(ty, &l.init);
+        let ty = match declared_type {
+            Some(decl) => {
+                let ty = self.coerce_literal(&decl, &l.init, ty);
+                if !decl.is_assignable_from(&ty) {
+                    self.errors.push(TypeError::new(format!(
+                            "type mismatch: expected '{}', found '{}'",
+                            decl.display_name(),
+                            ty.display_name()
+                        ), &l.span).with_code("E0003"));
+                }
+                decl
+            }
+            None => ty,
+        };
+
+        let elems = match ty {
+            ResolvedType::Tuple(elems) if elems.len() == l.names.len() => elems,
+            ResolvedType::Tuple(elems) => {
+                self.errors.push(TypeError::new(
+                    format!("cannot destructure a {}-element tuple into {} names", elems.len(), l.names.len()),
+                    &l.span,
+                ));
+                vec![ResolvedType::Error; l.names.len()]
+            }
+            ResolvedType::Error | ResolvedType::Unknown | ResolvedType::Any => vec![ty; l.names.len()],
+            other => {
+                self.errors.push(TypeError::new(
+                    format!("cannot destructure '{}' as a tuple", other.display_name()),
+                    &l.span,
+                ));
+                vec![ResolvedType::Error; l.names.len()]
+            }
+        };
+        for (name, ty) in l.names.iter().zip(elems) {
+            self.define_let_binding(name, ty, l.mutable, true, l.span);
+        }
+    }
+
+    /// Warn about a call to a function marked `@deprecated`
+    fn warn_if_deprecated(&mut self, name: &str, span: &Span) {
+        let Some(message) = self.symbols.lookup(name).and_then(|s| s.deprecated.clone()) else { return };
+        let warning = if message.is_empty() {
+            format!("function '{}' is deprecated", name)
+        } else {
+            format!("function '{}' is deprecated: {}", name, message)
+        };
+        self.warnings.push(Diagnostic::warning(warning, span));
+    }
+
+    /// Report a void call used where a value is needed
+    fn reject_void(&mut self, ty: ResolvedType, expr: &Expr) -> ResolvedType {
+        if ty != ResolvedType::Void {
+            return ty;
         }
+        // Point at the callee rather than the call's parenthesis
+        let span = match expr {
+            Expr::Call(callee, _, _) => callee.span(),
+            other => other.span(),
+        };
+        self.errors.push(TypeError::new("cannot use the result of a void function as a value", &span));
+        ResolvedType::Error
     }
 
     fn check_return(&mut self, r: &ReturnStmt) {
         let return_type = r.value.as_ref()
-            .map(|e| self.infer_expr_type(e))
+            .map(|e| {
+                let ty = self.infer_expr_type(e);
+                match self.current_return_type.clone() {
+                    Some(expected) => self.coerce_literal(&expected, e, ty),
+                    None => ty,
+                }
+            })
             .unwrap_or(ResolvedType::Void);
 
         if let Some(expected) = &self.current_return_type {
             if !expected.is_assignable_from(&return_type) {
-                self.errors.push(TypeError {
-                    message: format!(
+                self.errors.push(TypeError::new(format!(
                         "return type mismatch: expected '{}', found '{}'",
                         expected.display_name(),
                         return_type.display_name()
-                    ),
-                    line: r.span.line,
-                    column: r.span.column,
-                });
+                    ), &r.span).with_code("E0003"));
             }
         }
     }
@@ -321,43 +748,169 @@ impl TypeChecker {
     fn check_if(&mut self, i: &IfStmt) {
         let cond_type = self.infer_expr_type(&i.condition);
         if cond_type != ResolvedType::Bool {
-            self.errors.push(TypeError {
-                message: format!(
+            self.errors.push(TypeError::new(format!(
                     "if condition must be bool, found '{}'",
                     cond_type.display_name()
-                ),
-                line: i.span.line,
-                column: i.span.column,
-            });
+                ), &i.span).with_code("E0007"));
         }
 
+        let pending = self.symbols.uninitialized_names();
+
         self.symbols.push_scope();
+        self.narrow_by_condition(&i.condition, true);
         self.check_block(&i.then_block);
-        self.symbols.pop_scope();
+        self.pop_scope();
+        let then_assigned = self.assigned_since(&pending, &i.then_block);
+        self.reset_uninitialized(&pending);
+
+        let else_assigned = match &i.else_block {
+            Some(else_block) => {
+                self.symbols.push_scope();
+                self.narrow_by_condition(&i.condition, false);
+                self.check_block(else_block);
+                self.pop_scope();
+                let assigned = self.assigned_since(&pending, else_block);
+                self.reset_uninitialized(&pending);
+                assigned
+            }
+            None => Vec::new(),
+        };
+
+        // Only variables assigned on every path are definitely initialized
+        for name in &then_assigned {
+            if else_assigned.contains(name) {
+                self.symbols.set_initialized(name, true);
+            }
+        }
+
+        // `if x == nil { return; }` leaves `x` non-nil for the rest of the scope
+        if i.else_block.is_none() && Self::diverges(&i.then_block) {
+            self.narrow_by_condition(&i.condition, false);
+        }
+    }
+
+    /// Check `pattern` against the matched type `ty`, collecting each name it
+    /// binds with its type. Struct patterns must name the matched struct and
+    /// its fields, and their literal fields must fit the field types
+    fn check_pattern(&mut self, pattern: &Pattern, ty: &ResolvedType, span: &Span, bound: &mut Vec<(String, ResolvedType)>) {
+        match pattern {
+            Pattern::Identifier(name) => bound.push((name.clone(), ty.clone())),
+            Pattern::Binding { name, inner } => {
+                bound.push((name.clone(), ty.clone()));
+                self.check_pattern(inner, ty, span, bound);
+            }
+            Pattern::Struct { name, fields } => {
+                let declared = self.symbols.lookup_struct(name).map(|s| s.fields.clone());
+                match (&declared, ty) {
+                    (None, _) => self.errors.push(TypeError::new(format!("undefined struct '{}'", name), span)),
+                    (Some(_), ResolvedType::Struct(n)) if n == name => {}
+                    (Some(_), ResolvedType::Unknown | ResolvedType::Error | ResolvedType::Any) => {}
+                    (Some(_), other) => self.errors.push(TypeError::new(
+                        format!("struct pattern '{}' cannot match '{}'", name, other.display_name()),
+                        span,
+                    )),
+                }
+                for (field, inner) in fields {
+                    let field_ty = match &declared {
+                        Some(declared) => declared.get(field).cloned().unwrap_or_else(|| {
+                            self.errors.push(TypeError::new(format!("struct '{}' has no field '{}'", name, field), span).with_code("E0006"));
+                            ResolvedType::Error
+                        }),
+                        None => ResolvedType::Error,
+                    };
+                    if let Pattern::Literal(lit) = inner {
+                        let lit_expr = Expr::Literal(lit.clone());
+                        let lit_ty = self.infer_expr_type(&lit_expr);
+                        let lit_ty = self.coerce_literal(&field_ty, &lit_expr, lit_ty);
+                        if field_ty != ResolvedType::Error && !field_ty.is_assignable_from(&lit_ty) {
+                            self.errors.push(TypeError::new(
+                                format!("field '{}' is '{}', but the pattern is '{}'", field, field_ty.display_name(), lit_ty.display_name()),
+                                span,
+                            ));
+                        }
+                    }
+                    self.check_pattern(inner, &field_ty, span, bound);
+                }
+            }
+            Pattern::Literal(_) | Pattern::Wildcard | Pattern::Range(..) => {}
+        }
+    }
+
+    fn diverges(block: &Block) -> bool {
+        matches!(
+            block.statements.last(),
+            Some(Stmt::Return(_) | Stmt::Throw(_) | Stmt::Break(_) | Stmt::Continue(_))
+        )
+    }
+
+    /// Refine optional variables that `condition` proves non-nil when it
+    /// evaluates to `outcome`, for the rest of the current scope
+    fn narrow_by_condition(&mut self, condition: &Expr, outcome: bool) {
+        let mut names = Vec::new();
+        Self::non_nil_when(condition, outcome, &mut names);
+        for name in names {
+            if let Some(ResolvedType::Optional(inner)) = self.symbols.lookup(&name).map(|s| s.ty.clone()) {
+                self.symbols.narrow(&name, *inner);
+            }
+        }
+    }
 
-        if let Some(else_block) = &i.else_block {
-            self.symbols.push_scope();
-            self.check_block(else_block);
-            self.symbols.pop_scope();
+    /// Collect variables compared against `nil` that must be non-nil when
+    /// `condition` evaluates to `outcome`
+    fn non_nil_when(condition: &Expr, outcome: bool, names: &mut Vec<String>) {
+        match condition {
+            Expr::Binary(left, op @ (BinOp::Ne | BinOp::Eq), right, _) => {
+                let name = match (left.as_ref(), right.as_ref()) {
+                    (Expr::Identifier(name, _), Expr::Nil(_)) | (Expr::Nil(_), Expr::Identifier(name, _)) => name,
+                    _ => return,
+                };
+                if outcome == (*op == BinOp::Ne) {
+                    names.push(name.clone());
+                }
+            }
+            Expr::Binary(left, BinOp::And, right, _) if outcome => {
+                Self::non_nil_when(left, true, names);
+                Self::non_nil_when(right, true, names);
+            }
+            Expr::Binary(left, BinOp::Or, right, _) if !outcome => {
+                Self::non_nil_when(left, false, names);
+                Self::non_nil_when(right, false, names);
+            }
+            Expr::Unary(UnaryOp::Not, inner, _) => Self::non_nil_when(inner, !outcome, names),
+            _ => {}
+        }
+    }
+
+    /// Which of `pending` were assigned by `block` (all of them if it never falls through)
+    fn assigned_since(&self, pending: &[String], block: &Block) -> Vec<String> {
+        let diverges = matches!(block.statements.last(), Some(Stmt::Return(_)) | Some(Stmt::Throw(_)));
+        pending.iter()
+            .filter(|name| diverges || self.symbols.lookup(name).is_some_and(|s| s.initialized))
+            .cloned()
+            .collect()
+    }
+
+    /// Forget assignments made in a block that may not have run
+    fn reset_uninitialized(&mut self, pending: &[String]) {
+        for name in pending {
+            self.symbols.set_initialized(name, false);
         }
     }
 
     fn check_while(&mut self, w: &WhileStmt) {
         let cond_type = self.infer_expr_type(&w.condition);
         if cond_type != ResolvedType::Bool {
-            self.errors.push(TypeError {
-                message: format!(
+            self.errors.push(TypeError::new(format!(
                     "while condition must be bool, found '{}'",
                     cond_type.display_name()
-                ),
-                line: w.span.line,
-                column: w.span.column,
-            });
+                ), &w.span).with_code("E0007"));
         }
 
+        let pending = self.symbols.uninitialized_names();
         self.symbols.push_scope();
         self.check_block(&w.body);
-        self.symbols.pop_scope();
+        self.pop_scope();
+        self.reset_uninitialized(&pending);
     }
 
     fn check_for(&mut self, f: &ForStmt) {
@@ -366,28 +919,29 @@ impl TypeChecker {
         let elem_type = match iter_type {
             ResolvedType::Array(inner) => *inner,
             ResolvedType::Int => ResolvedType::Int, // For range-like iteration
+            ResolvedType::Any => ResolvedType::Any,
             _ => {
-                self.errors.push(TypeError {
-                    message: format!(
+                self.errors.push(TypeError::new(format!(
                         "cannot iterate over '{}'",
                         iter_type.display_name()
-                    ),
-                    line: f.span.line,
-                    column: f.span.column,
-                });
+                    ), &f.span));
                 ResolvedType::Error
             }
         };
 
+        let pending = self.symbols.uninitialized_names();
         self.symbols.push_scope();
         let _ = self.symbols.define(Symbol {
             name: f.var.clone(),
             ty: elem_type,
             mutable: false,
             kind: SymbolKind::Variable,
+            initialized: true,
+            deprecated: None,
         });
         self.check_block(&f.body);
-        self.symbols.pop_scope();
+        self.pop_scope();
+        self.reset_uninitialized(&pending);
     }
 
     /// Infer the type of an expression
@@ -395,27 +949,70 @@ impl TypeChecker {
         match expr {
             Expr::Literal(lit) => self.infer_literal_type(lit),
             Expr::Identifier(name, span) => {
+                self.symbols.mark_used(name);
                 if let Some(sym) = self.symbols.lookup(name) {
-                    sym.ty.clone()
+                    if !sym.initialized {
+                        self.errors.push(TypeError::new(
+                            format!("variable '{}' is used before being initialized", name),
+                            span,
+                        ).with_code("E0004"));
+                    }
+                    self.symbols.narrowed_type(name).unwrap_or(&sym.ty).clone()
+                } else if self.natives.contains(name) {
+                    ResolvedType::Any
                 } else {
                     self.errors.push(TypeError::new(
                         format!("undefined variable '{}'", name),
                         span,
-                    ));
+                    ).with_code("E0002"));
                     ResolvedType::Error
                 }
             }
             Expr::Binary(left, op, right, span) => {
                 let left_ty = self.infer_expr_type(left);
+                let left_ty = self.reject_void(left_ty, left);
                 let right_ty = self.infer_expr_type(right);
+                let right_ty = self.reject_void(right_ty, right);
+                // A literal operand takes the width of the other side
+                let right_ty = self.coerce_literal(&left_ty, right, right_ty);
+                let left_ty = self.coerce_literal(&right_ty, left, left_ty);
                 self.check_binary_op(&left_ty, op, &right_ty, span)
             }
             Expr::Unary(op, operand, span) => {
                 let operand_ty = self.infer_expr_type(operand);
+                let operand_ty = self.reject_void(operand_ty, operand);
                 self.check_unary_op(op, &operand_ty, span)
             }
             Expr::Call(callee, args, span) => {
+                // Builtin `len` is usable in const contexts, so it needs a signature here
+                if let Expr::Identifier(name, _) = callee.as_ref() {
+                    if name == "len" && self.symbols.lookup(name).is_none() {
+                        return self.check_len_call(args, span);
+                    }
+                    // Builtin `print` takes any arguments and produces no value
+                    if name == "print" && self.symbols.lookup(name).is_none() {
+                        for arg in args {
+                            let ty = self.infer_expr_type(arg);
+                            self.reject_void(ty, arg);
+                        }
+                        return ResolvedType::Void;
+                    }
+                    // Builtin `to_string`, which string interpolation calls, formats any value
+                    if name == "to_string" && self.symbols.lookup(name).is_none() {
+                        for arg in args {
+                            let ty = self.infer_expr_type(arg);
+                            self.reject_void(ty, arg);
+                        }
+                        return ResolvedType::String;
+                    }
+                    if let Some(width) = IntWidth::from_name(name) {
+                        return self.check_int_conversion(width, args, span);
+                    }
+                }
                 let callee_ty = self.infer_expr_type(callee);
+                if let Expr::Identifier(name, name_span) = callee.as_ref() {
+                    self.warn_if_deprecated(name, name_span);
+                }
                 self.check_call(&callee_ty, args, span)
             }
             Expr::Member(obj, field, span) => {
@@ -428,8 +1025,21 @@ impl TypeChecker {
                 self.check_index(&arr_ty, &idx_ty, span)
             }
             Expr::Assign(target, value, span) => {
-                let target_ty = self.infer_expr_type(target);
                 let value_ty = self.infer_expr_type(value);
+                if self.reject_declaration_target(target) {
+                    return ResolvedType::Error;
+                }
+                self.reject_captured_target(target);
+                // Assigning to a bare variable initializes it rather than reading it
+                let target_ty = match target.as_ref() {
+                    Expr::Identifier(name, _) if self.symbols.lookup(name).is_some() => {
+                        self.symbols.set_initialized(name, true);
+                        self.symbols.clear_narrowing(name);
+                        self.symbols.lookup(name).map(|s| s.ty.clone()).unwrap_or(ResolvedType::Error)
+                    }
+                    _ => self.infer_expr_type(target),
+                };
+                let value_ty = self.coerce_literal(&target_ty, value, value_ty);
                 if !target_ty.is_assignable_from(&value_ty) {
                     self.errors.push(TypeError::new(
                         format!(
@@ -442,15 +1052,31 @@ impl TypeChecker {
                 }
                 target_ty
             }
-            Expr::StructLit(name, fields, span) => {
+            Expr::StructLit(name, fields, base, span) => {
                 if let Some(struct_info) = self.symbols.lookup_struct(name) {
                     // Clone fields to avoid borrow conflict
                     let expected_fields = struct_info.fields.clone();
+
+                    // `..base` supplies the remaining fields, so it must be the same struct
+                    if let Some(base) = base {
+                        let base_ty = self.infer_expr_type(base);
+                        if base_ty != ResolvedType::Struct(name.clone()) && base_ty != ResolvedType::Error {
+                            self.errors.push(TypeError::new(
+                                format!(
+                                    "struct update base must be '{}', found '{}'",
+                                    name,
+                                    base_ty.display_name()
+                                ),
+                                &base.span(),
+                            ));
+                        }
+                    }
                     
                     // Check all fields are provided with correct types
                     for (field_name, value) in fields {
                         let value_ty = self.infer_expr_type(value);
                         if let Some(expected_ty) = expected_fields.get(field_name) {
+                            let value_ty = self.coerce_literal(expected_ty, value, value_ty);
                             if !expected_ty.is_assignable_from(&value_ty) {
                                 self.errors.push(TypeError::new(
                                     format!(
@@ -466,7 +1092,7 @@ impl TypeChecker {
                             self.errors.push(TypeError::new(
                                 format!("struct '{}' has no field '{}'", name, field_name),
                                 span,
-                            ));
+                            ).with_code("E0006"));
                         }
                     }
                     ResolvedType::Struct(name.clone())
@@ -478,6 +1104,12 @@ impl TypeChecker {
                     ResolvedType::Error
                 }
             }
+            Expr::Tuple(elements, _) => ResolvedType::Tuple(
+                elements.iter().map(|e| {
+                    let ty = self.infer_expr_type(e);
+                    self.reject_void(ty, e)
+                }).collect(),
+            ),
             Expr::ArrayLit(elements, _) => {
                 if elements.is_empty() {
                     ResolvedType::Array(Box::new(ResolvedType::Unknown))
@@ -486,14 +1118,50 @@ impl TypeChecker {
                     ResolvedType::Array(Box::new(elem_ty))
                 }
             }
-            Expr::Match(_, _, _) => {
-                // Match expressions are complex - return Unknown for now
+            Expr::Match(scrutinee, arms, _) => {
+                let scrutinee_ty = self.infer_expr_type(scrutinee);
+                for arm in arms {
+                    let mut bound = Vec::new();
+                    self.check_pattern(&arm.pattern, &scrutinee_ty, &arm.span, &mut bound);
+                    self.symbols.push_scope();
+                    for (name, ty) in bound {
+                        let _ = self.symbols.define(Symbol {
+                            name,
+                            ty,
+                            mutable: false,
+                            kind: SymbolKind::Variable,
+                            initialized: true,
+                            deprecated: None,
+                        });
+                    }
+                    self.infer_expr_type(&arm.body);
+                    self.pop_scope();
+                }
+                // Match result types are not unified yet
                 ResolvedType::Unknown
             }
+            Expr::Block(block, _) => {
+                self.symbols.push_scope();
+                let mut ty = ResolvedType::Void;
+                for (i, stmt) in block.statements.iter().enumerate() {
+                    match stmt {
+                        Stmt::Expr(e) if i + 1 == block.statements.len() => ty = self.infer_expr_type(e),
+                        _ => self.check_statement(stmt),
+                    }
+                }
+                self.pop_scope();
+                ty
+            }
             // Swift/C++ style expressions
             Expr::CompoundAssign(target, _op, value, span) => {
+                if self.reject_declaration_target(target) {
+                    self.infer_expr_type(value);
+                    return ResolvedType::Error;
+                }
+                self.reject_captured_target(target);
                 let target_ty = self.infer_expr_type(target);
                 let value_ty = self.infer_expr_type(value);
+                let value_ty = self.coerce_literal(&target_ty, value, value_ty);
                 if !target_ty.is_assignable_from(&value_ty) {
                     self.errors.push(TypeError::new(
                         format!("cannot compound assign '{}' to '{}'", value_ty.display_name(), target_ty.display_name()),
@@ -504,8 +1172,9 @@ impl TypeChecker {
             }
             Expr::PreIncrement(operand, span) | Expr::PreDecrement(operand, span) |  
             Expr::PostIncrement(operand, span) | Expr::PostDecrement(operand, span) => {
+                self.reject_captured_target(operand);
                 let ty = self.infer_expr_type(operand);
-                if !matches!(ty, ResolvedType::Int | ResolvedType::Float) {
+                if !matches!(ty, ResolvedType::Int | ResolvedType::Float | ResolvedType::Any) {
                     self.errors.push(TypeError::new(
                         format!("cannot increment/decrement '{}'", ty.display_name()),
                         span,
@@ -557,6 +1226,54 @@ impl TypeChecker {
                 // Range produces an array of integers
                 ResolvedType::Array(Box::new(ResolvedType::Int))
             }
+            Expr::Closure(params, return_type, body, _) => {
+                let own_name = self.closure_name.take();
+                let param_types: Vec<ResolvedType> = params
+                    .iter()
+                    .map(|p| self.resolve_type(&p.ty))
+                    .collect();
+                let ret = return_type
+                    .as_ref()
+                    .map(|t| self.resolve_type(t))
+                    .unwrap_or(ResolvedType::Void);
+
+                let fn_ty = ResolvedType::Function {
+                    params: param_types.clone(),
+                    ret: Box::new(ret.clone()),
+                };
+
+                self.closure_scopes.push(self.symbols.depth());
+                self.symbols.push_scope();
+                // `let f = action(..) { .. }` may call `f` from its body
+                if let Some(name) = own_name {
+                    let _ = self.symbols.define(Symbol {
+                        name,
+                        ty: fn_ty.clone(),
+                        mutable: false,
+                        kind: SymbolKind::Variable,
+                        initialized: true,
+                        deprecated: None,
+                    });
+                }
+                for (param, ty) in params.iter().zip(&param_types) {
+                    let _ = self.symbols.define(Symbol {
+                        name: param.name.clone(),
+                        ty: ty.clone(),
+                        mutable: false,
+                        kind: SymbolKind::Parameter,
+                        initialized: true,
+                        deprecated: None,
+                    });
+                }
+                // The closure body returns from the closure, not the enclosing function
+                let outer_return = self.current_return_type.replace(ret);
+                self.check_block(body);
+                self.current_return_type = outer_return;
+                self.pop_scope();
+                self.closure_scopes.pop();
+
+                fn_ty
+            }
         }
     }
 
@@ -565,22 +1282,168 @@ impl TypeChecker {
             Literal::Int(_, _) => ResolvedType::Int,
             Literal::Float(_, _) => ResolvedType::Float,
             Literal::String(_, _) => ResolvedType::String,
+            Literal::Char(_, _) => ResolvedType::Char,
             Literal::Bool(_, _) => ResolvedType::Bool,
         }
     }
 
-    fn check_binary_op(&mut self, left: &ResolvedType, op: &BinOp, right: &ResolvedType, span: &Span) -> ResolvedType {
-        match op {
-            // Arithmetic operators
-            BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod => {
-                match (left, right) {
-                    (ResolvedType::Int, ResolvedType::Int) => ResolvedType::Int,
-                    (ResolvedType::Float, ResolvedType::Float) => ResolvedType::Float,
-                    (ResolvedType::Float, ResolvedType::Int) => ResolvedType::Float,
-                    (ResolvedType::Int, ResolvedType::Float) => ResolvedType::Float,
-                    (ResolvedType::String, ResolvedType::String) if *op == BinOp::Add => {
-                        ResolvedType::String // String concatenation
-                    }
+    /// Report an assignment whose target names a function or struct rather than a variable
+    fn reject_declaration_target(&mut self, target: &Expr) -> bool {
+        let Expr::Identifier(name, span) = target else { return false };
+        let kind = match self.symbols.lookup(name).map(|s| &s.kind) {
+            Some(SymbolKind::Function) => "function",
+            Some(SymbolKind::Struct) => "struct",
+            None if self.symbols.lookup_struct(name).is_some() => "struct",
+            _ => return false,
+        };
+        self.errors.push(TypeError::new(format!("cannot assign to {} '{}'", kind, name), span));
+        true
+    }
+
+    /// Actions capture by value, so writing to a captured variable (or one of
+    /// its fields or elements) inside an action would be silently lost
+    fn reject_captured_target(&mut self, target: &Expr) {
+        let mut root = target;
+        while let Expr::Member(inner, _, _) | Expr::Index(inner, _, _) = root {
+            root = inner;
+        }
+        let Expr::Identifier(name, span) = root else { return };
+        let Some(&start) = self.closure_scopes.last() else { return };
+        let captured = self.symbols.scope_of(name).is_some_and(|i| i < start)
+            && self.symbols.lookup(name).is_some_and(|s| matches!(s.kind, SymbolKind::Variable | SymbolKind::Parameter));
+        if captured {
+            self.errors.push(TypeError::new(
+                format!("cannot assign to captured variable '{}'; actions capture variables by value", name),
+                span,
+            ));
+        }
+    }
+
+    /// An integer literal takes a fixed-width or float type from its context when
+    /// it fits, and array literals take their expected element type element-wise.
+    /// Out-of-range literals are reported here and treated as the expected type.
+    fn coerce_literal(&mut self, expected: &ResolvedType, expr: &Expr, actual: ResolvedType) -> ResolvedType {
+        match (expected, expr) {
+            (ResolvedType::FixedInt(width), _) => {
+                let Some(value) = int_literal_value(expr) else { return actual };
+                if !width.contains(value) {
+                    let (min, max) = width.range();
+                    self.errors.push(TypeError::new(
+                        format!(
+                            "integer literal {} is out of range for '{}' ({}..={})",
+                            value,
+                            width.name(),
+                            min,
+                            max
+                        ),
+                        &expr.span(),
+                    ));
+                }
+                expected.clone()
+            }
+            (ResolvedType::Float, Expr::Literal(Literal::Float(..))) => ResolvedType::Float,
+            (ResolvedType::Float, _) if int_literal_value(expr).is_some() => ResolvedType::Float,
+            (ResolvedType::Array(elem), Expr::ArrayLit(elements, _)) if !elements.is_empty() => {
+                // Every element is visited so each out-of-range literal is reported
+                let mut coerced = true;
+                for e in elements {
+                    coerced &= self.coerce_literal(elem, e, ResolvedType::Unknown) == **elem;
+                }
+                if coerced { expected.clone() } else { actual }
+            }
+            (ResolvedType::Tuple(expected_elems), Expr::Tuple(elements, _)) if expected_elems.len() == elements.len() => {
+                let ResolvedType::Tuple(actual_elems) = actual else { return actual };
+                ResolvedType::Tuple(
+                    expected_elems.iter().zip(elements).zip(actual_elems)
+                        .map(|((expected, e), actual)| self.coerce_literal(expected, e, actual))
+                        .collect(),
+                )
+            }
+            _ => actual,
+        }
+    }
+
+    /// `u8(x)` and friends convert any number to a fixed width, wrapping like a C cast
+    fn check_int_conversion(&mut self, width: IntWidth, args: &[Expr], span: &Span) -> ResolvedType {
+        if let [arg] = args {
+            let ty = self.infer_expr_type(arg);
+            if !ty.is_numeric() && !matches!(ty, ResolvedType::Error | ResolvedType::Any) {
+                self.errors.push(TypeError::new(
+                    format!("cannot convert '{}' to '{}'", ty.display_name(), width.name()),
+                    span,
+                ));
+            }
+        } else {
+            self.errors.push(TypeError::new(
+                format!("'{}' expects 1 argument, found {}", width.name(), args.len()),
+                span,
+            ));
+        }
+        ResolvedType::FixedInt(width)
+    }
+
+    fn check_binary_op(&mut self, left: &ResolvedType, op: &BinOp, right: &ResolvedType, span: &Span) -> ResolvedType {
+        // A native's result is only known at run time
+        if matches!(left, ResolvedType::Any) || matches!(right, ResolvedType::Any) {
+            return match op {
+                BinOp::Add if *left == ResolvedType::String || *right == ResolvedType::String => ResolvedType::String,
+                BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod
+                | BinOp::BitwiseAnd | BinOp::BitwiseOr | BinOp::BitwiseXor
+                | BinOp::ShiftLeft | BinOp::ShiftRight => ResolvedType::Any,
+                _ => ResolvedType::Bool,
+            };
+        }
+        match op {
+            // Arithmetic operators
+            BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod => {
+                match (left, right) {
+                    (ResolvedType::Int, ResolvedType::Int) => ResolvedType::Int,
+                    (l, r) if l.is_integer() && r.is_integer() => match l.common_int(r) {
+                        Some(ty) => ty,
+                        None => {
+                            self.errors.push(TypeError::new(
+                                format!(
+                                    "mixed integer widths '{}' and '{}' need an explicit conversion",
+                                    left.display_name(),
+                                    right.display_name()
+                                ),
+                                span,
+                            ));
+                            ResolvedType::Error
+                        }
+                    },
+                    (ResolvedType::Float, ResolvedType::Float) => ResolvedType::Float,
+                    (ResolvedType::Float, r) if r.is_integer() => ResolvedType::Float,
+                    (l, ResolvedType::Float) if l.is_integer() => ResolvedType::Float,
+                    (ResolvedType::String, ResolvedType::String) if *op == BinOp::Add => {
+                        ResolvedType::String // String concatenation
+                    }
+                    // The other operand is formatted with `to_string`
+                    (ResolvedType::String, _) | (_, ResolvedType::String) if *op == BinOp::Add => {
+                        ResolvedType::String
+                    }
+                    (ResolvedType::String, ResolvedType::Int) | (ResolvedType::Int, ResolvedType::String)
+                        if *op == BinOp::Mul => {
+                        ResolvedType::String // String repetition
+                    }
+                    (ResolvedType::Array(a), ResolvedType::Array(b)) if *op == BinOp::Add => {
+                        // Array concatenation; an empty literal takes the other side's element type
+                        if **a == ResolvedType::Unknown || a.is_assignable_from(b) {
+                            ResolvedType::Array(if **a == ResolvedType::Unknown { b.clone() } else { a.clone() })
+                        } else if b.is_assignable_from(a) {
+                            ResolvedType::Array(b.clone())
+                        } else {
+                            self.errors.push(TypeError::new(
+                                format!(
+                                    "cannot concatenate '{}' and '{}'",
+                                    left.display_name(),
+                                    right.display_name()
+                                ),
+                                span,
+                            ));
+                            ResolvedType::Error
+                        }
+                    }
                     _ => {
                         self.errors.push(TypeError::new(
                             format!(
@@ -596,9 +1459,23 @@ impl TypeChecker {
             }
             // Comparison operators
             BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge => {
-                if left == right || 
-                   (matches!(left, ResolvedType::Int | ResolvedType::Float) && 
-                    matches!(right, ResolvedType::Int | ResolvedType::Float)) {
+                let equality = matches!(op, BinOp::Eq | BinOp::Ne);
+                // `nil` (typed `<unknown>`) can be tested for equality with anything
+                let nil_test =
+                    equality && (*left == ResolvedType::Unknown || *right == ResolvedType::Unknown);
+                // Ordering is defined on numbers, chars (by code point) and
+                // (lexicographically) on strings
+                let ordered = (left.is_numeric() && right.is_numeric())
+                    || (*left == ResolvedType::String && *right == ResolvedType::String)
+                    || (*left == ResolvedType::Char && *right == ResolvedType::Char)
+                    || matches!(left, ResolvedType::Unknown | ResolvedType::Error)
+                    || matches!(right, ResolvedType::Unknown | ResolvedType::Error);
+                let comparable = if equality {
+                    left == right || (left.is_numeric() && right.is_numeric()) || nil_test
+                } else {
+                    ordered
+                };
+                if comparable {
                     ResolvedType::Bool
                 } else {
                     self.errors.push(TypeError::new(
@@ -612,6 +1489,40 @@ impl TypeChecker {
                     ResolvedType::Error
                 }
             }
+            // Membership: element in array, key in map, substring in string
+            BinOp::In => {
+                let ok = match right {
+                    ResolvedType::Array(elem) => {
+                        matches!(**elem, ResolvedType::Unknown | ResolvedType::Error)
+                            || elem.is_assignable_from(left)
+                            || left.is_assignable_from(elem)
+                    }
+                    ResolvedType::Map(key, _) => key.is_assignable_from(left),
+                    ResolvedType::String => matches!(left, ResolvedType::String | ResolvedType::Char),
+                    ResolvedType::Unknown | ResolvedType::Error => true,
+                    _ => {
+                        self.errors.push(TypeError::new(
+                            format!(
+                                "'in' requires an array, map or string, found '{}'",
+                                right.display_name()
+                            ),
+                            span,
+                        ));
+                        return ResolvedType::Error;
+                    }
+                };
+                if !ok && *left != ResolvedType::Error {
+                    self.errors.push(TypeError::new(
+                        format!(
+                            "cannot look up '{}' in '{}'",
+                            left.display_name(),
+                            right.display_name()
+                        ),
+                        span,
+                    ));
+                }
+                ResolvedType::Bool
+            }
             // Logical operators
             BinOp::And | BinOp::Or => {
                 if *left == ResolvedType::Bool && *right == ResolvedType::Bool {
@@ -630,9 +1541,9 @@ impl TypeChecker {
             }
             // Bitwise operators
             BinOp::BitwiseAnd | BinOp::BitwiseOr | BinOp::BitwiseXor | BinOp::ShiftLeft | BinOp::ShiftRight => {
-                match (left, right) {
-                    (ResolvedType::Int, ResolvedType::Int) => ResolvedType::Int,
-                    _ => {
+                match left.common_int(right) {
+                    Some(ty) => ty,
+                    None => {
                         self.errors.push(TypeError::new(
                             format!(
                                 "bitwise operators require int operands, found '{}' and '{}'",
@@ -649,9 +1560,12 @@ impl TypeChecker {
     }
 
     fn check_unary_op(&mut self, op: &UnaryOp, operand: &ResolvedType, span: &Span) -> ResolvedType {
+        if *operand == ResolvedType::Any {
+            return if *op == UnaryOp::Not { ResolvedType::Bool } else { ResolvedType::Any };
+        }
         match op {
             UnaryOp::Neg => {
-                if matches!(operand, ResolvedType::Int | ResolvedType::Float) {
+                if operand.is_numeric() {
                     operand.clone()
                 } else {
                     self.errors.push(TypeError::new(
@@ -673,8 +1587,8 @@ impl TypeChecker {
                 }
             }
             UnaryOp::BitwiseNot => {
-                if *operand == ResolvedType::Int {
-                    ResolvedType::Int
+                if operand.is_integer() {
+                    operand.clone()
                 } else {
                     self.errors.push(TypeError::new(
                         format!("cannot apply '~' to '{}'", operand.display_name()),
@@ -697,12 +1611,14 @@ impl TypeChecker {
                             args.len()
                         ),
                         span,
-                    ));
+                    ).with_code("E0005"));
                     return *ret.clone();
                 }
 
                 for (i, (arg, param)) in args.iter().zip(params.iter()).enumerate() {
                     let arg_ty = self.infer_expr_type(arg);
+                    let arg_ty = self.reject_void(arg_ty, arg);
+                    let arg_ty = self.coerce_literal(param, arg, arg_ty);
                     if !param.is_assignable_from(&arg_ty) {
                         self.errors.push(TypeError::new(
                             format!(
@@ -718,12 +1634,28 @@ impl TypeChecker {
 
                 *ret.clone()
             }
-            ResolvedType::Error => ResolvedType::Error,
+            ResolvedType::Error => {
+                // Still visit the arguments so their own errors and uses are recorded
+                for arg in args {
+                    self.infer_expr_type(arg);
+                }
+                ResolvedType::Error
+            }
+            ResolvedType::Any => {
+                for arg in args {
+                    let ty = self.infer_expr_type(arg);
+                    self.reject_void(ty, arg);
+                }
+                ResolvedType::Any
+            }
             _ => {
                 self.errors.push(TypeError::new(
                     format!("'{}' is not callable", callee.display_name()),
                     span,
                 ));
+                for arg in args {
+                    self.infer_expr_type(arg);
+                }
                 ResolvedType::Error
             }
         }
@@ -739,7 +1671,7 @@ impl TypeChecker {
                         self.errors.push(TypeError::new(
                             format!("struct '{}' has no field '{}'", name, field),
                             span,
-                        ));
+                        ).with_code("E0006"));
                         ResolvedType::Error
                     }
                 } else {
@@ -750,7 +1682,9 @@ impl TypeChecker {
                     ResolvedType::Error
                 }
             }
+            ResolvedType::Color if matches!(field, "r" | "g" | "b" | "a") => ResolvedType::Int,
             ResolvedType::Error => ResolvedType::Error,
+            ResolvedType::Any => ResolvedType::Any,
             _ => {
                 self.errors.push(TypeError::new(
                     format!("cannot access field '{}' on '{}'", field, obj.display_name()),
@@ -762,7 +1696,7 @@ impl TypeChecker {
     }
 
     fn check_index(&mut self, arr: &ResolvedType, idx: &ResolvedType, span: &Span) -> ResolvedType {
-        if *idx != ResolvedType::Int {
+        if !idx.is_integer() && !matches!(idx, ResolvedType::Error | ResolvedType::Any) {
             self.errors.push(TypeError::new(
                 format!("array index must be int, found '{}'", idx.display_name()),
                 span,
@@ -771,8 +1705,9 @@ impl TypeChecker {
 
         match arr {
             ResolvedType::Array(inner) => *inner.clone(),
-            ResolvedType::String => ResolvedType::String, // String indexing returns char/string
+            ResolvedType::String => ResolvedType::Char,
             ResolvedType::Error => ResolvedType::Error,
+            ResolvedType::Any => ResolvedType::Any,
             _ => {
                 self.errors.push(TypeError::new(
                     format!("cannot index into '{}'", arr.display_name()),
@@ -792,13 +1727,48 @@ impl Default for TypeChecker {
 
 /// Type check the AST (convenience function for backward compatibility)
 pub fn check(ast: &Ast) -> Ast {
+    check_with(ast, false).unwrap_or_else(|_| ast.clone())
+}
+
+/// Type check the AST, printing every diagnostic.
+///
+/// Type errors are reported without stopping code generation, as with
+/// `check`; when `warnings_as_errors` is set any warning fails the check.
+/// The returned AST has each const initializer replaced by its computed value.
+pub fn check_with(ast: &Ast, warnings_as_errors: bool) -> Result<Ast, String> {
     let mut checker = TypeChecker::new();
-    if let Err(errors) = checker.check_program(ast) {
-        for error in errors {
-            eprintln!("{}", error.display());
+    let diagnostics = match checker.check_program(ast) {
+        Ok(warnings) => warnings,
+        Err(all) => all,
+    };
+    for diagnostic in &diagnostics {
+        eprintln!("{}", diagnostic.display());
+    }
+    let warnings = checker.warnings().len();
+    if warnings_as_errors && warnings > 0 {
+        return Err(format!("{} warning(s) treated as errors", warnings));
+    }
+    let mut checked = ast.clone();
+    for decl in &mut checked.declarations {
+        if let Decl::Const(c) = decl {
+            if let Some(value) = checker.const_value(&c.name) {
+                c.value = value.to_expr(c.span);
+            }
         }
     }
-    ast.clone()
+    Ok(checked)
+}
+
+/// The value of an integer literal, possibly negated
+fn int_literal_value(expr: &Expr) -> Option<i128> {
+    match expr {
+        Expr::Literal(Literal::Int(n, _)) => Some(*n as i128),
+        Expr::Unary(UnaryOp::Neg, inner, _) => match inner.as_ref() {
+            Expr::Literal(Literal::Int(n, _)) => Some(-(*n as i128)),
+            _ => None,
+        },
+        _ => None,
+    }
 }
 
 #[cfg(test)]
@@ -811,7 +1781,7 @@ mod tests {
         let tokens = tokenize(source).unwrap();
         let ast = parse(&tokens);
         let mut checker = TypeChecker::new();
-        checker.check_program(&ast)
+        checker.check_program(&ast).map(|_| ())
     }
 
     #[test]
@@ -836,6 +1806,24 @@ mod tests {
         assert!(errors.iter().any(|e| e.message.contains("undefined")));
     }
 
+    #[test]
+    fn test_errors_carry_explain_codes() {
+        let errors = check_source(r#"
+            fn main() {
+                let x = y;
+                if 3 {
+                    let s: string = 42;
+                }
+            }
+        "#).unwrap_err();
+        let codes: Vec<_> = errors.iter().filter_map(|e| e.code).collect();
+        assert_eq!(codes, ["E0002", "E0007", "E0003"]);
+        for code in codes {
+            assert!(crate::explain::explain(code).is_ok(), "{} has no explanation", code);
+        }
+        assert_eq!(errors[0].display(), "type error[3:25]: undefined variable 'y' [E0002]");
+    }
+
     #[test]
     fn test_type_mismatch() {
         let result = check_source(r#"
@@ -913,4 +1901,803 @@ mod tests {
         let errors = result.unwrap_err();
         assert!(errors.iter().any(|e| e.message.contains("arguments")));
     }
+
+    #[test]
+    fn test_const_fn_result_as_array_size() {
+        let tokens = tokenize(r#"
+            const fn square(n: int) -> int {
+                return n * n;
+            }
+            const SIZE: int = square(4);
+            fn main() -> int {
+                let mut buf: [int] = [0];
+                while len(buf) < SIZE {
+                    buf = buf + [0];
+                }
+                return len(buf);
+            }
+        "#).unwrap();
+        let ast = parse(&tokens);
+        let mut checker = TypeChecker::new();
+        assert!(checker.check_program(&ast).is_ok());
+        assert_eq!(checker.const_value("SIZE"), Some(&ConstValue::Int(16)));
+        let len = crate::interpreter::Interpreter::new().eval_function(&ast, "main").unwrap();
+        assert!(matches!(len, crate::interpreter::Value::Int(16)));
+    }
+
+    #[test]
+    fn test_const_fn_rejects_calls_nested_in_match_and_index() {
+        let result = check_source(r#"
+            fn lookup(n: int) -> int {
+                return n;
+            }
+            const fn pick(n: int) -> int {
+                let table: [int] = [1, 2, 3];
+                let scaled: int = table[lookup(n)];
+                return match n {
+                    0 => lookup(1),
+                    _ => scaled,
+                };
+            }
+        "#);
+        let errors = result.unwrap_err();
+        let calls = errors.iter()
+            .filter(|e| e.message.contains("cannot call non-const function 'lookup'"))
+            .count();
+        assert_eq!(calls, 2, "{:?}", errors);
+    }
+
+    #[test]
+    fn test_const_len_of_literals() {
+        let tokens = tokenize(r#"
+            const N: int = len([1, 2, 3]);
+            const M: int = len("hello") * 2;
+            const fn width(label: string) -> int {
+                return len(label) + 2;
+            }
+            const W: int = width("ok");
+        "#).unwrap();
+        let ast = parse(&tokens);
+        let mut checker = TypeChecker::new();
+        assert!(checker.check_program(&ast).is_ok());
+        assert_eq!(checker.const_value("N"), Some(&ConstValue::Int(3)));
+        assert_eq!(checker.const_value("M"), Some(&ConstValue::Int(10)));
+        assert_eq!(checker.const_value("W"), Some(&ConstValue::Int(4)));
+    }
+
+    #[test]
+    fn test_const_fn_loop_control() {
+        let tokens = tokenize(r#"
+            const fn first_square_over(limit: int) -> int {
+                let mut i = 0;
+                while true {
+                    i += 1;
+                    if i % 2 == 0 { continue; }
+                    if i * i > limit { break; }
+                }
+                return i;
+            }
+            const N: int = first_square_over(20);
+        "#).unwrap();
+        let ast = parse(&tokens);
+        let mut checker = TypeChecker::new();
+        assert!(checker.check_program(&ast).is_ok());
+        assert_eq!(checker.const_value("N"), Some(&ConstValue::Int(5)));
+
+        let checked = check_with(&ast, false).unwrap();
+        let folded = checked.declarations.iter().find_map(|d| match d {
+            Decl::Const(c) => Some(&c.value),
+            _ => None,
+        });
+        assert!(matches!(folded, Some(Expr::Literal(Literal::Int(5, _)))));
+    }
+
+    #[test]
+    fn test_closures_capture_by_value() {
+        let errors = check_source(r#"
+            struct Counter { n: int }
+            fn main() {
+                let mut count = 0;
+                let mut c = Counter { n: 0 };
+                let inc = action() {
+                    let mut local = 0;
+                    local = local + 1;
+                    count = count + 1;
+                    c.n += 1;
+                    count++;
+                };
+                inc();
+            }
+        "#).unwrap_err();
+        let messages: Vec<&str> = errors.iter().filter(|e| e.is_error()).map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, [
+            "cannot assign to captured variable 'count'; actions capture variables by value",
+            "cannot assign to captured variable 'c'; actions capture variables by value",
+            "cannot assign to captured variable 'count'; actions capture variables by value",
+        ]);
+
+        // A `let`-bound action can call itself by name
+        check_source(r#"
+            fn main() {
+                let fact = action(n: int) -> int {
+                    if n <= 1 { return 1; }
+                    return n * fact(n - 1);
+                };
+                let x: int = fact(5);
+            }
+        "#).unwrap();
+    }
+
+    #[test]
+    fn test_natives_resolve_unless_redeclared() {
+        let source = r#"
+            fn pad_left(s: string) -> string {
+                return s;
+            }
+            fn main() {
+                let m = map_set(map_new(), "a", 1);
+                let total: int = map_get(m, "a") + 1;
+                let s: string = format("{}", total);
+                let short = pad_left(s, 4);
+            }
+        "#;
+        let ast = parse(&tokenize(source).unwrap());
+        let errors = TypeChecker::new().check_program(&ast).unwrap_err();
+        assert!(errors.iter().any(|e| e.message == "undefined variable 'map_set'"));
+
+        let natives = ["map_set", "map_new", "map_get", "format", "pad_left"].map(String::from);
+        let errors = TypeChecker::with_natives(natives).check_program(&ast).unwrap_err();
+        let messages: Vec<&str> = errors.iter().filter(|e| e.is_error()).map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, ["expected 1 arguments, found 2"]);
+    }
+
+    #[test]
+    fn test_const_fn_rejects_print() {
+        let result = check_source(r#"
+            const fn noisy(n: int) -> int {
+                print(n);
+                return n;
+            }
+        "#);
+        assert!(result.is_err());
+        let errors = result.unwrap_err();
+        assert!(errors.iter().any(|e| e.message.contains("non-const function 'print'")));
+    }
+
+    #[test]
+    fn test_const_initializer_must_be_constant() {
+        let result = check_source(r#"
+            fn runtime_value() -> int {
+                return 3;
+            }
+            const N: int = runtime_value();
+        "#);
+        assert!(result.is_err());
+        let errors = result.unwrap_err();
+        assert!(errors.iter().any(|e| e.message.contains("not a compile-time constant")));
+    }
+
+    #[test]
+    fn test_read_uninitialized_let_is_error() {
+        let result = check_source(r#"
+            fn main() {
+                let x: int;
+                let y: int = x + 1;
+            }
+        "#);
+        assert!(result.is_err());
+        let errors = result.unwrap_err();
+        assert!(errors.iter().any(|e| e.message.contains("used before being initialized")));
+    }
+
+    #[test]
+    fn test_let_initialized_before_use() {
+        let result = check_source(r#"
+            fn main() {
+                let x: int;
+                x = 5;
+                let y: int = x + 1;
+            }
+        "#);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_let_assigned_on_one_branch_only() {
+        let result = check_source(r#"
+            fn pick(flag: bool) -> int {
+                let x: int;
+                if (flag) {
+                    x = 1;
+                }
+                return x;
+            }
+        "#);
+        assert!(result.is_err());
+
+        let result = check_source(r#"
+            fn pick(flag: bool) -> int {
+                let x: int;
+                if (flag) {
+                    x = 1;
+                } else {
+                    x = 2;
+                }
+                return x;
+            }
+        "#);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_pass_matching_closure_to_function_type() {
+        let result = check_source(r#"
+            fn apply(f: fn(int) -> int, x: int) -> int {
+                return f(x);
+            }
+            fn main() {
+                let y: int = apply(action(n: int) -> int { return n * 2; }, 21);
+            }
+        "#);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_reject_mismatched_closure_argument() {
+        let result = check_source(r#"
+            fn apply(f: fn(int) -> int, x: int) -> int {
+                return f(x);
+            }
+            fn main() {
+                let y: int = apply(action(s: string) -> string { return s; }, 21);
+            }
+        "#);
+        assert!(result.is_err());
+        let errors = result.unwrap_err();
+        assert!(errors.iter().any(|e| e.message.contains("expected 'fn(int) -> int'")));
+    }
+
+    #[test]
+    fn test_string_times_int_is_string() {
+        let result = check_source(r#"
+            fn main() {
+                let line: string = "-" * 40;
+                let other: string = 3 * "ab";
+            }
+        "#);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_char_type() {
+        let result = check_source(r#"
+            fn main() {
+                let c: char = "abc"[0];
+                let later: bool = c < 'z';
+            }
+        "#);
+        assert!(result.is_ok());
+
+        let result = check_source(r#"
+            fn main() {
+                let s: string = 'a';
+            }
+        "#);
+        assert!(result.unwrap_err().iter().any(|e| e.message.contains("'char'")));
+    }
+
+    #[test]
+    fn test_string_plus_any_is_string() {
+        let result = check_source(r#"
+            fn main() {
+                let a: string = "count: " + 5;
+                let b: string = 2.5 + " units";
+                let c: int = 1 + 2;
+                let d: int = "n" + 1;
+            }
+        "#);
+        let errors = result.unwrap_err();
+        let messages: Vec<&str> = errors.iter().filter(|e| e.is_error()).map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, ["type mismatch: expected 'int', found 'string'"]);
+    }
+
+    #[test]
+    fn test_string_interpolation_is_string() {
+        let result = check_source(r#"
+            fn main() {
+                let n = 3;
+                let s: string = "n=${n}, half=${n / 2.0}";
+                let t: int = "${n}";
+            }
+        "#);
+        let errors = result.unwrap_err();
+        let messages: Vec<&str> = errors.iter().filter(|e| e.is_error()).map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, ["type mismatch: expected 'int', found 'string'"]);
+    }
+
+    #[test]
+    fn test_array_concat_and_in_types() {
+        let result = check_source(r#"
+            fn main() {
+                let xs: [int] = [1, 2] + [3];
+                let found: bool = 2 in xs;
+                let sub: bool = "a" in "abc";
+            }
+        "#);
+        assert!(result.is_ok());
+
+        let result = check_source(r#"
+            fn main() {
+                let bad: bool = 1 in 5;
+            }
+        "#);
+        assert!(result.unwrap_err().iter().any(|e| e.message.contains("'in' requires")));
+    }
+
+    #[test]
+    fn test_match_binding_pattern_defines_name() {
+        let result = check_source(r#"
+            fn main() {
+                let x: int = 4;
+                let y = match x {
+                    n @ 1..=5 => n + 1,
+                    _ => 0,
+                };
+            }
+        "#);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_struct_pattern_fields_are_checked() {
+        let ok = check_source(r#"
+            struct Point { x: float, y: int }
+            fn twice(n: int) -> int { return n * 2; }
+            fn f(p: Point) {
+                let r = match p { Point { x: 0, y } => twice(y), _ => 0 };
+                print(r);
+            }
+        "#);
+        assert!(ok.is_ok(), "{:?}", ok);
+
+        let errors = check_source(r#"
+            struct Point { x: float, y: int }
+            struct Size { w: int }
+            fn f(p: Point) {
+                let r = match p {
+                    Point { z } => 1,
+                    Point { x: "zero" } => 2,
+                    Size { w } => w,
+                    Circle { r } => 3,
+                    _ => 0,
+                };
+                print(r);
+            }
+        "#).unwrap_err();
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert!(messages.contains(&"struct 'Point' has no field 'z'"), "{:?}", messages);
+        assert!(messages.contains(&"field 'x' is 'float', but the pattern is 'string'"), "{:?}", messages);
+        assert!(messages.contains(&"struct pattern 'Size' cannot match 'Point'"), "{:?}", messages);
+        assert!(messages.contains(&"undefined struct 'Circle'"), "{:?}", messages);
+    }
+
+    #[test]
+    fn test_transparent_alias_is_interchangeable() {
+        let result = check_source(r#"
+            typealias Score = int;
+            fn bump(s: Score) -> int {
+                return s + 1;
+            }
+            fn main() {
+                let raw: int = 3;
+                let total: Score = bump(raw);
+            }
+        "#);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_distinct_newtype_requires_conversion() {
+        let result = check_source(r#"
+            typealias UserId = int distinct;
+            typealias OrderId = int distinct;
+            fn load(id: UserId) -> int {
+                return 0;
+            }
+            fn main() {
+                let ok: int = load(UserId(7));
+                let raw: int = load(7);
+                let order: OrderId = OrderId(1);
+                let wrong: int = load(order);
+            }
+        "#);
+        let errors: Vec<_> = result.unwrap_err().into_iter().filter(|d| d.is_error()).collect();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(errors[0].message.contains("expected 'UserId', found 'int'"));
+        assert!(errors[1].message.contains("expected 'UserId', found 'OrderId'"));
+    }
+
+    #[test]
+    fn test_lint_warnings_do_not_fail_check() {
+        let tokens = tokenize(r#"
+            fn main() -> int {
+                let unused = 1;
+                let x = 2;
+                if x > 1 {
+                    let x = 3;
+                    return x;
+                }
+                return x;
+                x = 4;
+            }
+        "#).unwrap();
+        let ast = parse(&tokens);
+        let mut checker = TypeChecker::new();
+        let warnings = checker.check_program(&ast).expect("only warnings expected");
+        let messages: Vec<&str> = warnings.iter().map(|w| w.message.as_str()).collect();
+        assert!(warnings.iter().all(|w| w.severity == Severity::Warning));
+        assert!(messages.contains(&"unused variable 'unused'"));
+        assert!(messages.contains(&"variable 'x' shadows a binding from an outer scope"));
+        assert!(messages.contains(&"unreachable statement"));
+        assert_eq!(warnings.len(), 3);
+
+        assert!(check_with(&ast, false).is_ok());
+        assert!(check_with(&ast, true).is_err());
+    }
+
+    #[test]
+    fn test_underscore_variables_are_not_reported_unused() {
+        let tokens = tokenize("fn main() { let _ignored = 1; }").unwrap();
+        let ast = parse(&tokens);
+        let mut checker = TypeChecker::new();
+        assert!(checker.check_program(&ast).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_directly_recursive_struct_requires_box() {
+        let errors = check_source("struct Node { value: int, next: Node }").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("recursive struct 'Node' has infinite size (Node -> Node)"));
+        assert!(errors[0].message.contains("Box<...>"));
+
+        let errors = check_source("struct A { b: B } struct B { a: A }").unwrap_err();
+        assert_eq!(errors.len(), 1, "a cycle is reported once");
+        assert!(errors[0].message.contains("(A -> B -> A)"));
+    }
+
+    #[test]
+    fn test_boxed_recursive_struct_type_checks() {
+        let result = check_source(r#"
+            struct Node { value: int, next: Box<Node> }
+            struct Tree { children: [Tree] }
+            fn second(n: Node) -> int {
+                return n.next.value;
+            }
+        "#);
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[test]
+    fn test_closure_struct_field_call() {
+        let result = check_source(r#"
+            struct Button { label: string, on_press: fn(int) -> int }
+            fn main() -> int {
+                let b = Button { label: "ok", on_press: action(n: int) -> int { return n * 2; } };
+                return b.on_press(21);
+            }
+        "#);
+        assert!(result.is_ok(), "{:?}", result);
+
+        let errors = check_source(r#"
+            struct Button { label: string, on_press: fn(int) -> int }
+            fn main() {
+                let b = Button { label: "ok", on_press: action(n: int) -> int { return n; } };
+                b.on_press("x");
+                b.label();
+            }
+        "#).unwrap_err();
+        assert!(errors[0].message.contains("argument 1 type mismatch: expected 'int', found 'string'"));
+        assert!(errors[1].message.contains("'string' is not callable"));
+    }
+
+    #[test]
+    fn test_u8_field_literal_range() {
+        let result = check_source(r#"
+            struct Pixel { r: u8, g: u8, b: u8, a: u8 }
+            fn main() -> Pixel {
+                return Pixel { r: 255, g: 0, b: 128, a: 255 };
+            }
+        "#);
+        assert!(result.is_ok(), "{:?}", result);
+
+        let errors = check_source(r#"
+            struct Pixel { r: u8, g: u8, b: u8, a: u8 }
+            fn main() -> Pixel {
+                return Pixel { r: 256, g: 0, b: 0, a: -1 };
+            }
+        "#).unwrap_err();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(errors[0].message.contains("integer literal 256 is out of range for 'u8' (0..=255)"));
+        assert!(errors[1].message.contains("integer literal -1 is out of range for 'u8'"));
+    }
+
+    #[test]
+    fn test_integer_widening_and_narrowing() {
+        let result = check_source(r#"
+            fn widen(small: u8, mid: i32) -> int {
+                let x: i16 = small;
+                let y: i64 = mid;
+                let z: u8 = small + 1;
+                return x + y + z;
+            }
+        "#);
+        assert!(result.is_ok(), "{:?}", result);
+
+        let errors = check_source(r#"
+            fn narrow(n: int, s: i8) -> u8 {
+                let a: u8 = n;
+                let b: u8 = s + 0;
+                return u8(n);
+            }
+        "#).unwrap_err();
+        let errors: Vec<_> = errors.into_iter().filter(|d| d.is_error()).collect();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(errors[0].message.contains("expected 'u8', found 'int'"));
+        assert!(errors[1].message.contains("expected 'u8', found 'i8'"));
+    }
+
+    #[test]
+    fn test_assign_to_function_or_struct_name() {
+        let errors = check_source(r#"
+            struct Point { x: int }
+            fn helper() -> int { return 1; }
+            fn main() {
+                helper = 5;
+                helper += 1;
+                Point = 2;
+            }
+        "#).unwrap_err();
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, vec![
+            "cannot assign to function 'helper'",
+            "cannot assign to function 'helper'",
+            "cannot assign to struct 'Point'",
+        ]);
+
+        // A local `let` may shadow the function and is then assignable
+        let result = check_source(r#"
+            fn helper() -> int { return 1; }
+            fn main() -> int {
+                let mut helper = 2;
+                helper = 3;
+                return helper;
+            }
+        "#);
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[test]
+    fn test_local_function_scope() {
+        let result = check_source(r#"
+            fn main() -> int {
+                fn square(x: int) -> int { return x * x; }
+                return square(3);
+            }
+        "#);
+        assert!(result.is_ok(), "{:?}", result);
+
+        // Visible only after its definition and only inside the enclosing block
+        let errors = check_source(r#"
+            fn main() {
+                twice(1);
+                {
+                    fn twice(x: int) -> int { return x * 2; }
+                }
+                fn half(x: int) -> string { return x / 2; }
+                twice(2);
+            }
+        "#).unwrap_err();
+        let messages: Vec<&str> = errors.iter().filter(|e| e.is_error()).map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, vec![
+            "undefined variable 'twice'",
+            "return type mismatch: expected 'string', found 'int'",
+            "undefined variable 'twice'",
+        ]);
+    }
+
+    #[test]
+    fn test_void_result_used_as_value() {
+        let errors = check_source(r#"
+            fn log(msg: string) { print(msg); }
+            fn main() {
+                let x = print("hi");
+                let y: int = 1 + log("a");
+                log(log("b"));
+                print("fine");
+            }
+        "#).unwrap_err();
+        let void_uses: Vec<(u32, u32)> = errors.iter()
+            .filter(|e| e.message == "cannot use the result of a void function as a value")
+            .map(|e| (e.line, e.column))
+            .collect();
+        assert_eq!(void_uses, vec![(4, 25), (5, 34), (6, 21)]);
+    }
+
+    #[test]
+    fn test_deprecated_function_call_warns() {
+        let tokens = tokenize(r#"
+            @deprecated("use area2 instead")
+            fn area(w: int, h: int) -> int { return w * h; }
+            @deprecated
+            fn old() {}
+            fn area2(w: int, h: int) -> int { return w * h; }
+            fn main() -> int {
+                old();
+                return area(2, 3) + area2(1, 1);
+            }
+        "#).unwrap();
+        let warnings = TypeChecker::new().check_program(&parse(&tokens)).unwrap();
+        let messages: Vec<&str> = warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(messages, vec![
+            "function 'old' is deprecated",
+            "function 'area' is deprecated: use area2 instead",
+        ]);
+        assert!(warnings.iter().all(|w| !w.is_error()));
+    }
+
+    #[test]
+    fn test_nil_checks_narrow_optionals() {
+        let result = check_source(r#"
+            fn first(a: int?, b: int?) -> int {
+                guard a != nil else { return 0; }
+                let x: int = a + 1;
+                if b == nil { return x; }
+                if b != nil && x > 0 {
+                    let y: int = b;
+                    return y;
+                }
+                return b;
+            }
+        "#);
+        assert!(result.is_ok(), "{:?}", result);
+
+        // No refinement outside the checked branch, or after reassignment
+        let errors = check_source(r#"
+            fn f(a: int?) -> int {
+                if a != nil {
+                    let n: int = a;
+                } else {
+                    let m: int = a;
+                }
+                guard a != nil else { return 0; }
+                a = nil;
+                return a;
+            }
+        "#).unwrap_err();
+        let messages: Vec<&str> = errors.iter().filter(|e| e.is_error()).map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, vec![
+            "type mismatch: expected 'int', found 'int?'",
+            "return type mismatch: expected 'int', found 'int?'",
+        ]);
+    }
+
+    #[test]
+    fn test_strict_mode_rejects_unused_variable() {
+        let tokens = tokenize("fn main() { let spare = 1; }").unwrap();
+        let ast = parse(&tokens);
+        assert!(check_with(&ast, false).is_ok());
+        assert_eq!(check_with(&ast, true).unwrap_err(), "1 warning(s) treated as errors");
+    }
+
+    #[test]
+    fn test_struct_update_base_type() {
+        let ok = check_source(r#"
+            struct Point { x: int, y: int }
+            fn main() {
+                let p = Point { x: 1, y: 2 };
+                let q = Point { ..p, x: 9 };
+                print(q.y);
+            }
+        "#);
+        assert!(ok.is_ok());
+
+        let errors = check_source(r#"
+            struct Point { x: int, y: int }
+            struct Size { w: int, h: int }
+            fn main() {
+                let s = Size { w: 1, h: 2 };
+                let q = Point { ..s, x: 9 };
+                print(q.x);
+            }
+        "#).unwrap_err();
+        assert!(errors.iter().any(|e| e.message == "struct update base must be 'Point', found 'Size'"));
+    }
+
+    #[test]
+    fn test_expected_type_coerces_int_literals() {
+        let ok = check_source(r#"
+            struct Point { x: float, y: float }
+            fn scale(factors: [float]) -> float { return factors[0]; }
+            fn main() {
+                let xs: [float] = [1, 2, 3];
+                let mixed: [float] = [1, 2.5, -3];
+                let grid: [[float]] = [[0, 1], [2, 3]];
+                let p = Point { x: 1, y: 2.5 };
+                print(scale([2, 4]) + xs[0] + mixed[0] + grid[0][0] + p.x);
+            }
+        "#);
+        assert!(ok.is_ok(), "{:?}", ok);
+
+        let errors = check_source(r#"
+            fn main() {
+                let bytes: [u8] = [1, 300, -1];
+                let names: [string] = [1, 2];
+                print(bytes, names);
+            }
+        "#).unwrap_err();
+        let messages: Vec<&str> = errors.iter().filter(|d| d.is_error()).map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, vec![
+            "integer literal 300 is out of range for 'u8' (0..=255)",
+            "integer literal -1 is out of range for 'u8' (0..=255)",
+            "type mismatch: expected '[string]', found '[int]'",
+        ]);
+    }
+
+    #[test]
+    fn test_color_components_are_ints() {
+        let ok = check_source(r#"
+            fn brightness(c: color) -> int { return (c.r + c.g + c.b) / 3 * c.a / 255; }
+        "#);
+        assert!(ok.is_ok(), "{:?}", ok);
+
+        let errors = check_source("fn hue(c: color) -> int { return c.h; }").unwrap_err();
+        assert!(errors.iter().any(|e| e.message == "cannot access field 'h' on 'color'"));
+    }
+
+    #[test]
+    fn test_relational_operators_need_ordered_types() {
+        let ok = check_source(r#"
+            fn before(a: string, b: string) -> bool { return a < b || a <= b; }
+            fn mixed(x: int, y: float) -> bool { return x >= y; }
+        "#);
+        assert!(ok.is_ok(), "{:?}", ok);
+
+        let errors = check_source(r#"
+            fn main() {
+                print(true < false, "a" > 1, [1] <= [2]);
+            }
+        "#).unwrap_err();
+        assert!(errors.iter().any(|e| e.message == "cannot compare 'bool' and 'bool'"));
+        assert!(errors.iter().any(|e| e.message == "cannot compare 'string' and 'int'"));
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+    }
+
+    #[test]
+    fn test_tuple_destructuring_arity() {
+        let ok = check_source(r#"
+            fn min_max(a: int, b: int) -> (int, int) {
+                if a < b { return (a, b); }
+                return (b, a);
+            }
+            fn main() {
+                let (lo, hi) = min_max(3, 1);
+                let (w, h): (float, float) = (4, 2.5);
+                print(lo + hi, w * h);
+            }
+        "#);
+        assert!(ok.is_ok(), "{:?}", ok);
+
+        let errors = check_source(r#"
+            fn main() {
+                let (a, b, c) = (1, 2);
+                let (d, e) = 5;
+                print(a, b, c, d, e);
+            }
+        "#).unwrap_err();
+        let messages: Vec<&str> = errors.iter().filter(|d| d.is_error()).map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, vec![
+            "cannot destructure a 2-element tuple into 3 names",
+            "cannot destructure 'int' as a tuple",
+        ]);
+    }
 }
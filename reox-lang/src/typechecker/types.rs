@@ -2,6 +2,7 @@
 // Zero external dependencies
 
 use std::collections::HashMap;
+use crate::lexer::Span;
 
 /// Resolved type (after type checking)
 #[derive(Debug, Clone, PartialEq)]
@@ -9,9 +10,17 @@ pub enum ResolvedType {
     Int,
     Float,
     String,
+    Char,
     Bool,
     Void,
     Struct(String),
+    // Fixed-width integer (`int` itself is 64-bit signed)
+    FixedInt(crate::parser::IntWidth),
+    // Distinct alias: nominal, never interchangeable with its base
+    Newtype {
+        name: String,
+        base: Box<ResolvedType>,
+    },
     Array(Box<ResolvedType>),
     Function {
         params: Vec<ResolvedType>,
@@ -25,6 +34,8 @@ pub enum ResolvedType {
     Color,
     Unknown,
     Error,
+    // Result of a dynamically typed interpreter native; fits wherever a value does
+    Any,
 }
 
 impl ResolvedType {
@@ -40,6 +51,45 @@ impl ResolvedType {
             crate::parser::Type::Array(inner) => {
                 ResolvedType::Array(Box::new(Self::from_parser_type(inner)))
             }
+            crate::parser::Type::Function(params, ret) => ResolvedType::Function {
+                params: params.iter().map(Self::from_parser_type).collect(),
+                ret: Box::new(Self::from_parser_type(ret)),
+            },
+            // Indirection is transparent to the type system
+            crate::parser::Type::Box(inner) => Self::from_parser_type(inner),
+            crate::parser::Type::FixedInt(w) => ResolvedType::FixedInt(*w),
+            crate::parser::Type::Optional(inner) => ResolvedType::Optional(Box::new(Self::from_parser_type(inner))),
+            crate::parser::Type::Tuple(elems) => ResolvedType::Tuple(elems.iter().map(Self::from_parser_type).collect()),
+        }
+    }
+
+    /// Width of an integer type, treating `int` as `i64`
+    pub fn int_width(&self) -> Option<crate::parser::IntWidth> {
+        match self {
+            ResolvedType::Int => Some(crate::parser::IntWidth::I64),
+            ResolvedType::FixedInt(w) => Some(*w),
+            _ => None,
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.int_width().is_some()
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        self.is_integer() || *self == ResolvedType::Float
+    }
+
+    /// Result type of arithmetic on two integers: the wider operand, or
+    /// `None` when neither width holds every value of the other
+    pub fn common_int(&self, other: &ResolvedType) -> Option<ResolvedType> {
+        let (a, b) = (self.int_width()?, other.int_width()?);
+        if a.widens_from(b) {
+            Some(self.clone())
+        } else if b.widens_from(a) {
+            Some(other.clone())
+        } else {
+            None
         }
     }
 
@@ -49,9 +99,15 @@ impl ResolvedType {
             return true;
         }
         match (self, other) {
+            (ResolvedType::Any, _) | (_, ResolvedType::Any) => true,
             // Float can be assigned from Int (widening)
-            (ResolvedType::Float, ResolvedType::Int) => true,
-            // Optional<T> can be assigned from T
+            (ResolvedType::Float, ResolvedType::Int | ResolvedType::FixedInt(_)) => true,
+            // Integers widen implicitly; narrowing needs a conversion like `u8(x)`
+            (ResolvedType::Int | ResolvedType::FixedInt(_), ResolvedType::Int | ResolvedType::FixedInt(_)) => {
+                matches!((self.int_width(), other.int_width()), (Some(a), Some(b)) if a.widens_from(b))
+            }
+            // Optional<T> can be assigned from nil or T
+            (ResolvedType::Optional(_), ResolvedType::Unknown) => true,
             (ResolvedType::Optional(inner), other) => inner.is_assignable_from(other),
             // Array<T> compatibility
             (ResolvedType::Array(a), ResolvedType::Array(b)) => a.is_assignable_from(b),
@@ -73,9 +129,12 @@ impl ResolvedType {
             ResolvedType::Int => "int".to_string(),
             ResolvedType::Float => "float".to_string(),
             ResolvedType::String => "string".to_string(),
+            ResolvedType::Char => "char".to_string(),
             ResolvedType::Bool => "bool".to_string(),
             ResolvedType::Void => "void".to_string(),
             ResolvedType::Struct(name) => name.clone(),
+            ResolvedType::FixedInt(w) => w.name().to_string(),
+            ResolvedType::Newtype { name, .. } => name.clone(),
             ResolvedType::Array(inner) => format!("[{}]", inner.display_name()),
             ResolvedType::Function { params, ret } => {
                 let params_str: Vec<String> = params.iter().map(|p| p.display_name()).collect();
@@ -87,9 +146,10 @@ impl ResolvedType {
                 let parts: Vec<String> = elems.iter().map(|e| e.display_name()).collect();
                 format!("({})", parts.join(", "))
             }
-            ResolvedType::Color => "Color".to_string(),
+            ResolvedType::Color => "color".to_string(),
             ResolvedType::Unknown => "<unknown>".to_string(),
             ResolvedType::Error => "<error>".to_string(),
+            ResolvedType::Any => "<any>".to_string(),
         }
     }
 }
@@ -101,6 +161,10 @@ pub struct Symbol {
     pub ty: ResolvedType,
     pub mutable: bool,
     pub kind: SymbolKind,
+    /// False for a `let` declared without an initializer until it is definitely assigned
+    pub initialized: bool,
+    /// Message from a function's `@deprecated` attribute, warned about at each call
+    pub deprecated: Option<String>,
 }
 
 /// Kind of symbol
@@ -123,12 +187,18 @@ pub struct StructInfo {
 #[derive(Debug)]
 struct Scope {
     symbols: HashMap<String, Symbol>,
+    /// `let` bindings declared here, with their span and whether they were read
+    locals: HashMap<String, (Span, bool)>,
+    /// Optional variables known to be non-nil here, with their unwrapped type
+    narrowed: HashMap<String, ResolvedType>,
 }
 
 impl Scope {
     fn new() -> Self {
         Self {
             symbols: HashMap::new(),
+            locals: HashMap::new(),
+            narrowed: HashMap::new(),
         }
     }
 }
@@ -155,11 +225,18 @@ impl SymbolTable {
         self.scopes.push(Scope::new());
     }
 
-    /// Exit current scope
-    pub fn pop_scope(&mut self) {
-        if self.scopes.len() > 1 {
-            self.scopes.pop();
+    /// Exit current scope, returning the `let` bindings that were never read
+    pub fn pop_scope(&mut self) -> Vec<(String, Span)> {
+        if self.scopes.len() <= 1 {
+            return Vec::new();
         }
+        let scope = self.scopes.pop().expect("no scope");
+        let mut unused: Vec<(String, Span)> = scope.locals.into_iter()
+            .filter(|(_, (_, used))| !used)
+            .map(|(name, (span, _))| (name, span))
+            .collect();
+        unused.sort_by_key(|(_, span)| (span.line, span.column));
+        unused
     }
 
     /// Define a symbol in current scope
@@ -175,6 +252,47 @@ impl SymbolTable {
         Ok(())
     }
 
+    /// Define a `let` binding in current scope, tracking whether it is read
+    pub fn define_local(&mut self, symbol: Symbol, span: Span) -> Result<(), String> {
+        let name = symbol.name.clone();
+        self.define(symbol)?;
+        let scope = self.scopes.last_mut().expect("no scope");
+        scope.locals.insert(name, (span, false));
+        Ok(())
+    }
+
+    /// Record a read of the innermost symbol named `name`
+    pub fn mark_used(&mut self, name: &str) {
+        for scope in self.scopes.iter_mut().rev() {
+            if scope.symbols.contains_key(name) {
+                if let Some((_, used)) = scope.locals.get_mut(name) {
+                    *used = true;
+                }
+                return;
+            }
+        }
+    }
+
+    /// Whether defining `name` in current scope would hide a variable
+    /// or parameter from an enclosing scope
+    pub fn shadows_outer(&self, name: &str) -> bool {
+        let (current, outer) = self.scopes.split_last().expect("no scope");
+        !current.symbols.contains_key(name)
+            && outer.iter().rev().find_map(|s| s.symbols.get(name)).is_some_and(|sym| {
+                matches!(sym.kind, SymbolKind::Variable | SymbolKind::Parameter)
+            })
+    }
+
+    /// Number of open scopes, the global scope included
+    pub fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    /// Index of the scope that binds `name`, counting the global scope as 0
+    pub fn scope_of(&self, name: &str) -> Option<usize> {
+        self.scopes.iter().rposition(|s| s.symbols.contains_key(name))
+    }
+
     /// Look up a symbol by name (searches all scopes)
     pub fn lookup(&self, name: &str) -> Option<&Symbol> {
         for scope in self.scopes.iter().rev() {
@@ -185,6 +303,56 @@ impl SymbolTable {
         None
     }
 
+    /// Treat `name` as having type `ty` until the current scope ends
+    pub fn narrow(&mut self, name: &str, ty: ResolvedType) {
+        let scope = self.scopes.last_mut().expect("no scope");
+        scope.narrowed.insert(name.to_string(), ty);
+    }
+
+    /// The refined type of `name`, unless a declaration nearer than the
+    /// refinement shadows it
+    pub fn narrowed_type(&self, name: &str) -> Option<&ResolvedType> {
+        for scope in self.scopes.iter().rev() {
+            if let Some(ty) = scope.narrowed.get(name) {
+                return Some(ty);
+            }
+            if scope.symbols.contains_key(name) {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// Forget refinements of `name` once it is reassigned
+    pub fn clear_narrowing(&mut self, name: &str) {
+        for scope in &mut self.scopes {
+            scope.narrowed.remove(name);
+        }
+    }
+
+    /// Mark the innermost symbol named `name` as (un)initialized
+    pub fn set_initialized(&mut self, name: &str, initialized: bool) {
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(sym) = scope.symbols.get_mut(name) {
+                sym.initialized = initialized;
+                return;
+            }
+        }
+    }
+
+    /// Names of visible symbols that are not yet definitely assigned
+    pub fn uninitialized_names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        for scope in &self.scopes {
+            for sym in scope.symbols.values() {
+                if !sym.initialized && self.lookup(&sym.name).is_some_and(|s| std::ptr::eq(s, sym)) {
+                    names.push(sym.name.clone());
+                }
+            }
+        }
+        names
+    }
+
     /// Define a struct
     pub fn define_struct(&mut self, info: StructInfo) -> Result<(), String> {
         if self.structs.contains_key(&info.name) {
@@ -233,6 +401,8 @@ mod tests {
             ty: ResolvedType::Int,
             mutable: false,
             kind: SymbolKind::Variable,
+            initialized: true,
+            deprecated: None,
         }).unwrap();
 
         assert!(table.lookup("x").is_some());
@@ -248,6 +418,8 @@ mod tests {
             ty: ResolvedType::Int,
             mutable: false,
             kind: SymbolKind::Variable,
+            initialized: true,
+            deprecated: None,
         }).unwrap();
 
         table.push_scope();
@@ -257,6 +429,8 @@ mod tests {
             ty: ResolvedType::Int,
             mutable: false,
             kind: SymbolKind::Variable,
+            initialized: true,
+            deprecated: None,
         }).unwrap();
 
         // Can see both x and y
@@ -4,6 +4,21 @@
 use reoxc::lexer::{tokenize, TokenKind};
 use reoxc::parser::Parser;
 use reoxc::interpreter::{Interpreter, Value};
+use std::process::{Command, Output};
+
+/// Write `source` to a temp file named after `name`, run `reoxc` with `args`
+/// followed by the file's path, and remove the file again
+fn run_reoxc(name: &str, source: &str, args: &[&str]) -> Output {
+    let path = std::env::temp_dir().join(format!("reox_smoke_{}_{}.reox", name, std::process::id()));
+    std::fs::write(&path, source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_reoxc"))
+        .args(args)
+        .arg(&path)
+        .output()
+        .expect("reoxc should run");
+    let _ = std::fs::remove_file(&path);
+    output
+}
 
 #[test]
 fn lexer_tokenizes_basic_code() {
@@ -118,3 +133,100 @@ fn interpreter_handles_string_literals() {
     let result = interp.eval(&ast);
     assert!(result.is_ok(), "Should handle strings");
 }
+
+#[test]
+fn test_command_reports_failures_and_keeps_going() {
+    let output = run_reoxc("tests", r#"
+        fn double(x: int) -> int { return x * 2; }
+
+        @test
+        fn doubles() { assert(double(2) == 4); }
+
+        @test
+        fn fails() { assert(double(2) == 5, "double(2) should be 5"); }
+
+        @test
+        fn panics() { panic("boom"); }
+
+        @test
+        fn runs_after_failures() { assert(double(0) == 0); }
+    "#, &["test"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(stdout.contains("test doubles ... ok"), "{}", stdout);
+    assert!(stdout.contains("assertion failed: double(2) should be 5"), "{}", stdout);
+    assert!(stdout.contains("runtime error[8:22]: assertion failed"), "failure should carry its span: {}", stdout);
+    assert!(stdout.contains("panic: boom"), "{}", stdout);
+    assert!(stdout.contains("test runs_after_failures ... ok"), "{}", stdout);
+    assert!(stdout.contains("test result: FAILED. 2 passed; 2 failed"), "{}", stdout);
+}
+
+#[test]
+fn test_command_exits_zero_when_all_pass() {
+    let output = run_reoxc("pass", "@test\nfn ok() { assert(1 < 2); }\n", &["test"]);
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("test result: ok. 1 passed; 0 failed"));
+}
+
+#[test]
+fn run_exits_with_main_return_value() {
+    let output = run_reoxc("exit", "fn main() -> int {\n    print(\"bye\");\n    return 3;\n}\n", &["--run"]);
+
+    assert_eq!(output.status.code(), Some(3), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("bye"));
+}
+
+#[test]
+fn run_exit_status_keeps_low_byte_and_ignores_untyped_main() {
+    let status = |name: &str, source: &str| run_reoxc(name, source, &["--run"]).status.code();
+
+    // As in C, -1 is 255 and 258 wraps to 2
+    assert_eq!(status("neg", "fn main() -> int { return -1; }\n"), Some(255));
+    assert_eq!(status("wrap", "fn main() -> int { return 258; }\n"), Some(2));
+    // Only `main() -> int` sets the status, not a value its body happens to end with
+    assert_eq!(status("void", "fn work() -> int { return 7; }\nfn main() { work(); }\n"), Some(0));
+}
+
+#[test]
+fn run_writes_profile_in_requested_format() {
+    let output = run_reoxc(
+        "profile",
+        "fn work() -> int { return 1; }\nfn main() { let n = work(); }\n",
+        &["--run", "--profile-format", "speedscope"],
+    );
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(stderr.contains("speedscope.app/file-format-schema.json"), "{}", stderr);
+    assert!(stderr.contains("{\"name\": \"work\"}"), "{}", stderr);
+}
+
+#[test]
+fn check_accepts_programs_calling_natives() {
+    let output = run_reoxc("check", r#"
+        fn main() {
+            let m = map_set(map_new(), "hits", 3);
+            let line: string = format("{:>4}", map_get(m, "hits"));
+            let padded = pad_left(line, 8, ".");
+            let ok = regex_match("^[.]+", padded);
+            let c = rgb(255, 0, 0);
+            print(padded, ok, c);
+        }
+    "#, &["check"]);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with(": ok\n"));
+}
+
+#[test]
+fn check_strict_fails_on_warnings() {
+    let source = "fn main() {\n    let unused = 1;\n}\n";
+    let lenient = run_reoxc("check_lenient", source, &["check"]);
+    let strict = run_reoxc("check_strict", source, &["check", "--strict"]);
+
+    assert!(lenient.status.success(), "{}", String::from_utf8_lossy(&lenient.stderr));
+    assert!(!strict.status.success());
+    assert!(String::from_utf8_lossy(&strict.stderr).contains("1 warning(s) treated as errors"));
+}